@@ -23,6 +23,7 @@ export function AddCredentialDialog({ open, onOpenChange }: AddCredentialDialogP
   const [refreshToken, setRefreshToken] = useState('')
   const [authMethod, setAuthMethod] = useState<AuthMethod>('social')
   const [region, setRegion] = useState('')
+  const [apiRegion, setApiRegion] = useState('')
   const [clientId, setClientId] = useState('')
   const [clientSecret, setClientSecret] = useState('')
   const [priority, setPriority] = useState('0')
@@ -33,6 +34,7 @@ export function AddCredentialDialog({ open, onOpenChange }: AddCredentialDialogP
     setRefreshToken('')
     setAuthMethod('social')
     setRegion('')
+    setApiRegion('')
     setClientId('')
     setClientSecret('')
     setPriority('0')
@@ -58,6 +60,7 @@ export function AddCredentialDialog({ open, onOpenChange }: AddCredentialDialogP
         refreshToken: refreshToken.trim(),
         authMethod,
         region: region.trim() || undefined,
+        apiRegion: apiRegion.trim() || undefined,
         clientId: clientId.trim() || undefined,
         clientSecret: clientSecret.trim() || undefined,
         priority: parseInt(priority) || 0,
@@ -129,6 +132,19 @@ export function AddCredentialDialog({ open, onOpenChange }: AddCredentialDialogP
               />
             </div>
 
+            <div className="space-y-2">
+              <label htmlFor="apiRegion" className="text-sm font-medium">
+                API 地域
+              </label>
+              <Input
+                id="apiRegion"
+                placeholder="例如 eu-central-1（留空则使用全局 apiRegions / region）"
+                value={apiRegion}
+                onChange={(e) => setApiRegion(e.target.value)}
+                disabled={isPending}
+              />
+            </div>
+
             {/* IdC/Builder-ID/IAM 额外字段 */}
             {authMethod === 'idc' && (
               <>
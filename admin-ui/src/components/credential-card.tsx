@@ -124,7 +124,9 @@ export function CredentialCard({ credential, onViewBalance }: CredentialCardProp
                 <Badge variant="success">当前</Badge>
               )}
               {credential.disabled && (
-                <Badge variant="destructive">已禁用</Badge>
+                <Badge variant="destructive">
+                  {credential.disabledReason === 'quotaExceeded' ? '额度用尽' : '已禁用'}
+                </Badge>
               )}
             </CardTitle>
             <div className="flex items-center gap-2">
@@ -196,6 +198,42 @@ export function CredentialCard({ credential, onViewBalance }: CredentialCardProp
               <span className="text-muted-foreground">Token 有效期：</span>
               <span className="font-medium">{formatExpiry(credential.expiresAt)}</span>
             </div>
+            {credential.disabled && credential.disabledUntil && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">禁用至：</span>
+                <span className="font-medium">
+                  {new Date(credential.disabledUntil).toLocaleString('zh-CN')}
+                </span>
+                <span className="text-xs text-muted-foreground ml-1">(额度重置后自动恢复)</span>
+              </div>
+            )}
+            {credential.balance && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">剩余额度：</span>
+                <span className="font-medium">
+                  {credential.balance.remaining.toFixed(2)} / {credential.balance.usageLimit.toFixed(2)}
+                </span>
+                <span className="text-xs text-muted-foreground ml-1">
+                  ({new Date(credential.balance.checkedAt).toLocaleString('zh-CN')} 查询)
+                </span>
+              </div>
+            )}
+            {credential.apiRegions.length > 0 && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">API 区域：</span>
+                <span className="font-medium">{credential.apiRegions.join(' → ')}</span>
+              </div>
+            )}
+            {credential.circuit.state !== 'closed' && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">熔断：</span>
+                <span className="font-medium text-red-500">
+                  {credential.circuit.state === 'open'
+                    ? `已打开（${credential.circuit.openRemainingSecs ?? 0} 秒后探测）`
+                    : '半开（探测中）'}
+                </span>
+              </div>
+            )}
             {credential.hasProfileArn && (
               <div className="col-span-2">
                 <Badge variant="secondary">有 Profile ARN</Badge>
@@ -69,9 +69,17 @@ export function RequestLog() {
                 {log.success ? "200" : "ERR"}
               </Badge>
 
-              <Badge variant="outline" className="shrink-0">
-                #{log.credentialId}
-              </Badge>
+              {log.credentialId !== undefined && (
+                <Badge variant="outline" className="shrink-0">
+                  #{log.credentialId}
+                </Badge>
+              )}
+
+              {log.region && (
+                <Badge variant="outline" className="shrink-0">
+                  {log.region}
+                </Badge>
+              )}
 
               <span className="font-semibold shrink-0">
                 {log.model}
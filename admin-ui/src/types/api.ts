@@ -4,6 +4,16 @@ export interface CredentialsStatusResponse {
   available: number
   currentId: number
   credentials: CredentialStatusItem[]
+  endpointCircuits: EndpointCircuitStatus[]
+  hedging: HedgingStats
+}
+
+// 对冲请求统计
+export interface HedgingStats {
+  fired: number
+  hedgeWon: number
+  primaryWon: number
+  rateLimited: number
 }
 
 // 单个凭据状态
@@ -16,6 +26,37 @@ export interface CredentialStatusItem {
   expiresAt: string | null
   authMethod: string | null
   hasProfileArn: boolean
+  disabledReason: string | null
+  disabledUntil: string | null
+  balance: CachedBalance | null
+  circuit: CircuitBreakerStatus
+  apiRegions: string[]
+}
+
+// 熔断器状态
+export interface CircuitBreakerStatus {
+  state: 'closed' | 'open' | 'halfOpen'
+  requests: number
+  failures: number
+  openRemainingSecs: number | null
+}
+
+// 上游端点熔断器状态
+export interface EndpointCircuitStatus extends CircuitBreakerStatus {
+  endpoint: string
+  region: string
+}
+
+// 缓存的余额（余额轮询或手动查询更新）
+export interface CachedBalance {
+  subscriptionTitle: string | null
+  currentUsage: number
+  usageLimit: number
+  remaining: number
+  remainingPercentage: number
+  nextResetAt: number | null
+  trialExpiresAt: number | null
+  checkedAt: string
 }
 
 // 余额响应
@@ -60,6 +101,8 @@ export interface AddCredentialRequest {
   clientSecret?: string
   priority?: number
   region?: string
+  apiRegion?: string
+  headerProfile?: string
 }
 
 // 添加凭据响应
@@ -77,7 +120,8 @@ export interface RequestLogEntry {
   maxTokens: number
   stream: boolean
   messageCount: number
-  credentialId: number
+  credentialId?: number
+  region?: string
   success: boolean
 }
 
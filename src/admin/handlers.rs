@@ -8,7 +8,10 @@ use axum::{
 
 use super::{
     middleware::AdminState,
-    types::{AddCredentialRequest, SetDisabledRequest, SetPriorityRequest, SuccessResponse},
+    types::{
+        AddCredentialRequest, ImportCredentialsRequest, SetDisabledRequest, SetPriorityRequest,
+        StartDeviceAuthRequest, SuccessResponse,
+    },
 };
 
 /// GET /api/admin/credentials
@@ -110,3 +113,38 @@ pub async fn get_request_logs(State(state): State<AdminState>) -> impl IntoRespo
     Json(response)
 }
 
+/// POST /api/admin/credentials/import
+/// 从 Kiro IDE / AWS SSO 缓存文件批量导入凭据
+pub async fn import_credentials(
+    State(state): State<AdminState>,
+    Json(payload): Json<ImportCredentialsRequest>,
+) -> impl IntoResponse {
+    match state.service.import_credentials(payload).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// POST /api/admin/credentials/device-auth
+/// 发起设备授权（Builder ID / IAM Identity Center）
+pub async fn start_device_auth(
+    State(state): State<AdminState>,
+    Json(payload): Json<StartDeviceAuthRequest>,
+) -> impl IntoResponse {
+    match state.service.start_device_auth(payload).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/device-auth/:session_id
+/// 轮询设备授权状态，授权完成时自动添加凭据
+pub async fn poll_device_auth(
+    State(state): State<AdminState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    match state.service.poll_device_auth(&session_id).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
@@ -9,7 +9,7 @@ use super::{
     handlers::{
         add_credential, delete_credential, get_all_credentials, get_credential_balance,
         reset_failure_count, set_credential_disabled, set_credential_priority,
-        get_request_logs,
+        get_request_logs, import_credentials, poll_device_auth, start_device_auth,
     },
     middleware::{AdminState, admin_auth_middleware},
 };
@@ -19,6 +19,9 @@ use super::{
 /// # 端点
 /// - `GET /credentials` - 获取所有凭据状态
 /// - `POST /credentials` - 添加新凭据
+/// - `POST /credentials/import` - 从 Kiro IDE / AWS SSO 缓存批量导入凭据
+/// - `POST /credentials/device-auth` - 发起设备授权
+/// - `GET /credentials/device-auth/:session_id` - 轮询设备授权状态
 /// - `DELETE /credentials/:id` - 删除凭据
 /// - `POST /credentials/:id/disabled` - 设置凭据禁用状态
 /// - `POST /credentials/:id/priority` - 设置凭据优先级
@@ -35,6 +38,12 @@ pub fn create_admin_router(state: AdminState) -> Router {
             "/credentials",
             get(get_all_credentials).post(add_credential),
         )
+        .route("/credentials/import", post(import_credentials))
+        .route("/credentials/device-auth", post(start_device_auth))
+        .route(
+            "/credentials/device-auth/{session_id}",
+            get(poll_device_auth),
+        )
         .route("/credentials/{id}", delete(delete_credential))
         .route("/credentials/{id}/disabled", post(set_credential_disabled))
         .route("/credentials/{id}/priority", post(set_credential_priority))
@@ -1,28 +1,53 @@
 //! Admin API 业务逻辑服务
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use parking_lot::Mutex;
+
+use crate::kiro::device_auth::{
+    BUILDER_ID_START_URL, DeviceAuthClient, DeviceAuthSession, PollOutcome,
+    SLOW_DOWN_INCREMENT_SECS,
+};
 use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::sso_import::{self, CacheFile};
 use crate::kiro::token_manager::MultiTokenManager;
 use crate::request_log::RequestLogger;
 
 use super::error::AdminServiceError;
 use super::types::{
     AddCredentialRequest, AddCredentialResponse, BalanceResponse, CredentialStatusItem,
-    CredentialsStatusResponse, RequestLogsResponse,
+    CredentialsStatusResponse, DeviceAuthStatusResponse, ImportCredentialsRequest,
+    ImportCredentialsResponse, ImportedCredentialItem, RequestLogsResponse, SkippedImportItem,
+    StartDeviceAuthRequest, StartDeviceAuthResponse,
 };
 
+/// 进行中的设备授权（会话 + 新凭据优先级）
+struct PendingDeviceAuth {
+    session: DeviceAuthSession,
+    priority: u32,
+}
+
 /// Admin 服务
 ///
 /// 封装所有 Admin API 的业务逻辑
 pub struct AdminService {
     token_manager: Arc<MultiTokenManager>,
     request_logger: Option<Arc<RequestLogger>>,
+    /// 进行中的设备授权会话（session_id -> 会话）
+    device_auth_sessions: Mutex<HashMap<String, PendingDeviceAuth>>,
 }
 
 impl AdminService {
-    pub fn new(token_manager: Arc<MultiTokenManager>, request_logger: Option<Arc<RequestLogger>>) -> Self {
-        Self { token_manager, request_logger }
+    pub fn new(
+        token_manager: Arc<MultiTokenManager>,
+        request_logger: Option<Arc<RequestLogger>>,
+    ) -> Self {
+        Self {
+            token_manager,
+            request_logger,
+            device_auth_sessions: Mutex::new(HashMap::new()),
+        }
     }
 
     /// 获取所有凭据状态
@@ -41,6 +66,11 @@ impl AdminService {
                 expires_at: entry.expires_at,
                 auth_method: entry.auth_method,
                 has_profile_arn: entry.has_profile_arn,
+                disabled_reason: entry.disabled_reason,
+                disabled_until: entry.disabled_until,
+                balance: entry.balance,
+                circuit: entry.circuit,
+                api_regions: entry.api_regions,
             })
             .collect();
 
@@ -52,6 +82,8 @@ impl AdminService {
             available: snapshot.available,
             current_id: snapshot.current_id,
             credentials,
+            endpoint_circuits: snapshot.endpoint_circuits,
+            hedging: snapshot.hedging,
         }
     }
 
@@ -131,7 +163,14 @@ impl AdminService {
             client_secret: req.client_secret,
             priority: req.priority,
             region: req.region,
+            api_region: req.api_region,
+            header_profile: req.header_profile,
             machine_id: req.machine_id,
+            proxy_url: req.proxy_url,
+            proxy_username: req.proxy_username,
+            proxy_password: req.proxy_password,
+            proxy_pool: req.proxy_pool,
+            max_concurrency: req.max_concurrency,
         };
 
         // 调用 token_manager 添加凭据
@@ -148,6 +187,166 @@ impl AdminService {
         })
     }
 
+    /// 从 Kiro IDE / AWS SSO 缓存文件批量导入凭据
+    pub async fn import_credentials(
+        &self,
+        req: ImportCredentialsRequest,
+    ) -> Result<ImportCredentialsResponse, AdminServiceError> {
+        if req.files.is_empty() {
+            return Err(AdminServiceError::InvalidCredential(
+                "请提供 files".to_string(),
+            ));
+        }
+
+        let files: Vec<_> = req
+            .files
+            .into_iter()
+            .map(|f| CacheFile::new(f.name, f.content))
+            .collect();
+        let (candidates, mut skipped) = sso_import::parse_cache_files(&files, req.priority);
+        let mut report = sso_import::import_candidates(&self.token_manager, candidates).await;
+        skipped.append(&mut report.skipped);
+
+        Ok(ImportCredentialsResponse {
+            imported: report
+                .imported
+                .into_iter()
+                .map(|(source, credential_id)| ImportedCredentialItem {
+                    source,
+                    credential_id,
+                })
+                .collect(),
+            skipped: skipped
+                .into_iter()
+                .map(|s| SkippedImportItem {
+                    source: s.source,
+                    reason: s.reason,
+                })
+                .collect(),
+        })
+    }
+
+    /// 发起设备授权（Builder ID / IAM Identity Center）
+    pub async fn start_device_auth(
+        &self,
+        req: StartDeviceAuthRequest,
+    ) -> Result<StartDeviceAuthResponse, AdminServiceError> {
+        let client = self.device_auth_client(req.region)?;
+        let start_url = req.start_url.as_deref().unwrap_or(BUILDER_ID_START_URL);
+        let session = client
+            .start(start_url)
+            .await
+            .map_err(|e| AdminServiceError::UpstreamError(e.to_string()))?;
+
+        // 顺带清理已过期的会话
+        let mut sessions = self.device_auth_sessions.lock();
+        sessions.retain(|_, pending| !pending.session.is_expired());
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let response = StartDeviceAuthResponse {
+            session_id: session_id.clone(),
+            user_code: session.authorization.user_code.clone(),
+            verification_uri: session.authorization.verification_uri.clone(),
+            verification_uri_complete: session.authorization.verification_uri_complete.clone(),
+            expires_in: session.authorization.expires_in,
+            interval: session.interval_secs,
+        };
+        sessions.insert(
+            session_id,
+            PendingDeviceAuth {
+                session,
+                priority: req.priority,
+            },
+        );
+        Ok(response)
+    }
+
+    /// 轮询一次设备授权状态，授权完成时添加凭据
+    pub async fn poll_device_auth(
+        &self,
+        session_id: &str,
+    ) -> Result<DeviceAuthStatusResponse, AdminServiceError> {
+        let (session, priority) = {
+            let sessions = self.device_auth_sessions.lock();
+            let pending = sessions
+                .get(session_id)
+                .ok_or_else(|| AdminServiceError::SessionNotFound(session_id.to_string()))?;
+            (pending.session.clone(), pending.priority)
+        };
+
+        if session.is_expired() {
+            self.device_auth_sessions.lock().remove(session_id);
+            return Err(AdminServiceError::InvalidCredential(
+                PollOutcome::EXPIRED_MESSAGE.to_string(),
+            ));
+        }
+
+        let client = self.device_auth_client(Some(session.region.clone()))?;
+        let outcome = client
+            .poll(&session)
+            .await
+            .map_err(|e| AdminServiceError::UpstreamError(e.to_string()))?;
+
+        match outcome {
+            PollOutcome::Expired => {
+                self.device_auth_sessions.lock().remove(session_id);
+                Err(AdminServiceError::InvalidCredential(
+                    PollOutcome::EXPIRED_MESSAGE.to_string(),
+                ))
+            }
+            PollOutcome::Denied => {
+                self.device_auth_sessions.lock().remove(session_id);
+                Err(AdminServiceError::InvalidCredential(
+                    PollOutcome::DENIED_MESSAGE.to_string(),
+                ))
+            }
+            PollOutcome::Pending => Ok(DeviceAuthStatusResponse {
+                status: "pending".to_string(),
+                interval: session.interval_secs,
+                credential_id: None,
+            }),
+            PollOutcome::SlowDown => {
+                let mut sessions = self.device_auth_sessions.lock();
+                let interval = match sessions.get_mut(session_id) {
+                    Some(pending) => {
+                        pending.session.interval_secs += SLOW_DOWN_INCREMENT_SECS;
+                        pending.session.interval_secs
+                    }
+                    None => session.interval_secs + SLOW_DOWN_INCREMENT_SECS,
+                };
+                Ok(DeviceAuthStatusResponse {
+                    status: "pending".to_string(),
+                    interval,
+                    credential_id: None,
+                })
+            }
+            PollOutcome::Complete(token) => {
+                self.device_auth_sessions.lock().remove(session_id);
+                let credential_id = self
+                    .token_manager
+                    .add_credential(session.credentials(token, priority))
+                    .await
+                    .map_err(|e| self.classify_add_error(e))?;
+                Ok(DeviceAuthStatusResponse {
+                    status: "completed".to_string(),
+                    interval: session.interval_secs,
+                    credential_id: Some(credential_id),
+                })
+            }
+        }
+    }
+
+    /// 构建设备授权客户端（沿用全局代理与 OIDC 地址覆盖）
+    fn device_auth_client(
+        &self,
+        region: Option<String>,
+    ) -> Result<DeviceAuthClient, AdminServiceError> {
+        let config = self.token_manager.config();
+        let region = region.unwrap_or_else(|| config.region.clone());
+        DeviceAuthClient::new(config, region, self.token_manager.proxy())
+            .map_err(|e| AdminServiceError::InternalError(e.to_string()))
+    }
+
     /// 删除凭据
     pub fn delete_credential(&self, id: u64) -> Result<(), AdminServiceError> {
         self.token_manager
@@ -208,7 +407,8 @@ impl AdminService {
             || msg.contains("refreshToken 已被截断")
             || msg.contains("凭证已过期或无效")
             || msg.contains("权限不足")
-            || msg.contains("已被限流");
+            || msg.contains("已被限流")
+            || msg.contains("请求头配置不存在");
 
         if is_invalid_credential {
             AdminServiceError::InvalidCredential(msg)
@@ -16,6 +16,10 @@ pub struct CredentialsStatusResponse {
     pub current_id: u64,
     /// 各凭据状态列表
     pub credentials: Vec<CredentialStatusItem>,
+    /// 上游端点熔断器状态
+    pub endpoint_circuits: Vec<crate::kiro::token_manager::EndpointCircuitSnapshot>,
+    /// 对冲请求统计
+    pub hedging: crate::kiro::hedging::HedgingStats,
 }
 
 /// 单个凭据的状态信息
@@ -38,6 +42,16 @@ pub struct CredentialStatusItem {
     pub auth_method: Option<String>,
     /// 是否有 Profile ARN
     pub has_profile_arn: bool,
+    /// 禁用原因（manual / tooManyFailures / quotaExceeded）
+    pub disabled_reason: Option<String>,
+    /// 额度用尽禁用的自动恢复时间（RFC3339）
+    pub disabled_until: Option<String>,
+    /// 最近一次查询到的余额（余额轮询或手动查询）
+    pub balance: Option<crate::kiro::token_manager::CachedBalance>,
+    /// 凭据级熔断器状态
+    pub circuit: crate::kiro::circuit_breaker::CircuitBreakerStatus,
+    /// API 区域（按故障转移顺序，首个为主区域）
+    pub api_regions: Vec<String>,
 }
 
 // ============ 操作请求 ============
@@ -83,9 +97,30 @@ pub struct AddCredentialRequest {
     /// 未配置时回退到 config.json 的全局 region
     pub region: Option<String>,
 
+    /// 凭据级 API 区域（可选）
+    pub api_region: Option<String>,
+
+    /// 请求头配置名称（可选）
+    pub header_profile: Option<String>,
+
     /// 凭据级 Machine ID（可选，64 位字符串）
     /// 未配置时回退到 config.json 的 machineId
     pub machine_id: Option<String>,
+
+    /// 凭据级代理地址（可选）
+    pub proxy_url: Option<String>,
+
+    /// 凭据级代理认证用户名（可选）
+    pub proxy_username: Option<String>,
+
+    /// 凭据级代理认证密码（可选）
+    pub proxy_password: Option<String>,
+
+    /// 代理池名称（可选）
+    pub proxy_pool: Option<String>,
+
+    /// 最大并发请求数（可选）
+    pub max_concurrency: Option<u32>,
 }
 
 fn default_auth_method() -> String {
@@ -102,6 +137,107 @@ pub struct AddCredentialResponse {
     pub credential_id: u64,
 }
 
+// ============ 缓存导入 ============
+
+/// 从 Kiro IDE / AWS SSO 缓存批量导入凭据请求
+///
+/// 只接受直接上传的缓存文件内容，不读取服务端文件系统
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCredentialsRequest {
+    /// 上传的缓存文件
+    #[serde(default)]
+    pub files: Vec<ImportFileItem>,
+
+    /// 导入凭据的优先级（可选，默认 0）
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// 上传的缓存文件
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFileItem {
+    /// 文件名（客户端注册文件需保留原文件名以匹配 clientIdHash）
+    pub name: String,
+    /// 文件 JSON 内容
+    pub content: serde_json::Value,
+}
+
+/// 批量导入响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCredentialsResponse {
+    /// 成功导入的凭据
+    pub imported: Vec<ImportedCredentialItem>,
+    /// 跳过的文件及原因
+    pub skipped: Vec<SkippedImportItem>,
+}
+
+/// 成功导入的凭据
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedCredentialItem {
+    pub source: String,
+    pub credential_id: u64,
+}
+
+/// 跳过的导入来源
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedImportItem {
+    pub source: String,
+    pub reason: String,
+}
+
+// ============ 设备授权 ============
+
+/// 发起设备授权请求
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartDeviceAuthRequest {
+    /// IAM Identity Center 起始 URL（可选，未配置时使用 Builder ID）
+    pub start_url: Option<String>,
+
+    /// OIDC Region（可选，未配置时回退到 config.json 的全局 region）
+    pub region: Option<String>,
+
+    /// 新凭据优先级（可选，默认 0）
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// 发起设备授权响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartDeviceAuthResponse {
+    /// 会话 ID（用于轮询授权状态）
+    pub session_id: String,
+    /// 需要用户在浏览器中输入的验证码
+    pub user_code: String,
+    /// 验证地址
+    pub verification_uri: String,
+    /// 已包含验证码的验证地址
+    pub verification_uri_complete: Option<String>,
+    /// 设备码有效期（秒）
+    pub expires_in: i64,
+    /// 建议的轮询间隔（秒）
+    pub interval: u64,
+}
+
+/// 设备授权状态响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceAuthStatusResponse {
+    /// 状态（pending / completed）
+    pub status: String,
+    /// 建议的轮询间隔（秒）
+    pub interval: u64,
+    /// 授权完成后新添加的凭据 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<u64>,
+}
+
 // ============ 余额查询 ============
 
 /// 余额查询响应
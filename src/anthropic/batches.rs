@@ -0,0 +1,178 @@
+//! Message Batches API Handler 函数
+
+use std::sync::Arc;
+
+use axum::{
+    Json as JsonExtractor,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+
+use crate::batch::types::{CreateBatchRequest, ListBatchesQuery};
+use crate::batch::{BatchError, BatchExecutor, BatchItemError};
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::provider::KiroProvider;
+use crate::token;
+
+use super::citations::CitationIndex;
+use super::converter::{ConversionError, convert_request};
+use super::handlers::build_message_response;
+use super::middleware::AppState;
+use super::types::{ErrorResponse, MessagesRequest};
+
+/// 创建基于 KiroProvider 的批处理执行器
+///
+/// 每个批处理请求按非流式 `/v1/messages` 的方式执行，凭据选择与故障转移由 `call_api` 负责
+pub fn create_batch_executor(provider: KiroProvider) -> BatchExecutor {
+    let provider = Arc::new(provider);
+    Arc::new(move |params| {
+        let provider = provider.clone();
+        Box::pin(async move { execute_message_request(&provider, params).await })
+    })
+}
+
+/// 以非流式 `/v1/messages` 的方式执行单个请求，返回 Anthropic Message 响应
+///
+/// 供批处理与 MCP `ask_model` 工具共用
+pub(crate) async fn execute_message_request(
+    provider: &KiroProvider,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, BatchItemError> {
+    let payload: MessagesRequest = serde_json::from_value(params).map_err(|e| {
+        BatchItemError::new("invalid_request_error", format!("请求参数无效: {}", e))
+    })?;
+
+    let conversion_result = convert_request(&payload).map_err(|e| match e {
+        ConversionError::UnsupportedModel(model) => {
+            BatchItemError::new("invalid_request_error", format!("模型不支持: {}", model))
+        }
+        ConversionError::EmptyMessages => {
+            BatchItemError::new("invalid_request_error", "消息列表为空")
+        }
+    })?;
+
+    let kiro_request = KiroRequest::new(conversion_result.conversation_state);
+
+    let citations = CitationIndex::from_request(&payload);
+    let input_tokens = token::count_all_tokens(
+        payload.model.clone(),
+        payload.system,
+        payload.messages,
+        payload.tools,
+    ) as i32;
+
+    let response = provider
+        .call_api(&kiro_request)
+        .await
+        .map_err(|e| BatchItemError::new("api_error", format!("上游 API 调用失败: {}", e)))?;
+    let body_bytes = response
+        .bytes()
+        .await
+        .map_err(|e| BatchItemError::new("api_error", format!("读取响应失败: {}", e)))?;
+
+    let mut message = build_message_response(&body_bytes, &payload.model, input_tokens);
+    citations.annotate_message(&mut message);
+    Ok(message)
+}
+
+/// 批处理管理器未启用时的 503 响应
+fn batch_unavailable() -> Response {
+    tracing::error!("BatchManager 未配置");
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse::new(
+            "service_unavailable",
+            "Message Batches not configured",
+        )),
+    )
+        .into_response()
+}
+
+fn error_response(e: BatchError) -> Response {
+    let (status, error_type) = match &e {
+        BatchError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found_error"),
+        BatchError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, "invalid_request_error"),
+        BatchError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "api_error"),
+    };
+    (status, Json(ErrorResponse::new(error_type, e.to_string()))).into_response()
+}
+
+/// POST /v1/messages/batches
+pub async fn create_batch(
+    State(state): State<AppState>,
+    JsonExtractor(payload): JsonExtractor<CreateBatchRequest>,
+) -> Response {
+    tracing::info!(
+        request_count = %payload.requests.len(),
+        "Received POST /v1/messages/batches request"
+    );
+    let Some(manager) = state.batch_manager.clone() else {
+        return batch_unavailable();
+    };
+    match manager.create(payload) {
+        Ok(batch) => Json(batch).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// GET /v1/messages/batches
+pub async fn list_batches(
+    State(state): State<AppState>,
+    Query(query): Query<ListBatchesQuery>,
+) -> Response {
+    let Some(manager) = state.batch_manager.clone() else {
+        return batch_unavailable();
+    };
+    match manager.list(&query) {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// GET /v1/messages/batches/:batch_id
+pub async fn get_batch(State(state): State<AppState>, Path(batch_id): Path<String>) -> Response {
+    let Some(manager) = state.batch_manager.clone() else {
+        return batch_unavailable();
+    };
+    match manager.get(&batch_id) {
+        Some(batch) => Json(batch).into_response(),
+        None => error_response(BatchError::NotFound(batch_id)),
+    }
+}
+
+/// POST /v1/messages/batches/:batch_id/cancel
+pub async fn cancel_batch(State(state): State<AppState>, Path(batch_id): Path<String>) -> Response {
+    let Some(manager) = state.batch_manager.clone() else {
+        return batch_unavailable();
+    };
+    match manager.cancel(&batch_id) {
+        Ok(batch) => Json(batch).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// GET /v1/messages/batches/:batch_id/results
+///
+/// 以 JSONL 格式返回每个请求的执行结果
+pub async fn get_batch_results(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+) -> Response {
+    let Some(manager) = state.batch_manager.clone() else {
+        return batch_unavailable();
+    };
+    let path = match manager.results_path(&batch_id) {
+        Ok(path) => path,
+        Err(e) => return error_response(e),
+    };
+    match tokio::fs::read(&path).await {
+        Ok(content) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/x-jsonl")],
+            content,
+        )
+            .into_response(),
+        Err(e) => error_response(BatchError::Internal(format!("读取结果文件失败: {}", e))),
+    }
+}
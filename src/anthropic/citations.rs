@@ -0,0 +1,621 @@
+//! 引用（citations）支持
+//!
+//! Kiro 不支持原生引用。代理为搜索结果与启用了 `citations` 的文档句子分配引用标签
+//! （搜索结果 `[S1]`，文档句子 `[D1.2]`），要求模型在使用相应信息的语句后写出标签，
+//! 再把回答中的标签转换为 Anthropic 风格的 `citations`：
+//! - 搜索结果 → `web_search_result_location`
+//! - 纯文本文档 → `char_location`（含换页符 `\f` 的文本按页 → `page_location`）
+//! - 自定义内容文档 → `content_block_location`
+
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+
+use super::types::{Message, MessagesRequest};
+use super::websearch::WebSearchResults;
+
+/// 引用标签最大长度（不含方括号）
+const MAX_LABEL_LEN: usize = 12;
+
+/// 搜索结果 cited_text 的最大字符数
+const MAX_CITED_TEXT_CHARS: usize = 150;
+
+/// 引用标签 → 引用位置
+#[derive(Debug, Default)]
+pub struct CitationIndex {
+    locations: HashMap<String, Value>,
+    search_count: usize,
+}
+
+impl CitationIndex {
+    /// 收集请求中启用了引用的文档句子
+    pub fn from_request(req: &MessagesRequest) -> Self {
+        let mut index = Self::default();
+        for (document_index, block) in document_blocks(&req.messages).enumerate() {
+            if !citations_enabled(block) {
+                continue;
+            }
+            if let Some(document) = parse_document(block, document_index) {
+                for chunk in document.chunks {
+                    index.locations.insert(chunk.label, chunk.location);
+                }
+            }
+        }
+        index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// 为一次搜索的结果分配引用标签，返回与结果一一对应的标签
+    pub fn add_search_results(&mut self, results: &Option<WebSearchResults>) -> Vec<String> {
+        let Some(results) = results else {
+            return Vec::new();
+        };
+
+        results
+            .results
+            .iter()
+            .map(|result| {
+                self.search_count += 1;
+                let label = format!("S{}", self.search_count);
+                let cited_text = result.snippet.as_deref().unwrap_or(&result.title);
+                self.locations.insert(
+                    label.clone(),
+                    json!({
+                        "type": "web_search_result_location",
+                        "url": result.url,
+                        "title": result.title,
+                        "encrypted_index": hex::encode(&label),
+                        "cited_text": truncate_chars(cited_text, MAX_CITED_TEXT_CHARS)
+                    }),
+                );
+                label
+            })
+            .collect()
+    }
+
+    /// 将模型输出的文本拆分为带 `citations` 的文本块，并移除引用标签
+    pub fn annotate(&self, text: &str) -> Vec<Value> {
+        let (segments, _) = self.split_segments(text, true);
+        segments_to_blocks(segments)
+    }
+
+    /// 对内容块列表中的文本块应用引用，其余块保持不变
+    pub fn annotate_content(&self, content: Vec<Value>) -> Vec<Value> {
+        if self.is_empty() {
+            return content;
+        }
+        content
+            .into_iter()
+            .flat_map(|block| match block["text"].as_str() {
+                Some(text) if block["type"] == "text" => self.annotate(text),
+                _ => vec![block],
+            })
+            .collect()
+    }
+
+    /// 对 Anthropic Message 响应的 content 应用引用
+    pub fn annotate_message(&self, message: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(content) = message.get_mut("content")
+            && let Value::Array(blocks) = content.take()
+        {
+            *content = Value::Array(self.annotate_content(blocks));
+        }
+    }
+
+    /// 扫描文本中的引用标签
+    ///
+    /// `finished` 为 false 时，末尾可能未写完的标签不会输出，而是作为剩余文本返回
+    fn split_segments<'a>(&self, text: &'a str, finished: bool) -> (Vec<Segment>, &'a str) {
+        let mut segments = Vec::new();
+        let mut text_start = 0;
+        let mut search_from = 0;
+
+        while let Some(offset) = text[search_from..].find('[') {
+            let pos = search_from + offset;
+            match parse_marker(&text[pos..]) {
+                Marker::Complete(label, len) if self.locations.contains_key(label) => {
+                    push_text_segment(&mut segments, &text[text_start..pos]);
+                    segments.push(Segment::Citation(self.locations[label].clone()));
+                    search_from = pos + len;
+                    text_start = search_from;
+                }
+                Marker::Partial if !finished => {
+                    push_text_segment(&mut segments, &text[text_start..pos]);
+                    return (segments, &text[pos..]);
+                }
+                _ => search_from = pos + 1,
+            }
+        }
+
+        push_text_segment(&mut segments, &text[text_start..]);
+        (segments, "")
+    }
+}
+
+/// 文本片段或其后附带的引用
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Citation(Value),
+}
+
+fn push_text_segment(segments: &mut Vec<Segment>, text: &str) {
+    if !text.is_empty() {
+        segments.push(Segment::Text(text.to_string()));
+    }
+}
+
+/// 流式输出时的引用标签解析器
+///
+/// 标签可能被拆分到多个增量中，未写完的部分会暂存到下一次输入
+#[derive(Debug)]
+pub struct CitationStream {
+    index: CitationIndex,
+    buffer: String,
+    /// 当前文本块已附带引用，后续文本需开启新的文本块
+    pub block_cited: bool,
+}
+
+impl CitationStream {
+    pub fn new(index: CitationIndex) -> Self {
+        Self {
+            index,
+            buffer: String::new(),
+            block_cited: false,
+        }
+    }
+
+    /// 输入一段增量文本，返回可以立即输出的片段
+    pub fn push(&mut self, text: &str) -> Vec<Segment> {
+        self.buffer.push_str(text);
+        let buffer = std::mem::take(&mut self.buffer);
+        let (segments, rest) = self.index.split_segments(&buffer, false);
+        self.buffer = rest.to_string();
+        segments
+    }
+
+    /// 输出暂存的剩余文本
+    pub fn finish(&mut self) -> Vec<Segment> {
+        let buffer = std::mem::take(&mut self.buffer);
+        self.index.split_segments(&buffer, true).0
+    }
+}
+
+/// 引用标签解析结果
+enum Marker<'a> {
+    /// 完整标签及其总长度（含方括号）
+    Complete(&'a str, usize),
+    /// 可能是尚未写完的标签
+    Partial,
+    Invalid,
+}
+
+/// 解析以 `[` 开头的引用标签，如 `[S3]`、`[D1.2]`
+fn parse_marker(text: &str) -> Marker<'_> {
+    let body = &text[1..];
+    let mut chars = body.char_indices();
+    match chars.next() {
+        Some((_, 'S' | 'D')) => {}
+        Some(_) => return Marker::Invalid,
+        None => return Marker::Partial,
+    }
+    for (i, c) in chars {
+        if c == ']' {
+            return if i > 1 {
+                Marker::Complete(&body[..i], i + 2)
+            } else {
+                Marker::Invalid
+            };
+        }
+        if !(c.is_ascii_digit() || c == '.') || i > MAX_LABEL_LEN {
+            return Marker::Invalid;
+        }
+    }
+    if body.len() > MAX_LABEL_LEN {
+        Marker::Invalid
+    } else {
+        Marker::Partial
+    }
+}
+
+/// 将片段合并为文本块：引用附加到其前面的文本块
+fn segments_to_blocks(segments: Vec<Segment>) -> Vec<Value> {
+    let mut blocks: Vec<(String, Vec<Value>)> = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => match blocks.last_mut() {
+                Some((existing, citations)) if citations.is_empty() => existing.push_str(&text),
+                _ => blocks.push((text, Vec::new())),
+            },
+            Segment::Citation(citation) => {
+                if let Some((_, citations)) = blocks.last_mut() {
+                    citations.push(citation);
+                }
+            }
+        }
+    }
+
+    blocks
+        .into_iter()
+        .map(|(text, citations)| {
+            if citations.is_empty() {
+                json!({"type": "text", "text": text})
+            } else {
+                json!({"type": "text", "text": text, "citations": citations})
+            }
+        })
+        .collect()
+}
+
+/// 文档中可被引用的片段
+struct Chunk {
+    label: String,
+    text: String,
+    location: Value,
+}
+
+/// 解析后的文档
+struct Document {
+    title: Option<String>,
+    context: Option<String>,
+    /// 按页划分时的页码（与 chunks 一一对应）
+    pages: Vec<usize>,
+    chunks: Vec<Chunk>,
+}
+
+/// 按请求顺序遍历所有用户消息中的文档块
+fn document_blocks(messages: &[Message]) -> impl Iterator<Item = &Value> {
+    messages
+        .iter()
+        .filter(|m| m.role == "user")
+        .filter_map(|m| m.content.as_array())
+        .flatten()
+        .filter(|block| block["type"] == "document")
+}
+
+fn citations_enabled(block: &Value) -> bool {
+    block["citations"]["enabled"].as_bool().unwrap_or(false)
+}
+
+/// 解析文档块并划分可引用片段；不支持的来源（如 base64 PDF、URL）返回 None
+fn parse_document(block: &Value, document_index: usize) -> Option<Document> {
+    let title = block["title"].as_str().map(str::to_string);
+    let context = block["context"].as_str().map(str::to_string);
+    let source = &block["source"];
+    let label = |n: usize| format!("D{}.{}", document_index + 1, n);
+
+    let mut pages = Vec::new();
+    let mut chunks = Vec::new();
+    match source["type"].as_str()? {
+        "text" => {
+            let data = source["data"].as_str()?;
+            if data.contains('\u{c}') {
+                for (page_index, page) in data.split('\u{c}').enumerate() {
+                    for (start, end) in split_sentences(page) {
+                        let text = char_slice(page, start, end);
+                        chunks.push(Chunk {
+                            label: label(chunks.len() + 1),
+                            location: json!({
+                                "type": "page_location",
+                                "cited_text": text,
+                                "document_index": document_index,
+                                "document_title": title,
+                                "start_page_number": page_index + 1,
+                                "end_page_number": page_index + 2
+                            }),
+                            text,
+                        });
+                        pages.push(page_index + 1);
+                    }
+                }
+            } else {
+                for (start, end) in split_sentences(data) {
+                    let text = char_slice(data, start, end);
+                    chunks.push(Chunk {
+                        label: label(chunks.len() + 1),
+                        location: json!({
+                            "type": "char_location",
+                            "cited_text": text,
+                            "document_index": document_index,
+                            "document_title": title,
+                            "start_char_index": start,
+                            "end_char_index": end
+                        }),
+                        text,
+                    });
+                }
+            }
+        }
+        "content" => {
+            let blocks: Vec<String> = match &source["content"] {
+                Value::String(text) => vec![text.clone()],
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| item["text"].as_str().unwrap_or_default().to_string())
+                    .collect(),
+                _ => return None,
+            };
+            for (block_index, text) in blocks.into_iter().enumerate() {
+                if text.trim().is_empty() {
+                    continue;
+                }
+                chunks.push(Chunk {
+                    label: label(chunks.len() + 1),
+                    location: json!({
+                        "type": "content_block_location",
+                        "cited_text": text,
+                        "document_index": document_index,
+                        "document_title": title,
+                        "start_block_index": block_index,
+                        "end_block_index": block_index + 1
+                    }),
+                    text,
+                });
+            }
+        }
+        _ => return None,
+    }
+
+    Some(Document {
+        title,
+        context,
+        pages,
+        chunks,
+    })
+}
+
+/// 将文档块渲染为提供给模型的文本
+fn render_document(block: &Value, document_index: usize) -> Option<String> {
+    let document = parse_document(block, document_index)?;
+    let cited = citations_enabled(block);
+
+    let mut text = format!("<document index=\"{}\"", document_index + 1);
+    if let Some(title) = &document.title {
+        text.push_str(&format!(" title=\"{}\"", title));
+    }
+    text.push_str(">\n");
+    if let Some(context) = &document.context {
+        text.push_str(&format!("Context: {}\n", context));
+    }
+    if cited {
+        text.push_str(&format!(
+            "Each passage below is labeled like [D{}.1]. When your answer uses information \
+             from this document, cite it by writing the passage label right after the statement.\n",
+            document_index + 1
+        ));
+    }
+
+    let mut current_page = 0;
+    for (i, chunk) in document.chunks.iter().enumerate() {
+        if let Some(&page) = document.pages.get(i)
+            && page != current_page
+        {
+            current_page = page;
+            text.push_str(&format!("--- Page {} ---\n", page));
+        }
+        if cited {
+            text.push_str(&format!("[{}] {}\n", chunk.label, chunk.text));
+        } else {
+            text.push_str(&chunk.text);
+            text.push('\n');
+        }
+    }
+    text.push_str("</document>");
+    Some(text)
+}
+
+/// 将消息中的文档块展开为文本块；请求中没有文档时返回 None
+pub fn inline_documents(messages: &[Message]) -> Option<Vec<Message>> {
+    document_blocks(messages).next()?;
+
+    let mut document_index = 0;
+    let messages = messages
+        .iter()
+        .map(|message| {
+            let Some(blocks) = message
+                .content
+                .as_array()
+                .filter(|_| message.role == "user")
+            else {
+                return message.clone();
+            };
+            let content = blocks
+                .iter()
+                .filter_map(|block| {
+                    if block["type"] != "document" {
+                        return Some(block.clone());
+                    }
+                    let rendered = render_document(block, document_index);
+                    if rendered.is_none() {
+                        tracing::warn!(
+                            "不支持的文档来源类型: {}，已忽略",
+                            block["source"]["type"].as_str().unwrap_or("unknown")
+                        );
+                    }
+                    document_index += 1;
+                    rendered.map(|text| json!({"type": "text", "text": text}))
+                })
+                .collect();
+            Message {
+                role: message.role.clone(),
+                content: Value::Array(content),
+            }
+        })
+        .collect();
+    Some(messages)
+}
+
+/// 按句子划分文本，返回字符索引区间 [start, end)（不含首尾空白）
+fn split_sentences(text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+
+    let mut push_range = |start: usize, end: usize| {
+        let mut s = start;
+        let mut e = end;
+        while s < e && chars[s].is_whitespace() {
+            s += 1;
+        }
+        while e > s && chars[e - 1].is_whitespace() {
+            e -= 1;
+        }
+        if s < e {
+            ranges.push((s, e));
+        }
+    };
+
+    for i in 0..chars.len() {
+        let c = chars[i];
+        let at_boundary = match c {
+            '\n' => true,
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' => chars.get(i + 1).is_none_or(|next| next.is_whitespace()),
+            _ => false,
+        };
+        if at_boundary {
+            push_range(start, i + 1);
+            start = i + 1;
+        }
+    }
+    push_range(start, chars.len());
+    ranges
+}
+
+/// 按字符索引截取子串
+fn char_slice(text: &str, start: usize, end: usize) -> String {
+    text.chars().skip(start).take(end - start).collect()
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anthropic::websearch::WebSearchResult;
+
+    fn search_results() -> Option<WebSearchResults> {
+        Some(WebSearchResults {
+            results: vec![WebSearchResult {
+                title: "Rust".to_string(),
+                url: "https://www.rust-lang.org".to_string(),
+                snippet: Some("A language empowering everyone.".to_string()),
+                published_date: None,
+                id: None,
+                domain: None,
+                max_verbatim_word_limit: None,
+                public_domain: None,
+            }],
+            total_results: None,
+            query: None,
+            error: None,
+        })
+    }
+
+    fn document_request(source: Value) -> MessagesRequest {
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "document", "title": "Notes", "source": source, "citations": {"enabled": true}},
+                    {"type": "text", "text": "Summarize."}
+                ]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_annotate_web_search_results() {
+        let mut index = CitationIndex::default();
+        let labels = index.add_search_results(&search_results());
+        assert_eq!(labels, vec!["S1"]);
+
+        let blocks = index.annotate("Rust is fast [S1]. Unknown [S9] stays.");
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0]["text"], "Rust is fast ");
+        let citation = &blocks[0]["citations"][0];
+        assert_eq!(citation["type"], "web_search_result_location");
+        assert_eq!(citation["url"], "https://www.rust-lang.org");
+        assert_eq!(citation["cited_text"], "A language empowering everyone.");
+        assert_eq!(blocks[1]["text"], ". Unknown [S9] stays.");
+        assert!(blocks[1].get("citations").is_none());
+    }
+
+    #[test]
+    fn test_char_location_documents() {
+        let req = document_request(json!({
+            "type": "text",
+            "media_type": "text/plain",
+            "data": "The grass is green. The sky is blue."
+        }));
+        let index = CitationIndex::from_request(&req);
+
+        let blocks = index.annotate("The sky is blue [D1.2][D1.1].");
+        let citations = blocks[0]["citations"].as_array().unwrap();
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[0]["type"], "char_location");
+        assert_eq!(citations[0]["cited_text"], "The sky is blue.");
+        assert_eq!(citations[0]["start_char_index"], 20);
+        assert_eq!(citations[0]["end_char_index"], 36);
+        assert_eq!(citations[1]["start_char_index"], 0);
+        assert_eq!(citations[0]["document_title"], "Notes");
+
+        let messages = inline_documents(&req.messages).unwrap();
+        let text = messages[0].content[0]["text"].as_str().unwrap();
+        assert!(text.contains("[D1.1] The grass is green.\n[D1.2] The sky is blue."));
+        assert_eq!(messages[0].content[1]["text"], "Summarize.");
+    }
+
+    #[test]
+    fn test_page_location_documents() {
+        let req = document_request(json!({
+            "type": "text",
+            "media_type": "text/plain",
+            "data": "Page one.\u{c}Page two."
+        }));
+        let index = CitationIndex::from_request(&req);
+        let blocks = index.annotate("Second [D1.2]");
+        let citation = &blocks[0]["citations"][0];
+        assert_eq!(citation["type"], "page_location");
+        assert_eq!(citation["start_page_number"], 2);
+        assert_eq!(citation["end_page_number"], 3);
+    }
+
+    #[test]
+    fn test_citation_stream_buffers_partial_markers() {
+        let mut index = CitationIndex::default();
+        index.add_search_results(&search_results());
+        let mut stream = CitationStream::new(index);
+
+        let first = stream.push("Rust is fast [S");
+        assert_eq!(first, vec![Segment::Text("Rust is fast ".to_string())]);
+        let second = stream.push("1] and safe [x]");
+        assert!(
+            matches!(&second[0], Segment::Citation(c) if c["url"] == "https://www.rust-lang.org")
+        );
+        assert_eq!(second[1], Segment::Text(" and safe [x]".to_string()));
+        assert!(stream.finish().is_empty());
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("Version 1.80 shipped. 你好。\nNext"),
+            vec![(0, 21), (22, 25), (26, 30)]
+        );
+    }
+}
@@ -12,7 +12,9 @@ use crate::kiro::model::requests::tool::{
     InputSchema, Tool, ToolResult, ToolSpecification, ToolUseEntry,
 };
 
+use super::citations;
 use super::types::{ContentBlock, MessagesRequest, Thinking};
+use super::{webfetch, websearch};
 
 /// 模型映射：将 Anthropic 模型名映射到 Kiro 模型 ID
 ///
@@ -119,6 +121,15 @@ fn create_placeholder_tool(name: &str) -> Tool {
 
 /// 将 Anthropic 请求转换为 Kiro 请求
 pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, ConversionError> {
+    // 0. 文档块展开为文本（启用引用时附带句子标签）
+    if let Some(messages) = citations::inline_documents(&req.messages) {
+        let req = MessagesRequest {
+            messages,
+            ..req.clone()
+        };
+        return convert_request(&req);
+    }
+
     // 1. 映射模型
     let model_id = map_model(&req.model)
         .ok_or_else(|| ConversionError::UnsupportedModel(req.model.clone()))?;
@@ -387,6 +398,26 @@ fn convert_tools(tools: &Option<Vec<super::types::Tool>>) -> Vec<Tool> {
     tools
         .iter()
         .map(|t| {
+            // web_search / web_fetch 服务端工具没有 input_schema，转换为普通工具由代理执行
+            if t.is_web_search() {
+                return Tool {
+                    tool_specification: ToolSpecification {
+                        name: t.name.clone(),
+                        description: websearch::WEB_SEARCH_TOOL_DESCRIPTION.to_string(),
+                        input_schema: InputSchema::from_json(websearch::web_search_input_schema()),
+                    },
+                };
+            }
+            if t.is_web_fetch() {
+                return Tool {
+                    tool_specification: ToolSpecification {
+                        name: t.name.clone(),
+                        description: webfetch::WEB_FETCH_TOOL_DESCRIPTION.to_string(),
+                        input_schema: InputSchema::from_json(webfetch::web_fetch_input_schema()),
+                    },
+                };
+            }
+
             let description = t.description.clone();
             // 限制描述长度为 10000 字符（安全截断 UTF-8，单次遍历）
             let description = match description.char_indices().nth(10000) {
@@ -587,6 +618,35 @@ fn convert_assistant_message(
                                 tool_uses.push(ToolUseEntry::new(id, name).with_input(input));
                             }
                         }
+                        // 服务端工具已由代理执行，结果以文本形式保留在上下文中
+                        "server_tool_use" => {
+                            let name = block.name.as_deref().unwrap_or("web_search");
+                            let argument = block
+                                .input
+                                .as_ref()
+                                .and_then(|i| i.get("query").or_else(|| i.get("url")))
+                                .and_then(|q| q.as_str())
+                                .unwrap_or_default();
+                            text_content.push_str(&format!("\n[{}: {}]\n", name, argument));
+                        }
+                        "web_search_tool_result" => {
+                            if let Some(serde_json::Value::Array(results)) = &block.content {
+                                for result in results {
+                                    let title = result["title"].as_str().unwrap_or_default();
+                                    let url = result["url"].as_str().unwrap_or_default();
+                                    text_content.push_str(&format!("- {} ({})\n", title, url));
+                                }
+                            }
+                        }
+                        "web_fetch_tool_result" => {
+                            if let Some(result) = &block.content {
+                                let title = result["content"]["title"].as_str().unwrap_or_default();
+                                let url = result["url"].as_str().unwrap_or_default();
+                                if !url.is_empty() {
+                                    text_content.push_str(&format!("- {} ({})\n", title, url));
+                                }
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -1126,4 +1186,80 @@ mod tests {
         assert_eq!(tool_uses.len(), 1);
         assert_eq!(tool_uses[0].tool_use_id, "toolu_02XYZ");
     }
+
+    #[test]
+    fn test_convert_tools_web_search() {
+        use super::super::types::Tool as AnthropicTool;
+
+        let tools = Some(vec![AnthropicTool {
+            tool_type: Some("web_search_20250305".to_string()),
+            name: "web_search".to_string(),
+            description: String::new(),
+            input_schema: Default::default(),
+            max_uses: Some(3),
+            allowed_domains: None,
+            blocked_domains: None,
+            max_content_tokens: None,
+        }]);
+
+        let result = convert_tools(&tools);
+        assert_eq!(result.len(), 1);
+        let spec = &result[0].tool_specification;
+        assert_eq!(spec.name, "web_search");
+        assert_eq!(spec.description, websearch::WEB_SEARCH_TOOL_DESCRIPTION);
+    }
+
+    #[test]
+    fn test_convert_tools_web_fetch() {
+        use super::super::types::Tool as AnthropicTool;
+
+        let tools = Some(vec![AnthropicTool {
+            tool_type: Some("web_fetch_20250910".to_string()),
+            name: "web_fetch".to_string(),
+            description: String::new(),
+            input_schema: Default::default(),
+            max_uses: Some(5),
+            allowed_domains: Some(vec!["docs.rs".to_string()]),
+            blocked_domains: None,
+            max_content_tokens: Some(10000),
+        }]);
+
+        let result = convert_tools(&tools);
+        assert_eq!(result.len(), 1);
+        let spec = &result[0].tool_specification;
+        assert_eq!(spec.name, "web_fetch");
+        assert_eq!(spec.description, webfetch::WEB_FETCH_TOOL_DESCRIPTION);
+    }
+
+    #[test]
+    fn test_convert_assistant_message_flattens_server_tool_blocks() {
+        use super::super::types::Message as AnthropicMessage;
+
+        // server_tool_use 与 web_search/web_fetch_tool_result 由代理执行，转换为文本保留在上下文中
+        let msg = AnthropicMessage {
+            role: "assistant".to_string(),
+            content: serde_json::json!([
+                {"type": "server_tool_use", "id": "srvtoolu_01", "name": "web_search", "input": {"query": "rust"}},
+                {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_01", "content": [
+                    {"type": "web_search_result", "title": "Rust", "url": "https://www.rust-lang.org"}
+                ]},
+                {"type": "server_tool_use", "id": "srvtoolu_02", "name": "web_fetch", "input": {"url": "https://www.rust-lang.org"}},
+                {"type": "web_fetch_tool_result", "tool_use_id": "srvtoolu_02", "content": {
+                    "type": "web_fetch_result",
+                    "url": "https://www.rust-lang.org",
+                    "content": {"type": "document", "title": "Rust Programming Language"}
+                }},
+                {"type": "text", "text": "Rust is a language."}
+            ]),
+        };
+
+        let result = convert_assistant_message(&msg).expect("应该成功转换");
+        let content = &result.assistant_response_message.content;
+        assert!(content.contains("[web_search: rust]"));
+        assert!(content.contains("- Rust (https://www.rust-lang.org)"));
+        assert!(content.contains("[web_fetch: https://www.rust-lang.org]"));
+        assert!(content.contains("- Rust Programming Language (https://www.rust-lang.org)"));
+        assert!(content.ends_with("Rust is a language."));
+        assert!(result.assistant_response_message.tool_uses.is_none());
+    }
 }
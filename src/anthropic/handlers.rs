@@ -5,6 +5,7 @@ use std::convert::Infallible;
 use crate::kiro::model::events::Event;
 use crate::kiro::model::requests::kiro::KiroRequest;
 use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::request_log::{self, RequestSummary};
 use crate::token;
 use axum::{
     Json as JsonExtractor,
@@ -22,11 +23,12 @@ use uuid::Uuid;
 
 use super::converter::{ConversionError, convert_request};
 use super::middleware::AppState;
+use super::citations::CitationIndex;
+use super::server_tools;
 use super::stream::{SseEvent, StreamContext};
 use super::types::{
     CountTokensRequest, CountTokensResponse, ErrorResponse, MessagesRequest, Model, ModelsResponse,
 };
-use super::websearch;
 
 /// GET /v1/models
 ///
@@ -100,136 +102,112 @@ pub async fn post_messages(
         }
     };
 
-    // 获取凭据上下文以记录使用的凭据 ID
-    let credential_id = match provider.token_manager().acquire_context().await {
-        Ok(ctx) => ctx.id,
-        Err(e) => {
-            tracing::error!("获取凭据上下文失败: {}", e);
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(ErrorResponse::new(
-                    "service_unavailable",
-                    "No available credentials",
-                )),
-            )
-                .into_response();
-        }
+    // 请求日志记录实际处理请求的凭据与 API 区域
+    let summary = RequestSummary {
+        model: payload.model.clone(),
+        max_tokens: payload.max_tokens,
+        stream: payload.stream,
+        message_count: payload.messages.len(),
     };
+    request_log::track(state.request_logger.as_deref(), summary, async move {
+        // 声明了 web_search / web_fetch 服务端工具：由服务端工具循环代为执行
+        if server_tools::has_server_tools(&payload) {
+            tracing::info!("检测到服务端工具，路由到服务端工具循环");
+
+            // 估算输入 tokens
+            let input_tokens = token::count_all_tokens(
+                payload.model.clone(),
+                payload.system.clone(),
+                payload.messages.clone(),
+                payload.tools.clone(),
+            ) as i32;
+
+            return if payload.stream {
+                server_tools::handle_server_tool_stream(provider, payload, input_tokens).await
+            } else {
+                server_tools::handle_server_tool_json(provider, payload, input_tokens).await
+            };
+        }
 
-    // 记录请求日志
-    if let Some(logger) = &state.request_logger {
-        logger.log_request(crate::request_log::RequestLogEntry {
-            id: uuid::Uuid::new_v4().to_string(),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            model: payload.model.clone(),
-            max_tokens: payload.max_tokens,
-            stream: payload.stream,
-            message_count: payload.messages.len(),
-            credential_id,
-            success: true, // 默认成功，后续可以根据实际结果更新
-        });
-    }
+        // 转换请求
+        let conversion_result = match convert_request(&payload) {
+            Ok(result) => result,
+            Err(e) => {
+                let (error_type, message) = match &e {
+                    ConversionError::UnsupportedModel(model) => {
+                        ("invalid_request_error", format!("模型不支持: {}", model))
+                    }
+                    ConversionError::EmptyMessages => {
+                        ("invalid_request_error", "消息列表为空".to_string())
+                    }
+                };
+                tracing::warn!("请求转换失败: {}", e);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new(error_type, message)),
+                )
+                    .into_response();
+            }
+        };
 
-    // 检查是否为 WebSearch 请求
-    if websearch::has_web_search_tool(&payload) {
-        tracing::info!("检测到 WebSearch 工具，路由到 WebSearch 处理");
+        // 构建 Kiro 请求
+        let kiro_request = KiroRequest::new(conversion_result.conversation_state);
+
+        // 启用了引用的文档句子
+        let citations = CitationIndex::from_request(&payload);
 
         // 估算输入 tokens
         let input_tokens = token::count_all_tokens(
             payload.model.clone(),
-            payload.system.clone(),
-            payload.messages.clone(),
-            payload.tools.clone(),
+            payload.system,
+            payload.messages,
+            payload.tools,
         ) as i32;
 
-        return websearch::handle_websearch_request(provider, &payload, input_tokens).await;
-    }
-
-    // 转换请求
-    let conversion_result = match convert_request(&payload) {
-        Ok(result) => result,
-        Err(e) => {
-            let (error_type, message) = match &e {
-                ConversionError::UnsupportedModel(model) => {
-                    ("invalid_request_error", format!("模型不支持: {}", model))
-                }
-                ConversionError::EmptyMessages => {
-                    ("invalid_request_error", "消息列表为空".to_string())
-                }
-            };
-            tracing::warn!("请求转换失败: {}", e);
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(error_type, message)),
+        // 检查是否启用了thinking
+        let thinking_enabled = payload
+            .thinking
+            .as_ref()
+            .map(|t| t.thinking_type == "enabled")
+            .unwrap_or(false);
+
+        if payload.stream {
+            // 流式响应
+            handle_stream_request(
+                provider,
+                &kiro_request,
+                &payload.model,
+                input_tokens,
+                thinking_enabled,
+                citations,
             )
-                .into_response();
-        }
-    };
-
-    // 构建 Kiro 请求
-    let kiro_request = KiroRequest {
-        conversation_state: conversion_result.conversation_state,
-        profile_arn: state.profile_arn.clone(),
-    };
-
-    let request_body = match serde_json::to_string(&kiro_request) {
-        Ok(body) => body,
-        Err(e) => {
-            tracing::error!("序列化请求失败: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "internal_error",
-                    format!("序列化请求失败: {}", e),
-                )),
+            .await
+        } else {
+            // 非流式响应
+            handle_non_stream_request(
+                provider,
+                &kiro_request,
+                &payload.model,
+                input_tokens,
+                &citations,
             )
-                .into_response();
+            .await
         }
-    };
-
-    tracing::debug!("Kiro request body: {}", request_body);
-
-    // 估算输入 tokens
-    let input_tokens = token::count_all_tokens(
-        payload.model.clone(),
-        payload.system,
-        payload.messages,
-        payload.tools,
-    ) as i32;
-
-    // 检查是否启用了thinking
-    let thinking_enabled = payload
-        .thinking
-        .as_ref()
-        .map(|t| t.thinking_type == "enabled")
-        .unwrap_or(false);
-
-    if payload.stream {
-        // 流式响应
-        handle_stream_request(
-            provider,
-            &request_body,
-            &payload.model,
-            input_tokens,
-            thinking_enabled,
-        )
-        .await
-    } else {
-        // 非流式响应
-        handle_non_stream_request(provider, &request_body, &payload.model, input_tokens).await
-    }
+    })
+    .await
 }
 
 /// 处理流式请求
 async fn handle_stream_request(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
-    request_body: &str,
+    request: &KiroRequest,
     model: &str,
     input_tokens: i32,
     thinking_enabled: bool,
+    citations: CitationIndex,
 ) -> Response {
     // 调用 Kiro API（支持多凭据故障转移）
-    let response = match provider.call_api_stream(request_body).await {
+    let response = match provider.call_api_stream(request).await {
         Ok(resp) => resp,
         Err(e) => {
             tracing::error!("Kiro API 调用失败: {}", e);
@@ -245,7 +223,8 @@ async fn handle_stream_request(
     };
 
     // 创建流处理上下文
-    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled);
+    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled)
+        .with_citations(citations);
 
     // 生成初始事件
     let initial_events = ctx.generate_initial_events();
@@ -369,12 +348,13 @@ const CONTEXT_WINDOW_SIZE: i32 = 200_000;
 /// 处理非流式请求
 async fn handle_non_stream_request(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
-    request_body: &str,
+    request: &KiroRequest,
     model: &str,
     input_tokens: i32,
+    citations: &CitationIndex,
 ) -> Response {
     // 调用 Kiro API（支持多凭据故障转移）
-    let response = match provider.call_api(request_body).await {
+    let response = match provider.call_api(request).await {
         Ok(resp) => resp,
         Err(e) => {
             tracing::error!("Kiro API 调用失败: {}", e);
@@ -405,9 +385,23 @@ async fn handle_non_stream_request(
         }
     };
 
+    let mut response_body = build_message_response(&body_bytes, model, input_tokens);
+    citations.annotate_message(&mut response_body);
+
+    (StatusCode::OK, Json(response_body)).into_response()
+}
+
+/// 将上游非流式响应体（AWS Event Stream）解析为 Anthropic Messages 响应
+///
+/// 供 `/v1/messages` 非流式请求与 Message Batches 后台任务共用
+pub(crate) fn build_message_response(
+    body_bytes: &[u8],
+    model: &str,
+    input_tokens: i32,
+) -> serde_json::Value {
     // 解析事件流
     let mut decoder = EventStreamDecoder::new();
-    if let Err(e) = decoder.feed(&body_bytes) {
+    if let Err(e) = decoder.feed(body_bytes) {
         tracing::warn!("缓冲区溢出: {}", e);
     }
 
@@ -511,7 +505,7 @@ async fn handle_non_stream_request(
     let final_input_tokens = context_input_tokens.unwrap_or(input_tokens);
 
     // 构建 Anthropic 响应
-    let response_body = json!({
+    json!({
         "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
         "type": "message",
         "role": "assistant",
@@ -523,9 +517,7 @@ async fn handle_non_stream_request(
             "input_tokens": final_input_tokens,
             "output_tokens": output_tokens
         }
-    });
-
-    (StatusCode::OK, Json(response_body)).into_response()
+    })
 }
 
 /// POST /v1/messages/count_tokens
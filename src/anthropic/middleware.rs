@@ -10,6 +10,7 @@ use axum::{
     response::{IntoResponse, Json, Response},
 };
 
+use crate::batch::BatchManager;
 use crate::common::auth;
 use crate::kiro::provider::KiroProvider;
 use crate::request_log::RequestLogger;
@@ -24,10 +25,10 @@ pub struct AppState {
     /// Kiro Provider（可选，用于实际 API 调用）
     /// 内部使用 MultiTokenManager，已支持线程安全的多凭据管理
     pub kiro_provider: Option<Arc<KiroProvider>>,
-    /// Profile ARN（可选，用于请求）
-    pub profile_arn: Option<String>,
     /// 请求日志记录器（可选，用于记录请求）
     pub request_logger: Option<Arc<RequestLogger>>,
+    /// Message Batches 管理器（可选，用于批处理 API）
+    pub batch_manager: Option<Arc<BatchManager>>,
 }
 
 impl AppState {
@@ -36,8 +37,8 @@ impl AppState {
         Self {
             api_key: api_key.into(),
             kiro_provider: None,
-            profile_arn: None,
             request_logger: None,
+            batch_manager: None,
         }
     }
 
@@ -47,17 +48,17 @@ impl AppState {
         self
     }
 
-    /// 设置 Profile ARN
-    pub fn with_profile_arn(mut self, arn: impl Into<String>) -> Self {
-        self.profile_arn = Some(arn.into());
-        self
-    }
-
     /// 设置请求日志记录器
     pub fn with_request_logger(mut self, logger: Arc<RequestLogger>) -> Self {
         self.request_logger = Some(logger);
         self
     }
+
+    /// 设置 Message Batches 管理器
+    pub fn with_batch_manager(mut self, manager: Arc<BatchManager>) -> Self {
+        self.batch_manager = Some(manager);
+        self
+    }
 }
 
 /// API Key 认证中间件
@@ -6,6 +6,7 @@
 //! - `GET /v1/models` - 获取可用模型列表
 //! - `POST /v1/messages` - 创建消息（对话）
 //! - `POST /v1/messages/count_tokens` - 计算 token 数量
+//! - `/v1/messages/batches` - Message Batches API（创建、查询、列表、取消、结果）
 //!
 //! # 使用示例
 //! ```rust,ignore
@@ -16,12 +17,21 @@
 //! axum::serve(listener, app).await?;
 //! ```
 
+mod batches;
+mod citations;
 mod converter;
 mod handlers;
 mod middleware;
 mod router;
+mod search_backend;
+mod server_tools;
 mod stream;
 pub mod types;
-mod websearch;
+mod webfetch;
+pub(crate) mod websearch;
 
+pub use batches::create_batch_executor;
+pub(crate) use batches::execute_message_request;
 pub use router::create_router_with_provider;
+pub use search_backend::init_web_search;
+pub use webfetch::init_web_fetch;
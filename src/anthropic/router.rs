@@ -9,10 +9,12 @@ use axum::{
 
 use std::sync::Arc;
 
+use crate::batch::BatchManager;
 use crate::kiro::provider::KiroProvider;
 use crate::request_log::RequestLogger;
 
 use super::{
+    batches::{cancel_batch, create_batch, get_batch, get_batch_results, list_batches},
     handlers::{count_tokens, get_models, post_messages},
     middleware::{AppState, auth_middleware, cors_layer},
 };
@@ -26,6 +28,11 @@ const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 /// - `GET /v1/models` - 获取可用模型列表
 /// - `POST /v1/messages` - 创建消息（对话）
 /// - `POST /v1/messages/count_tokens` - 计算 token 数量
+/// - `POST /v1/messages/batches` - 创建批处理
+/// - `GET /v1/messages/batches` - 列出批处理
+/// - `GET /v1/messages/batches/:batch_id` - 获取批处理
+/// - `POST /v1/messages/batches/:batch_id/cancel` - 取消批处理
+/// - `GET /v1/messages/batches/:batch_id/results` - 获取批处理结果（JSONL）
 ///
 /// # 认证
 /// 所有 `/v1` 路径需要 API Key 认证，支持：
@@ -35,30 +42,35 @@ const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 /// # 参数
 /// - `api_key`: API 密钥，用于验证客户端请求
 /// - `kiro_provider`: 可选的 KiroProvider，用于调用上游 API
+/// - `batch_manager`: 可选的批处理管理器，未配置时批处理端点返回 503
 
 /// 创建带有 KiroProvider 的 Anthropic API 路由
 pub fn create_router_with_provider(
     api_key: impl Into<String>,
     kiro_provider: Option<KiroProvider>,
-    profile_arn: Option<String>,
     request_logger: Option<Arc<RequestLogger>>,
+    batch_manager: Option<Arc<BatchManager>>,
 ) -> Router {
     let mut state = AppState::new(api_key);
     if let Some(provider) = kiro_provider {
         state = state.with_kiro_provider(provider);
     }
-    if let Some(arn) = profile_arn {
-        state = state.with_profile_arn(arn);
-    }
     if let Some(logger) = request_logger {
         state = state.with_request_logger(logger);
     }
+    if let Some(manager) = batch_manager {
+        state = state.with_batch_manager(manager);
+    }
 
     // 需要认证的 /v1 路由
     let v1_routes = Router::new()
         .route("/models", get(get_models))
         .route("/messages", post(post_messages))
         .route("/messages/count_tokens", post(count_tokens))
+        .route("/messages/batches", post(create_batch).get(list_batches))
+        .route("/messages/batches/{batch_id}", get(get_batch))
+        .route("/messages/batches/{batch_id}/cancel", post(cancel_batch))
+        .route("/messages/batches/{batch_id}/results", get(get_batch_results))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -0,0 +1,485 @@
+//! 可插拔的网页搜索后端
+//!
+//! - `kiro`: Kiro MCP 网页搜索（默认，消耗凭据额度）
+//! - `http`: 通用 JSON HTTP 搜索服务（SearXNG 或自建服务）
+//! - `fixture`: 从本地 JSON 文件返回固定结果（用于测试）
+//!
+//! 后端在启动时根据配置初始化一次，外层统一包装超时与结果缓存。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use serde_json::Value;
+
+use crate::http_client::{ProxyConfig, build_client};
+use crate::kiro::provider::KiroProvider;
+use crate::model::config::{HttpSearchConfig, TlsBackend, WebSearchBackendKind, WebSearchConfig};
+
+use super::websearch::{self, WebSearchResult, WebSearchResults};
+
+/// 搜索后端返回的 Future
+pub type SearchFuture<'a> = BoxFuture<'a, anyhow::Result<Option<WebSearchResults>>>;
+
+/// 网页搜索后端
+pub trait WebSearchBackend: Send + Sync {
+    /// 后端名称（用于日志）
+    fn name(&self) -> &'static str;
+
+    /// 未配置 `timeoutSecs` 时的超时时间
+    fn default_timeout(&self) -> Duration;
+
+    /// 执行一次搜索，无结果时可返回 None
+    fn search<'a>(&'a self, provider: &'a KiroProvider, query: &'a str) -> SearchFuture<'a>;
+}
+
+/// Kiro MCP 搜索后端
+pub struct KiroMcpBackend;
+
+impl WebSearchBackend for KiroMcpBackend {
+    fn name(&self) -> &'static str {
+        "kiro"
+    }
+
+    fn default_timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn search<'a>(&'a self, provider: &'a KiroProvider, query: &'a str) -> SearchFuture<'a> {
+        Box::pin(websearch::search_mcp(provider, query))
+    }
+}
+
+/// 通用 JSON HTTP 搜索后端
+pub struct HttpJsonBackend {
+    client: reqwest::Client,
+    config: HttpSearchConfig,
+}
+
+impl HttpJsonBackend {
+    pub fn new(
+        config: HttpSearchConfig,
+        proxy: Option<&ProxyConfig>,
+        timeout: Duration,
+        tls_backend: TlsBackend,
+    ) -> anyhow::Result<Self> {
+        let proxy = if config.use_proxy { proxy } else { None };
+        let client = build_client(proxy, timeout.as_secs().max(1), tls_backend)?;
+        Ok(Self { client, config })
+    }
+
+    async fn search_http(&self, query: &str) -> anyhow::Result<Option<WebSearchResults>> {
+        let mut request = self
+            .client
+            .get(&self.config.url)
+            .query(&[(self.config.query_param.as_str(), query)]);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("HTTP 搜索服务返回 {}", status);
+        }
+
+        let body: Value = response.json().await?;
+        Ok(Some(map_http_results(&body, query, &self.config)))
+    }
+}
+
+impl WebSearchBackend for HttpJsonBackend {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn default_timeout(&self) -> Duration {
+        Duration::from_secs(15)
+    }
+
+    fn search<'a>(&'a self, _provider: &'a KiroProvider, query: &'a str) -> SearchFuture<'a> {
+        Box::pin(self.search_http(query))
+    }
+}
+
+/// 按配置的字段名把 JSON 响应映射为搜索结果
+fn map_http_results(body: &Value, query: &str, config: &HttpSearchConfig) -> WebSearchResults {
+    let results: Vec<WebSearchResult> = body[config.results_field.as_str()]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let url = item[config.url_field.as_str()].as_str()?;
+            let title = item[config.title_field.as_str()].as_str().unwrap_or(url);
+            let snippet = item[config.snippet_field.as_str()]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(str::to_string);
+            Some(WebSearchResult {
+                title: title.to_string(),
+                url: url.to_string(),
+                snippet,
+                published_date: None,
+                id: None,
+                domain: None,
+                max_verbatim_word_limit: None,
+                public_domain: None,
+            })
+        })
+        .take(config.max_results)
+        .collect();
+
+    WebSearchResults {
+        total_results: Some(results.len() as i32),
+        results,
+        query: Some(query.to_string()),
+        error: None,
+    }
+}
+
+/// 固定结果后端
+///
+/// 结果文件为 JSON 对象：键为查询（不区分大小写），值为结果数组；键 `*` 匹配其余所有查询
+pub struct FixtureBackend {
+    entries: HashMap<String, Vec<WebSearchResult>>,
+}
+
+impl FixtureBackend {
+    pub fn new(entries: HashMap<String, Vec<WebSearchResult>>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|(query, results)| (normalize_query(&query), results))
+            .collect();
+        Self { entries }
+    }
+
+    /// 从结果文件加载
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::new(serde_json::from_str(&content)?))
+    }
+
+    fn lookup(&self, query: &str) -> Option<WebSearchResults> {
+        let results = self
+            .entries
+            .get(&normalize_query(query))
+            .or_else(|| self.entries.get("*"))?;
+        Some(WebSearchResults {
+            results: results.clone(),
+            total_results: Some(results.len() as i32),
+            query: Some(query.to_string()),
+            error: None,
+        })
+    }
+}
+
+impl WebSearchBackend for FixtureBackend {
+    fn name(&self) -> &'static str {
+        "fixture"
+    }
+
+    fn default_timeout(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn search<'a>(&'a self, _provider: &'a KiroProvider, query: &'a str) -> SearchFuture<'a> {
+        let results = self.lookup(query);
+        Box::pin(async move { Ok(results) })
+    }
+}
+
+/// 搜索结果缓存（按查询归一化后缓存，只缓存成功结果）
+struct SearchCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, Option<WebSearchResults>)>>,
+}
+
+impl SearchCache {
+    fn get(&self, key: &str) -> Option<Option<WebSearchResults>> {
+        let mut entries = self.entries.lock();
+        match entries.get(key) {
+            Some((stored_at, results)) if stored_at.elapsed() < self.ttl => Some(results.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, results: Option<WebSearchResults>) {
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+            // 仍然已满时淘汰最早写入的条目
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (stored_at, _))| *stored_at)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, (Instant::now(), results));
+    }
+}
+
+/// 搜索服务：后端 + 超时 + 缓存
+pub struct SearchService {
+    backend: Box<dyn WebSearchBackend>,
+    timeout: Duration,
+    cache: Option<SearchCache>,
+}
+
+impl SearchService {
+    pub fn new(
+        backend: Box<dyn WebSearchBackend>,
+        timeout: Option<Duration>,
+        cache_ttl: Duration,
+        cache_capacity: usize,
+    ) -> Self {
+        let timeout = timeout.unwrap_or_else(|| backend.default_timeout());
+        let cache = (!cache_ttl.is_zero() && cache_capacity > 0).then(|| SearchCache {
+            ttl: cache_ttl,
+            capacity: cache_capacity,
+            entries: Mutex::new(HashMap::new()),
+        });
+        Self {
+            backend,
+            timeout,
+            cache,
+        }
+    }
+
+    /// 根据配置创建搜索服务
+    pub fn from_config(
+        config: &WebSearchConfig,
+        proxy: Option<&ProxyConfig>,
+        tls_backend: TlsBackend,
+    ) -> anyhow::Result<Self> {
+        let timeout = config.timeout_secs.map(Duration::from_secs);
+        let backend: Box<dyn WebSearchBackend> = match config.backend {
+            WebSearchBackendKind::Kiro => Box::new(KiroMcpBackend),
+            WebSearchBackendKind::Http => {
+                let Some(http) = config.http.clone() else {
+                    anyhow::bail!("webSearch.backend 为 http 时必须配置 webSearch.http");
+                };
+                let client_timeout = timeout.unwrap_or(Duration::from_secs(15));
+                Box::new(HttpJsonBackend::new(
+                    http,
+                    proxy,
+                    client_timeout,
+                    tls_backend,
+                )?)
+            }
+            WebSearchBackendKind::Fixture => {
+                let Some(path) = &config.fixture_path else {
+                    anyhow::bail!("webSearch.backend 为 fixture 时必须配置 webSearch.fixturePath");
+                };
+                Box::new(FixtureBackend::load(path)?)
+            }
+        };
+
+        Ok(Self::new(
+            backend,
+            timeout,
+            Duration::from_secs(config.cache_ttl_secs),
+            config.cache_capacity,
+        ))
+    }
+
+    /// 后端名称
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// 执行搜索（命中缓存时直接返回）
+    pub async fn search(
+        &self,
+        provider: &KiroProvider,
+        query: &str,
+    ) -> anyhow::Result<Option<WebSearchResults>> {
+        let key = normalize_query(query);
+        if let Some(cached) = self.cache.as_ref().and_then(|c| c.get(&key)) {
+            tracing::debug!(query = %query, "网页搜索命中缓存");
+            return Ok(cached);
+        }
+
+        let results = tokio::time::timeout(self.timeout, self.backend.search(provider, query))
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "{} 搜索超时（{}s）",
+                    self.backend.name(),
+                    self.timeout.as_secs()
+                )
+            })??;
+
+        if let Some(cache) = &self.cache {
+            cache.insert(key, results.clone());
+        }
+        Ok(results)
+    }
+}
+
+/// 全局搜索服务
+static SEARCH_SERVICE: OnceLock<SearchService> = OnceLock::new();
+
+/// 初始化网页搜索后端
+///
+/// 应在应用启动时调用一次；未调用时使用 Kiro MCP 后端与默认缓存
+pub fn init_web_search(
+    config: &WebSearchConfig,
+    proxy: Option<&ProxyConfig>,
+    tls_backend: TlsBackend,
+) -> anyhow::Result<()> {
+    let service = SearchService::from_config(config, proxy, tls_backend)?;
+    tracing::info!("网页搜索后端: {}", service.backend_name());
+    let _ = SEARCH_SERVICE.set(service);
+    Ok(())
+}
+
+/// 获取全局搜索服务
+pub(super) fn service() -> &'static SearchService {
+    SEARCH_SERVICE.get_or_init(|| {
+        SearchService::from_config(&WebSearchConfig::default(), None, TlsBackend::default())
+            .expect("默认 Kiro 搜索后端不需要额外配置")
+    })
+}
+
+fn normalize_query(query: &str) -> String {
+    query.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::kiro::token_manager::MultiTokenManager;
+    use crate::model::config::Config;
+
+    fn create_test_provider() -> KiroProvider {
+        let tm = MultiTokenManager::new(
+            Config::default(),
+            vec![KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        KiroProvider::new(Arc::new(tm))
+    }
+
+    fn fixture_result(title: &str) -> WebSearchResult {
+        serde_json::from_value(serde_json::json!({
+            "title": title,
+            "url": format!("https://example.com/{}", title),
+            "snippet": "snippet"
+        }))
+        .unwrap()
+    }
+
+    /// 记录调用次数的后端，可选延迟
+    struct CountingBackend {
+        calls: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl WebSearchBackend for CountingBackend {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn default_timeout(&self) -> Duration {
+            Duration::from_secs(5)
+        }
+
+        fn search<'a>(&'a self, _provider: &'a KiroProvider, _query: &'a str) -> SearchFuture<'a> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                Ok(None)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fixture_backend_lookup() {
+        let provider = create_test_provider();
+        let backend = FixtureBackend::new(HashMap::from([
+            ("Rust".to_string(), vec![fixture_result("rust")]),
+            ("*".to_string(), vec![fixture_result("fallback")]),
+        ]));
+
+        let results = backend.search(&provider, " rust ").await.unwrap().unwrap();
+        assert_eq!(results.results[0].title, "rust");
+
+        let results = backend.search(&provider, "go").await.unwrap().unwrap();
+        assert_eq!(results.results[0].title, "fallback");
+    }
+
+    #[tokio::test]
+    async fn test_search_service_caches_results() {
+        let provider = create_test_provider();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let backend = CountingBackend {
+            calls: calls.clone(),
+            delay: Duration::ZERO,
+        };
+        let service = SearchService::new(Box::new(backend), None, Duration::from_secs(60), 8);
+
+        service.search(&provider, "rust").await.unwrap();
+        service.search(&provider, "RUST ").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        service.search(&provider, "go").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_service_timeout() {
+        let provider = create_test_provider();
+        let backend = CountingBackend {
+            calls: Arc::new(AtomicUsize::new(0)),
+            delay: Duration::from_secs(5),
+        };
+        let service = SearchService::new(
+            Box::new(backend),
+            Some(Duration::from_millis(10)),
+            Duration::ZERO,
+            0,
+        );
+
+        let err = service.search(&provider, "rust").await.unwrap_err();
+        assert!(err.to_string().contains("超时"));
+    }
+
+    #[test]
+    fn test_map_http_results_searxng() {
+        let config: HttpSearchConfig = serde_json::from_value(serde_json::json!({
+            "url": "http://127.0.0.1:8888/search?format=json",
+            "maxResults": 1
+        }))
+        .unwrap();
+        let body = serde_json::json!({
+            "query": "rust",
+            "results": [
+                {"title": "Rust", "url": "https://www.rust-lang.org", "content": "A language"},
+                {"title": "Crates", "url": "https://crates.io", "content": ""}
+            ]
+        });
+
+        let results = map_http_results(&body, "rust", &config);
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].url, "https://www.rust-lang.org");
+        assert_eq!(results.results[0].snippet.as_deref(), Some("A language"));
+    }
+}
@@ -0,0 +1,750 @@
+//! 服务端工具执行循环
+//!
+//! 请求中声明了 web_search / web_fetch 服务端工具时，由代理代为执行模型发起的调用：
+//! 模型调用工具 → 代理执行搜索或抓取网页 → 结果作为 tool_result 回填 → 再次请求模型，
+//! 直到模型给出最终回答、调用客户端工具或达到 `max_uses` 上限。
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::provider::KiroProvider;
+use crate::token;
+
+use super::citations::CitationIndex;
+use super::converter::convert_request;
+use super::handlers::build_message_response;
+use super::stream::SseEvent;
+use super::types::{ErrorResponse, Message, MessagesRequest, Tool};
+use super::{webfetch, websearch};
+
+/// Ping 事件间隔（25秒）
+const PING_INTERVAL_SECS: u64 = 25;
+
+/// 事件通道容量（客户端读取过慢时服务端工具循环等待）
+const EVENT_BUFFER: usize = 64;
+
+/// 服务端工具循环的最终结果
+#[derive(Debug)]
+pub struct LoopOutcome {
+    /// 客户端可见的全部内容块
+    pub content: Vec<Value>,
+    /// 最终 stop_reason
+    pub stop_reason: String,
+    /// 所有轮次累计的输入 tokens（每轮按当时的上下文估算）
+    pub input_tokens: i32,
+    /// 所有轮次累计的输出 tokens
+    pub output_tokens: i32,
+    /// 实际执行的搜索次数
+    pub web_search_requests: i32,
+    /// 实际执行的网页抓取次数
+    pub web_fetch_requests: i32,
+}
+
+/// 检查请求是否声明了需要代理执行的服务端工具（web_search / web_fetch）
+pub fn has_server_tools(req: &MessagesRequest) -> bool {
+    websearch::has_web_search_tool(req) || webfetch::web_fetch_tool(req).is_some()
+}
+
+/// 单次服务端工具调用的执行结果
+struct ToolExecution {
+    /// 客户端可见的结果块
+    result_block: Value,
+    /// 回填给模型的 tool_result 文本
+    model_text: String,
+    is_error: bool,
+}
+
+/// 执行服务端工具循环
+///
+/// `input_tokens` 为首轮请求的输入 tokens 估算，后续轮次按追加了工具结果的上下文重新估算。
+/// 每轮结束后通过 `on_blocks` 回调本轮新增的客户端可见内容块，便于流式输出；
+/// 回调返回 false（客户端已断开）时停止循环，不再调用上游与执行工具
+pub async fn run_server_tool_loop<F>(
+    provider: &KiroProvider,
+    mut payload: MessagesRequest,
+    input_tokens: i32,
+    mut on_blocks: impl FnMut(Vec<Value>) -> F,
+) -> anyhow::Result<LoopOutcome>
+where
+    F: Future<Output = bool>,
+{
+    let search_tool = websearch::web_search_tool(&payload).cloned();
+    let fetch_tool = webfetch::web_fetch_tool(&payload).cloned();
+    if search_tool.is_none() && fetch_tool.is_none() {
+        anyhow::bail!("请求中没有服务端工具");
+    }
+    let server_tool_names: Vec<String> = search_tool
+        .iter()
+        .chain(fetch_tool.iter())
+        .map(|t| t.name.clone())
+        .collect();
+
+    // 搜索结果与文档句子的引用标签，回答中的标签会转换为 citations
+    let mut citations = CitationIndex::from_request(&payload);
+
+    let mut outcome = LoopOutcome {
+        content: Vec::new(),
+        stop_reason: "end_turn".to_string(),
+        input_tokens: 0,
+        output_tokens: 0,
+        web_search_requests: 0,
+        web_fetch_requests: 0,
+    };
+
+    // 超出 max_uses 后模型仍可能继续调用工具，额外留出两轮让其基于已有结果作答
+    let max_turns = search_tool
+        .iter()
+        .chain(fetch_tool.iter())
+        .map(|t| max_uses(t) as usize)
+        .sum::<usize>()
+        + 2;
+    for turn in 0..max_turns {
+        let turn_input_tokens = if turn == 0 {
+            input_tokens
+        } else {
+            count_input_tokens(&payload)
+        };
+        let message = call_model(provider, &payload, turn_input_tokens).await?;
+        outcome.input_tokens += turn_input_tokens;
+        outcome.output_tokens += message["usage"]["output_tokens"].as_i64().unwrap_or(0) as i32;
+        outcome.stop_reason = message["stop_reason"]
+            .as_str()
+            .unwrap_or("end_turn")
+            .to_string();
+
+        let blocks = message["content"].as_array().cloned().unwrap_or_default();
+        let (server_calls, client_calls) = split_tool_calls(&blocks, &server_tool_names);
+
+        let mut visible: Vec<Value> = citations.annotate_content(
+            blocks
+                .iter()
+                .filter(|b| b["type"] == "text")
+                .cloned()
+                .collect(),
+        );
+
+        // 没有服务端工具调用：本轮即为最终回答（或仅调用了客户端工具）
+        if server_calls.is_empty() {
+            visible.extend(client_calls);
+            outcome.content.extend(visible.iter().cloned());
+            on_blocks(visible).await;
+            return Ok(outcome);
+        }
+
+        tracing::info!(
+            turn = turn + 1,
+            count = server_calls.len(),
+            "执行服务端工具调用"
+        );
+
+        let mut tool_results = Vec::new();
+        for call in &server_calls {
+            let server_id = websearch::server_tool_use_id();
+            let execution = match (&search_tool, &fetch_tool) {
+                (Some(tool), _) if call["name"] == tool.name.as_str() => {
+                    let query = call["input"]["query"].as_str().unwrap_or_default();
+                    visible.push(server_tool_use_block(
+                        &server_id,
+                        tool,
+                        json!({"query": query}),
+                    ));
+                    execute_search(provider, query, tool, &mut citations, &mut outcome).await
+                }
+                (_, Some(tool)) => {
+                    let url = call["input"]["url"].as_str().unwrap_or_default();
+                    visible.push(server_tool_use_block(&server_id, tool, json!({"url": url})));
+                    execute_fetch(url, tool, &mut outcome).await
+                }
+                _ => unreachable!("调用已按服务端工具名筛选"),
+            };
+
+            let mut result_block = execution.result_block;
+            result_block["tool_use_id"] = json!(server_id);
+            visible.push(result_block);
+            tool_results.push(json!({
+                "type": "tool_result",
+                "tool_use_id": call["id"],
+                "content": execution.model_text,
+                "is_error": execution.is_error
+            }));
+        }
+
+        // 本轮的 assistant 输出（含原始 tool_use）加入上下文
+        payload.messages.push(Message {
+            role: "assistant".to_string(),
+            content: Value::Array(blocks),
+        });
+
+        // 同时调用了客户端工具：服务端工具已执行，把客户端工具调用交还给调用方
+        if !client_calls.is_empty() {
+            visible.extend(client_calls);
+            outcome.content.extend(visible.iter().cloned());
+            outcome.stop_reason = "tool_use".to_string();
+            on_blocks(visible).await;
+            return Ok(outcome);
+        }
+
+        payload.messages.push(Message {
+            role: "user".to_string(),
+            content: Value::Array(tool_results),
+        });
+        outcome.content.extend(visible.iter().cloned());
+        if !on_blocks(visible).await {
+            anyhow::bail!("客户端已断开连接，停止服务端工具循环");
+        }
+    }
+
+    tracing::warn!("服务端工具循环达到最大轮次 {}，提前结束", max_turns);
+    outcome.stop_reason = "end_turn".to_string();
+    Ok(outcome)
+}
+
+/// 以非流式方式调用一次模型，返回 Anthropic Message 响应
+async fn call_model(
+    provider: &KiroProvider,
+    payload: &MessagesRequest,
+    input_tokens: i32,
+) -> anyhow::Result<Value> {
+    let conversion_result = convert_request(payload)?;
+    let kiro_request = KiroRequest::new(conversion_result.conversation_state);
+
+    let response = provider.call_api(&kiro_request).await?;
+    let body_bytes = response.bytes().await?;
+    Ok(build_message_response(
+        &body_bytes,
+        &payload.model,
+        input_tokens,
+    ))
+}
+
+/// 估算当前上下文的输入 tokens
+fn count_input_tokens(payload: &MessagesRequest) -> i32 {
+    token::count_all_tokens(
+        payload.model.clone(),
+        payload.system.clone(),
+        payload.messages.clone(),
+        payload.tools.clone(),
+    ) as i32
+}
+
+/// 将本轮的 tool_use 块拆分为 (服务端工具调用, 客户端工具调用)
+fn split_tool_calls(blocks: &[Value], server_tool_names: &[String]) -> (Vec<Value>, Vec<Value>) {
+    blocks
+        .iter()
+        .filter(|b| b["type"] == "tool_use")
+        .cloned()
+        .partition(|b| {
+            b["name"]
+                .as_str()
+                .is_some_and(|name| server_tool_names.iter().any(|n| n == name))
+        })
+}
+
+fn max_uses(tool: &Tool) -> i32 {
+    tool.max_uses.unwrap_or(websearch::DEFAULT_MAX_USES).max(0)
+}
+
+fn server_tool_use_block(id: &str, tool: &Tool, input: Value) -> Value {
+    json!({
+        "type": "server_tool_use",
+        "id": id,
+        "name": tool.name,
+        "input": input
+    })
+}
+
+/// 执行一次 web_search 调用
+async fn execute_search(
+    provider: &KiroProvider,
+    query: &str,
+    tool: &Tool,
+    citations: &mut CitationIndex,
+    outcome: &mut LoopOutcome,
+) -> ToolExecution {
+    let (content, model_text, is_error) = if outcome.web_search_requests >= max_uses(tool) {
+        (
+            search_error("max_uses_exceeded"),
+            "Error: the web search limit for this request has been reached. \
+             Answer using the information already gathered."
+                .to_string(),
+            true,
+        )
+    } else {
+        outcome.web_search_requests += 1;
+        tracing::info!(query = %query, "处理 WebSearch 请求");
+        match websearch::search(provider, query).await {
+            Ok(results) => {
+                let labels = citations.add_search_results(&results);
+                (
+                    Value::Array(websearch::search_result_blocks(&results)),
+                    websearch::generate_cited_search_summary(query, &results, &labels),
+                    false,
+                )
+            }
+            Err(e) => {
+                tracing::warn!("MCP API 调用失败: {}", e);
+                (
+                    search_error("unavailable"),
+                    format!("Error: web search failed: {}", e),
+                    true,
+                )
+            }
+        }
+    };
+
+    ToolExecution {
+        result_block: json!({"type": "web_search_tool_result", "content": content}),
+        model_text,
+        is_error,
+    }
+}
+
+/// 执行一次 web_fetch 调用
+async fn execute_fetch(url: &str, tool: &Tool, outcome: &mut LoopOutcome) -> ToolExecution {
+    let (content, model_text, is_error) = if outcome.web_fetch_requests >= max_uses(tool) {
+        (
+            webfetch::fetch_error_content("max_uses_exceeded"),
+            "Error: the web fetch limit for this request has been reached. \
+             Answer using the information already gathered."
+                .to_string(),
+            true,
+        )
+    } else {
+        outcome.web_fetch_requests += 1;
+        tracing::info!(url = %url, "处理 WebFetch 请求");
+        match webfetch::fetch(url, tool).await {
+            Ok(doc) => (
+                webfetch::fetch_result_content(&doc),
+                webfetch::generate_fetch_summary(&doc),
+                false,
+            ),
+            Err(e) => {
+                tracing::warn!(url = %url, "网页抓取失败: {}", e);
+                (
+                    webfetch::fetch_error_content(e.error_code()),
+                    format!("Error: failed to fetch {}: {}", url, e),
+                    true,
+                )
+            }
+        }
+    };
+
+    ToolExecution {
+        result_block: json!({"type": "web_fetch_tool_result", "content": content}),
+        model_text,
+        is_error,
+    }
+}
+
+/// web_search_tool_result 的错误内容
+fn search_error(error_code: &str) -> Value {
+    json!({
+        "type": "web_search_tool_result_error",
+        "error_code": error_code
+    })
+}
+
+/// 处理带服务端工具的非流式请求
+pub async fn handle_server_tool_json(
+    provider: Arc<KiroProvider>,
+    payload: MessagesRequest,
+    input_tokens: i32,
+) -> Response {
+    let model = payload.model.clone();
+    match run_server_tool_loop(&provider, payload, input_tokens, |_| async { true }).await {
+        Ok(outcome) => (StatusCode::OK, Json(message_json(&model, &outcome))).into_response(),
+        Err(e) => {
+            tracing::error!("服务端工具循环失败: {}", e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse::new(
+                    "api_error",
+                    format!("上游 API 调用失败: {}", e),
+                )),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 构建非流式 Message 响应
+fn message_json(model: &str, outcome: &LoopOutcome) -> Value {
+    json!({
+        "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
+        "type": "message",
+        "role": "assistant",
+        "content": outcome.content,
+        "model": model,
+        "stop_reason": outcome.stop_reason,
+        "stop_sequence": null,
+        "usage": usage_json(outcome)
+    })
+}
+
+fn usage_json(outcome: &LoopOutcome) -> Value {
+    json!({
+        "input_tokens": outcome.input_tokens,
+        "output_tokens": outcome.output_tokens,
+        "server_tool_use": {
+            "web_search_requests": outcome.web_search_requests,
+            "web_fetch_requests": outcome.web_fetch_requests
+        }
+    })
+}
+
+/// 处理带服务端工具的流式请求
+///
+/// 每轮结束后立即把该轮的内容块以 SSE 事件推送给客户端，轮次之间发送 ping 保活
+pub async fn handle_server_tool_stream(
+    provider: Arc<KiroProvider>,
+    payload: MessagesRequest,
+    input_tokens: i32,
+) -> Response {
+    let model = payload.model.clone();
+    let (tx, rx) = mpsc::channel::<SseEvent>(EVENT_BUFFER);
+
+    let task = tokio::spawn(async move {
+        let mut next_index = 0;
+        let result = run_server_tool_loop(&provider, payload, input_tokens, |blocks| {
+            let mut events = Vec::new();
+            for block in blocks {
+                events.extend(block_to_sse_events(next_index, &block));
+                next_index += 1;
+            }
+            let tx = tx.clone();
+            async move {
+                for event in events {
+                    if tx.send(event).await.is_err() {
+                        return false;
+                    }
+                }
+                true
+            }
+        })
+        .await;
+
+        let events = match result {
+            Ok(outcome) => final_events(&outcome),
+            Err(e) => {
+                tracing::error!("服务端工具循环失败: {}", e);
+                vec![SseEvent::new(
+                    "error",
+                    json!({
+                        "type": "error",
+                        "error": {
+                            "type": "api_error",
+                            "message": format!("上游 API 调用失败: {}", e)
+                        }
+                    }),
+                )]
+            }
+        };
+        for event in events {
+            if tx.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let start = message_start_event(&model, input_tokens);
+    let body = stream::once(async move { Ok::<_, Infallible>(Bytes::from(start.to_sse_string())) })
+        .chain(create_channel_stream(rx, AbortOnDrop(task.abort_handle())));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::CONNECTION, "keep-alive")
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+/// 响应流被丢弃（客户端断开）时终止服务端工具循环
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// 将事件通道转换为 SSE 字节流，等待期间每 25 秒发送 ping
+fn create_channel_stream(
+    rx: mpsc::Receiver<SseEvent>,
+    task: AbortOnDrop,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    stream::unfold(
+        (rx, interval(Duration::from_secs(PING_INTERVAL_SECS)), task),
+        |(mut rx, mut ping_interval, task)| async move {
+            tokio::select! {
+                event = rx.recv() => {
+                    let event = event?;
+                    Some((Ok(Bytes::from(event.to_sse_string())), (rx, ping_interval, task)))
+                }
+                _ = ping_interval.tick() => {
+                    tracing::trace!("发送 ping 保活事件");
+                    let ping = Bytes::from("event: ping\ndata: {\"type\": \"ping\"}\n\n");
+                    Some((Ok(ping), (rx, ping_interval, task)))
+                }
+            }
+        },
+    )
+}
+
+fn message_start_event(model: &str, input_tokens: i32) -> SseEvent {
+    SseEvent::new(
+        "message_start",
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "model": model,
+                "stop_reason": null,
+                "stop_sequence": null,
+                "usage": {
+                    "input_tokens": input_tokens,
+                    "output_tokens": 1
+                }
+            }
+        }),
+    )
+}
+
+/// 将单个完整内容块转换为 SSE 事件序列（start / delta / stop）
+fn block_to_sse_events(index: i32, block: &Value) -> Vec<SseEvent> {
+    let block_type = block["type"].as_str().unwrap_or_default();
+    let mut events = Vec::new();
+
+    let (start_block, delta) = match block_type {
+        "text" => (
+            json!({"type": "text", "text": ""}),
+            Some(json!({"type": "text_delta", "text": block["text"]})),
+        ),
+        "tool_use" | "server_tool_use" => (
+            json!({
+                "type": block_type,
+                "id": block["id"],
+                "name": block["name"],
+                "input": {}
+            }),
+            Some(json!({
+                "type": "input_json_delta",
+                "partial_json": serde_json::to_string(&block["input"]).unwrap_or_default()
+            })),
+        ),
+        // 其余块（如 web_search_tool_result）在 start 中携带完整内容
+        _ => (block.clone(), None),
+    };
+
+    events.push(SseEvent::new(
+        "content_block_start",
+        json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": start_block
+        }),
+    ));
+    if let Some(delta) = delta {
+        events.push(SseEvent::new(
+            "content_block_delta",
+            json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": delta
+            }),
+        ));
+    }
+    if let Some(citations) = block["citations"].as_array() {
+        for citation in citations {
+            events.push(SseEvent::new(
+                "content_block_delta",
+                json!({
+                    "type": "content_block_delta",
+                    "index": index,
+                    "delta": {"type": "citations_delta", "citation": citation}
+                }),
+            ));
+        }
+    }
+    events.push(SseEvent::new(
+        "content_block_stop",
+        json!({
+            "type": "content_block_stop",
+            "index": index
+        }),
+    ));
+    events
+}
+
+fn final_events(outcome: &LoopOutcome) -> Vec<SseEvent> {
+    vec![
+        SseEvent::new(
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {
+                    "stop_reason": outcome.stop_reason,
+                    "stop_sequence": null
+                },
+                "usage": usage_json(outcome)
+            }),
+        ),
+        SseEvent::new("message_stop", json!({"type": "message_stop"})),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_channel_stream_drop_aborts_loop() {
+        let (tx, rx) = mpsc::channel::<SseEvent>(1);
+        let task = tokio::spawn(async move {
+            let _tx = tx;
+            std::future::pending::<()>().await
+        });
+        let stream = create_channel_stream(rx, AbortOnDrop(task.abort_handle()));
+
+        // 客户端断开时响应流被丢弃，服务端工具循环随之终止
+        drop(stream);
+        assert!(task.await.unwrap_err().is_cancelled());
+    }
+
+    #[test]
+    fn test_split_tool_calls() {
+        let blocks = vec![
+            json!({"type": "text", "text": "Let me search."}),
+            json!({"type": "tool_use", "id": "t1", "name": "web_search", "input": {"query": "rust"}}),
+            json!({"type": "tool_use", "id": "t2", "name": "read_file", "input": {"path": "a"}}),
+        ];
+        let (search, client) = split_tool_calls(&blocks, &["web_search".to_string()]);
+        assert_eq!(search.len(), 1);
+        assert_eq!(search[0]["id"], "t1");
+        assert_eq!(client.len(), 1);
+        assert_eq!(client[0]["name"], "read_file");
+    }
+
+    #[test]
+    fn test_block_to_sse_events() {
+        let events = block_to_sse_events(
+            1,
+            &json!({"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "rust"}}),
+        );
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].data["content_block"]["type"], "server_tool_use");
+        assert_eq!(
+            events[1].data["delta"]["partial_json"],
+            r#"{"query":"rust"}"#
+        );
+        assert_eq!(events[2].data["index"], 1);
+
+        let events = block_to_sse_events(
+            2,
+            &json!({"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1", "content": []}),
+        );
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].data["content_block"]["tool_use_id"], "srvtoolu_1");
+
+        let events = block_to_sse_events(
+            3,
+            &json!({"type": "text", "text": "Rust is fast", "citations": [
+                {"type": "web_search_result_location", "url": "https://www.rust-lang.org"}
+            ]}),
+        );
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[2].data["delta"]["type"], "citations_delta");
+        assert_eq!(
+            events[2].data["delta"]["citation"]["url"],
+            "https://www.rust-lang.org"
+        );
+    }
+
+    #[test]
+    fn test_input_tokens_grow_with_tool_results() {
+        let mut payload: MessagesRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "What is Rust?"}],
+            "tools": [{"type": "web_search_20250305", "name": "web_search"}]
+        }))
+        .unwrap();
+        let first = count_input_tokens(&payload);
+
+        // 回填工具结果后，下一轮的输入按增长后的上下文重新估算
+        payload.messages.push(Message {
+            role: "user".to_string(),
+            content: json!([{
+                "type": "tool_result",
+                "tool_use_id": "toolu_1",
+                "content": "Rust is a systems programming language focused on safety and speed."
+            }]),
+        });
+        assert!(count_input_tokens(&payload) > first);
+    }
+
+    #[test]
+    fn test_message_json() {
+        let outcome = LoopOutcome {
+            content: vec![
+                json!({"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "rust"}}),
+                json!({"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1", "content": []}),
+                json!({"type": "text", "text": "Rust is a language."}),
+            ],
+            stop_reason: "end_turn".to_string(),
+            input_tokens: 10,
+            output_tokens: 5,
+            web_search_requests: 1,
+            web_fetch_requests: 0,
+        };
+        let message = message_json("claude-sonnet-4-5", &outcome);
+        assert_eq!(message["type"], "message");
+        assert_eq!(message["content"].as_array().unwrap().len(), 3);
+        assert_eq!(message["stop_reason"], "end_turn");
+        assert_eq!(
+            message["usage"]["server_tool_use"]["web_search_requests"],
+            1
+        );
+    }
+
+    #[test]
+    fn test_final_events_report_search_usage() {
+        let outcome = LoopOutcome {
+            content: Vec::new(),
+            stop_reason: "end_turn".to_string(),
+            input_tokens: 10,
+            output_tokens: 5,
+            web_search_requests: 2,
+            web_fetch_requests: 1,
+        };
+        let events = final_events(&outcome);
+        assert_eq!(
+            events[0].data["usage"]["server_tool_use"]["web_search_requests"],
+            2
+        );
+        assert_eq!(
+            events[0].data["usage"]["server_tool_use"]["web_fetch_requests"],
+            1
+        );
+        assert_eq!(events[1].event, "message_stop");
+    }
+}
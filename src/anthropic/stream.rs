@@ -9,6 +9,8 @@ use uuid::Uuid;
 
 use crate::kiro::model::events::Event;
 
+use super::citations::{CitationIndex, CitationStream, Segment};
+
 /// 找到小于等于目标位置的最近有效UTF-8字符边界
 ///
 /// UTF-8字符可能占用1-4个字节，直接按字节位置切片可能会切在多字节字符中间导致panic。
@@ -477,6 +479,8 @@ pub struct StreamContext {
     pub thinking_block_index: Option<i32>,
     /// 文本块索引（thinking 启用时动态分配）
     pub text_block_index: Option<i32>,
+    /// 引用标签解析器（请求包含可引用内容时启用）
+    pub citations: Option<CitationStream>,
 }
 
 impl StreamContext {
@@ -500,9 +504,18 @@ impl StreamContext {
             thinking_extracted: false,
             thinking_block_index: None,
             text_block_index: None,
+            citations: None,
         }
     }
 
+    /// 启用引用：文本中的引用标签会被转换为 citations_delta 事件
+    pub fn with_citations(mut self, index: CitationIndex) -> Self {
+        if !index.is_empty() {
+            self.citations = Some(CitationStream::new(index));
+        }
+        self
+    }
+
     /// 生成 message_start 事件
     pub fn create_message_start_event(&self) -> serde_json::Value {
         json!({
@@ -743,13 +756,82 @@ impl StreamContext {
         events
     }
 
-    /// 创建 text_delta 事件
+    /// 创建 text_delta 事件（启用引用时先解析引用标签）
+    fn create_text_delta_events(&mut self, text: &str) -> Vec<SseEvent> {
+        match self.citations.as_mut() {
+            Some(citations) => {
+                let segments = citations.push(text);
+                self.emit_citation_segments(segments)
+            }
+            None => self.emit_text_delta(text),
+        }
+    }
+
+    /// 输出引用解析后的片段
+    ///
+    /// 引用以 citations_delta 附加到当前文本块，其后的文本开启新的文本块，
+    /// 与非流式响应中按引用拆分文本块的结构一致。
+    fn emit_citation_segments(&mut self, segments: Vec<Segment>) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => {
+                    let block_cited = self
+                        .citations
+                        .as_mut()
+                        .is_some_and(|c| std::mem::take(&mut c.block_cited));
+                    if block_cited
+                        && let Some(idx) = self.text_block_index.take()
+                        && let Some(stop_event) = self.state_manager.handle_content_block_stop(idx)
+                    {
+                        events.push(stop_event);
+                    }
+                    events.extend(self.emit_text_delta(&text));
+                }
+                Segment::Citation(citation) => {
+                    let Some(idx) = self.text_block_index else {
+                        continue;
+                    };
+                    if let Some(delta_event) = self.state_manager.handle_content_block_delta(
+                        idx,
+                        json!({
+                            "type": "content_block_delta",
+                            "index": idx,
+                            "delta": {
+                                "type": "citations_delta",
+                                "citation": citation
+                            }
+                        }),
+                    ) {
+                        events.push(delta_event);
+                        if let Some(citations) = self.citations.as_mut() {
+                            citations.block_cited = true;
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// 输出引用解析器中暂存的文本（如流结束时未写完的标签）
+    fn flush_citations(&mut self) -> Vec<SseEvent> {
+        match self.citations.as_mut() {
+            Some(citations) => {
+                let segments = citations.finish();
+                self.emit_citation_segments(segments)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// 发送 text_delta 事件
     ///
     /// 如果文本块尚未创建，会先创建文本块。
     /// 当发生 tool_use 时，状态机会自动关闭当前文本块；后续文本会自动创建新的文本块继续输出。
     ///
     /// 返回值包含可能的 content_block_start 事件和 content_block_delta 事件。
-    fn create_text_delta_events(&mut self, text: &str) -> Vec<SseEvent> {
+    fn emit_text_delta(&mut self, text: &str) -> Vec<SseEvent> {
         let mut events = Vec::new();
 
         // 如果当前 text_block_index 指向的块已经被关闭（例如 tool_use 开始时自动 stop），
@@ -878,6 +960,7 @@ impl StreamContext {
             let buffered = std::mem::take(&mut self.thinking_buffer);
             events.extend(self.create_text_delta_events(&buffered));
         }
+        events.extend(self.flush_citations());
 
         // 获取或分配块索引
         let block_index = if let Some(&idx) = self.tool_block_indices.get(&tool_use.tool_use_id) {
@@ -1000,6 +1083,7 @@ impl StreamContext {
             }
             self.thinking_buffer.clear();
         }
+        events.extend(self.flush_citations());
 
         // 使用从 contextUsageEvent 计算的 input_tokens，如果没有则使用估算值
         let final_input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
@@ -1141,6 +1225,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_citation_markers_become_citations_delta() {
+        let req: crate::anthropic::types::MessagesRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "messages": [{
+                "role": "user",
+                "content": [{
+                    "type": "document",
+                    "source": {"type": "text", "media_type": "text/plain", "data": "The sky is blue."},
+                    "citations": {"enabled": true}
+                }]
+            }]
+        }))
+        .unwrap();
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false)
+            .with_citations(CitationIndex::from_request(&req));
+        ctx.generate_initial_events();
+
+        // 标签被拆分到两个增量中
+        let mut events = ctx.create_text_delta_events("The sky is blue [D1");
+        events.extend(ctx.create_text_delta_events(".1]. Indeed."));
+        events.extend(ctx.generate_final_events());
+
+        let texts: Vec<_> = events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "text_delta")
+            .map(|e| e.data["delta"]["text"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(texts.concat(), "The sky is blue . Indeed.");
+
+        let citation = events
+            .iter()
+            .find(|e| e.data["delta"]["type"] == "citations_delta")
+            .expect("should emit citations_delta");
+        assert_eq!(citation.data["index"], 0);
+        assert_eq!(citation.data["delta"]["citation"]["type"], "char_location");
+
+        // 引用之后的文本开启新的文本块
+        assert!(
+            events
+                .iter()
+                .any(|e| { e.event == "content_block_start" && e.data["index"] == 1 })
+        );
+    }
+
     #[test]
     fn test_tool_use_flushes_pending_thinking_buffer_text_before_tool_block() {
         // thinking 模式下，短文本可能被暂存在 thinking_buffer 以等待 `<thinking>` 的跨 chunk 匹配。
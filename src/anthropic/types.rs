@@ -94,7 +94,7 @@ pub struct Metadata {
 }
 
 /// Messages 请求体
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct MessagesRequest {
     pub model: String,
     pub max_tokens: i32,
@@ -187,7 +187,8 @@ pub struct SystemMessage {
 ///
 /// 支持两种格式：
 /// 1. 普通工具：{ name, description, input_schema }
-/// 2. WebSearch 工具：{ type: "web_search_20250305", name: "web_search", max_uses: 8 }
+/// 2. 服务端工具：{ type: "web_search_20250305", name: "web_search", max_uses: 8 }
+///    或 { type: "web_fetch_20250910", name: "web_fetch", allowed_domains: [...] }
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Tool {
     /// 工具类型，如 "web_search_20250305"（可选，仅 WebSearch 工具）
@@ -202,9 +203,18 @@ pub struct Tool {
     /// 输入参数 schema（普通工具必需，WebSearch 工具无此字段）
     #[serde(default)]
     pub input_schema: HashMap<String, serde_json::Value>,
-    /// 最大使用次数（仅 WebSearch 工具）
+    /// 最大使用次数（仅服务端工具）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_uses: Option<i32>,
+    /// 允许访问的域名（仅服务端工具）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_domains: Option<Vec<String>>,
+    /// 禁止访问的域名（仅服务端工具）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_domains: Option<Vec<String>>,
+    /// 抓取内容的最大 token 数（仅 WebFetch 工具）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_content_tokens: Option<i32>,
 }
 
 impl Tool {
@@ -214,6 +224,13 @@ impl Tool {
             .as_ref()
             .is_some_and(|t| t.starts_with("web_search"))
     }
+
+    /// 检查是否为 WebFetch 工具
+    pub fn is_web_fetch(&self) -> bool {
+        self.tool_type
+            .as_ref()
+            .is_some_and(|t| t.starts_with("web_fetch"))
+    }
 }
 
 /// 内容块
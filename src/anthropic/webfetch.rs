@@ -0,0 +1,904 @@
+//! WebFetch 工具处理模块
+//!
+//! 抓取模型请求的网页，提取可读文本（HTML 转为 Markdown 风格纯文本），
+//! 并转换为 `web_fetch_tool_result` 内容块。
+//!
+//! 访问控制：配置与工具定义中的域名白名单/黑名单同时生效，每次重定向都会重新校验；
+//! 默认拒绝 localhost 与内网地址：URL 中的 IP 字面量直接校验，域名在 DNS 解析后
+//! 过滤掉内网地址（经代理访问时由代理解析域名，不做解析层面的校验）。
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+
+use futures::StreamExt;
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use serde_json::{Value, json};
+
+use crate::http_client::{ProxyConfig, client_builder};
+use crate::model::config::{TlsBackend, WebFetchConfig};
+
+use super::types::{MessagesRequest, Tool};
+
+/// web_fetch 工具提供给模型的描述
+pub const WEB_FETCH_TOOL_DESCRIPTION: &str = "Fetch the content of a web page by URL. \
+Use this to read pages found by web search or URLs provided by the user. \
+Returns the page title and its readable text.";
+
+/// 最大重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// URL 最大长度
+const MAX_URL_LENGTH: usize = 2048;
+
+/// 获取请求中的 WebFetch 工具定义
+pub fn web_fetch_tool(req: &MessagesRequest) -> Option<&Tool> {
+    req.tools.as_ref()?.iter().find(|t| t.is_web_fetch())
+}
+
+/// web_fetch 工具的输入参数 schema（转换为普通 Kiro 工具时使用）
+pub fn web_fetch_input_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "url": {
+                "type": "string",
+                "description": "The absolute http(s) URL to fetch"
+            }
+        },
+        "required": ["url"]
+    })
+}
+
+/// 抓取失败原因（对应 `web_fetch_tool_error.error_code`）
+#[derive(Debug)]
+pub enum FetchError {
+    InvalidInput(String),
+    UrlTooLong,
+    UrlNotAllowed(String),
+    UrlNotAccessible(String),
+    TooManyRequests,
+    UnsupportedContentType(String),
+}
+
+impl FetchError {
+    /// Anthropic 错误码
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            FetchError::InvalidInput(_) => "invalid_input",
+            FetchError::UrlTooLong => "url_too_long",
+            FetchError::UrlNotAllowed(_) => "url_not_allowed",
+            FetchError::UrlNotAccessible(_) => "url_not_accessible",
+            FetchError::TooManyRequests => "too_many_requests",
+            FetchError::UnsupportedContentType(_) => "unsupported_content_type",
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::InvalidInput(msg) => write!(f, "无效的 URL: {}", msg),
+            FetchError::UrlTooLong => write!(f, "URL 超过 {} 字符", MAX_URL_LENGTH),
+            FetchError::UrlNotAllowed(host) => write!(f, "不允许访问的域名: {}", host),
+            FetchError::UrlNotAccessible(msg) => write!(f, "无法访问: {}", msg),
+            FetchError::TooManyRequests => write!(f, "目标站点限流"),
+            FetchError::UnsupportedContentType(ct) => write!(f, "不支持的内容类型: {}", ct),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// 抓取到的文档
+#[derive(Debug)]
+pub struct FetchedDocument {
+    /// 最终 URL（跟随重定向后）
+    pub url: String,
+    pub title: Option<String>,
+    /// 提取后的文本
+    pub text: String,
+    /// 内容是否被截断
+    pub truncated: bool,
+    /// 抓取时间（RFC 3339）
+    pub retrieved_at: String,
+}
+
+/// 网页抓取器
+struct Fetcher {
+    client: reqwest::Client,
+    config: WebFetchConfig,
+}
+
+/// 全局抓取器
+static FETCHER: OnceLock<Fetcher> = OnceLock::new();
+
+/// 初始化网页抓取配置
+///
+/// 应在应用启动时调用一次；未调用时使用默认配置（不经代理）
+pub fn init_web_fetch(
+    config: &WebFetchConfig,
+    proxy: Option<&ProxyConfig>,
+    tls_backend: TlsBackend,
+) -> anyhow::Result<()> {
+    let _ = FETCHER.set(Fetcher::new(config.clone(), proxy, tls_backend)?);
+    Ok(())
+}
+
+fn fetcher() -> &'static Fetcher {
+    FETCHER.get_or_init(|| {
+        Fetcher::new(WebFetchConfig::default(), None, TlsBackend::default())
+            .expect("默认网页抓取配置应可构建 HTTP Client")
+    })
+}
+
+/// 抓取 URL，应用配置与工具定义中的访问限制
+pub async fn fetch(url: &str, tool: &Tool) -> Result<FetchedDocument, FetchError> {
+    fetcher().fetch(url, tool).await
+}
+
+impl Fetcher {
+    fn new(
+        config: WebFetchConfig,
+        proxy: Option<&ProxyConfig>,
+        tls_backend: TlsBackend,
+    ) -> anyhow::Result<Self> {
+        Self::with_resolver(config, proxy, tls_backend, SystemResolver)
+    }
+
+    fn with_resolver<R: Resolve + 'static>(
+        config: WebFetchConfig,
+        proxy: Option<&ProxyConfig>,
+        tls_backend: TlsBackend,
+        resolver: R,
+    ) -> anyhow::Result<Self> {
+        let proxy = if config.use_proxy { proxy } else { None };
+        // 重定向由 fetch 手动跟随，以便逐跳校验访问限制
+        let builder = client_builder(proxy, config.timeout_secs.max(1), tls_backend)?
+            .redirect(Policy::none());
+        let builder = if config.allow_private_hosts {
+            builder.dns_resolver(Arc::new(resolver))
+        } else {
+            builder.dns_resolver(Arc::new(PublicResolver(resolver)))
+        };
+        Ok(Self {
+            client: builder.build()?,
+            config,
+        })
+    }
+
+    async fn fetch(&self, raw_url: &str, tool: &Tool) -> Result<FetchedDocument, FetchError> {
+        if raw_url.len() > MAX_URL_LENGTH {
+            return Err(FetchError::UrlTooLong);
+        }
+        let mut url =
+            Url::parse(raw_url.trim()).map_err(|e| FetchError::InvalidInput(e.to_string()))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            self.check_url(&url, tool)?;
+
+            let response = self
+                .client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(send_error)?;
+            let status = response.status();
+
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or_else(|| {
+                        FetchError::UrlNotAccessible("重定向缺少 Location".to_string())
+                    })?;
+                url = url
+                    .join(location)
+                    .map_err(|e| FetchError::UrlNotAccessible(e.to_string()))?;
+                continue;
+            }
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Err(FetchError::TooManyRequests);
+            }
+            if !status.is_success() {
+                return Err(FetchError::UrlNotAccessible(format!("HTTP {}", status)));
+            }
+
+            let content_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("text/plain")
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+            if !self
+                .config
+                .allowed_content_types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(&content_type))
+            {
+                return Err(FetchError::UnsupportedContentType(content_type));
+            }
+
+            let (body, mut truncated) = self.read_body(response).await?;
+            let (title, mut text) = if content_type.contains("html") {
+                html_to_text(&body)
+            } else {
+                (None, body)
+            };
+
+            if let Some(max_tokens) = tool.max_content_tokens.filter(|t| *t > 0) {
+                // 粗略按 4 字符 ≈ 1 token 截断
+                if let Some((idx, _)) = text.char_indices().nth(max_tokens as usize * 4) {
+                    text.truncate(idx);
+                    truncated = true;
+                }
+            }
+
+            return Ok(FetchedDocument {
+                url: url.to_string(),
+                title,
+                text,
+                truncated,
+                retrieved_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+
+        Err(FetchError::UrlNotAccessible("重定向次数过多".to_string()))
+    }
+
+    /// 读取响应体，超过 max_bytes 时截断
+    async fn read_body(&self, response: reqwest::Response) -> Result<(String, bool), FetchError> {
+        let max_bytes = self.config.max_bytes;
+        let mut body = Vec::new();
+        let mut truncated = false;
+        let mut stream = response.bytes_stream();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| FetchError::UrlNotAccessible(e.to_string()))?;
+            let remaining = max_bytes.saturating_sub(body.len());
+            if chunk.len() > remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok((String::from_utf8_lossy(&body).into_owned(), truncated))
+    }
+
+    /// 校验协议、内网地址与域名限制
+    fn check_url(&self, url: &Url, tool: &Tool) -> Result<(), FetchError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(FetchError::InvalidInput(format!(
+                "不支持的协议: {}",
+                url.scheme()
+            )));
+        }
+        let Some(host) = url.host_str() else {
+            return Err(FetchError::InvalidInput("缺少主机名".to_string()));
+        };
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_lowercase();
+
+        if !self.config.allow_private_hosts && is_private_host(&host) {
+            return Err(FetchError::UrlNotAllowed(host));
+        }
+
+        let empty = Vec::new();
+        let allowed_by_config = is_domain_allowed(
+            &host,
+            &self.config.allowed_domains,
+            &self.config.blocked_domains,
+        );
+        let allowed_by_tool = is_domain_allowed(
+            &host,
+            tool.allowed_domains.as_ref().unwrap_or(&empty),
+            tool.blocked_domains.as_ref().unwrap_or(&empty),
+        );
+        if !allowed_by_config || !allowed_by_tool {
+            return Err(FetchError::UrlNotAllowed(host));
+        }
+        Ok(())
+    }
+}
+
+/// 系统 DNS 解析
+struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 过滤内网地址的 DNS 解析器（防止域名解析到内网地址绕过访问限制）
+struct PublicResolver<R>(R);
+
+impl<R: Resolve> Resolve for PublicResolver<R> {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let resolving = self.0.resolve(name);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = resolving
+                .await?
+                .filter(|addr| !is_private_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(Box::new(PrivateAddress(host)) as _);
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 域名只解析到内网地址
+#[derive(Debug)]
+struct PrivateAddress(String);
+
+impl std::fmt::Display for PrivateAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "域名解析到内网地址: {}", self.0)
+    }
+}
+
+impl std::error::Error for PrivateAddress {}
+
+/// 请求发送失败：解析到内网地址视为不允许访问
+fn send_error(error: reqwest::Error) -> FetchError {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&error);
+    while let Some(e) = source {
+        if let Some(private) = e.downcast_ref::<PrivateAddress>() {
+            return FetchError::UrlNotAllowed(private.0.clone());
+        }
+        source = e.source();
+    }
+    FetchError::UrlNotAccessible(error.to_string())
+}
+
+/// 域名是否匹配规则（规则同时匹配其子域名）
+fn domain_matches(host: &str, rule: &str) -> bool {
+    let rule = rule
+        .trim()
+        .trim_start_matches("*.")
+        .trim_start_matches('.')
+        .to_lowercase();
+    !rule.is_empty() && (host == rule || host.ends_with(&format!(".{}", rule)))
+}
+
+/// 黑名单优先；白名单为空时不限制
+fn is_domain_allowed(host: &str, allowed: &[String], blocked: &[String]) -> bool {
+    if blocked.iter().any(|d| domain_matches(host, d)) {
+        return false;
+    }
+    allowed.is_empty() || allowed.iter().any(|d| domain_matches(host, d))
+}
+
+/// 是否为 localhost 或内网 IP 字面量
+fn is_private_host(host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    if host == "localhost" || host.ends_with(".localhost") {
+        return true;
+    }
+    host.parse::<IpAddr>().is_ok_and(is_private_ip)
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => is_private_ipv6(ip),
+    }
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_private_ipv4(v4);
+    }
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || (first & 0xfe00) == 0xfc00 // 唯一本地地址 fc00::/7
+        || (first & 0xffc0) == 0xfe80 // 链路本地地址 fe80::/10
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64 // 运营商级 NAT 100.64.0.0/10
+}
+
+/// 构建 web_fetch_tool_result 的成功内容
+pub fn fetch_result_content(doc: &FetchedDocument) -> Value {
+    json!({
+        "type": "web_fetch_result",
+        "url": doc.url,
+        "content": {
+            "type": "document",
+            "source": {
+                "type": "text",
+                "media_type": "text/plain",
+                "data": doc.text
+            },
+            "title": doc.title
+        },
+        "retrieved_at": doc.retrieved_at
+    })
+}
+
+/// 构建 web_fetch_tool_result 的错误内容
+pub fn fetch_error_content(error_code: &str) -> Value {
+    json!({
+        "type": "web_fetch_tool_error",
+        "error_code": error_code
+    })
+}
+
+/// 生成提供给模型的抓取结果文本
+pub fn generate_fetch_summary(doc: &FetchedDocument) -> String {
+    let mut summary = format!("Content of {}", doc.url);
+    if let Some(title) = &doc.title {
+        summary.push_str(&format!(" (title: {})", title));
+    }
+    summary.push_str(":\n\n");
+    summary.push_str(&doc.text);
+    if doc.truncated {
+        summary.push_str("\n\n[Content truncated]");
+    }
+    summary
+}
+
+/// 内容会被整体跳过的标签
+const SKIPPED_TAGS: &[&str] = &["script", "style", "noscript", "template", "svg", "iframe"];
+
+/// 块级标签（前后换行）
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "nav",
+    "aside",
+    "ul",
+    "ol",
+    "table",
+    "tr",
+    "blockquote",
+    "pre",
+    "figure",
+    "form",
+    "dl",
+    "dt",
+    "dd",
+];
+
+/// 将 HTML 转换为 Markdown 风格的可读文本，返回 (标题, 正文)
+pub fn html_to_text(html: &str) -> (Option<String>, String) {
+    let mut out = String::new();
+    let mut title: Option<String> = None;
+    let mut in_title = false;
+    let mut pre_depth = 0usize;
+    // 未闭合的链接：(链接文本在 out 中的起始位置, href)
+    let mut links: Vec<(usize, Option<String>)> = Vec::new();
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_text(&mut out, rest, pre_depth > 0, in_title, &mut title);
+            break;
+        };
+        push_text(&mut out, &rest[..lt], pre_depth > 0, in_title, &mut title);
+        rest = &rest[lt..];
+
+        // 注释与声明
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        let Some(gt) = rest.find('>') else {
+            push_text(&mut out, rest, pre_depth > 0, in_title, &mut title);
+            break;
+        };
+        let tag = &rest[1..gt];
+        let closing = tag.starts_with('/');
+        let tag_body = tag.trim_start_matches('/');
+        let name: String = tag_body
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        if name.is_empty() {
+            // 不是合法标签（如 "a < b"），按文本处理
+            push_text(&mut out, "<", pre_depth > 0, in_title, &mut title);
+            rest = &rest[1..];
+            continue;
+        }
+        rest = &rest[gt + 1..];
+
+        if !closing && SKIPPED_TAGS.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            rest = find_ignore_case(rest, &close)
+                .and_then(|idx| rest[idx..].find('>').map(|end| &rest[idx + end + 1..]))
+                .unwrap_or("");
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("title", false) => in_title = true,
+            ("title", true) => in_title = false,
+            ("br", _) => out.push('\n'),
+            ("hr", _) => push_block(&mut out, "\n---\n"),
+            ("li", false) => {
+                ensure_newline(&mut out);
+                out.push_str("- ");
+            }
+            ("pre", false) => {
+                pre_depth += 1;
+                push_block(&mut out, "\n```\n");
+            }
+            ("pre", true) => {
+                pre_depth = pre_depth.saturating_sub(1);
+                push_block(&mut out, "\n```\n");
+            }
+            ("td" | "th", false) => out.push(' '),
+            ("a", false) => {
+                links.push((out.len(), attr_value(tag_body, "href")));
+                out.push('[');
+            }
+            ("a", true) => {
+                if let Some((start, href)) = links.pop() {
+                    let text = out[start + 1..].trim().to_string();
+                    match href.filter(|h| h.starts_with("http")) {
+                        Some(href) if !text.is_empty() => {
+                            out.truncate(start);
+                            out.push_str(&format!("[{}]({})", text, href));
+                        }
+                        _ => {
+                            out.truncate(start);
+                            out.push_str(&text);
+                        }
+                    }
+                }
+            }
+            (h, false) if is_heading(h) => {
+                let level = h[1..].parse::<usize>().unwrap_or(1);
+                push_block(&mut out, "\n\n");
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            (h, true) if is_heading(h) => push_block(&mut out, "\n\n"),
+            (block, _) if BLOCK_TAGS.contains(&block) => push_block(&mut out, "\n\n"),
+            _ => {}
+        }
+    }
+
+    // 未闭合的链接保留文本
+    for (start, _) in links.into_iter().rev() {
+        if out.get(start..start + 1) == Some("[") {
+            out.remove(start);
+        }
+    }
+
+    (
+        title
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty()),
+        normalize_lines(&out),
+    )
+}
+
+fn is_heading(name: &str) -> bool {
+    name.len() == 2
+        && name.starts_with('h')
+        && matches!(&name[1..], "1" | "2" | "3" | "4" | "5" | "6")
+}
+
+/// 输出文本：非 pre 内折叠空白，title 内容单独收集
+fn push_text(
+    out: &mut String,
+    raw: &str,
+    preformatted: bool,
+    in_title: bool,
+    title: &mut Option<String>,
+) {
+    if raw.is_empty() {
+        return;
+    }
+    let text = decode_entities(raw);
+    if in_title {
+        title.get_or_insert_with(String::new).push_str(&text);
+        return;
+    }
+    if preformatted {
+        out.push_str(&text);
+        return;
+    }
+    for (i, word) in text.split_whitespace().enumerate() {
+        let needs_space = (i > 0 || text.starts_with(char::is_whitespace))
+            && !out.is_empty()
+            && !out.ends_with(char::is_whitespace)
+            && !out.ends_with('[');
+        if needs_space {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+        out.push(' ');
+    }
+}
+
+fn push_block(out: &mut String, separator: &str) {
+    let trimmed_len = out.trim_end_matches(' ').len();
+    out.truncate(trimmed_len);
+    out.push_str(separator);
+}
+
+fn ensure_newline(out: &mut String) {
+    let trimmed_len = out.trim_end_matches(' ').len();
+    out.truncate(trimmed_len);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// 去除行首尾空白，合并多余空行（代码块内保持原样）
+fn normalize_lines(text: &str) -> String {
+    let mut result = String::new();
+    let mut blank_lines = 0;
+    let mut in_fence = false;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() && !in_fence {
+            blank_lines += 1;
+            continue;
+        }
+        if !result.is_empty() {
+            result.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        blank_lines = 0;
+        if line.trim() == "```" {
+            in_fence = !in_fence;
+            result.push_str("```");
+        } else if in_fence {
+            result.push_str(line);
+        } else {
+            result.push_str(line.trim_start_matches(' '));
+        }
+    }
+    result
+}
+
+/// 大小写不敏感查找
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+/// 读取标签属性值
+fn attr_value(tag: &str, attr: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(pos) = lower[search_from..].find(attr) {
+        let start = search_from + pos;
+        search_from = start + attr.len();
+        // 属性名前必须是空白，避免匹配到 data-href 之类
+        if !lower[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let after = lower[search_from..].trim_start();
+        if !after.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - after.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// 解码常见 HTML 实体
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| {
+                let entity = &rest[1..end + 1];
+                let ch = match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" | "#39" => Some('\''),
+                    "nbsp" => Some(' '),
+                    _ => entity
+                        .strip_prefix("#x")
+                        .or_else(|| entity.strip_prefix("#X"))
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                        .and_then(char::from_u32),
+                };
+                ch.map(|c| (c, end + 2))
+            });
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Rust &amp; Cargo</title>
+<style>body { color: red; }</style>
+<script>var x = "<p>no</p>";</script></head>
+<body>
+<h1>Getting   started</h1>
+<p>Install with <a href="https://rustup.rs">rustup</a>.<br>Then run <code>cargo new</code>.</p>
+<!-- comment -->
+<ul><li>Fast</li><li>Safe &lt;3</li></ul>
+<pre>fn main() {
+    println!("hi");
+}</pre>
+</body></html>"#;
+
+        let (title, text) = html_to_text(html);
+        assert_eq!(title.as_deref(), Some("Rust & Cargo"));
+        assert!(text.starts_with("# Getting started"));
+        assert!(text.contains("Install with [rustup](https://rustup.rs).\nThen run cargo new."));
+        assert!(text.contains("- Fast\n- Safe <3"));
+        assert!(text.contains("    println!(\"hi\");"));
+        assert!(!text.contains("color: red"));
+        assert!(!text.contains("no</p>"));
+        assert!(!text.contains("comment"));
+    }
+
+    #[test]
+    fn test_domain_rules() {
+        let allowed = vec!["rust-lang.org".to_string()];
+        let blocked = vec!["blog.rust-lang.org".to_string()];
+        assert!(is_domain_allowed("www.rust-lang.org", &allowed, &blocked));
+        assert!(is_domain_allowed("rust-lang.org", &allowed, &blocked));
+        assert!(!is_domain_allowed("blog.rust-lang.org", &allowed, &blocked));
+        assert!(!is_domain_allowed("evil-rust-lang.org", &allowed, &blocked));
+        assert!(is_domain_allowed("example.com", &[], &blocked));
+    }
+
+    #[test]
+    fn test_private_hosts() {
+        assert!(is_private_host("localhost"));
+        assert!(is_private_host("127.0.0.1"));
+        assert!(is_private_host("10.1.2.3"));
+        assert!(is_private_host("169.254.169.254"));
+        assert!(is_private_host("::1"));
+        assert!(is_private_host("::ffff:192.168.1.1"));
+        assert!(is_private_host("fd00::1"));
+        assert!(!is_private_host("8.8.8.8"));
+        assert!(!is_private_host("example.com"));
+        assert!(is_private_host("localhost."));
+    }
+
+    /// 把所有域名解析到本机
+    struct LoopbackResolver;
+
+    impl Resolve for LoopbackResolver {
+        fn resolve(&self, _name: Name) -> Resolving {
+            Box::pin(async {
+                Ok(Box::new(std::iter::once(SocketAddr::from(([127, 0, 0, 1], 0)))) as Addrs)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hostname_resolving_to_loopback() {
+        let app = axum::Router::new().route("/", axum::routing::get(|| async { "secret" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let tool: Tool = serde_json::from_value(json!({
+            "type": "web_fetch_20250910",
+            "name": "web_fetch"
+        }))
+        .unwrap();
+        let url = format!("http://internal.example.com:{}/", port);
+
+        let fetcher = Fetcher::with_resolver(
+            WebFetchConfig::default(),
+            None,
+            TlsBackend::Rustls,
+            LoopbackResolver,
+        )
+        .unwrap();
+        let error = fetcher.fetch(&url, &tool).await.unwrap_err();
+        assert_eq!(error.error_code(), "url_not_allowed");
+
+        // 允许内网地址时正常抓取
+        let config = WebFetchConfig {
+            allow_private_hosts: true,
+            ..Default::default()
+        };
+        let fetcher =
+            Fetcher::with_resolver(config, None, TlsBackend::Rustls, LoopbackResolver).unwrap();
+        let doc = fetcher.fetch(&url, &tool).await.unwrap();
+        assert_eq!(doc.text, "secret");
+    }
+
+    #[test]
+    fn test_check_url() {
+        let fetcher = Fetcher::new(WebFetchConfig::default(), None, TlsBackend::Rustls).unwrap();
+        let tool: Tool = serde_json::from_value(json!({
+            "type": "web_fetch_20250910",
+            "name": "web_fetch",
+            "blocked_domains": ["example.org"]
+        }))
+        .unwrap();
+
+        let check = |url: &str| fetcher.check_url(&Url::parse(url).unwrap(), &tool);
+        assert!(check("https://example.com/a").is_ok());
+        assert_eq!(
+            check("ftp://example.com").unwrap_err().error_code(),
+            "invalid_input"
+        );
+        assert_eq!(
+            check("http://127.0.0.1:8080").unwrap_err().error_code(),
+            "url_not_allowed"
+        );
+        assert_eq!(
+            check("https://www.example.org").unwrap_err().error_code(),
+            "url_not_allowed"
+        );
+    }
+}
@@ -1,22 +1,13 @@
 //! WebSearch 工具处理模块
 //!
-//! 实现 Anthropic WebSearch 请求到 Kiro MCP 的转换和响应生成
+//! 实现 Kiro MCP 网页搜索调用，以及搜索结果到 Anthropic 内容块的转换
+//! （搜索后端的选择见 `search_backend`）
 
-use std::convert::Infallible;
-
-use axum::{
-    body::Body,
-    http::{StatusCode, header},
-    response::{IntoResponse, Json, Response},
-};
-use bytes::Bytes;
-use futures::{Stream, stream};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 
-use super::stream::SseEvent;
-use super::types::{ErrorResponse, MessagesRequest};
+use super::types::{MessagesRequest, Tool};
 
 /// MCP 请求
 #[derive(Debug, Serialize)]
@@ -73,7 +64,7 @@ pub struct McpContent {
 }
 
 /// WebSearch 搜索结果
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct WebSearchResults {
     pub results: Vec<WebSearchResult>,
     #[serde(rename = "totalResults")]
@@ -98,47 +89,38 @@ pub struct WebSearchResult {
     pub public_domain: Option<bool>,
 }
 
-/// 检查请求是否为纯 WebSearch 请求
+/// 未设置 `max_uses` 时单次请求允许的最大搜索次数
+pub const DEFAULT_MAX_USES: i32 = 5;
+
+/// web_search 工具提供给模型的描述
+pub const WEB_SEARCH_TOOL_DESCRIPTION: &str = "Search the web for up-to-date information. \
+Use this when the answer depends on recent events or facts you are not sure about. \
+Returns a list of result titles, URLs and snippets.";
+
+/// 检查请求是否声明了 WebSearch 服务端工具
 ///
-/// 条件：tools 有且只有一个，且 name 为 web_search
+/// 可与客户端自定义工具同时存在
 pub fn has_web_search_tool(req: &MessagesRequest) -> bool {
-    req.tools.as_ref().is_some_and(|tools| {
-        tools.len() == 1 && tools.first().is_some_and(|t| t.name == "web_search")
-    })
+    web_search_tool(req).is_some()
 }
 
-/// 从消息中提取搜索查询
-///
-/// 读取 messages 的第一条消息的第一个内容块
-/// 并去除 "Perform a web search for the query: " 前缀
-pub fn extract_search_query(req: &MessagesRequest) -> Option<String> {
-    // 获取第一条消息
-    let first_msg = req.messages.first()?;
-
-    // 提取文本内容
-    let text = match &first_msg.content {
-        serde_json::Value::String(s) => s.clone(),
-        serde_json::Value::Array(arr) => {
-            // 获取第一个内容块
-            let first_block = arr.first()?;
-            if first_block.get("type")?.as_str()? == "text" {
-                first_block.get("text")?.as_str()?.to_string()
-            } else {
-                return None;
-            }
-        }
-        _ => return None,
-    };
-
-    // 去除前缀 "Perform a web search for the query: "
-    const PREFIX: &str = "Perform a web search for the query: ";
-    let query = if text.starts_with(PREFIX) {
-        text[PREFIX.len()..].to_string()
-    } else {
-        text
-    };
+/// 获取请求中的 WebSearch 工具定义
+pub fn web_search_tool(req: &MessagesRequest) -> Option<&Tool> {
+    req.tools.as_ref()?.iter().find(|t| t.is_web_search())
+}
 
-    if query.is_empty() { None } else { Some(query) }
+/// web_search 工具的输入参数 schema（转换为普通 Kiro 工具时使用）
+pub fn web_search_input_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "query": {
+                "type": "string",
+                "description": "The search query"
+            }
+        },
+        "required": ["query"]
+    })
 }
 
 /// 生成22位大小写字母和数字的随机字符串
@@ -163,6 +145,14 @@ fn generate_random_id_8() -> String {
         .collect()
 }
 
+/// 生成服务端工具调用 ID（srvtoolu_ 前缀）
+pub fn server_tool_use_id() -> String {
+    format!(
+        "srvtoolu_{}",
+        Uuid::new_v4().to_string().replace('-', "")[..32].to_string()
+    )
+}
+
 /// 创建 MCP 请求
 ///
 /// ID 格式: web_search_tooluse_{22位随机}_{毫秒时间戳}_{8位随机}
@@ -176,11 +166,7 @@ pub fn create_mcp_request(query: &str) -> (String, McpRequest) {
         random_22, timestamp, random_8
     );
 
-    // tool_use_id 使用相同格式
-    let tool_use_id = format!(
-        "srvtoolu_{}",
-        Uuid::new_v4().to_string().replace('-', "")[..32].to_string()
-    );
+    let tool_use_id = server_tool_use_id();
 
     let request = McpRequest {
         id: request_id,
@@ -209,222 +195,64 @@ pub fn parse_search_results(mcp_response: &McpResponse) -> Option<WebSearchResul
     serde_json::from_str(&content.text).ok()
 }
 
-/// 生成 WebSearch SSE 响应流
-pub fn create_websearch_sse_stream(
-    model: String,
-    query: String,
-    tool_use_id: String,
-    search_results: Option<WebSearchResults>,
-    input_tokens: i32,
-) -> impl Stream<Item = Result<Bytes, Infallible>> {
-    let events =
-        generate_websearch_events(&model, &query, &tool_use_id, search_results, input_tokens);
-
-    stream::iter(
-        events
-            .into_iter()
-            .map(|e| Ok(Bytes::from(e.to_sse_string()))),
-    )
-}
-
-/// 生成 WebSearch SSE 事件序列
-fn generate_websearch_events(
-    model: &str,
-    query: &str,
-    tool_use_id: &str,
-    search_results: Option<WebSearchResults>,
-    input_tokens: i32,
-) -> Vec<SseEvent> {
-    let mut events = Vec::new();
-    let message_id = format!(
-        "msg_{}",
-        Uuid::new_v4().to_string().replace('-', "")[..24].to_string()
-    );
-
-    // 1. message_start
-    events.push(SseEvent::new(
-        "message_start",
-        json!({
-            "type": "message_start",
-            "message": {
-                "id": message_id,
-                "type": "message",
-                "role": "assistant",
-                "model": model,
-                "content": [],
-                "stop_reason": null,
-                "stop_sequence": null,
-                "usage": {
-                    "input_tokens": input_tokens,
-                    "output_tokens": 0,
-                    "cache_creation_input_tokens": 0,
-                    "cache_read_input_tokens": 0
-                }
-            }
-        }),
-    ));
-
-    // 2. content_block_start (server_tool_use)
-    events.push(SseEvent::new(
-        "content_block_start",
-        json!({
-            "type": "content_block_start",
-            "index": 0,
-            "content_block": {
-                "id": tool_use_id,
-                "type": "server_tool_use",
-                "name": "web_search",
-                "input": {}
-            }
-        }),
-    ));
-
-    // 3. content_block_delta (input_json_delta)
-    let input_json = json!({"query": query});
-    events.push(SseEvent::new(
-        "content_block_delta",
-        json!({
-            "type": "content_block_delta",
-            "index": 0,
-            "delta": {
-                "type": "input_json_delta",
-                "partial_json": serde_json::to_string(&input_json).unwrap_or_default()
-            }
-        }),
-    ));
-
-    // 4. content_block_stop (server_tool_use)
-    events.push(SseEvent::new(
-        "content_block_stop",
-        json!({
-            "type": "content_block_stop",
-            "index": 0
-        }),
-    ));
-
-    // 5. content_block_start (web_search_tool_result)
-    let search_content = if let Some(ref results) = search_results {
-        results
-            .results
-            .iter()
-            .map(|r| {
-                json!({
-                    "type": "web_search_result",
-                    "title": r.title,
-                    "url": r.url,
-                    "encrypted_content": r.snippet.clone().unwrap_or_default(),
-                    "page_age": null
-                })
-            })
-            .collect::<Vec<_>>()
-    } else {
-        vec![]
+/// 将搜索结果转换为 `web_search_tool_result` 块的 content 列表
+pub fn search_result_blocks(results: &Option<WebSearchResults>) -> Vec<serde_json::Value> {
+    let Some(results) = results else {
+        return Vec::new();
     };
 
-    events.push(SseEvent::new(
-        "content_block_start",
-        json!({
-            "type": "content_block_start",
-            "index": 1,
-            "content_block": {
-                "type": "web_search_tool_result",
-                "tool_use_id": tool_use_id,
-                "content": search_content
-            }
-        }),
-    ));
-
-    // 6. content_block_stop (web_search_tool_result)
-    events.push(SseEvent::new(
-        "content_block_stop",
-        json!({
-            "type": "content_block_stop",
-            "index": 1
-        }),
-    ));
-
-    // 7. content_block_start (text)
-    events.push(SseEvent::new(
-        "content_block_start",
-        json!({
-            "type": "content_block_start",
-            "index": 2,
-            "content_block": {
-                "type": "text",
-                "text": ""
-            }
-        }),
-    ));
-
-    // 8. content_block_delta (text_delta) - 生成搜索结果摘要
-    let summary = generate_search_summary(query, &search_results);
-
-    // 分块发送文本
-    let chunk_size = 100;
-    for chunk in summary.chars().collect::<Vec<_>>().chunks(chunk_size) {
-        let text: String = chunk.iter().collect();
-        events.push(SseEvent::new(
-            "content_block_delta",
+    results
+        .results
+        .iter()
+        .map(|r| {
             json!({
-                "type": "content_block_delta",
-                "index": 2,
-                "delta": {
-                    "type": "text_delta",
-                    "text": text
-                }
-            }),
-        ));
-    }
-
-    // 9. content_block_stop (text)
-    events.push(SseEvent::new(
-        "content_block_stop",
-        json!({
-            "type": "content_block_stop",
-            "index": 2
-        }),
-    ));
-
-    // 10. message_delta
-    let output_tokens = (summary.len() as i32 + 3) / 4; // 简单估算
-    events.push(SseEvent::new(
-        "message_delta",
-        json!({
-            "type": "message_delta",
-            "delta": {
-                "stop_reason": "end_turn",
-                "stop_sequence": null
-            },
-            "usage": {
-                "output_tokens": output_tokens
-            }
-        }),
-    ));
-
-    // 11. message_stop
-    events.push(SseEvent::new(
-        "message_stop",
-        json!({
-            "type": "message_stop"
-        }),
-    ));
-
-    events
+                "type": "web_search_result",
+                "title": r.title,
+                "url": r.url,
+                "encrypted_content": r.snippet.clone().unwrap_or_default(),
+                "page_age": null
+            })
+        })
+        .collect()
 }
 
 /// 生成搜索结果摘要
-fn generate_search_summary(query: &str, results: &Option<WebSearchResults>) -> String {
+///
+/// 作为 web_search 的 tool_result 回填给模型
+pub fn generate_search_summary(query: &str, results: &Option<WebSearchResults>) -> String {
+    format_search_results(query, results, &[])
+}
+
+/// 生成带引用标签的搜索结果摘要
+///
+/// `labels` 与搜索结果一一对应（见 `CitationIndex::add_search_results`），
+/// 模型在回答中写出的标签会被转换为 `web_search_result_location` 引用
+pub fn generate_cited_search_summary(
+    query: &str,
+    results: &Option<WebSearchResults>,
+    labels: &[String],
+) -> String {
+    format_search_results(query, results, labels)
+}
+
+fn format_search_results(
+    query: &str,
+    results: &Option<WebSearchResults>,
+    labels: &[String],
+) -> String {
     let mut summary = format!("Here are the search results for \"{}\":\n\n", query);
 
     if let Some(results) = results {
         for (i, result) in results.results.iter().enumerate() {
-            summary.push_str(&format!("{}. **{}**\n", i + 1, result.title));
+            match labels.get(i) {
+                Some(label) => summary.push_str(&format!("[{}] **{}**\n", label, result.title)),
+                None => summary.push_str(&format!("{}. **{}**\n", i + 1, result.title)),
+            }
             if let Some(ref snippet) = result.snippet {
-                // 截断过长的摘要
-                let truncated = if snippet.len() > 200 {
-                    format!("{}...", &snippet[..200])
-                } else {
-                    snippet.clone()
+                // 截断过长的摘要（按字符截断，避免切断 UTF-8 字符）
+                let truncated = match snippet.char_indices().nth(200) {
+                    Some((idx, _)) => format!("{}...", &snippet[..idx]),
+                    None => snippet.clone(),
                 };
                 summary.push_str(&format!("   {}\n", truncated));
             }
@@ -434,58 +262,37 @@ fn generate_search_summary(query: &str, results: &Option<WebSearchResults>) -> S
         summary.push_str("No results found.\n");
     }
 
+    if !labels.is_empty() {
+        summary.push_str(
+            "\nWhen your answer uses information from a result, cite it by writing its label \
+             right after the statement, for example: \"Rust 1.0 was released in 2015 [S1].\"",
+        );
+    }
     summary.push_str("\nPlease note that these are web search results and may not be fully accurate or up-to-date.");
 
     summary
 }
 
-/// 处理 WebSearch 请求
-pub async fn handle_websearch_request(
-    provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
-    payload: &MessagesRequest,
-    input_tokens: i32,
-) -> Response {
-    // 1. 提取搜索查询
-    let query = match extract_search_query(payload) {
-        Some(q) => q,
-        None => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(
-                    "invalid_request_error",
-                    "无法从消息中提取搜索查询",
-                )),
-            )
-                .into_response();
-        }
-    };
-
-    tracing::info!(query = %query, "处理 WebSearch 请求");
-
-    // 2. 创建 MCP 请求
-    let (tool_use_id, mcp_request) = create_mcp_request(&query);
-
-    // 3. 调用 Kiro MCP API
-    let search_results = match call_mcp_api(&provider, &mcp_request).await {
-        Ok(response) => parse_search_results(&response),
-        Err(e) => {
-            tracing::warn!("MCP API 调用失败: {}", e);
-            None
-        }
-    };
+/// 执行一次网页搜索，返回解析后的搜索结果
+///
+/// 经由配置的搜索后端执行（带超时与缓存），供服务端工具循环与 MCP 服务端 `web_search` 工具使用
+pub async fn search(
+    provider: &crate::kiro::provider::KiroProvider,
+    query: &str,
+) -> anyhow::Result<Option<WebSearchResults>> {
+    super::search_backend::service()
+        .search(provider, query)
+        .await
+}
 
-    // 4. 生成 SSE 响应
-    let model = payload.model.clone();
-    let stream =
-        create_websearch_sse_stream(model, query, tool_use_id, search_results, input_tokens);
-
-    Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, "text/event-stream")
-        .header(header::CACHE_CONTROL, "no-cache")
-        .header(header::CONNECTION, "keep-alive")
-        .body(Body::from_stream(stream))
-        .unwrap()
+/// 通过 Kiro MCP 执行网页搜索
+pub(super) async fn search_mcp(
+    provider: &crate::kiro::provider::KiroProvider,
+    query: &str,
+) -> anyhow::Result<Option<WebSearchResults>> {
+    let (_, mcp_request) = create_mcp_request(query);
+    let response = call_mcp_api(provider, &mcp_request).await?;
+    Ok(parse_search_results(&response))
 }
 
 /// 调用 Kiro MCP API
@@ -538,6 +345,9 @@ mod tests {
                 description: String::new(),
                 input_schema: Default::default(),
                 max_uses: Some(8),
+                allowed_domains: None,
+                blocked_domains: None,
+                max_content_tokens: None,
             }]),
             tool_choice: None,
             thinking: None,
@@ -548,7 +358,7 @@ mod tests {
     }
 
     #[test]
-    fn test_has_web_search_tool_multiple_tools() {
+    fn test_has_web_search_tool_with_client_tools() {
         use crate::anthropic::types::{Message, Tool};
 
         let req = MessagesRequest {
@@ -567,6 +377,9 @@ mod tests {
                     description: String::new(),
                     input_schema: Default::default(),
                     max_uses: Some(8),
+                    allowed_domains: None,
+                    blocked_domains: None,
+                    max_content_tokens: None,
                 },
                 Tool {
                     tool_type: None,
@@ -574,6 +387,9 @@ mod tests {
                     description: "Other tool".to_string(),
                     input_schema: Default::default(),
                     max_uses: None,
+                    allowed_domains: None,
+                    blocked_domains: None,
+                    max_content_tokens: None,
                 },
             ]),
             tool_choice: None,
@@ -581,58 +397,9 @@ mod tests {
             metadata: None,
         };
 
-        // 多个工具时不应该被识别为纯 websearch 请求
-        assert!(!has_web_search_tool(&req));
-    }
-
-    #[test]
-    fn test_extract_search_query_with_prefix() {
-        use crate::anthropic::types::Message;
-
-        let req = MessagesRequest {
-            model: "claude-sonnet-4".to_string(),
-            max_tokens: 1024,
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: serde_json::json!([{
-                    "type": "text",
-                    "text": "Perform a web search for the query: rust latest version 2026"
-                }]),
-            }],
-            stream: true,
-            system: None,
-            tools: None,
-            tool_choice: None,
-            thinking: None,
-            metadata: None,
-        };
-
-        let query = extract_search_query(&req);
-        // 前缀应该被去除
-        assert_eq!(query, Some("rust latest version 2026".to_string()));
-    }
-
-    #[test]
-    fn test_extract_search_query_plain_text() {
-        use crate::anthropic::types::Message;
-
-        let req = MessagesRequest {
-            model: "claude-sonnet-4".to_string(),
-            max_tokens: 1024,
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: serde_json::json!("What is the weather today?"),
-            }],
-            stream: true,
-            system: None,
-            tools: None,
-            tool_choice: None,
-            thinking: None,
-            metadata: None,
-        };
-
-        let query = extract_search_query(&req);
-        assert_eq!(query, Some("What is the weather today?".to_string()));
+        // web_search 可与客户端工具同时使用
+        assert!(has_web_search_tool(&req));
+        assert_eq!(web_search_tool(&req).unwrap().max_uses, Some(8));
     }
 
     #[test]
@@ -0,0 +1,725 @@
+//! Message Batches 任务管理器
+//!
+//! 负责批处理的创建、调度执行、取消以及重启后的恢复
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use super::store::{BatchStore, ResultWriter};
+use super::types::{
+    BATCH_ID_PREFIX, BatchRequestItem, BatchResult, BatchResultLine, CreateBatchRequest,
+    ListBatchesQuery, ListBatchesResponse, MessageBatch, ProcessingStatus, RequestCounts,
+};
+
+/// 单个批处理最多包含的请求数
+const MAX_REQUESTS_PER_BATCH: usize = 100_000;
+
+/// custom_id 最大长度
+const MAX_CUSTOM_ID_LEN: usize = 64;
+
+/// 批处理有效期（小时），超时未执行的请求标记为 expired
+const BATCH_EXPIRY_HOURS: i64 = 24;
+
+/// 列表默认/最大分页大小
+const DEFAULT_LIST_LIMIT: usize = 20;
+const MAX_LIST_LIMIT: usize = 1000;
+
+/// 单个请求执行失败的错误信息
+#[derive(Debug, Clone)]
+pub struct BatchItemError {
+    /// Anthropic 错误类型（如 invalid_request_error、api_error）
+    pub error_type: String,
+    /// 错误消息
+    pub message: String,
+}
+
+impl BatchItemError {
+    pub fn new(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            error_type: error_type.into(),
+            message: message.into(),
+        }
+    }
+
+    /// 转换为 Anthropic 错误对象
+    fn to_error_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": self.error_type,
+                "message": self.message
+            }
+        })
+    }
+}
+
+/// 批处理请求执行器
+///
+/// 接收单个请求的 `params`，返回 Message 响应 JSON
+pub type BatchExecutor = Arc<
+    dyn Fn(serde_json::Value) -> BoxFuture<'static, Result<serde_json::Value, BatchItemError>>
+        + Send
+        + Sync,
+>;
+
+/// 批处理管理错误
+#[derive(Debug)]
+pub enum BatchError {
+    /// 批处理不存在
+    NotFound(String),
+    /// 请求参数无效
+    InvalidRequest(String),
+    /// 内部错误（存储失败等）
+    Internal(String),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::NotFound(id) => write!(f, "批处理不存在: {}", id),
+            BatchError::InvalidRequest(msg) => write!(f, "{}", msg),
+            BatchError::Internal(msg) => write!(f, "内部错误: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// 批处理管理器
+pub struct BatchManager {
+    store: BatchStore,
+    executor: BatchExecutor,
+    /// 全局并发控制（所有批处理共享）
+    semaphore: Arc<Semaphore>,
+    batches: Mutex<HashMap<String, MessageBatch>>,
+    /// 运行中批处理的取消标志
+    cancel_flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl BatchManager {
+    /// 创建管理器并加载磁盘上已有的批处理
+    ///
+    /// 未结束的批处理需调用 [`BatchManager::resume`] 继续执行
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        concurrency: usize,
+        executor: BatchExecutor,
+    ) -> anyhow::Result<Arc<Self>> {
+        let store = BatchStore::new(dir)?;
+
+        let mut batches = HashMap::new();
+        for mut batch in store.load_all()? {
+            if batch.processing_status != ProcessingStatus::Ended {
+                // 以落盘结果为准重新统计（进程崩溃时元数据可能落后于结果文件）
+                let total = store.load_requests(&batch.id)?.len();
+                let results = store.load_results(&batch.id)?;
+                batch.request_counts = count_results(total, &results);
+            }
+            batches.insert(batch.id.clone(), batch);
+        }
+
+        Ok(Arc::new(Self {
+            store,
+            executor,
+            semaphore: Arc::new(Semaphore::new(concurrency.max(1))),
+            batches: Mutex::new(batches),
+            cancel_flags: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// 恢复所有未结束的批处理（需在 tokio 运行时中调用）
+    pub fn resume(self: &Arc<Self>) {
+        let pending: Vec<(String, bool)> = self
+            .batches
+            .lock()
+            .values()
+            .filter(|b| b.processing_status != ProcessingStatus::Ended)
+            .map(|b| {
+                (
+                    b.id.clone(),
+                    b.processing_status == ProcessingStatus::Canceling,
+                )
+            })
+            .collect();
+
+        for (id, canceling) in pending {
+            tracing::info!("恢复未完成的批处理: {}", id);
+            self.spawn_batch(id, canceling);
+        }
+    }
+
+    /// 创建批处理并开始后台执行
+    pub fn create(
+        self: &Arc<Self>,
+        request: CreateBatchRequest,
+    ) -> Result<MessageBatch, BatchError> {
+        validate_requests(&request.requests)?;
+
+        let now = Utc::now();
+        let batch = MessageBatch {
+            id: format!("{}{}", BATCH_ID_PREFIX, Uuid::new_v4().simple()),
+            batch_type: "message_batch".to_string(),
+            processing_status: ProcessingStatus::InProgress,
+            request_counts: RequestCounts {
+                processing: request.requests.len() as u64,
+                ..Default::default()
+            },
+            ended_at: None,
+            created_at: format_time(now),
+            expires_at: format_time(now + Duration::hours(BATCH_EXPIRY_HOURS)),
+            cancel_initiated_at: None,
+            archived_at: None,
+            results_url: None,
+        };
+
+        self.store
+            .create(&batch, &request.requests)
+            .map_err(|e| BatchError::Internal(format!("保存批处理失败: {}", e)))?;
+
+        tracing::info!(
+            "创建批处理 {}，共 {} 个请求",
+            batch.id,
+            request.requests.len()
+        );
+        self.batches.lock().insert(batch.id.clone(), batch.clone());
+        self.spawn_batch(batch.id.clone(), false);
+
+        Ok(batch)
+    }
+
+    /// 获取批处理
+    pub fn get(&self, id: &str) -> Option<MessageBatch> {
+        self.batches.lock().get(id).cloned()
+    }
+
+    /// 分页列出批处理（按创建时间倒序）
+    pub fn list(&self, query: &ListBatchesQuery) -> Result<ListBatchesResponse, BatchError> {
+        let mut all: Vec<MessageBatch> = self.batches.lock().values().cloned().collect();
+        all.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .clamp(1, MAX_LIST_LIMIT);
+        let position = |id: &str| {
+            all.iter()
+                .position(|b| b.id == id)
+                .ok_or_else(|| BatchError::InvalidRequest(format!("无效的分页游标: {}", id)))
+        };
+
+        let (start, end, has_more) = if let Some(after_id) = &query.after_id {
+            let start = position(after_id)? + 1;
+            let end = (start + limit).min(all.len());
+            (start, end, end < all.len())
+        } else if let Some(before_id) = &query.before_id {
+            let end = position(before_id)?;
+            let start = end.saturating_sub(limit);
+            (start, end, start > 0)
+        } else {
+            let end = limit.min(all.len());
+            (0, end, end < all.len())
+        };
+
+        let data: Vec<MessageBatch> = all[start..end].to_vec();
+        Ok(ListBatchesResponse {
+            first_id: data.first().map(|b| b.id.clone()),
+            last_id: data.last().map(|b| b.id.clone()),
+            data,
+            has_more,
+        })
+    }
+
+    /// 取消批处理
+    ///
+    /// 已在执行中的请求会继续完成，尚未开始的请求标记为 canceled
+    pub fn cancel(&self, id: &str) -> Result<MessageBatch, BatchError> {
+        let mut batches = self.batches.lock();
+        let batch = batches
+            .get_mut(id)
+            .ok_or_else(|| BatchError::NotFound(id.to_string()))?;
+
+        if batch.processing_status == ProcessingStatus::InProgress {
+            batch.processing_status = ProcessingStatus::Canceling;
+            batch.cancel_initiated_at = Some(format_time(Utc::now()));
+            if let Err(e) = self.store.save_batch(batch) {
+                tracing::error!("保存批处理 {} 失败: {}", id, e);
+            }
+            if let Some(flag) = self.cancel_flags.lock().get(id) {
+                flag.store(true, Ordering::SeqCst);
+            }
+            tracing::info!("批处理 {} 已发起取消", id);
+        }
+
+        Ok(batch.clone())
+    }
+
+    /// 获取结果文件路径（仅在批处理结束后可用）
+    pub fn results_path(&self, id: &str) -> Result<PathBuf, BatchError> {
+        let batches = self.batches.lock();
+        let batch = batches
+            .get(id)
+            .ok_or_else(|| BatchError::NotFound(id.to_string()))?;
+        if batch.processing_status != ProcessingStatus::Ended {
+            return Err(BatchError::InvalidRequest(format!(
+                "批处理 {} 尚未结束，结果暂不可用",
+                id
+            )));
+        }
+        Ok(self.store.results_path(id))
+    }
+
+    fn spawn_batch(self: &Arc<Self>, id: String, canceled: bool) {
+        let flag = Arc::new(AtomicBool::new(canceled));
+        self.cancel_flags.lock().insert(id.clone(), flag.clone());
+        let manager = self.clone();
+        tokio::spawn(async move { manager.run_batch(id, flag).await });
+    }
+
+    async fn run_batch(self: Arc<Self>, id: String, cancel: Arc<AtomicBool>) {
+        // 先打开结果文件（截断中断时残留的不完整末行），再读取已完成的结果
+        let writer = match self.store.open_results(&id) {
+            Ok(writer) => Arc::new(writer),
+            Err(e) => {
+                tracing::error!("打开批处理 {} 的结果文件失败: {}", id, e);
+                return;
+            }
+        };
+        let items = self.store.load_requests(&id).unwrap_or_else(|e| {
+            tracing::error!("读取批处理 {} 的请求失败: {}", id, e);
+            Vec::new()
+        });
+        let done: HashSet<String> = self
+            .store
+            .load_results(&id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| r.custom_id)
+            .collect();
+        let expires_at = self
+            .get(&id)
+            .and_then(|b| DateTime::parse_from_rfc3339(&b.expires_at).ok());
+
+        let mut tasks = JoinSet::new();
+        for item in items.into_iter().filter(|i| !done.contains(&i.custom_id)) {
+            let Ok(permit) = self.semaphore.clone().acquire_owned().await else {
+                break;
+            };
+
+            if cancel.load(Ordering::SeqCst) {
+                self.record(&id, &writer, item.custom_id, BatchResult::Canceled)
+                    .await;
+                continue;
+            }
+            if expires_at.is_some_and(|t| Utc::now() >= t) {
+                self.record(&id, &writer, item.custom_id, BatchResult::Expired)
+                    .await;
+                continue;
+            }
+
+            let manager = self.clone();
+            let id = id.clone();
+            let writer = writer.clone();
+            tasks.spawn(async move {
+                let result = match (manager.executor)(item.params).await {
+                    Ok(message) => BatchResult::Succeeded { message },
+                    Err(e) => {
+                        tracing::warn!(
+                            "批处理 {} 请求 {} 执行失败: {}",
+                            id,
+                            item.custom_id,
+                            e.message
+                        );
+                        BatchResult::Errored {
+                            error: e.to_error_json(),
+                        }
+                    }
+                };
+                drop(permit);
+                manager.record(&id, &writer, item.custom_id, result).await;
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            if let Err(e) = joined {
+                tracing::error!("批处理 {} 的执行任务异常退出: {}", id, e);
+            }
+        }
+
+        self.finish(&id).await;
+    }
+
+    /// 记录单个请求结果：更新计数并追加结果文件
+    ///
+    /// 元数据只在批处理结束时保存（重启后以结果文件重新统计计数）；
+    /// 结果文件在阻塞线程池中写入，不持有批处理表的锁
+    async fn record(
+        &self,
+        id: &str,
+        writer: &Arc<ResultWriter>,
+        custom_id: String,
+        result: BatchResult,
+    ) {
+        if let Some(batch) = self.batches.lock().get_mut(id) {
+            let counts = &mut batch.request_counts;
+            counts.processing = counts.processing.saturating_sub(1);
+            increment(counts, &result);
+        }
+
+        let writer = writer.clone();
+        let line = BatchResultLine { custom_id, result };
+        let written = tokio::task::spawn_blocking(move || writer.append(&line))
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+        if let Err(e) = written {
+            tracing::error!("写入批处理 {} 结果失败: {}", id, e);
+        }
+    }
+
+    async fn finish(self: &Arc<Self>, id: &str) {
+        self.cancel_flags.lock().remove(id);
+        let batch = {
+            let mut batches = self.batches.lock();
+            let Some(batch) = batches.get_mut(id) else {
+                return;
+            };
+            batch.processing_status = ProcessingStatus::Ended;
+            batch.ended_at = Some(format_time(Utc::now()));
+            batch.results_url = Some(format!("/v1/messages/batches/{}/results", id));
+            batch.clone()
+        };
+
+        let manager = self.clone();
+        let counts = batch.request_counts.clone();
+        let saved = tokio::task::spawn_blocking(move || manager.store.save_batch(&batch))
+            .await
+            .unwrap_or_else(|e| Err(e.into()));
+        if let Err(e) = saved {
+            tracing::error!("保存批处理 {} 失败: {}", id, e);
+        }
+        tracing::info!(
+            "批处理 {} 已结束: succeeded={}, errored={}, canceled={}, expired={}",
+            id,
+            counts.succeeded,
+            counts.errored,
+            counts.canceled,
+            counts.expired
+        );
+    }
+}
+
+/// 校验批处理请求
+fn validate_requests(requests: &[BatchRequestItem]) -> Result<(), BatchError> {
+    if requests.is_empty() {
+        return Err(BatchError::InvalidRequest("requests 不能为空".to_string()));
+    }
+    if requests.len() > MAX_REQUESTS_PER_BATCH {
+        return Err(BatchError::InvalidRequest(format!(
+            "单个批处理最多包含 {} 个请求",
+            MAX_REQUESTS_PER_BATCH
+        )));
+    }
+
+    let mut seen = HashSet::new();
+    for item in requests {
+        if item.custom_id.is_empty() || item.custom_id.len() > MAX_CUSTOM_ID_LEN {
+            return Err(BatchError::InvalidRequest(format!(
+                "custom_id 长度必须在 1-{} 之间: {}",
+                MAX_CUSTOM_ID_LEN, item.custom_id
+            )));
+        }
+        if !seen.insert(item.custom_id.as_str()) {
+            return Err(BatchError::InvalidRequest(format!(
+                "custom_id 重复: {}",
+                item.custom_id
+            )));
+        }
+        if !item.params.is_object() {
+            return Err(BatchError::InvalidRequest(format!(
+                "请求 {} 的 params 必须是对象",
+                item.custom_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 根据结果文件统计请求计数
+fn count_results(total: usize, results: &[BatchResultLine]) -> RequestCounts {
+    let mut counts = RequestCounts {
+        processing: total.saturating_sub(results.len()) as u64,
+        ..Default::default()
+    };
+    for line in results {
+        increment(&mut counts, &line.result);
+    }
+    counts
+}
+
+fn increment(counts: &mut RequestCounts, result: &BatchResult) {
+    match result {
+        BatchResult::Succeeded { .. } => counts.succeeded += 1,
+        BatchResult::Errored { .. } => counts.errored += 1,
+        BatchResult::Canceled => counts.canceled += 1,
+        BatchResult::Expired => counts.expired += 1,
+    }
+}
+
+/// 统一时间格式（固定精度，保证字符串可按字典序比较）
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("kiro-batch-test-{}", Uuid::new_v4()))
+    }
+
+    /// params 中 `ok` 为 true 时返回成功，否则返回错误
+    fn fake_executor(calls: Arc<AtomicUsize>, delay_ms: u64) -> BatchExecutor {
+        Arc::new(move |params| {
+            let calls = calls.clone();
+            Box::pin(async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                if delay_ms > 0 {
+                    tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+                }
+                if params["ok"].as_bool() == Some(true) {
+                    Ok(serde_json::json!({"type": "message", "content": []}))
+                } else {
+                    Err(BatchItemError::new("invalid_request_error", "bad request"))
+                }
+            })
+        })
+    }
+
+    fn item(custom_id: &str, ok: bool) -> BatchRequestItem {
+        BatchRequestItem {
+            custom_id: custom_id.to_string(),
+            params: serde_json::json!({"ok": ok}),
+        }
+    }
+
+    async fn wait_ended(manager: &BatchManager, id: &str) -> MessageBatch {
+        for _ in 0..200 {
+            let batch = manager.get(id).unwrap();
+            if batch.processing_status == ProcessingStatus::Ended {
+                return batch;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("批处理 {} 未在预期时间内结束", id);
+    }
+
+    #[tokio::test]
+    async fn test_batch_runs_to_completion() {
+        let dir = temp_dir();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let manager = BatchManager::new(&dir, 2, fake_executor(calls.clone(), 0)).unwrap();
+
+        let batch = manager
+            .create(CreateBatchRequest {
+                requests: vec![item("a", true), item("b", false), item("c", true)],
+            })
+            .unwrap();
+        assert!(batch.id.starts_with(BATCH_ID_PREFIX));
+        assert_eq!(batch.request_counts.processing, 3);
+
+        let batch = wait_ended(&manager, &batch.id).await;
+        assert_eq!(batch.request_counts.succeeded, 2);
+        assert_eq!(batch.request_counts.errored, 1);
+        assert_eq!(batch.request_counts.processing, 0);
+        assert!(batch.results_url.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let results = manager.store.load_results(&batch.id).unwrap();
+        let errored = results.iter().find(|r| r.custom_id == "b").unwrap();
+        match &errored.result {
+            BatchResult::Errored { error } => {
+                assert_eq!(error["error"]["type"], "invalid_request_error")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_batch_resume_after_restart() {
+        let dir = temp_dir();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        // 模拟进程在处理完第一个请求后退出
+        let store = BatchStore::new(&dir).unwrap();
+        let batch = MessageBatch {
+            id: format!("{}resume", BATCH_ID_PREFIX),
+            batch_type: "message_batch".to_string(),
+            processing_status: ProcessingStatus::InProgress,
+            request_counts: RequestCounts {
+                processing: 3,
+                ..Default::default()
+            },
+            ended_at: None,
+            created_at: format_time(Utc::now()),
+            expires_at: format_time(Utc::now() + Duration::hours(1)),
+            cancel_initiated_at: None,
+            archived_at: None,
+            results_url: None,
+        };
+        store
+            .create(&batch, &[item("a", true), item("b", true), item("c", true)])
+            .unwrap();
+        store
+            .open_results(&batch.id)
+            .unwrap()
+            .append(&BatchResultLine {
+                custom_id: "a".to_string(),
+                result: BatchResult::Succeeded {
+                    message: serde_json::json!({}),
+                },
+            })
+            .unwrap();
+        // 写入第二个结果时进程中断，留下不完整的末行
+        let mut results = std::fs::OpenOptions::new()
+            .append(true)
+            .open(store.results_path(&batch.id))
+            .unwrap();
+        std::io::Write::write_all(&mut results, br#"{"custom_id":"b","res"#).unwrap();
+
+        let manager = BatchManager::new(&dir, 2, fake_executor(calls.clone(), 0)).unwrap();
+        let loaded = manager.get(&batch.id).unwrap();
+        assert_eq!(loaded.request_counts.processing, 2);
+        assert_eq!(loaded.request_counts.succeeded, 1);
+
+        manager.resume();
+        let ended = wait_ended(&manager, &batch.id).await;
+        assert_eq!(ended.request_counts.succeeded, 3);
+        // 已完成的请求不会重复执行
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        // 残行被截断，之后的结果逐行完整追加
+        let content = std::fs::read_to_string(store.results_path(&batch.id)).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        assert!(
+            content
+                .lines()
+                .all(|line| serde_json::from_str::<BatchResultLine>(line).is_ok())
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_batch_cancel() {
+        let dir = temp_dir();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let manager = BatchManager::new(&dir, 1, fake_executor(calls.clone(), 50)).unwrap();
+
+        let batch = manager
+            .create(CreateBatchRequest {
+                requests: (0..5).map(|i| item(&format!("r{}", i), true)).collect(),
+            })
+            .unwrap();
+        let canceling = manager.cancel(&batch.id).unwrap();
+        assert_eq!(canceling.processing_status, ProcessingStatus::Canceling);
+        assert!(canceling.cancel_initiated_at.is_some());
+
+        let ended = wait_ended(&manager, &batch.id).await;
+        let counts = &ended.request_counts;
+        assert!(counts.canceled >= 4);
+        assert_eq!(counts.succeeded + counts.canceled, 5);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_results_unavailable_until_ended() {
+        let dir = temp_dir();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let manager = BatchManager::new(&dir, 1, fake_executor(calls, 200)).unwrap();
+
+        let batch = manager
+            .create(CreateBatchRequest {
+                requests: vec![item("a", true)],
+            })
+            .unwrap();
+        assert!(matches!(
+            manager.results_path(&batch.id),
+            Err(BatchError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            manager.results_path("msgbatch_missing"),
+            Err(BatchError::NotFound(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        let dir = temp_dir();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let manager = BatchManager::new(&dir, 4, fake_executor(calls, 0)).unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let batch = manager
+                .create(CreateBatchRequest {
+                    requests: vec![item("a", true)],
+                })
+                .unwrap();
+            ids.push(batch.id);
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let page = manager
+            .list(&ListBatchesQuery {
+                limit: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(page.has_more);
+        assert_eq!(page.first_id.as_deref(), Some(ids[2].as_str()));
+        assert_eq!(page.last_id.as_deref(), Some(ids[1].as_str()));
+
+        let next = manager
+            .list(&ListBatchesQuery {
+                limit: Some(2),
+                after_id: page.last_id.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(!next.has_more);
+        assert_eq!(next.data.len(), 1);
+        assert_eq!(next.data[0].id, ids[0]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_validate_requests() {
+        assert!(validate_requests(&[]).is_err());
+        assert!(validate_requests(&[item("a", true), item("a", true)]).is_err());
+        assert!(validate_requests(&[item("", true)]).is_err());
+        let mut bad = item("a", true);
+        bad.params = serde_json::json!("text");
+        assert!(validate_requests(&[bad]).is_err());
+        assert!(validate_requests(&[item("a", true), item("b", true)]).is_ok());
+    }
+}
@@ -0,0 +1,12 @@
+//! Message Batches 模块
+//!
+//! 提供与 Anthropic Message Batches API 兼容的本地批处理任务队列：
+//! - 批处理请求落盘存储，服务重启后自动恢复未完成的任务
+//! - 全局信号量控制并发，通过 `KiroProvider::call_api` 在凭据池中执行
+//! - 每个请求产出 `succeeded` / `errored` / `canceled` / `expired` 结果（JSONL）
+
+mod manager;
+mod store;
+pub mod types;
+
+pub use manager::{BatchError, BatchExecutor, BatchItemError, BatchManager};
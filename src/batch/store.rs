@@ -0,0 +1,191 @@
+//! Message Batches 磁盘存储
+//!
+//! 目录结构：
+//! ```text
+//! <batch_dir>/
+//! └── msgbatch_xxx/
+//!     ├── batch.json       # 批处理元数据（原子替换写入）
+//!     ├── requests.jsonl   # 原始请求（创建时一次性写入）
+//!     └── results.jsonl    # 执行结果（逐行追加）
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+
+use super::types::{BATCH_ID_PREFIX, BatchRequestItem, BatchResultLine, MessageBatch};
+
+const BATCH_FILE: &str = "batch.json";
+const REQUESTS_FILE: &str = "requests.jsonl";
+const RESULTS_FILE: &str = "results.jsonl";
+
+/// 批处理磁盘存储
+pub struct BatchStore {
+    root: PathBuf,
+}
+
+impl BatchStore {
+    /// 创建存储（目录不存在时自动创建）
+    pub fn new(root: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    fn batch_dir(&self, id: &str) -> PathBuf {
+        self.root.join(id)
+    }
+
+    /// 结果文件路径
+    pub fn results_path(&self, id: &str) -> PathBuf {
+        self.batch_dir(id).join(RESULTS_FILE)
+    }
+
+    /// 创建新的批处理目录，写入请求与元数据
+    pub fn create(&self, batch: &MessageBatch, items: &[BatchRequestItem]) -> anyhow::Result<()> {
+        let dir = self.batch_dir(&batch.id);
+        fs::create_dir_all(&dir)?;
+
+        let mut content = String::new();
+        for item in items {
+            content.push_str(&serde_json::to_string(item)?);
+            content.push('\n');
+        }
+        write_atomic(&dir.join(REQUESTS_FILE), content.as_bytes())?;
+        File::create(dir.join(RESULTS_FILE))?;
+        self.save_batch(batch)
+    }
+
+    /// 保存批处理元数据（临时文件 + rename，避免写入中断导致文件损坏）
+    pub fn save_batch(&self, batch: &MessageBatch) -> anyhow::Result<()> {
+        let json = serde_json::to_vec_pretty(batch)?;
+        write_atomic(&self.batch_dir(&batch.id).join(BATCH_FILE), &json)
+    }
+
+    /// 打开结果文件用于追加
+    ///
+    /// 进程在追加过程中被中断可能留下不完整的末行，先截断到最后一个完整行，
+    /// 避免新结果接在残行之后
+    pub fn open_results(&self, id: &str) -> anyhow::Result<ResultWriter> {
+        let path = self.results_path(id);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+        let len = file.metadata()?.len();
+        let keep = complete_len(&mut file, len)?;
+        if keep < len {
+            tracing::warn!(
+                "结果文件末行不完整，已截断 {} 字节: {}",
+                len - keep,
+                path.display()
+            );
+            file.set_len(keep)?;
+        }
+        Ok(ResultWriter {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// 加载所有批处理元数据
+    ///
+    /// 无法解析的目录会被跳过并记录警告
+    pub fn load_all(&self) -> anyhow::Result<Vec<MessageBatch>> {
+        let mut batches = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(BATCH_ID_PREFIX) || !entry.path().is_dir() {
+                continue;
+            }
+            let path = entry.path().join(BATCH_FILE);
+            match fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| serde_json::from_slice(&bytes).map_err(Into::into))
+            {
+                Ok(batch) => batches.push(batch),
+                Err(e) => tracing::warn!("加载批处理 {} 失败，已跳过: {}", name, e),
+            }
+        }
+        Ok(batches)
+    }
+
+    /// 加载批处理的全部请求
+    pub fn load_requests(&self, id: &str) -> anyhow::Result<Vec<BatchRequestItem>> {
+        read_jsonl(&self.batch_dir(id).join(REQUESTS_FILE))
+    }
+
+    /// 加载批处理已产出的结果
+    pub fn load_results(&self, id: &str) -> anyhow::Result<Vec<BatchResultLine>> {
+        read_jsonl(&self.results_path(id))
+    }
+}
+
+/// 结果文件写入器（同一批处理的结果逐行串行追加）
+pub struct ResultWriter {
+    file: Mutex<File>,
+}
+
+impl ResultWriter {
+    /// 追加一条执行结果
+    pub fn append(&self, line: &BatchResultLine) -> anyhow::Result<()> {
+        let mut json = serde_json::to_string(line)?;
+        json.push('\n');
+        self.file.lock().write_all(json.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// 文件中完整行（以换行结尾）部分的长度，从末尾按块向前查找最后一个换行
+fn complete_len(file: &mut File, len: u64) -> anyhow::Result<u64> {
+    let mut buf = [0u8; 8192];
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(buf.len() as u64);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(pos) = chunk.iter().rposition(|b| *b == b'\n') {
+            return Ok(start + pos as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// 原子写入文件：先写同目录临时文件，再 rename 覆盖
+fn write_atomic(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 读取 JSONL 文件
+///
+/// 进程在追加过程中被中断可能留下不完整的末行，解析失败的行会被忽略
+fn read_jsonl<T: serde::de::DeserializeOwned>(path: &Path) -> anyhow::Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let reader = BufReader::new(File::open(path)?);
+    let mut items = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(item) => items.push(item),
+            Err(e) => tracing::warn!("忽略无法解析的行 ({}): {}", path.display(), e),
+        }
+    }
+    Ok(items)
+}
@@ -0,0 +1,140 @@
+//! Message Batches 类型定义
+
+use serde::{Deserialize, Serialize};
+
+/// 批处理 ID 前缀
+pub const BATCH_ID_PREFIX: &str = "msgbatch_";
+
+/// 批处理状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStatus {
+    /// 处理中
+    InProgress,
+    /// 取消中（剩余请求将被标记为 canceled）
+    Canceling,
+    /// 已结束
+    Ended,
+}
+
+/// 各状态的请求计数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub processing: u64,
+    pub succeeded: u64,
+    pub errored: u64,
+    pub canceled: u64,
+    pub expired: u64,
+}
+
+/// 批处理对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageBatch {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub batch_type: String,
+    pub processing_status: ProcessingStatus,
+    pub request_counts: RequestCounts,
+    pub ended_at: Option<String>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub cancel_initiated_at: Option<String>,
+    pub archived_at: Option<String>,
+    pub results_url: Option<String>,
+}
+
+/// 单个批处理请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequestItem {
+    /// 客户端自定义 ID（批内唯一）
+    pub custom_id: String,
+    /// Messages API 请求参数
+    pub params: serde_json::Value,
+}
+
+/// 创建批处理请求体
+#[derive(Debug, Deserialize)]
+pub struct CreateBatchRequest {
+    pub requests: Vec<BatchRequestItem>,
+}
+
+/// 单个请求的执行结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchResult {
+    /// 执行成功，包含完整的 Message 响应
+    Succeeded { message: serde_json::Value },
+    /// 执行失败，包含 Anthropic 格式的错误对象
+    Errored { error: serde_json::Value },
+    /// 批处理被取消，请求未执行
+    Canceled,
+    /// 批处理过期，请求未执行
+    Expired,
+}
+
+/// 结果文件中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResultLine {
+    pub custom_id: String,
+    pub result: BatchResult,
+}
+
+/// 列表查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ListBatchesQuery {
+    /// 每页数量（1-1000，默认 20）
+    pub limit: Option<usize>,
+    /// 返回该 ID 之前（更新）的一页
+    pub before_id: Option<String>,
+    /// 返回该 ID 之后（更旧）的一页
+    pub after_id: Option<String>,
+}
+
+/// 列表响应
+#[derive(Debug, Serialize)]
+pub struct ListBatchesResponse {
+    pub data: Vec<MessageBatch>,
+    pub has_more: bool,
+    pub first_id: Option<String>,
+    pub last_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_result_serialize() {
+        let line = BatchResultLine {
+            custom_id: "req-1".to_string(),
+            result: BatchResult::Canceled,
+        };
+        let json = serde_json::to_string(&line).unwrap();
+        assert_eq!(
+            json,
+            r#"{"custom_id":"req-1","result":{"type":"canceled"}}"#
+        );
+
+        let line = BatchResultLine {
+            custom_id: "req-2".to_string(),
+            result: BatchResult::Succeeded {
+                message: serde_json::json!({"id": "msg_1"}),
+            },
+        };
+        let json = serde_json::to_string(&line).unwrap();
+        assert!(json.contains(r#""type":"succeeded""#));
+        assert!(json.contains(r#""message":{"id":"msg_1"}"#));
+    }
+
+    #[test]
+    fn test_processing_status_serialize() {
+        assert_eq!(
+            serde_json::to_string(&ProcessingStatus::InProgress).unwrap(),
+            "\"in_progress\""
+        );
+        assert_eq!(
+            serde_json::to_string(&ProcessingStatus::Ended).unwrap(),
+            "\"ended\""
+        );
+    }
+}
@@ -30,6 +30,32 @@ pub fn extract_api_key(request: &Request<Body>) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// 从请求中提取 Gemini 风格的 API Key
+///
+/// 依次检查 `x-goog-api-key` header、`key` 查询参数，以及通用的 `x-api-key` / Bearer
+pub fn extract_gemini_api_key(request: &Request<Body>) -> Option<String> {
+    if let Some(key) = request
+        .headers()
+        .get("x-goog-api-key")
+        .and_then(|v| v.to_str().ok())
+    {
+        return Some(key.to_string());
+    }
+
+    if let Some(key) = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == "key")
+            .and_then(|(_, v)| urlencoding::decode(v).ok())
+            .map(|v| v.into_owned())
+    }) {
+        return Some(key);
+    }
+
+    extract_api_key(request)
+}
+
 /// 常量时间字符串比较，防止时序攻击
 ///
 /// 无论字符串内容如何，比较所需的时间都是恒定的，
@@ -0,0 +1,633 @@
+//! Gemini → Kiro 协议转换器
+//!
+//! 负责将 Gemini generateContent 请求格式转换为 Kiro API 请求格式
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use uuid::Uuid;
+
+use crate::kiro::model::requests::conversation::{
+    AssistantMessage, ConversationState, CurrentMessage, HistoryAssistantMessage,
+    HistoryUserMessage, KiroImage, Message, UserInputMessage, UserInputMessageContext, UserMessage,
+};
+use crate::kiro::model::requests::tool::{
+    InputSchema, Tool, ToolResult, ToolSpecification, ToolUseEntry,
+};
+
+use super::types::{Content, FunctionDeclaration, GeminiTool, GenerateContentRequest};
+
+/// 模型映射：将模型名映射到 Kiro 模型 ID
+///
+/// 支持的映射：
+/// - *sonnet* 或 *pro* → claude-sonnet-4.5
+/// - *opus* → claude-opus-4.5
+/// - *haiku* 或 *flash* → claude-haiku-4.5
+/// - 其他模型返回 None（不支持）
+pub fn map_model(model: &str) -> Option<String> {
+    let model_lower = model.to_lowercase();
+
+    if model_lower.contains("opus") {
+        Some("claude-opus-4.5".to_string())
+    } else if model_lower.contains("sonnet") || model_lower.contains("pro") {
+        Some("claude-sonnet-4.5".to_string())
+    } else if model_lower.contains("haiku") || model_lower.contains("flash") {
+        Some("claude-haiku-4.5".to_string())
+    } else {
+        None
+    }
+}
+
+/// 转换结果
+#[derive(Debug)]
+pub struct ConversionResult {
+    /// 转换后的 Kiro 请求
+    pub conversation_state: ConversationState,
+}
+
+/// 转换错误
+#[derive(Debug)]
+pub enum ConversionError {
+    UnsupportedModel(String),
+    EmptyContents,
+    UnsupportedMimeType(String),
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::UnsupportedModel(model) => write!(f, "模型不支持: {}", model),
+            ConversionError::EmptyContents => write!(f, "contents 为空"),
+            ConversionError::UnsupportedMimeType(mime) => {
+                write!(f, "不支持的 inlineData 类型: {}", mime)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// 单条 user 内容解析结果
+#[derive(Default)]
+struct UserParts {
+    text: String,
+    images: Vec<KiroImage>,
+    tool_results: Vec<ToolResult>,
+}
+
+/// 函数调用 ID 分配器
+///
+/// Gemini 的 functionCall/functionResponse 通常不带 ID，按函数名先进先出配对，
+/// 为 Kiro 生成所需的 toolUseId
+#[derive(Default)]
+struct ToolIdTracker {
+    pending: HashMap<String, VecDeque<String>>,
+    counter: usize,
+}
+
+impl ToolIdTracker {
+    fn next_id(&mut self) -> String {
+        self.counter += 1;
+        format!("call_{}", self.counter)
+    }
+
+    /// 为 functionCall 分配 ID
+    fn assign_call(&mut self, name: &str, id: Option<&str>) -> String {
+        let id = id.map(str::to_string).unwrap_or_else(|| self.next_id());
+        self.pending
+            .entry(name.to_string())
+            .or_default()
+            .push_back(id.clone());
+        id
+    }
+
+    /// 为 functionResponse 找到对应的调用 ID
+    fn resolve_response(&mut self, name: &str, id: Option<&str>) -> String {
+        let queue = self.pending.entry(name.to_string()).or_default();
+        if let Some(id) = id {
+            queue.retain(|pending| pending != id);
+            return id.to_string();
+        }
+        queue.pop_front().unwrap_or_else(|| self.next_id())
+    }
+}
+
+/// 将 Gemini 请求转换为 Kiro 请求
+///
+/// `model` 来自 URL 路径（`/v1beta/models/{model}:generateContent`）
+pub fn convert_request(
+    model: &str,
+    req: &GenerateContentRequest,
+) -> Result<ConversionResult, ConversionError> {
+    // 1. 映射模型
+    let model_id =
+        map_model(model).ok_or_else(|| ConversionError::UnsupportedModel(model.to_string()))?;
+
+    // 2. 检查内容列表
+    if req.contents.is_empty() {
+        return Err(ConversionError::EmptyContents);
+    }
+
+    // 3. 生成会话 ID 和代理 ID
+    let conversation_id = Uuid::new_v4().to_string();
+    let agent_continuation_id = Uuid::new_v4().to_string();
+
+    // 4. 构建历史（最后一条 user 内容作为 currentMessage）
+    let mut tracker = ToolIdTracker::default();
+    let last_is_user = req.contents.last().map(|c| !is_model(c)).unwrap_or(false);
+    let history_contents = if last_is_user {
+        &req.contents[..req.contents.len() - 1]
+    } else {
+        &req.contents[..]
+    };
+    let mut history = build_history(history_contents, &model_id, &mut tracker)?;
+
+    let current = if last_is_user {
+        process_user_content(req.contents.last().unwrap(), &mut tracker)?
+    } else {
+        UserParts {
+            text: "Continue".to_string(),
+            ..Default::default()
+        }
+    };
+
+    // 5. 转换工具定义，并为历史中缺失的工具生成占位符
+    let mut tools = convert_tools(&req.tools);
+    let existing_tool_names: HashSet<_> = tools
+        .iter()
+        .map(|t| t.tool_specification.name.to_lowercase())
+        .collect();
+    for tool_name in collect_history_tool_names(&history) {
+        if !existing_tool_names.contains(&tool_name.to_lowercase()) {
+            tools.push(create_placeholder_tool(&tool_name));
+        }
+    }
+
+    // 6. 过滤孤立的 functionResponse
+    let tool_results = validate_tool_pairing(&history, current.tool_results);
+
+    // 7. 构建当前消息
+    let mut context = UserInputMessageContext::new();
+    if !tools.is_empty() {
+        context = context.with_tools(tools);
+    }
+    if !tool_results.is_empty() {
+        context = context.with_tool_results(tool_results);
+    }
+
+    let mut user_input = UserInputMessage::new(current.text, &model_id)
+        .with_context(context)
+        .with_origin("AI_EDITOR");
+    if !current.images.is_empty() {
+        user_input = user_input.with_images(current.images);
+    }
+
+    // 8. systemInstruction 作为 user + assistant 配对放在历史最前面
+    let system_content = req
+        .system_instruction
+        .as_ref()
+        .map(extract_text)
+        .unwrap_or_default();
+    if !system_content.is_empty() {
+        history.insert(
+            0,
+            Message::User(HistoryUserMessage::new(system_content, &model_id)),
+        );
+        history.insert(
+            1,
+            Message::Assistant(HistoryAssistantMessage::new(
+                "I will follow these instructions.",
+            )),
+        );
+    }
+
+    let conversation_state = ConversationState::new(conversation_id)
+        .with_agent_continuation_id(agent_continuation_id)
+        .with_agent_task_type("vibe")
+        .with_chat_trigger_type("MANUAL")
+        .with_current_message(CurrentMessage::new(user_input))
+        .with_history(history);
+
+    Ok(ConversionResult { conversation_state })
+}
+
+fn is_model(content: &Content) -> bool {
+    content.role.as_deref() == Some("model")
+}
+
+/// 构建历史消息，保证 user/assistant 交替
+fn build_history(
+    contents: &[Content],
+    model_id: &str,
+    tracker: &mut ToolIdTracker,
+) -> Result<Vec<Message>, ConversionError> {
+    let mut history = Vec::new();
+    let mut user_buffer: Vec<UserParts> = Vec::new();
+
+    for content in contents {
+        if is_model(content) {
+            // Kiro 要求 user/assistant 交替，缺少前置 user 时补一条
+            let user = if user_buffer.is_empty() {
+                HistoryUserMessage::new("Continue", model_id)
+            } else {
+                merge_user_parts(std::mem::take(&mut user_buffer), model_id)
+            };
+            history.push(Message::User(user));
+            history.push(Message::Assistant(convert_model_content(content, tracker)));
+        } else {
+            user_buffer.push(process_user_content(content, tracker)?);
+        }
+    }
+
+    // 处理结尾的孤立 user 内容
+    if !user_buffer.is_empty() {
+        history.push(Message::User(merge_user_parts(user_buffer, model_id)));
+        history.push(Message::Assistant(HistoryAssistantMessage::new("OK")));
+    }
+
+    Ok(history)
+}
+
+/// 提取纯文本（忽略思考片段）
+fn extract_text(content: &Content) -> String {
+    content
+        .parts
+        .iter()
+        .filter(|p| p.thought != Some(true))
+        .filter_map(|p| p.text.clone())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 解析 user 内容：文本、图片和函数执行结果
+fn process_user_content(
+    content: &Content,
+    tracker: &mut ToolIdTracker,
+) -> Result<UserParts, ConversionError> {
+    let mut texts = Vec::new();
+    let mut parts = UserParts::default();
+
+    for part in &content.parts {
+        if let Some(text) = &part.text {
+            texts.push(text.clone());
+        }
+        if let Some(inline) = &part.inline_data {
+            let format = get_image_format(&inline.mime_type)
+                .ok_or_else(|| ConversionError::UnsupportedMimeType(inline.mime_type.clone()))?;
+            parts
+                .images
+                .push(KiroImage::from_base64(format, inline.data.clone()));
+        }
+        if let Some(response) = &part.function_response {
+            let id = tracker.resolve_response(&response.name, response.id.as_deref());
+            parts
+                .tool_results
+                .push(convert_function_response(id, &response.response));
+        }
+    }
+
+    parts.text = texts.join("\n");
+    Ok(parts)
+}
+
+/// 将 functionResponse.response 转换为工具结果
+///
+/// 包含 `error` 字段时视为执行失败
+fn convert_function_response(id: String, response: &serde_json::Value) -> ToolResult {
+    let text = match response {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if response.get("error").is_some() {
+        ToolResult::error(id, text)
+    } else {
+        ToolResult::success(id, text)
+    }
+}
+
+/// 从 MIME 类型获取图片格式
+fn get_image_format(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "image/png" => Some("png"),
+        "image/jpeg" | "image/jpg" => Some("jpeg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+/// 合并多条 user 内容
+fn merge_user_parts(buffer: Vec<UserParts>, model_id: &str) -> HistoryUserMessage {
+    let mut texts = Vec::new();
+    let mut images = Vec::new();
+    let mut tool_results = Vec::new();
+
+    for parts in buffer {
+        if !parts.text.is_empty() {
+            texts.push(parts.text);
+        }
+        images.extend(parts.images);
+        tool_results.extend(parts.tool_results);
+    }
+
+    let mut user_msg = UserMessage::new(texts.join("\n"), model_id);
+    if !images.is_empty() {
+        user_msg = user_msg.with_images(images);
+    }
+    if !tool_results.is_empty() {
+        user_msg =
+            user_msg.with_context(UserInputMessageContext::new().with_tool_results(tool_results));
+    }
+
+    HistoryUserMessage {
+        user_input_message: user_msg,
+    }
+}
+
+/// 转换 model 内容
+fn convert_model_content(
+    content: &Content,
+    tracker: &mut ToolIdTracker,
+) -> HistoryAssistantMessage {
+    let mut tool_uses = Vec::new();
+    for part in &content.parts {
+        if let Some(call) = &part.function_call {
+            let id = tracker.assign_call(&call.name, call.id.as_deref());
+            let input = if call.args.is_null() {
+                serde_json::json!({})
+            } else {
+                call.args.clone()
+            };
+            tool_uses.push(ToolUseEntry::new(id, &call.name).with_input(input));
+        }
+    }
+
+    let mut assistant = AssistantMessage::new(extract_text(content));
+    if !tool_uses.is_empty() {
+        assistant = assistant.with_tool_uses(tool_uses);
+    }
+
+    HistoryAssistantMessage {
+        assistant_response_message: assistant,
+    }
+}
+
+/// 转换工具定义
+fn convert_tools(tools: &Option<Vec<GeminiTool>>) -> Vec<Tool> {
+    let Some(tools) = tools else {
+        return Vec::new();
+    };
+
+    tools
+        .iter()
+        .filter_map(|t| t.function_declarations.as_ref())
+        .flatten()
+        .map(convert_function_declaration)
+        .collect()
+}
+
+fn convert_function_declaration(decl: &FunctionDeclaration) -> Tool {
+    let description = decl.description.clone().unwrap_or_default();
+    // 限制描述长度为 10000 字符
+    let description = match description.char_indices().nth(10000) {
+        Some((idx, _)) => description[..idx].to_string(),
+        None => description,
+    };
+
+    let schema = decl
+        .parameters_json_schema
+        .clone()
+        .or_else(|| decl.parameters.clone().map(normalize_schema))
+        .unwrap_or_else(|| {
+            serde_json::json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            })
+        });
+
+    Tool {
+        tool_specification: ToolSpecification {
+            name: decl.name.clone(),
+            description,
+            input_schema: InputSchema::from_json(schema),
+        },
+    }
+}
+
+/// 将 Gemini OpenAPI 子集 Schema 规范化为 JSON Schema
+///
+/// Gemini 的类型名为大写（`STRING`、`OBJECT`），需转换为小写
+fn normalize_schema(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = match (k.as_str(), v) {
+                        ("type", serde_json::Value::String(s)) => {
+                            serde_json::Value::String(s.to_lowercase())
+                        }
+                        (_, v) => normalize_schema(v),
+                    };
+                    (k, v)
+                })
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(normalize_schema).collect())
+        }
+        other => other,
+    }
+}
+
+/// 收集历史消息中使用的所有工具名称
+fn collect_history_tool_names(history: &[Message]) -> Vec<String> {
+    let mut tool_names = Vec::new();
+
+    for msg in history {
+        if let Message::Assistant(assistant_msg) = msg
+            && let Some(ref tool_uses) = assistant_msg.assistant_response_message.tool_uses
+        {
+            for tool_use in tool_uses {
+                if !tool_names.contains(&tool_use.name) {
+                    tool_names.push(tool_use.name.clone());
+                }
+            }
+        }
+    }
+
+    tool_names
+}
+
+/// 为历史中使用但不在 tools 列表中的工具创建占位符定义
+fn create_placeholder_tool(name: &str) -> Tool {
+    Tool {
+        tool_specification: ToolSpecification {
+            name: name.to_string(),
+            description: "Tool used in conversation history".to_string(),
+            input_schema: InputSchema::from_json(serde_json::json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "properties": {},
+                "required": [],
+                "additionalProperties": true
+            })),
+        },
+    }
+}
+
+/// 过滤当前消息中找不到对应 functionCall 的 functionResponse
+fn validate_tool_pairing(history: &[Message], tool_results: Vec<ToolResult>) -> Vec<ToolResult> {
+    let mut valid_ids: HashSet<String> = HashSet::new();
+    for msg in history {
+        if let Message::Assistant(assistant_msg) = msg
+            && let Some(ref tool_uses) = assistant_msg.assistant_response_message.tool_uses
+        {
+            valid_ids.extend(tool_uses.iter().map(|t| t.tool_use_id.clone()));
+        }
+    }
+
+    tool_results
+        .into_iter()
+        .filter(|result| {
+            let valid = valid_ids.contains(&result.tool_use_id);
+            if !valid {
+                tracing::warn!(
+                    "跳过孤立的 functionResponse：找不到对应的 functionCall，tool_use_id={}",
+                    result.tool_use_id
+                );
+            }
+            valid
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(json: serde_json::Value) -> GenerateContentRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_map_model() {
+        assert_eq!(map_model("gemini-2.5-pro").unwrap(), "claude-sonnet-4.5");
+        assert_eq!(map_model("gemini-2.5-flash").unwrap(), "claude-haiku-4.5");
+        assert_eq!(map_model("claude-opus-4").unwrap(), "claude-opus-4.5");
+        assert_eq!(map_model("claude-haiku-4.5").unwrap(), "claude-haiku-4.5");
+        assert!(map_model("gemma-3-27b-it").is_none());
+        assert!(map_model("text-embedding-004").is_none());
+    }
+
+    #[test]
+    fn test_convert_simple_request_with_system_instruction() {
+        let req = request(serde_json::json!({
+            "systemInstruction": {"parts": [{"text": "be brief"}]},
+            "contents": [{"role": "user", "parts": [{"text": "hello"}]}]
+        }));
+        let result = convert_request("gemini-2.5-pro", &req).unwrap();
+        let state = result.conversation_state;
+
+        assert_eq!(state.current_message.user_input_message.content, "hello");
+        assert_eq!(
+            state.current_message.user_input_message.model_id,
+            "claude-sonnet-4.5"
+        );
+        assert_eq!(state.history.len(), 2);
+        assert!(state.history[0].is_user());
+    }
+
+    #[test]
+    fn test_convert_function_call_round_trip() {
+        let req = request(serde_json::json!({
+            "tools": [{"functionDeclarations": [{
+                "name": "get_weather",
+                "description": "Get weather",
+                "parameters": {"type": "OBJECT", "properties": {"city": {"type": "STRING"}}}
+            }]}],
+            "contents": [
+                {"role": "user", "parts": [{"text": "weather in Paris?"}]},
+                {"role": "model", "parts": [{"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}]},
+                {"role": "user", "parts": [{"functionResponse": {"name": "get_weather", "response": {"temp": 20}}}]}
+            ]
+        }));
+        let state = convert_request("gemini-2.5-flash", &req)
+            .unwrap()
+            .conversation_state;
+
+        let Message::Assistant(assistant) = &state.history[1] else {
+            panic!("expected assistant message");
+        };
+        let tool_uses = assistant
+            .assistant_response_message
+            .tool_uses
+            .as_ref()
+            .unwrap();
+        let ctx = &state
+            .current_message
+            .user_input_message
+            .user_input_message_context;
+        assert_eq!(ctx.tool_results.len(), 1);
+        assert_eq!(ctx.tool_results[0].tool_use_id, tool_uses[0].tool_use_id);
+
+        // Gemini 大写类型名被规范化
+        let schema = &ctx.tools[0].tool_specification.input_schema.json;
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["city"]["type"], "string");
+    }
+
+    #[test]
+    fn test_orphan_function_response_is_dropped() {
+        let req = request(serde_json::json!({
+            "contents": [
+                {"role": "user", "parts": [{"functionResponse": {"name": "x", "response": {}}}]}
+            ]
+        }));
+        let state = convert_request("gemini-2.5-flash", &req)
+            .unwrap()
+            .conversation_state;
+        assert!(
+            state
+                .current_message
+                .user_input_message
+                .user_input_message_context
+                .tool_results
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_inline_data_image() {
+        let req = request(serde_json::json!({
+            "contents": [{"role": "user", "parts": [
+                {"text": "what is this?"},
+                {"inlineData": {"mimeType": "image/jpeg", "data": "AAAA"}}
+            ]}]
+        }));
+        let state = convert_request("gemini-2.5-flash", &req)
+            .unwrap()
+            .conversation_state;
+        let images = &state.current_message.user_input_message.images;
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].format, "jpeg");
+
+        let bad = request(serde_json::json!({
+            "contents": [{"role": "user", "parts": [
+                {"inlineData": {"mimeType": "application/pdf", "data": "AAAA"}}
+            ]}]
+        }));
+        assert!(matches!(
+            convert_request("gemini-2.5-flash", &bad),
+            Err(ConversionError::UnsupportedMimeType(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_contents() {
+        let req = request(serde_json::json!({"contents": []}));
+        assert!(matches!(
+            convert_request("gemini-2.5-flash", &req),
+            Err(ConversionError::EmptyContents)
+        ));
+    }
+}
@@ -0,0 +1,426 @@
+//! Gemini API Handler 函数
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    Json as JsonExtractor,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use tokio::time::interval;
+
+use crate::kiro::model::events::Event;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::provider::KiroProvider;
+use crate::request_log::{self, RequestLogger, RequestSummary};
+use crate::token;
+
+use super::converter::{ConversionError, convert_request};
+use super::stream::StreamContext;
+use super::types::{
+    CountTokensResponse, ErrorResponse, GeminiModel, GenerateContentRequest,
+    GenerateContentResponse, ModelsResponse,
+};
+
+/// 应用状态
+#[derive(Clone)]
+pub struct AppState {
+    pub api_key: String,
+    pub kiro_provider: Option<Arc<KiroProvider>>,
+    pub request_logger: Option<Arc<RequestLogger>>,
+}
+
+impl AppState {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            kiro_provider: None,
+            request_logger: None,
+        }
+    }
+
+    pub fn with_kiro_provider(mut self, provider: KiroProvider) -> Self {
+        self.kiro_provider = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn with_request_logger(mut self, logger: Arc<RequestLogger>) -> Self {
+        self.request_logger = Some(logger);
+        self
+    }
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    let status_name = match status {
+        StatusCode::BAD_REQUEST => "INVALID_ARGUMENT",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::SERVICE_UNAVAILABLE => "UNAVAILABLE",
+        _ => "INTERNAL",
+    };
+    (
+        status,
+        Json(ErrorResponse::new(status.as_u16(), status_name, message)),
+    )
+        .into_response()
+}
+
+/// 可用模型列表
+fn available_models() -> Vec<GeminiModel> {
+    [
+        ("claude-sonnet-4.5", "Claude Sonnet 4.5"),
+        ("claude-opus-4.5", "Claude Opus 4.5"),
+        ("claude-haiku-4.5", "Claude Haiku 4.5"),
+    ]
+    .into_iter()
+    .map(|(id, display_name)| GeminiModel {
+        name: format!("models/{}", id),
+        display_name: display_name.to_string(),
+        input_token_limit: 200_000,
+        output_token_limit: 32_000,
+        supported_generation_methods: vec![
+            "generateContent".to_string(),
+            "streamGenerateContent".to_string(),
+            "countTokens".to_string(),
+        ],
+    })
+    .collect()
+}
+
+/// GET /v1beta/models
+pub async fn list_models() -> impl IntoResponse {
+    tracing::info!("Received GET /v1beta/models request");
+    Json(ModelsResponse {
+        models: available_models(),
+    })
+}
+
+/// GET /v1beta/models/:model
+pub async fn get_model(Path(model): Path<String>) -> Response {
+    let name = format!("models/{}", model);
+    match available_models().into_iter().find(|m| m.name == name) {
+        Some(model) => Json(model).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, format!("模型不存在: {}", model)),
+    }
+}
+
+/// POST /v1beta/models/:model_action
+///
+/// 路径格式为 `{model}:{action}`，支持 `generateContent`、`streamGenerateContent`、`countTokens`
+pub async fn post_model_action(
+    State(state): State<AppState>,
+    Path(model_action): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    JsonExtractor(payload): JsonExtractor<GenerateContentRequest>,
+) -> Response {
+    let Some((model, action)) = model_action.rsplit_once(':') else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("无效的请求路径: {}", model_action),
+        );
+    };
+
+    let stream = match action {
+        "generateContent" => false,
+        "streamGenerateContent" => true,
+        "countTokens" => {
+            return Json(CountTokensResponse {
+                total_tokens: estimate_input_tokens(&payload),
+            })
+            .into_response();
+        }
+        other => {
+            return error_response(StatusCode::NOT_FOUND, format!("不支持的操作: {}", other));
+        }
+    };
+    let use_sse = params.get("alt").map(|alt| alt == "sse").unwrap_or(false);
+
+    tracing::info!(
+        model = %model,
+        max_tokens = %payload.max_output_tokens(),
+        stream = %stream,
+        content_count = %payload.contents.len(),
+        "Received POST /v1beta/models/{}:{} request",
+        model,
+        action
+    );
+
+    // 检查 KiroProvider 是否可用
+    let provider = match &state.kiro_provider {
+        Some(p) => p.clone(),
+        None => {
+            tracing::error!("KiroProvider 未配置");
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Kiro API provider not configured",
+            );
+        }
+    };
+
+    // 请求日志记录实际处理请求的凭据与 API 区域
+    let summary = RequestSummary {
+        model: model.to_string(),
+        max_tokens: payload.max_output_tokens(),
+        stream,
+        message_count: payload.contents.len(),
+    };
+    request_log::track(state.request_logger.as_deref(), summary, async move {
+        // 转换请求
+        let conversion_result = match convert_request(model, &payload) {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::warn!("请求转换失败: {}", e);
+                let status = match e {
+                    ConversionError::UnsupportedModel(_)
+                    | ConversionError::EmptyContents
+                    | ConversionError::UnsupportedMimeType(_) => StatusCode::BAD_REQUEST,
+                };
+                return error_response(status, e.to_string());
+            }
+        };
+
+        // 构建 Kiro 请求
+        let kiro_request = KiroRequest::new(conversion_result.conversation_state);
+
+        let ctx = StreamContext::new(model, estimate_input_tokens(&payload));
+
+        if stream {
+            handle_stream_request(provider, &kiro_request, ctx, use_sse).await
+        } else {
+            handle_non_stream_request(provider, &kiro_request, ctx).await
+        }
+    })
+    .await
+}
+
+/// 估算输入 tokens
+fn estimate_input_tokens(payload: &GenerateContentRequest) -> i32 {
+    let mut text = String::new();
+    let contents = payload
+        .system_instruction
+        .iter()
+        .chain(payload.contents.iter());
+    for content in contents {
+        for part in &content.parts {
+            if let Some(t) = &part.text {
+                text.push_str(t);
+            }
+            if let Some(call) = &part.function_call {
+                text.push_str(&call.args.to_string());
+            }
+            if let Some(response) = &part.function_response {
+                text.push_str(&response.response.to_string());
+            }
+        }
+    }
+    if let Some(tools) = &payload.tools {
+        for decl in tools
+            .iter()
+            .filter_map(|t| t.function_declarations.as_ref())
+            .flatten()
+        {
+            text.push_str(&decl.name);
+            text.push_str(decl.description.as_deref().unwrap_or_default());
+            if let Some(params) = decl
+                .parameters
+                .as_ref()
+                .or(decl.parameters_json_schema.as_ref())
+            {
+                text.push_str(&params.to_string());
+            }
+        }
+    }
+
+    (token::count_tokens(&text) as i32).max(1)
+}
+
+/// 处理流式请求
+///
+/// `alt=sse` 时输出 SSE，否则按 Gemini 默认行为输出逐步写入的 JSON 数组
+async fn handle_stream_request(
+    provider: Arc<KiroProvider>,
+    request: &KiroRequest,
+    ctx: StreamContext,
+    use_sse: bool,
+) -> Response {
+    let response = match provider.call_api_stream(request).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Kiro API 调用失败: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, format!("上游 API 调用失败: {}", e));
+        }
+    };
+
+    let chunks = create_chunk_stream(response, ctx);
+
+    if use_sse {
+        let body = chunks.map(|item| {
+            Ok::<_, Infallible>(match item {
+                StreamItem::Chunk(json) => Bytes::from(format!("data: {}\n\n", json)),
+                StreamItem::Ping => Bytes::from(": ping\n\n"),
+            })
+        });
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header(header::CONNECTION, "keep-alive")
+            .body(Body::from_stream(body))
+            .unwrap()
+    } else {
+        // JSON 数组模式：元素之间用逗号分隔，ping 以空白字符保活
+        let elements = chunks.scan(true, |first, item| {
+            let bytes = match item {
+                StreamItem::Chunk(json) => {
+                    let separator = if std::mem::take(first) { "" } else { "," };
+                    Bytes::from(format!("{}{}", separator, json))
+                }
+                StreamItem::Ping => Bytes::from_static(b"\n"),
+            };
+            futures::future::ready(Some(bytes))
+        });
+        let body = stream::once(async { Bytes::from_static(b"[") })
+            .chain(elements)
+            .chain(stream::once(async { Bytes::from_static(b"]") }))
+            .map(Ok::<_, Infallible>);
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from_stream(body))
+            .unwrap()
+    }
+}
+
+/// Ping 间隔（25秒）
+const PING_INTERVAL_SECS: u64 = 25;
+
+/// 流式输出项
+enum StreamItem {
+    /// 序列化后的 GenerateContentResponse
+    Chunk(String),
+    /// 保活
+    Ping,
+}
+
+fn chunk_item(chunk: &GenerateContentResponse) -> StreamItem {
+    StreamItem::Chunk(serde_json::to_string(chunk).unwrap_or_default())
+}
+
+/// 将 Kiro 响应流转换为 Gemini chunk 流
+fn create_chunk_stream(
+    response: reqwest::Response,
+    ctx: StreamContext,
+) -> impl Stream<Item = StreamItem> {
+    let body_stream = response.bytes_stream();
+
+    stream::unfold(
+        (
+            body_stream,
+            ctx,
+            EventStreamDecoder::new(),
+            false,
+            interval(Duration::from_secs(PING_INTERVAL_SECS)),
+        ),
+        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
+            if finished {
+                return None;
+            }
+
+            tokio::select! {
+                chunk_result = body_stream.next() => {
+                    match chunk_result {
+                        Some(Ok(chunk)) => {
+                            if let Err(e) = decoder.feed(&chunk) {
+                                tracing::warn!("缓冲区溢出: {}", e);
+                            }
+
+                            let mut items = Vec::new();
+                            for result in decoder.decode_iter() {
+                                match result {
+                                    Ok(frame) => {
+                                        if let Ok(event) = Event::from_frame(frame) {
+                                            let parts = ctx.process_kiro_event(&event);
+                                            if !parts.is_empty() {
+                                                items.push(chunk_item(&ctx.chunk(parts)));
+                                            }
+                                        }
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!("解码事件失败: {}", e);
+                                    }
+                                }
+                            }
+
+                            Some((stream::iter(items), (body_stream, ctx, decoder, false, ping_interval)))
+                        }
+                        Some(Err(e)) => {
+                            tracing::error!("读取响应流失败: {}", e);
+                            let items = vec![chunk_item(&ctx.final_chunk())];
+                            Some((stream::iter(items), (body_stream, ctx, decoder, true, ping_interval)))
+                        }
+                        None => {
+                            let items = vec![chunk_item(&ctx.final_chunk())];
+                            Some((stream::iter(items), (body_stream, ctx, decoder, true, ping_interval)))
+                        }
+                    }
+                }
+                _ = ping_interval.tick() => {
+                    tracing::trace!("发送 ping 保活事件");
+                    Some((stream::iter(vec![StreamItem::Ping]), (body_stream, ctx, decoder, false, ping_interval)))
+                }
+            }
+        },
+    )
+    .flatten()
+}
+
+/// 处理非流式请求
+async fn handle_non_stream_request(
+    provider: Arc<KiroProvider>,
+    request: &KiroRequest,
+    mut ctx: StreamContext,
+) -> Response {
+    let response = match provider.call_api(request).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Kiro API 调用失败: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, format!("上游 API 调用失败: {}", e));
+        }
+    };
+
+    let body_bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("读取响应体失败: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, format!("读取响应失败: {}", e));
+        }
+    };
+
+    let mut decoder = EventStreamDecoder::new();
+    if let Err(e) = decoder.feed(&body_bytes) {
+        tracing::warn!("缓冲区溢出: {}", e);
+    }
+
+    let mut parts = Vec::new();
+    for result in decoder.decode_iter() {
+        match result {
+            Ok(frame) => {
+                if let Ok(event) = Event::from_frame(frame) {
+                    parts.extend(ctx.process_kiro_event(&event));
+                }
+            }
+            Err(e) => {
+                tracing::warn!("解码事件失败: {}", e);
+            }
+        }
+    }
+
+    (StatusCode::OK, Json(ctx.complete_response(parts))).into_response()
+}
@@ -0,0 +1,128 @@
+//! Gemini API 路由配置
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{DefaultBodyLimit, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::get,
+};
+
+use crate::common::auth;
+use crate::kiro::provider::KiroProvider;
+use crate::request_log::RequestLogger;
+
+use super::handlers::{AppState, get_model, list_models, post_model_action};
+use super::types::ErrorResponse;
+
+/// 请求体最大大小限制 (50MB)
+const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
+
+/// API Key 认证中间件
+async fn auth_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    match auth::extract_gemini_api_key(&request) {
+        Some(key) if auth::constant_time_eq(&key, &state.api_key) => next.run(request).await,
+        _ => {
+            let error = ErrorResponse::authentication_error();
+            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+        }
+    }
+}
+
+/// CORS 中间件层
+fn cors_layer() -> tower_http::cors::CorsLayer {
+    use tower_http::cors::{Any, CorsLayer};
+
+    CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+}
+
+/// 创建带有 KiroProvider 的 Gemini API 路由
+///
+/// # 端点
+/// - `GET /v1beta/models` - 获取可用模型列表
+/// - `GET /v1beta/models/:model` - 获取模型信息
+/// - `POST /v1beta/models/:model:generateContent` - 生成内容
+/// - `POST /v1beta/models/:model:streamGenerateContent` - 流式生成内容（`alt=sse` 时为 SSE）
+/// - `POST /v1beta/models/:model:countTokens` - 估算 Token 数量
+///
+/// # 认证
+/// 支持 `x-goog-api-key` header、`?key=` 查询参数、`x-api-key` header
+/// 以及 `Authorization: Bearer <token>` header
+pub fn create_router_with_provider(
+    api_key: impl Into<String>,
+    kiro_provider: Option<KiroProvider>,
+    request_logger: Option<Arc<RequestLogger>>,
+) -> Router {
+    let mut state = AppState::new(api_key);
+    if let Some(provider) = kiro_provider {
+        state = state.with_kiro_provider(provider);
+    }
+    if let Some(logger) = request_logger {
+        state = state.with_request_logger(logger);
+    }
+
+    let v1beta_routes = Router::new()
+        .route("/models", get(list_models))
+        .route(
+            "/models/{model_action}",
+            get(get_model).post(post_model_action),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest("/v1beta", v1beta_routes)
+        .layer(cors_layer())
+        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_api_key_sources() {
+        let request = Request::builder()
+            .uri("/v1beta/models")
+            .header("x-goog-api-key", "goog-key")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            auth::extract_gemini_api_key(&request).as_deref(),
+            Some("goog-key")
+        );
+
+        let request = Request::builder()
+            .uri("/v1beta/models/gemini-pro:generateContent?alt=sse&key=query%2Bkey")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            auth::extract_gemini_api_key(&request).as_deref(),
+            Some("query+key")
+        );
+
+        let request = Request::builder()
+            .uri("/v1beta/models")
+            .header("x-api-key", "plain-key")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            auth::extract_gemini_api_key(&request).as_deref(),
+            Some("plain-key")
+        );
+    }
+}
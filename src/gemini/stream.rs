@@ -0,0 +1,228 @@
+//! Gemini 响应处理模块
+//!
+//! 实现 Kiro 事件 → Gemini GenerateContentResponse 转换，流式与非流式共用
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::kiro::model::events::{Event, ToolUseEvent};
+use crate::token;
+
+use super::types::{
+    Candidate, Content, FunctionCall, GenerateContentResponse, Part, UsageMetadata,
+};
+
+/// 上下文窗口大小（200k tokens）
+const CONTEXT_WINDOW_SIZE: i32 = 200_000;
+
+/// 响应处理上下文
+pub struct StreamContext {
+    /// 请求的模型名称
+    pub model: String,
+    /// 响应 ID
+    pub response_id: String,
+    /// 输入 tokens（估算值）
+    pub input_tokens: i32,
+    /// 从 contextUsageEvent 计算的实际输入 tokens
+    pub context_input_tokens: Option<i32>,
+    /// 输出 tokens 累计
+    pub output_tokens: i32,
+    /// 工具输入缓冲 (tool_use_id -> JSON 片段)
+    tool_buffers: HashMap<String, String>,
+    /// 结束原因
+    finish_reason: Option<String>,
+}
+
+impl StreamContext {
+    /// 创建新的处理上下文
+    pub fn new(model: impl Into<String>, input_tokens: i32) -> Self {
+        Self {
+            model: model.into(),
+            response_id: Uuid::new_v4().simple().to_string(),
+            input_tokens,
+            context_input_tokens: None,
+            output_tokens: 0,
+            tool_buffers: HashMap::new(),
+            finish_reason: None,
+        }
+    }
+
+    /// 处理 Kiro 事件，返回需要输出的内容片段
+    pub fn process_kiro_event(&mut self, event: &Event) -> Vec<Part> {
+        match event {
+            Event::AssistantResponse(resp) => {
+                if resp.content.is_empty() {
+                    return Vec::new();
+                }
+                self.output_tokens += token::count_tokens(&resp.content) as i32;
+                vec![Part::text(resp.content.clone())]
+            }
+            Event::ToolUse(tool_use) => self.process_tool_use(tool_use),
+            Event::ContextUsage(context_usage) => {
+                let actual_input_tokens = (context_usage.context_usage_percentage
+                    * (CONTEXT_WINDOW_SIZE as f64)
+                    / 100.0) as i32;
+                self.context_input_tokens = Some(actual_input_tokens);
+                Vec::new()
+            }
+            Event::Error {
+                error_code,
+                error_message,
+            } => {
+                tracing::error!("收到错误事件: {} - {}", error_code, error_message);
+                Vec::new()
+            }
+            Event::Exception {
+                exception_type,
+                message,
+            } => {
+                if exception_type == "ContentLengthExceededException" {
+                    self.finish_reason = Some("MAX_TOKENS".to_string());
+                }
+                tracing::warn!("收到异常事件: {} - {}", exception_type, message);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// 处理工具使用事件
+    ///
+    /// Gemini 的 functionCall 不支持增量参数，累积到 stop 后一次性输出
+    fn process_tool_use(&mut self, tool_use: &ToolUseEvent) -> Vec<Part> {
+        let buffer = self
+            .tool_buffers
+            .entry(tool_use.tool_use_id.clone())
+            .or_default();
+        buffer.push_str(&tool_use.input);
+        if !tool_use.input.is_empty() {
+            self.output_tokens += (tool_use.input.len() as i32 + 3) / 4;
+        }
+
+        if !tool_use.stop {
+            return Vec::new();
+        }
+
+        let raw = self
+            .tool_buffers
+            .remove(&tool_use.tool_use_id)
+            .unwrap_or_default();
+        let args = if raw.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(&raw).unwrap_or_else(|e| {
+                tracing::warn!(
+                    "工具输入 JSON 解析失败: {}, tool_use_id: {}",
+                    e,
+                    tool_use.tool_use_id
+                );
+                serde_json::json!({})
+            })
+        };
+
+        vec![Part::function_call(FunctionCall {
+            id: Some(tool_use.tool_use_id.clone()),
+            name: tool_use.name.clone(),
+            args,
+        })]
+    }
+
+    /// 获取最终的 usage
+    pub fn usage(&self) -> UsageMetadata {
+        let input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
+        UsageMetadata::new(input_tokens, self.output_tokens)
+    }
+
+    /// 构建包含指定片段的响应 chunk
+    pub fn chunk(&self, parts: Vec<Part>) -> GenerateContentResponse {
+        self.response(parts, None, None)
+    }
+
+    /// 生成最终 chunk（携带 finishReason 和 usageMetadata）
+    pub fn final_chunk(&self) -> GenerateContentResponse {
+        self.response(Vec::new(), Some(self.finish_reason()), Some(self.usage()))
+    }
+
+    /// 构建非流式完整响应，合并相邻的文本片段
+    pub fn complete_response(&self, parts: Vec<Part>) -> GenerateContentResponse {
+        let mut merged: Vec<Part> = Vec::new();
+        for part in parts {
+            match (merged.last_mut(), &part.text) {
+                (Some(last), Some(text)) if last.text.is_some() => {
+                    last.text.as_mut().unwrap().push_str(text);
+                }
+                _ => merged.push(part),
+            }
+        }
+        self.response(merged, Some(self.finish_reason()), Some(self.usage()))
+    }
+
+    fn finish_reason(&self) -> String {
+        self.finish_reason
+            .clone()
+            .unwrap_or_else(|| "STOP".to_string())
+    }
+
+    fn response(
+        &self,
+        parts: Vec<Part>,
+        finish_reason: Option<String>,
+        usage_metadata: Option<UsageMetadata>,
+    ) -> GenerateContentResponse {
+        GenerateContentResponse {
+            candidates: vec![Candidate {
+                content: Content {
+                    role: Some("model".to_string()),
+                    parts,
+                },
+                finish_reason,
+                index: 0,
+            }],
+            usage_metadata,
+            model_version: self.model.clone(),
+            response_id: self.response_id.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_event(input: &str, stop: bool) -> Event {
+        Event::ToolUse(ToolUseEvent {
+            name: "get_weather".to_string(),
+            tool_use_id: "tool-1".to_string(),
+            input: input.to_string(),
+            stop,
+        })
+    }
+
+    #[test]
+    fn test_tool_use_emitted_when_complete() {
+        let mut ctx = StreamContext::new("gemini-2.5-pro", 10);
+        assert!(
+            ctx.process_kiro_event(&tool_event("{\"city\":", false))
+                .is_empty()
+        );
+        let parts = ctx.process_kiro_event(&tool_event("\"Paris\"}", true));
+        assert_eq!(parts.len(), 1);
+        let call = parts[0].function_call.as_ref().unwrap();
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(call.args["city"], "Paris");
+    }
+
+    #[test]
+    fn test_complete_response_merges_text() {
+        let ctx = StreamContext::new("gemini-2.5-pro", 10);
+        let response = ctx.complete_response(vec![Part::text("Hello, "), Part::text("world")]);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json["candidates"][0]["content"]["parts"][0]["text"],
+            "Hello, world"
+        );
+        assert_eq!(json["candidates"][0]["finishReason"], "STOP");
+        assert_eq!(json["usageMetadata"]["promptTokenCount"], 10);
+    }
+}
@@ -0,0 +1,297 @@
+//! Gemini API 类型定义
+//!
+//! 字段采用 Gemini REST 的 camelCase 命名，同时兼容 protobuf JSON 的 snake_case 写法
+
+use serde::{Deserialize, Serialize};
+
+// === 错误响应 ===
+
+/// Gemini API 错误响应
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
+}
+
+/// 错误详情
+#[derive(Debug, Serialize)]
+pub struct ErrorDetail {
+    pub code: u16,
+    pub message: String,
+    pub status: String,
+}
+
+impl ErrorResponse {
+    /// 创建新的错误响应
+    pub fn new(code: u16, status: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            error: ErrorDetail {
+                code,
+                message: message.into(),
+                status: status.into(),
+            },
+        }
+    }
+
+    /// 创建认证错误响应
+    pub fn authentication_error() -> Self {
+        Self::new(
+            401,
+            "UNAUTHENTICATED",
+            "API key not valid. Please pass a valid API key.",
+        )
+    }
+}
+
+// === generateContent 请求类型 ===
+
+/// generateContent / streamGenerateContent 请求体
+///
+/// `toolConfig`、`safetySettings` 等 Kiro 不支持的字段会被忽略
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentRequest {
+    #[serde(default)]
+    pub contents: Vec<Content>,
+    #[serde(default, alias = "system_instruction")]
+    pub system_instruction: Option<Content>,
+    #[serde(default)]
+    pub tools: Option<Vec<GeminiTool>>,
+    #[serde(default, alias = "generation_config")]
+    pub generation_config: Option<GenerationConfig>,
+}
+
+impl GenerateContentRequest {
+    /// 获取最大输出 tokens（未设置时默认 8192）
+    pub fn max_output_tokens(&self) -> i32 {
+        self.generation_config
+            .as_ref()
+            .and_then(|c| c.max_output_tokens)
+            .unwrap_or(8192)
+    }
+}
+
+/// 生成配置
+///
+/// Kiro 上游不支持采样参数（temperature、topP 等），解析时直接忽略
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    #[serde(default, alias = "max_output_tokens")]
+    pub max_output_tokens: Option<i32>,
+}
+
+/// 对话内容
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Content {
+    /// "user" 或 "model"（systemInstruction 中可省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<Part>,
+}
+
+/// 内容片段
+///
+/// 每个 part 只会设置其中一个字段
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(
+        default,
+        alias = "inline_data",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub inline_data: Option<InlineData>,
+    #[serde(
+        default,
+        alias = "function_call",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub function_call: Option<FunctionCall>,
+    #[serde(
+        default,
+        alias = "function_response",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub function_response: Option<FunctionResponse>,
+    /// 是否为思考内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+}
+
+impl Part {
+    /// 创建文本片段
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    /// 创建函数调用片段
+    pub fn function_call(call: FunctionCall) -> Self {
+        Self {
+            function_call: Some(call),
+            ..Default::default()
+        }
+    }
+}
+
+/// 内联二进制数据（图片）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineData {
+    #[serde(alias = "mime_type")]
+    pub mime_type: String,
+    /// base64 编码数据
+    pub data: String,
+}
+
+/// 函数调用
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// 函数执行结果
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub response: serde_json::Value,
+}
+
+/// 工具定义
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiTool {
+    #[serde(default, alias = "function_declarations")]
+    pub function_declarations: Option<Vec<FunctionDeclaration>>,
+}
+
+/// 函数声明
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionDeclaration {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// OpenAPI 子集格式的参数 Schema
+    #[serde(default)]
+    pub parameters: Option<serde_json::Value>,
+    /// JSON Schema 格式的参数（与 parameters 二选一）
+    #[serde(default, alias = "parameters_json_schema")]
+    pub parameters_json_schema: Option<serde_json::Value>,
+}
+
+// === generateContent 响应类型 ===
+
+/// generateContent 响应（流式时每个 chunk 也是该结构）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentResponse {
+    pub candidates: Vec<Candidate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_metadata: Option<UsageMetadata>,
+    pub model_version: String,
+    pub response_id: String,
+}
+
+/// 候选结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pub content: Content,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    pub index: i32,
+}
+
+/// 用量统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageMetadata {
+    pub prompt_token_count: i32,
+    pub candidates_token_count: i32,
+    pub total_token_count: i32,
+}
+
+impl UsageMetadata {
+    pub fn new(prompt_tokens: i32, candidates_tokens: i32) -> Self {
+        Self {
+            prompt_token_count: prompt_tokens,
+            candidates_token_count: candidates_tokens,
+            total_token_count: prompt_tokens + candidates_tokens,
+        }
+    }
+}
+
+/// countTokens 响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountTokensResponse {
+    pub total_tokens: i32,
+}
+
+// === 模型列表 ===
+
+/// 模型信息
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiModel {
+    pub name: String,
+    pub display_name: String,
+    pub input_token_limit: i32,
+    pub output_token_limit: i32,
+    pub supported_generation_methods: Vec<String>,
+}
+
+/// 模型列表响应
+#[derive(Debug, Serialize)]
+pub struct ModelsResponse {
+    pub models: Vec<GeminiModel>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_request_camel_and_snake_case() {
+        let json = r#"{
+            "contents": [{"role": "user", "parts": [
+                {"text": "hi"},
+                {"inline_data": {"mime_type": "image/png", "data": "AAAA"}}
+            ]}],
+            "systemInstruction": {"parts": [{"text": "be brief"}]},
+            "generationConfig": {"maxOutputTokens": 256}
+        }"#;
+        let req: GenerateContentRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.contents.len(), 1);
+        assert_eq!(req.contents[0].parts.len(), 2);
+        assert_eq!(
+            req.contents[0].parts[1]
+                .inline_data
+                .as_ref()
+                .unwrap()
+                .mime_type,
+            "image/png"
+        );
+        assert!(req.system_instruction.is_some());
+        assert_eq!(req.max_output_tokens(), 256);
+    }
+
+    #[test]
+    fn test_serialize_part_skips_empty_fields() {
+        let json = serde_json::to_string(&Part::text("hello")).unwrap();
+        assert_eq!(json, r#"{"text":"hello"}"#);
+    }
+}
@@ -2,13 +2,15 @@
 //!
 //! 提供统一的 HTTP Client 构建功能，支持代理配置
 
-use reqwest::{Client, Proxy};
+use parking_lot::Mutex;
+use reqwest::{Client, ClientBuilder, Proxy, RequestBuilder, Response};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
-use crate::model::config::TlsBackend;
+use crate::model::config::{Config, TlsBackend, UpstreamHttpConfig};
 
 /// 代理配置
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct ProxyConfig {
     /// 代理地址，支持 http/https/socks5
     pub url: String,
@@ -49,8 +51,27 @@ pub fn build_client(
     timeout_secs: u64,
     tls_backend: TlsBackend,
 ) -> anyhow::Result<Client> {
-    let mut builder = Client::builder().timeout(Duration::from_secs(timeout_secs));
+    Ok(client_builder(proxy, timeout_secs, tls_backend)?.build()?)
+}
+
+/// 构建已应用代理、超时与 TLS 配置的 ClientBuilder
+///
+/// 供需要额外定制（如重定向策略）的调用方使用
+pub fn client_builder(
+    proxy: Option<&ProxyConfig>,
+    timeout_secs: u64,
+    tls_backend: TlsBackend,
+) -> anyhow::Result<ClientBuilder> {
+    let builder = Client::builder().timeout(Duration::from_secs(timeout_secs));
+    apply_transport(builder, proxy, tls_backend)
+}
 
+/// 应用代理与 TLS 配置
+fn apply_transport(
+    mut builder: ClientBuilder,
+    proxy: Option<&ProxyConfig>,
+    tls_backend: TlsBackend,
+) -> anyhow::Result<ClientBuilder> {
     if tls_backend == TlsBackend::Rustls {
         builder = builder.use_rustls_tls();
     }
@@ -67,7 +88,134 @@ pub fn build_client(
         tracing::debug!("HTTP Client 使用代理: {}", proxy_config.url);
     }
 
-    Ok(builder.build()?)
+    Ok(builder)
+}
+
+/// Kiro 上游端点（连接复用可按端点开关）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpstreamEndpoint {
+    /// generateAssistantResponse
+    Api,
+    /// MCP（网页搜索）
+    Mcp,
+    /// Token 刷新
+    TokenRefresh,
+    /// 使用额度查询
+    UsageLimits,
+}
+
+impl UpstreamEndpoint {
+    /// 端点名称（用于日志与 Admin API）
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UpstreamEndpoint::Api => "api",
+            UpstreamEndpoint::Mcp => "mcp",
+            UpstreamEndpoint::TokenRefresh => "tokenRefresh",
+            UpstreamEndpoint::UsageLimits => "usageLimits",
+        }
+    }
+
+    /// 该端点是否复用连接
+    pub fn keep_alive(self, config: &UpstreamHttpConfig) -> bool {
+        let keep_alive = &config.keep_alive;
+        match self {
+            UpstreamEndpoint::Api => keep_alive.api,
+            UpstreamEndpoint::Mcp => keep_alive.mcp,
+            UpstreamEndpoint::TokenRefresh => keep_alive.token_refresh,
+            UpstreamEndpoint::UsageLimits => keep_alive.usage_limits,
+        }
+    }
+}
+
+/// 上游客户端缓存键：相同代理与连接配置的请求共用同一个连接池
+#[derive(PartialEq, Eq, Hash)]
+struct UpstreamClientKey {
+    proxy: Option<ProxyConfig>,
+    tls_backend: TlsBackend,
+    keep_alive: bool,
+    http: UpstreamHttpConfig,
+}
+
+impl UpstreamClientKey {
+    fn new(proxy: Option<&ProxyConfig>, config: &Config, endpoint: UpstreamEndpoint) -> Self {
+        Self {
+            proxy: proxy.cloned(),
+            tls_backend: config.tls_backend,
+            keep_alive: endpoint.keep_alive(&config.upstream_http),
+            http: config.upstream_http.clone(),
+        }
+    }
+}
+
+/// 访问 Kiro 上游的 HTTP Client 缓存
+///
+/// 按代理与连接配置缓存，启用连接复用的端点之间共享连接池；
+/// 关闭复用的端点不保留空闲连接且只使用 HTTP/1.1
+#[derive(Default)]
+pub struct UpstreamClients {
+    clients: Mutex<HashMap<UpstreamClientKey, Client>>,
+}
+
+impl UpstreamClients {
+    /// 获取指定出口代理与端点的 HTTP Client
+    pub fn get(
+        &self,
+        proxy: Option<&ProxyConfig>,
+        config: &Config,
+        endpoint: UpstreamEndpoint,
+    ) -> anyhow::Result<Client> {
+        let http = &config.upstream_http;
+        let key = UpstreamClientKey::new(proxy, config, endpoint);
+
+        let mut clients = self.clients.lock();
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let mut builder = Client::builder()
+            .connect_timeout(Duration::from_secs(http.connect_timeout_secs))
+            .read_timeout(Duration::from_secs(http.stream_idle_timeout_secs));
+        builder = if key.keep_alive {
+            builder
+                .pool_max_idle_per_host(http.pool_max_idle_per_host)
+                .pool_idle_timeout(Duration::from_secs(http.pool_idle_timeout_secs))
+        } else {
+            builder.pool_max_idle_per_host(0)
+        };
+        if !(key.keep_alive && http.http2) {
+            builder = builder.http1_only();
+        }
+        let client = apply_transport(builder, proxy, config.tls_backend)?.build()?;
+
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// 移除不再使用的出口代理的客户端（直连客户端始终保留）
+    pub fn retain_proxies(&self, active: &HashSet<ProxyConfig>) {
+        self.clients.lock().retain(|key, _| {
+            key.proxy
+                .as_ref()
+                .is_none_or(|proxy| active.contains(proxy))
+        });
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.clients.lock().len()
+    }
+}
+
+/// 发送上游请求，超过首字节超时仍未收到响应头时返回错误
+pub async fn send_upstream(
+    request: RequestBuilder,
+    config: &UpstreamHttpConfig,
+) -> anyhow::Result<Response> {
+    let timeout = Duration::from_secs(config.first_byte_timeout_secs);
+    match tokio::time::timeout(timeout, request.send()).await {
+        Ok(result) => Ok(result?),
+        Err(_) => anyhow::bail!("等待上游响应超时（{} 秒）", timeout.as_secs()),
+    }
 }
 
 #[cfg(test)]
@@ -96,6 +244,74 @@ mod tests {
         assert!(client.is_ok());
     }
 
+    #[test]
+    fn test_upstream_client_key_per_proxy_and_keep_alive() {
+        let mut config = Config::default();
+        let proxy = ProxyConfig::new("http://127.0.0.1:7890");
+        let key =
+            |config: &Config, proxy, endpoint| UpstreamClientKey::new(proxy, config, endpoint);
+
+        // 默认各端点都复用连接，同一代理下共用客户端
+        assert!(
+            key(&config, None, UpstreamEndpoint::Api) == key(&config, None, UpstreamEndpoint::Mcp)
+        );
+        assert!(
+            key(&config, None, UpstreamEndpoint::Api)
+                != key(&config, Some(&proxy), UpstreamEndpoint::Api)
+        );
+
+        config.upstream_http.keep_alive.token_refresh = false;
+        assert!(
+            key(&config, None, UpstreamEndpoint::Api)
+                != key(&config, None, UpstreamEndpoint::TokenRefresh)
+        );
+        assert!(
+            UpstreamClients::default()
+                .get(None, &config, UpstreamEndpoint::TokenRefresh)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_upstream_clients_evict_unused_proxies() {
+        let config = Config::default();
+        let clients = UpstreamClients::default();
+        let a = ProxyConfig::new("http://127.0.0.1:7890");
+        let b = ProxyConfig::new("http://127.0.0.1:7891");
+        for proxy in [None, Some(&a), Some(&b)] {
+            clients.get(proxy, &config, UpstreamEndpoint::Api).unwrap();
+        }
+        assert_eq!(clients.len(), 3);
+
+        clients.retain_proxies(&HashSet::from([a]));
+        assert_eq!(clients.len(), 2);
+        clients.retain_proxies(&HashSet::new());
+        assert_eq!(clients.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_upstream_first_byte_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // 接受连接但从不响应
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let config = UpstreamHttpConfig {
+            first_byte_timeout_secs: 1,
+            ..Default::default()
+        };
+        let client = build_client(None, 30, TlsBackend::Rustls).unwrap();
+        let err = send_upstream(client.get(format!("http://{}/", addr)), &config)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("超时"));
+    }
+
     #[test]
     fn test_build_client_with_proxy() {
         let config = ProxyConfig::new("http://127.0.0.1:7890");
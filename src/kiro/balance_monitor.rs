@@ -0,0 +1,235 @@
+//! 余额轮询与低额度告警
+//!
+//! 按配置的间隔轮询所有凭据的 getUsageLimits，结果缓存在凭据管理器中，
+//! 并在剩余额度低于阈值或免费试用即将过期时发出告警（日志 + 可选 webhook）。
+//! 同一凭据的同类告警在条件解除前只发送一次。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::http_client::build_client;
+use crate::kiro::token_manager::{CachedBalance, MultiTokenManager};
+use crate::model::config::BalanceMonitorConfig;
+
+/// 告警类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    /// 剩余额度低于阈值
+    LowQuota,
+    /// 免费试用即将过期
+    TrialExpiring,
+}
+
+impl AlertKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::LowQuota => "low_quota",
+            AlertKind::TrialExpiring => "trial_expiring",
+        }
+    }
+}
+
+/// 告警内容（同时作为 webhook 请求体）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceAlert {
+    /// 事件类型（low_quota / trial_expiring）
+    pub event: &'static str,
+    /// 凭据 ID
+    pub credential_id: u64,
+    /// 告警说明
+    pub message: String,
+    /// 触发告警时的余额
+    pub balance: CachedBalance,
+}
+
+/// 余额监控器
+pub struct BalanceMonitor {
+    manager: Arc<MultiTokenManager>,
+    config: BalanceMonitorConfig,
+    client: reqwest::Client,
+    /// 已发出且尚未解除的告警
+    active_alerts: Mutex<HashSet<(u64, AlertKind)>>,
+}
+
+impl BalanceMonitor {
+    /// 创建余额监控器
+    ///
+    /// webhook 一般指向本地服务，不经过代理
+    pub fn new(manager: Arc<MultiTokenManager>) -> anyhow::Result<Self> {
+        let config = manager.config().balance_monitor.clone();
+        let client = build_client(None, 10, manager.config().tls_backend)?;
+        Ok(Self {
+            manager,
+            config,
+            client,
+            active_alerts: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// 轮询一轮所有凭据的余额并发送告警
+    pub async fn poll_once(&self) {
+        for id in self.manager.credential_ids() {
+            match self.manager.get_usage_limits_for(id).await {
+                Ok(usage) => {
+                    let balance = CachedBalance::from_usage(&usage, Utc::now());
+                    for alert in self.evaluate(id, &balance, Utc::now()) {
+                        self.dispatch(&alert).await;
+                    }
+                }
+                Err(e) => tracing::warn!("凭据 #{} 余额轮询失败: {}", id, e),
+            }
+        }
+    }
+
+    /// 根据余额判断需要新发出的告警，并清除已解除的告警
+    pub fn evaluate(
+        &self,
+        id: u64,
+        balance: &CachedBalance,
+        now: DateTime<Utc>,
+    ) -> Vec<BalanceAlert> {
+        let low_quota = balance.is_low(self.config.low_quota_percent);
+        let warning_secs = self.config.trial_expiry_warning_days as f64 * 86400.0;
+        let trial_expiring = balance
+            .trial_expires_at
+            .is_some_and(|ts| ts - (now.timestamp() as f64) < warning_secs);
+
+        let mut alerts = Vec::new();
+        let mut active = self.active_alerts.lock();
+        for (kind, triggered) in [
+            (AlertKind::LowQuota, low_quota),
+            (AlertKind::TrialExpiring, trial_expiring),
+        ] {
+            if !triggered {
+                active.remove(&(id, kind));
+                continue;
+            }
+            if !active.insert((id, kind)) {
+                continue;
+            }
+
+            let message = match kind {
+                AlertKind::LowQuota => format!(
+                    "凭据 #{} 剩余额度不足：{:.2}/{:.2}（{:.1}%）",
+                    id, balance.remaining, balance.usage_limit, balance.remaining_percentage
+                ),
+                AlertKind::TrialExpiring => {
+                    let expiry = balance
+                        .trial_expires_at
+                        .and_then(|ts| DateTime::from_timestamp(ts as i64, 0))
+                        .map(|t| t.to_rfc3339())
+                        .unwrap_or_default();
+                    format!("凭据 #{} 免费试用即将过期：{}", id, expiry)
+                }
+            };
+            alerts.push(BalanceAlert {
+                event: kind.as_str(),
+                credential_id: id,
+                message,
+                balance: balance.clone(),
+            });
+        }
+        alerts
+    }
+
+    /// 发送告警：记录日志，配置了 webhook 时同时推送
+    async fn dispatch(&self, alert: &BalanceAlert) {
+        tracing::warn!("{}", alert.message);
+
+        let Some(url) = self.config.webhook_url.as_deref() else {
+            return;
+        };
+        match self.client.post(url).json(alert).send().await {
+            Ok(resp) if !resp.status().is_success() => {
+                tracing::warn!("余额告警 webhook 返回错误状态: {}", resp.status());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("余额告警 webhook 发送失败: {}", e),
+        }
+    }
+}
+
+/// 启动余额轮询后台任务
+pub fn spawn_balance_monitor(
+    monitor: Arc<BalanceMonitor>,
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            monitor.poll_once().await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::model::config::Config;
+
+    fn monitor(low_quota_percent: f64) -> BalanceMonitor {
+        let config = Config {
+            balance_monitor: BalanceMonitorConfig {
+                low_quota_percent,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let manager =
+            MultiTokenManager::new(config, vec![KiroCredentials::default()], None, None, false)
+                .unwrap();
+        BalanceMonitor::new(Arc::new(manager)).unwrap()
+    }
+
+    fn balance(remaining: f64, trial_expires_at: Option<f64>) -> CachedBalance {
+        CachedBalance {
+            subscription_title: None,
+            current_usage: 100.0 - remaining,
+            usage_limit: 100.0,
+            remaining,
+            remaining_percentage: remaining,
+            next_reset_at: None,
+            trial_expires_at,
+            checked_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_low_quota_alert_fires_once_until_cleared() {
+        let monitor = monitor(10.0);
+        let now = Utc::now();
+
+        let alerts = monitor.evaluate(1, &balance(5.0, None), now);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].event, "low_quota");
+        assert!(monitor.evaluate(1, &balance(4.0, None), now).is_empty());
+
+        // 额度恢复后解除，再次下降时重新告警
+        assert!(monitor.evaluate(1, &balance(50.0, None), now).is_empty());
+        assert_eq!(monitor.evaluate(1, &balance(3.0, None), now).len(), 1);
+    }
+
+    #[test]
+    fn test_trial_expiring_alert() {
+        let monitor = monitor(10.0);
+        let now = Utc::now();
+        let ts = now.timestamp() as f64;
+
+        assert!(
+            monitor
+                .evaluate(1, &balance(80.0, Some(ts + 30.0 * 86400.0)), now)
+                .is_empty()
+        );
+        let alerts = monitor.evaluate(1, &balance(80.0, Some(ts + 86400.0)), now);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].event, "trial_expiring");
+    }
+}
@@ -0,0 +1,282 @@
+//! 熔断器
+//!
+//! 按滑动窗口内的错误率在 closed / open / half-open 之间切换：
+//! - closed: 正常放行，窗口内请求数达到下限且错误率超过阈值时打开
+//! - open: 拒绝请求，持续 `openSecs` 后进入 half-open
+//! - half-open: 每个 `openSecs` 周期放行一个探测请求，成功则关闭，失败则重新打开
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::model::config::CircuitBreakerConfig;
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// 熔断器状态快照（用于 Admin API）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitState,
+    /// 滑动窗口内的请求数
+    pub requests: usize,
+    /// 滑动窗口内的失败数
+    pub failures: usize,
+    /// 距离允许下一次（探测）请求的剩余秒数，仅 open 状态有值
+    pub open_remaining_secs: Option<u64>,
+}
+
+/// 单个熔断器
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: CircuitState,
+    /// 进入 open 或最近一次放行探测请求的时间
+    since: Option<Instant>,
+    /// 滑动窗口内的调用结果（时间, 是否成功）
+    outcomes: VecDeque<(Instant, bool)>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: CircuitState::Closed,
+            since: None,
+            outcomes: VecDeque::new(),
+        }
+    }
+
+    fn open_duration(&self) -> Duration {
+        Duration::from_secs(self.config.open_secs)
+    }
+
+    /// 当前是否允许请求（不改变状态，用于筛选）
+    pub fn permits(&self, now: Instant) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        match (self.state, self.since) {
+            (CircuitState::Closed, _) | (_, None) => true,
+            (_, Some(since)) => now.duration_since(since) >= self.open_duration(),
+        }
+    }
+
+    /// 放行一次请求；open 超时后转为 half-open 并占用探测名额
+    ///
+    /// 返回是否允许
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        if !self.permits(now) {
+            return false;
+        }
+        if self.config.enabled && self.state != CircuitState::Closed {
+            self.state = CircuitState::HalfOpen;
+            self.since = Some(now);
+        }
+        true
+    }
+
+    /// 归还未使用的探测名额：half-open 时恢复为可立即探测的 open 状态
+    pub fn release(&mut self, now: Instant) {
+        if self.state == CircuitState::HalfOpen {
+            self.state = CircuitState::Open;
+            self.since = now.checked_sub(self.open_duration());
+        }
+    }
+
+    /// 记录一次调用结果
+    pub fn record(&mut self, success: bool, now: Instant) {
+        if !self.config.enabled {
+            return;
+        }
+
+        match self.state {
+            CircuitState::HalfOpen if success => {
+                self.state = CircuitState::Closed;
+                self.since = None;
+                self.outcomes.clear();
+            }
+            CircuitState::HalfOpen => self.open(now),
+            // 打开期间的迟到结果不影响状态
+            CircuitState::Open => {}
+            CircuitState::Closed => {
+                self.outcomes.push_back((now, success));
+                self.prune(now);
+                let requests = self.outcomes.len();
+                let failures = self.failures();
+                if requests >= self.config.min_requests as usize
+                    && failures as f64 * 100.0 >= self.config.failure_rate_percent * requests as f64
+                {
+                    self.open(now);
+                }
+            }
+        }
+    }
+
+    /// 手动重置为 closed
+    pub fn reset(&mut self) {
+        self.state = CircuitState::Closed;
+        self.since = None;
+        self.outcomes.clear();
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    pub fn status(&self, now: Instant) -> CircuitBreakerStatus {
+        let open_remaining_secs = match (self.state, self.since) {
+            (CircuitState::Open, Some(since)) => Some(
+                self.open_duration()
+                    .saturating_sub(now.duration_since(since))
+                    .as_secs(),
+            ),
+            _ => None,
+        };
+        let window = Duration::from_secs(self.config.window_secs);
+        let recent = self
+            .outcomes
+            .iter()
+            .filter(|(t, _)| now.duration_since(*t) < window);
+        let (requests, failures) =
+            recent.fold((0, 0), |(r, f), (_, ok)| (r + 1, f + usize::from(!ok)));
+
+        CircuitBreakerStatus {
+            state: self.state,
+            requests,
+            failures,
+            open_remaining_secs,
+        }
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = CircuitState::Open;
+        self.since = Some(now);
+        self.outcomes.clear();
+    }
+
+    fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|(_, ok)| !ok).count()
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let window = Duration::from_secs(self.config.window_secs);
+        while self
+            .outcomes
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) >= window)
+        {
+            self.outcomes.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            min_requests: 4,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_opens_when_error_rate_exceeds_threshold() {
+        let mut cb = breaker();
+        let now = Instant::now();
+
+        cb.record(true, now);
+        cb.record(false, now);
+        cb.record(true, now);
+        assert_eq!(cb.state(), CircuitState::Closed);
+
+        // 4 次中 2 次失败，达到 50%
+        cb.record(false, now);
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!cb.permits(now));
+        assert_eq!(cb.status(now).open_remaining_secs, Some(30));
+    }
+
+    #[test]
+    fn test_old_outcomes_leave_window() {
+        let mut cb = breaker();
+        let start = Instant::now();
+        for _ in 0..3 {
+            cb.record(false, start);
+        }
+
+        // 窗口外的失败不再计入
+        let later = start + Duration::from_secs(61);
+        cb.record(false, later);
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.status(later).requests, 1);
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let mut cb = breaker();
+        let start = Instant::now();
+        for _ in 0..4 {
+            cb.record(false, start);
+        }
+        assert!(!cb.try_acquire(start + Duration::from_secs(10)));
+
+        // open 超时后放行一个探测请求，其余请求继续拒绝
+        let probe = start + Duration::from_secs(30);
+        assert!(cb.try_acquire(probe));
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(!cb.try_acquire(probe));
+
+        // 探测失败重新打开
+        cb.record(false, probe);
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        // 探测成功关闭
+        let probe = probe + Duration::from_secs(30);
+        assert!(cb.try_acquire(probe));
+        cb.record(true, probe);
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(cb.permits(probe));
+    }
+
+    #[test]
+    fn test_release_returns_probe() {
+        let mut cb = breaker();
+        let start = Instant::now();
+        for _ in 0..4 {
+            cb.record(false, start);
+        }
+
+        // 归还未使用的探测名额后可立即再次探测
+        let probe = start + Duration::from_secs(30);
+        assert!(cb.try_acquire(probe));
+        cb.release(probe);
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(cb.try_acquire(probe));
+    }
+
+    #[test]
+    fn test_disabled_never_opens() {
+        let mut cb = CircuitBreaker::new(CircuitBreakerConfig {
+            enabled: false,
+            min_requests: 1,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        for _ in 0..10 {
+            cb.record(false, now);
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(cb.try_acquire(now));
+    }
+}
@@ -0,0 +1,204 @@
+//! 并发限制与排队
+//!
+//! 全局与凭据级并发由信号量控制：`acquire_context` 获取槽位，
+//! 槽位随调用上下文和上游响应体一起释放（流式响应在流结束后释放）。
+//! 所有槽位占满时请求进入有界队列等待，排队位置通过 `x-queue-position` 响应头返回。
+
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use tokio::sync::{Notify, OwnedSemaphorePermit};
+
+/// 排队位置响应头
+pub const QUEUE_POSITION_HEADER: &str = "x-queue-position";
+
+tokio::task_local! {
+    static QUEUE_POSITION: Cell<Option<usize>>;
+}
+
+/// 已获取的并发槽位，释放时唤醒排队中的请求
+pub struct ConcurrencyPermit {
+    global: Option<OwnedSemaphorePermit>,
+    credential: Option<OwnedSemaphorePermit>,
+    released: Arc<Notify>,
+}
+
+impl ConcurrencyPermit {
+    pub fn new(
+        global: Option<OwnedSemaphorePermit>,
+        credential: Option<OwnedSemaphorePermit>,
+        released: Arc<Notify>,
+    ) -> Self {
+        Self {
+            global,
+            credential,
+            released,
+        }
+    }
+
+    /// 是否实际占用了槽位（未配置任何并发上限时为 false）
+    pub fn is_limited(&self) -> bool {
+        self.global.is_some() || self.credential.is_some()
+    }
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        // 先归还槽位再唤醒，避免被唤醒的请求仍看到槽位占满
+        self.global.take();
+        self.credential.take();
+        self.released.notify_waiters();
+    }
+}
+
+impl std::fmt::Debug for ConcurrencyPermit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConcurrencyPermit")
+            .field("global", &self.global.is_some())
+            .field("credential", &self.credential.is_some())
+            .finish()
+    }
+}
+
+/// 排队中的请求，离开队列时释放名额
+pub struct QueueTicket {
+    waiting: Arc<AtomicUsize>,
+    position: usize,
+}
+
+impl QueueTicket {
+    /// 进入队列，队列已满时返回 None
+    pub fn enter(waiting: &Arc<AtomicUsize>, capacity: usize) -> Option<Self> {
+        let mut current = waiting.load(Ordering::Relaxed);
+        loop {
+            if current >= capacity {
+                return None;
+            }
+            match waiting.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(Self {
+                        waiting: waiting.clone(),
+                        position: current + 1,
+                    });
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// 进入队列时的位置（从 1 开始）
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        self.waiting.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 让上游响应体持有并发槽位，直到响应体读取完毕或被丢弃
+pub fn hold_until_body_done(
+    response: reqwest::Response,
+    permit: Arc<ConcurrencyPermit>,
+) -> reqwest::Response {
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let extensions = response.extensions().clone();
+    let body = response.bytes_stream().map(move |chunk| {
+        let _held = &permit;
+        chunk
+    });
+
+    let mut held = http::Response::new(reqwest::Body::wrap_stream(body));
+    *held.status_mut() = status;
+    *held.version_mut() = version;
+    *held.headers_mut() = headers;
+    *held.extensions_mut() = extensions;
+    reqwest::Response::from(held)
+}
+
+/// 记录当前请求的排队位置（仅在 `queue_position_middleware` 作用域内生效）
+pub fn report_queue_position(position: usize) {
+    let _ = QUEUE_POSITION.try_with(|cell| {
+        if cell.get().is_none() {
+            cell.set(Some(position));
+        }
+    });
+}
+
+/// 为排队过的请求添加 `x-queue-position` 响应头
+pub async fn queue_position_middleware(request: Request<Body>, next: Next) -> Response {
+    QUEUE_POSITION
+        .scope(Cell::new(None), async move {
+            let mut response = next.run(request).await;
+            if let Some(position) = QUEUE_POSITION.with(Cell::get) {
+                response
+                    .headers_mut()
+                    .insert(QUEUE_POSITION_HEADER, HeaderValue::from(position));
+            }
+            response
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Semaphore;
+
+    #[test]
+    fn test_queue_ticket_is_bounded() {
+        let waiting = Arc::new(AtomicUsize::new(0));
+        let first = QueueTicket::enter(&waiting, 2).unwrap();
+        let second = QueueTicket::enter(&waiting, 2).unwrap();
+        assert_eq!(first.position(), 1);
+        assert_eq!(second.position(), 2);
+        assert!(QueueTicket::enter(&waiting, 2).is_none());
+
+        drop(first);
+        assert!(QueueTicket::enter(&waiting, 2).is_some());
+        drop(second);
+        assert_eq!(waiting.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_permit_held_until_body_consumed() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let permit = Arc::new(ConcurrencyPermit::new(
+            Some(semaphore.clone().try_acquire_owned().unwrap()),
+            None,
+            Arc::new(Notify::new()),
+        ));
+
+        let upstream = reqwest::Response::from(
+            http::Response::builder()
+                .status(201)
+                .header("x-upstream", "1")
+                .body("hello")
+                .unwrap(),
+        );
+        let response = hold_until_body_done(upstream, permit);
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.headers()["x-upstream"], "1");
+        assert_eq!(semaphore.available_permits(), 0);
+
+        assert_eq!(response.text().await.unwrap(), "hello");
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+}
@@ -0,0 +1,537 @@
+//! 凭据文件存储：加密与原子写入
+//!
+//! 回写通过临时文件 + fsync + rename 原子替换，并保留若干轮转备份。
+//!
+//! 配置密钥后，凭据文件以 AES-256-GCM 加密的 JSON 信封保存：
+//! `CredentialsConfig::load` 自动识别并解密，`persist_credentials` 回写时自动加密。
+//!
+//! 密钥来源（按优先级）：
+//! 1. 环境变量 `KIRO_CREDENTIALS_KEY`
+//! 2. `config.json` 的 `credentialsKeyFile` 指向的密钥文件
+//!
+//! 密钥内容必须为 base64 编码的 32 字节随机数（`generate-key` 生成）。
+//!
+//! 加密迁移与密钥轮换会同时处理已有的 `.bak.N` 备份，不在磁盘上残留明文或旧密钥可解密的副本
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::kiro::model::credentials::CredentialsConfig;
+use crate::model::config::Config;
+
+/// 密钥环境变量名
+pub const CREDENTIALS_KEY_ENV: &str = "KIRO_CREDENTIALS_KEY";
+
+/// 加密信封格式版本
+const ENVELOPE_VERSION: u32 = 1;
+
+/// 加密算法标识
+const ALGORITHM: &str = "AES-256-GCM";
+
+/// 凭据文件加密密钥
+#[derive(Clone)]
+pub struct CredentialsKey([u8; 32]);
+
+impl std::fmt::Debug for CredentialsKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CredentialsKey(***)")
+    }
+}
+
+impl CredentialsKey {
+    /// 解析密钥内容：必须为 base64 编码的 32 字节随机数
+    pub fn parse(material: &str) -> anyhow::Result<Self> {
+        BASE64
+            .decode(material.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+            .map(Self)
+            .ok_or_else(|| {
+                anyhow::anyhow!("密钥必须是 base64 编码的 32 字节随机数（可用 generate-key 生成）")
+            })
+    }
+
+    /// 生成随机密钥
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng).into())
+    }
+
+    /// 从密钥文件读取
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let material = std::fs::read_to_string(path)
+            .with_context(|| format!("读取密钥文件失败: {:?}", path))?;
+        if material.trim().is_empty() {
+            anyhow::bail!("密钥文件为空: {:?}", path);
+        }
+        Self::parse(&material).with_context(|| format!("密钥文件无效: {:?}", path))
+    }
+
+    /// 按环境变量、配置文件的顺序解析密钥，均未配置时返回 None
+    pub fn resolve(config: &Config) -> anyhow::Result<Option<Self>> {
+        if let Ok(material) = std::env::var(CREDENTIALS_KEY_ENV)
+            && !material.trim().is_empty()
+        {
+            return Self::parse(&material)
+                .with_context(|| format!("环境变量 {} 无效", CREDENTIALS_KEY_ENV))
+                .map(Some);
+        }
+        config
+            .credentials_key_file
+            .as_ref()
+            .map(Self::from_file)
+            .transpose()
+    }
+
+    /// base64 编码（用于写入密钥文件或环境变量）
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0))
+    }
+}
+
+/// 加密信封
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptedEnvelope {
+    kiro_encrypted: u32,
+    algorithm: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 全局凭据密钥（启动时初始化一次）
+static CREDENTIALS_KEY: OnceLock<Option<CredentialsKey>> = OnceLock::new();
+
+/// 初始化全局凭据密钥
+///
+/// 应在加载凭据文件前调用一次
+pub fn init_credentials_key(key: Option<CredentialsKey>) {
+    let _ = CREDENTIALS_KEY.set(key);
+}
+
+/// 获取全局凭据密钥
+fn credentials_key() -> Option<&'static CredentialsKey> {
+    CREDENTIALS_KEY.get().and_then(|key| key.as_ref())
+}
+
+/// 判断文件内容是否为加密信封
+pub fn is_encrypted(content: &str) -> bool {
+    serde_json::from_str::<EncryptedEnvelope>(content).is_ok()
+}
+
+/// 使用指定密钥加密
+pub fn encrypt_with(plaintext: &str, key: &CredentialsKey) -> anyhow::Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = key
+        .cipher()
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("凭据加密失败"))?;
+
+    let envelope = EncryptedEnvelope {
+        kiro_encrypted: ENVELOPE_VERSION,
+        algorithm: ALGORITHM.to_string(),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+/// 使用指定密钥解密
+pub fn decrypt_with(content: &str, key: &CredentialsKey) -> anyhow::Result<String> {
+    let envelope: EncryptedEnvelope =
+        serde_json::from_str(content).context("凭据文件不是加密格式")?;
+    if envelope.kiro_encrypted != ENVELOPE_VERSION || envelope.algorithm != ALGORITHM {
+        anyhow::bail!(
+            "不支持的凭据加密格式: v{} {}",
+            envelope.kiro_encrypted,
+            envelope.algorithm
+        );
+    }
+
+    let nonce = BASE64.decode(&envelope.nonce).context("nonce 解码失败")?;
+    if nonce.len() != 12 {
+        anyhow::bail!("nonce 长度无效");
+    }
+    let ciphertext = BASE64
+        .decode(&envelope.ciphertext)
+        .context("密文解码失败")?;
+    let plaintext = key
+        .cipher()
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow::anyhow!("凭据文件解密失败（密钥不匹配或文件已损坏）"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// 读取凭据文件内容：加密信封使用全局密钥解密，明文原样返回
+pub fn open(content: &str) -> anyhow::Result<String> {
+    if !is_encrypted(content) {
+        return Ok(content.to_string());
+    }
+    let key = credentials_key().ok_or_else(|| {
+        anyhow::anyhow!(
+            "凭据文件已加密，但未配置密钥（{} 或 credentialsKeyFile）",
+            CREDENTIALS_KEY_ENV
+        )
+    })?;
+    decrypt_with(content, key)
+}
+
+/// 准备写入凭据文件的内容：配置了全局密钥时加密，否则保持明文
+pub fn seal(plaintext: &str) -> anyhow::Result<String> {
+    match credentials_key() {
+        Some(key) => encrypt_with(plaintext, key),
+        None => Ok(plaintext.to_string()),
+    }
+}
+
+/// 内容指纹（SHA-256 十六进制），用于检测文件是否在外部被修改
+pub fn fingerprint(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+/// 第 `n` 个备份文件路径：`<path>.bak.<n>`（1 为最新）
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", n));
+    PathBuf::from(name)
+}
+
+/// 轮转备份：丢弃最旧的，其余依次后移，当前文件复制为 `.bak.1`
+fn rotate_backups(path: &Path, backups: usize) -> anyhow::Result<()> {
+    let oldest = backup_path(path, backups);
+    if oldest.exists() {
+        std::fs::remove_file(&oldest).with_context(|| format!("删除旧备份失败: {:?}", oldest))?;
+    }
+    for n in (1..backups).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            std::fs::rename(&from, backup_path(path, n + 1))
+                .with_context(|| format!("轮转备份失败: {:?}", from))?;
+        }
+    }
+    std::fs::copy(path, backup_path(path, 1))
+        .with_context(|| format!("创建备份失败: {:?}", path))?;
+    Ok(())
+}
+
+/// 现有的全部备份文件（含超出当前备份数量配置的旧备份）
+fn existing_backups(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let prefix = match path.file_name() {
+        Some(name) => format!("{}.bak.", name.to_string_lossy()),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("读取目录失败: {:?}", dir))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name
+            .to_string_lossy()
+            .strip_prefix(&prefix)
+            .is_some_and(|n| n.parse::<usize>().is_ok())
+        {
+            backups.push(entry.path());
+        }
+    }
+    Ok(backups)
+}
+
+/// 使用新密钥重新加密现有备份
+///
+/// 明文备份直接加密；加密备份用 `old_key` 解密后重新加密，无法解密的删除
+fn reseal_backups(
+    path: &Path,
+    old_key: Option<&CredentialsKey>,
+    new_key: &CredentialsKey,
+) -> anyhow::Result<()> {
+    for backup in existing_backups(path)? {
+        let content = std::fs::read_to_string(&backup)
+            .with_context(|| format!("读取备份失败: {:?}", backup))?;
+        let plaintext = if is_encrypted(&content) {
+            old_key.and_then(|key| decrypt_with(&content, key).ok())
+        } else {
+            Some(content)
+        };
+        match plaintext {
+            Some(plaintext) => write_atomic(&backup, &encrypt_with(&plaintext, new_key)?, 0)?,
+            None => std::fs::remove_file(&backup)
+                .with_context(|| format!("删除备份失败: {:?}", backup))?,
+        }
+    }
+    Ok(())
+}
+
+/// 使用全局密钥加密现有的明文备份（凭据文件首次加密写入时调用）
+pub fn seal_backups(path: &Path) -> anyhow::Result<()> {
+    match credentials_key() {
+        Some(key) => reseal_backups(path, Some(key), key),
+        None => Ok(()),
+    }
+}
+
+/// 原子写入文件
+///
+/// 先写入同目录临时文件并 fsync，再 rename 覆盖目标文件，中途崩溃或磁盘写满不会破坏原文件。
+/// `backups > 0` 时覆盖前将原文件轮转备份为 `<path>.bak.1..=backups`。
+/// 新文件沿用原文件权限（Unix 下新建文件为 0600）
+pub fn write_atomic(path: &Path, content: &str, backups: usize) -> anyhow::Result<()> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("无效的文件路径: {:?}", path))?
+        .to_string_lossy();
+    let tmp_path = dir.join(format!(
+        ".{}.tmp-{}",
+        file_name,
+        uuid::Uuid::new_v4().simple()
+    ));
+
+    let written = (|| -> anyhow::Result<()> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp_path)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    })();
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e.context(format!("写入临时文件失败: {:?}", tmp_path)));
+    }
+
+    if backups > 0
+        && path.exists()
+        && let Err(e) = rotate_backups(path, backups)
+    {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(anyhow::Error::new(e).context(format!("替换文件失败: {:?}", path)));
+    }
+
+    // 确保 rename 本身落盘
+    #[cfg(unix)]
+    if let Ok(dir) = std::fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// 将明文凭据文件加密（迁移）
+pub fn encrypt_file(path: impl AsRef<Path>, key: &CredentialsKey) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("读取凭据文件失败: {:?}", path))?;
+    if is_encrypted(&content) {
+        anyhow::bail!("凭据文件已是加密格式: {:?}", path);
+    }
+    serde_json::from_str::<CredentialsConfig>(&content).context("凭据文件格式无效")?;
+
+    // 不保留备份，并加密已有的明文备份，避免在磁盘上残留明文副本
+    write_atomic(path, &encrypt_with(&content, key)?, 0)?;
+    reseal_backups(path, Some(key), key)
+}
+
+/// 使用新密钥重新加密凭据文件（明文文件直接用新密钥加密）
+pub fn rotate_file(
+    path: impl AsRef<Path>,
+    old_key: Option<&CredentialsKey>,
+    new_key: &CredentialsKey,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("读取凭据文件失败: {:?}", path))?;
+    let plaintext = if is_encrypted(&content) {
+        let old_key = old_key.ok_or_else(|| anyhow::anyhow!("凭据文件已加密，需要提供当前密钥"))?;
+        decrypt_with(&content, old_key)?
+    } else {
+        content
+    };
+    serde_json::from_str::<CredentialsConfig>(&plaintext).context("凭据文件格式无效")?;
+
+    // 不保留备份，并用新密钥重新加密已有备份，避免残留明文或可被旧密钥解密的副本
+    write_atomic(path, &encrypt_with(&plaintext, new_key)?, 0)?;
+    reseal_backups(path, old_key, new_key)
+}
+
+/// 生成新密钥并写入密钥文件（文件已存在时报错，Unix 下权限为 0600）
+pub fn write_new_key_file(path: impl AsRef<Path>) -> anyhow::Result<CredentialsKey> {
+    let path = path.as_ref();
+    let key = CredentialsKey::generate();
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("创建密钥文件失败: {:?}", path))?;
+    writeln!(file, "{}", key.to_base64())?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"[{"refreshToken":"secret-refresh","clientSecret":"secret-client"}]"#;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("kiro-{}-{}", name, uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = CredentialsKey::generate();
+        let sealed = encrypt_with(SAMPLE, &key).unwrap();
+
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("secret-refresh"));
+        assert_eq!(decrypt_with(&sealed, &key).unwrap(), SAMPLE);
+
+        let wrong = CredentialsKey::generate();
+        let err = decrypt_with(&sealed, &wrong).unwrap_err().to_string();
+        assert!(err.contains("解密失败"));
+    }
+
+    #[test]
+    fn test_parse_key_material() {
+        let key = CredentialsKey::generate();
+        assert_eq!(CredentialsKey::parse(&key.to_base64()).unwrap().0, key.0);
+        assert_eq!(
+            CredentialsKey::parse(&format!(" {}\n", key.to_base64()))
+                .unwrap()
+                .0,
+            key.0
+        );
+        // 口令或长度不足的密钥直接拒绝
+        assert!(CredentialsKey::parse("passphrase").is_err());
+        assert!(CredentialsKey::parse(&BASE64.encode([0u8; 16])).is_err());
+        assert!(!is_encrypted(SAMPLE));
+    }
+
+    #[test]
+    fn test_write_atomic_rotates_backups() {
+        let path = temp_path("atomic.json");
+        for content in ["v1", "v2", "v3", "v4"] {
+            write_atomic(&path, content, 2).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v4");
+        assert_eq!(
+            std::fs::read_to_string(backup_path(&path, 1)).unwrap(),
+            "v3"
+        );
+        assert_eq!(
+            std::fs::read_to_string(backup_path(&path, 2)).unwrap(),
+            "v2"
+        );
+        assert!(!backup_path(&path, 3).exists());
+
+        for p in [path.clone(), backup_path(&path, 1), backup_path(&path, 2)] {
+            std::fs::remove_file(p).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_encrypt_and_rotate_file() {
+        let path = temp_path("credentials.json");
+        std::fs::write(&path, SAMPLE).unwrap();
+
+        let old_key = CredentialsKey::generate();
+        encrypt_file(&path, &old_key).unwrap();
+        assert!(encrypt_file(&path, &old_key).is_err());
+
+        let new_key_path = temp_path("credentials.key");
+        let new_key = write_new_key_file(&new_key_path).unwrap();
+        assert!(write_new_key_file(&new_key_path).is_err());
+        assert_eq!(
+            CredentialsKey::from_file(&new_key_path).unwrap().0,
+            new_key.0
+        );
+
+        rotate_file(&path, Some(&old_key), &new_key).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(decrypt_with(&content, &old_key).is_err());
+        assert_eq!(decrypt_with(&content, &new_key).unwrap(), SAMPLE);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&new_key_path).unwrap();
+    }
+
+    #[test]
+    fn test_no_plaintext_backup_survives() {
+        let path = temp_path("credentials.json");
+        for _ in 0..3 {
+            write_atomic(&path, SAMPLE, 2).unwrap();
+        }
+        // 超出当前备份数量配置的旧备份
+        std::fs::write(backup_path(&path, 5), SAMPLE).unwrap();
+
+        let old_key = CredentialsKey::generate();
+        encrypt_file(&path, &old_key).unwrap();
+        let backups = existing_backups(&path).unwrap();
+        assert_eq!(backups.len(), 3);
+        for backup in &backups {
+            let content = std::fs::read_to_string(backup).unwrap();
+            assert!(!content.contains("secret-refresh"));
+            assert_eq!(decrypt_with(&content, &old_key).unwrap(), SAMPLE);
+        }
+
+        // 轮换密钥后备份只能用新密钥解密；无法解密的备份被删除
+        std::fs::write(
+            backup_path(&path, 4),
+            encrypt_with(SAMPLE, &CredentialsKey::generate()).unwrap(),
+        )
+        .unwrap();
+        let new_key = CredentialsKey::generate();
+        rotate_file(&path, Some(&old_key), &new_key).unwrap();
+        assert!(!backup_path(&path, 4).exists());
+        let backups = existing_backups(&path).unwrap();
+        assert_eq!(backups.len(), 3);
+        for backup in &backups {
+            let content = std::fs::read_to_string(backup).unwrap();
+            assert!(decrypt_with(&content, &old_key).is_err());
+            assert_eq!(decrypt_with(&content, &new_key).unwrap(), SAMPLE);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+        for backup in backups {
+            std::fs::remove_file(backup).unwrap();
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! AWS SSO OIDC 设备授权流程
+//!
+//! 通过 Builder ID 或 IAM Identity Center 的设备授权生成可直接使用的 IdC 凭据：
+//! 注册 OIDC 客户端 → 发起设备授权 → 用户在浏览器中确认 → 轮询换取 Token。
+//!
+//! OIDC 地址可通过 `config.json` 的 `oidcEndpoint` 覆盖（如指向本地替身服务做测试）
+
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{Client, Url};
+
+use crate::http_client::{ProxyConfig, build_client};
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::model::device_auth::{
+    DeviceTokenRequest, DeviceTokenResponse, OidcErrorResponse, RegisterClientRequest,
+    RegisterClientResponse, StartDeviceAuthorizationRequest, StartDeviceAuthorizationResponse,
+};
+use crate::model::config::Config;
+
+/// Builder ID 的起始 URL
+pub const BUILDER_ID_START_URL: &str = "https://view.awsapps.com/start";
+
+/// 注册 OIDC 客户端时使用的名称
+const CLIENT_NAME: &str = "kiro-rs";
+
+/// Kiro 所需的 CodeWhisperer scopes
+const SCOPES: &[&str] = &[
+    "codewhisperer:completions",
+    "codewhisperer:analysis",
+    "codewhisperer:conversations",
+    "codewhisperer:transformations",
+    "codewhisperer:taskassist",
+];
+
+/// 设备码授权类型
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// 服务端未返回 interval 时的默认轮询间隔（秒）
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+
+/// 收到 slow_down 时增加的轮询间隔（秒）
+pub const SLOW_DOWN_INCREMENT_SECS: u64 = 5;
+
+/// 获取 OIDC 服务地址
+///
+/// 优先使用 `config.oidc_endpoint`，未配置时为 `https://oidc.{region}.amazonaws.com`
+pub fn oidc_base_url(config: &Config, region: &str) -> String {
+    match config.oidc_endpoint.as_deref().map(str::trim) {
+        Some(endpoint) if !endpoint.is_empty() => endpoint.trim_end_matches('/').to_string(),
+        _ => format!("https://oidc.{}.amazonaws.com", region),
+    }
+}
+
+/// OIDC 服务地址对应的 Host header（含非默认端口）
+pub fn oidc_host(base_url: &str) -> String {
+    Url::parse(base_url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// 进行中的设备授权会话
+#[derive(Debug, Clone)]
+pub struct DeviceAuthSession {
+    /// 注册得到的 OIDC 客户端
+    pub client: RegisterClientResponse,
+    /// 设备授权信息（含用户码与验证地址）
+    pub authorization: StartDeviceAuthorizationResponse,
+    /// OIDC 区域（写入凭据，用于后续刷新）
+    pub region: String,
+    /// 当前轮询间隔（秒）
+    pub interval_secs: u64,
+    /// 设备码过期时间
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DeviceAuthSession {
+    /// 设备码是否已过期
+    pub fn is_expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+
+    /// 由换取到的 Token 生成 IdC 凭据
+    pub fn credentials(&self, token: DeviceTokenResponse, priority: u32) -> KiroCredentials {
+        KiroCredentials {
+            access_token: Some(token.access_token),
+            refresh_token: Some(token.refresh_token),
+            expires_at: token
+                .expires_in
+                .map(|secs| (Utc::now() + Duration::seconds(secs)).to_rfc3339()),
+            auth_method: Some("idc".to_string()),
+            client_id: Some(self.client.client_id.clone()),
+            client_secret: Some(self.client.client_secret.clone()),
+            priority,
+            region: Some(self.region.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// 单次轮询结果
+#[derive(Debug)]
+pub enum PollOutcome {
+    /// 用户尚未完成授权
+    Pending,
+    /// 轮询过快，需要增大间隔
+    SlowDown,
+    /// 授权完成
+    Complete(DeviceTokenResponse),
+    /// 设备码已过期，需要重新发起授权
+    Expired,
+    /// 用户拒绝了授权请求
+    Denied,
+}
+
+impl PollOutcome {
+    /// 设备码过期的提示
+    pub const EXPIRED_MESSAGE: &str = "设备码已过期，请重新发起授权";
+    /// 用户拒绝授权的提示
+    pub const DENIED_MESSAGE: &str = "用户拒绝了授权请求";
+}
+
+/// 设备授权客户端
+pub struct DeviceAuthClient {
+    client: Client,
+    base_url: String,
+    region: String,
+}
+
+impl DeviceAuthClient {
+    pub fn new(
+        config: &Config,
+        region: impl Into<String>,
+        proxy: Option<&ProxyConfig>,
+    ) -> anyhow::Result<Self> {
+        let region = region.into();
+        Ok(Self {
+            client: build_client(proxy, 30, config.tls_backend)?,
+            base_url: oidc_base_url(config, &region),
+            region,
+        })
+    }
+
+    /// 注册 OIDC 客户端并发起设备授权
+    ///
+    /// `start_url` 为 Builder ID 以外的地址时按 IAM Identity Center 注册
+    pub async fn start(&self, start_url: &str) -> anyhow::Result<DeviceAuthSession> {
+        let issuer_url = (start_url != BUILDER_ID_START_URL).then(|| start_url.to_string());
+        let client: RegisterClientResponse = self
+            .post_json(
+                "client/register",
+                &RegisterClientRequest {
+                    client_name: CLIENT_NAME.to_string(),
+                    client_type: "public".to_string(),
+                    scopes: SCOPES.iter().map(|s| s.to_string()).collect(),
+                    grant_types: vec![
+                        DEVICE_CODE_GRANT_TYPE.to_string(),
+                        "refresh_token".to_string(),
+                    ],
+                    issuer_url,
+                },
+            )
+            .await?;
+
+        let authorization: StartDeviceAuthorizationResponse = self
+            .post_json(
+                "device_authorization",
+                &StartDeviceAuthorizationRequest {
+                    client_id: client.client_id.clone(),
+                    client_secret: client.client_secret.clone(),
+                    start_url: start_url.to_string(),
+                },
+            )
+            .await?;
+
+        tracing::info!(
+            "已发起设备授权，用户码: {}，有效期 {} 秒",
+            authorization.user_code,
+            authorization.expires_in
+        );
+
+        Ok(DeviceAuthSession {
+            interval_secs: authorization.interval.unwrap_or(DEFAULT_POLL_INTERVAL_SECS),
+            expires_at: Utc::now() + Duration::seconds(authorization.expires_in),
+            region: self.region.clone(),
+            client,
+            authorization,
+        })
+    }
+
+    /// 轮询一次设备码换取 Token
+    pub async fn poll(&self, session: &DeviceAuthSession) -> anyhow::Result<PollOutcome> {
+        let body = DeviceTokenRequest {
+            client_id: session.client.client_id.clone(),
+            client_secret: session.client.client_secret.clone(),
+            grant_type: DEVICE_CODE_GRANT_TYPE.to_string(),
+            device_code: session.authorization.device_code.clone(),
+        };
+        let response = self
+            .client
+            .post(format!("{}/token", self.base_url))
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(PollOutcome::Complete(response.json().await?));
+        }
+
+        let body_text = response.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<OidcErrorResponse>(&body_text).ok();
+        match error.as_ref().map(|e| e.error.as_str()) {
+            Some("authorization_pending") => Ok(PollOutcome::Pending),
+            Some("slow_down") => Ok(PollOutcome::SlowDown),
+            Some("expired_token") => Ok(PollOutcome::Expired),
+            Some("access_denied") => Ok(PollOutcome::Denied),
+            _ => {
+                let detail = error.and_then(|e| e.error_description).unwrap_or(body_text);
+                anyhow::bail!("设备授权 Token 获取失败: {} {}", status, detail)
+            }
+        }
+    }
+
+    async fn post_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl serde::Serialize,
+    ) -> anyhow::Result<T> {
+        let response = self
+            .client
+            .post(format!("{}/{}", self.base_url, path))
+            .json(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("OIDC {} 请求失败: {} {}", path, status, body_text);
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// 完整执行设备授权流程，直到用户完成授权或设备码过期
+///
+/// 发起授权后通过 `on_prompt` 通知调用方展示验证地址与用户码
+pub async fn run_device_flow(
+    client: &DeviceAuthClient,
+    start_url: &str,
+    priority: u32,
+    on_prompt: impl FnOnce(&DeviceAuthSession),
+) -> anyhow::Result<KiroCredentials> {
+    let mut session = client.start(start_url).await?;
+    on_prompt(&session);
+
+    loop {
+        if session.is_expired() {
+            anyhow::bail!(PollOutcome::EXPIRED_MESSAGE);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(session.interval_secs)).await;
+
+        match client.poll(&session).await? {
+            PollOutcome::Pending => {}
+            PollOutcome::SlowDown => session.interval_secs += SLOW_DOWN_INCREMENT_SECS,
+            PollOutcome::Complete(token) => return Ok(session.credentials(token, priority)),
+            PollOutcome::Expired => anyhow::bail!(PollOutcome::EXPIRED_MESSAGE),
+            PollOutcome::Denied => anyhow::bail!(PollOutcome::DENIED_MESSAGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{Json, Router, http::StatusCode, response::IntoResponse, routing::post};
+    use serde_json::json;
+
+    /// 本地 OIDC 替身：第一次轮询返回 authorization_pending，第二次返回 Token
+    async fn spawn_oidc_stand_in() -> String {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/client/register",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["clientType"], "public");
+                    Json(json!({"clientId": "cid", "clientSecret": "csecret"}))
+                }),
+            )
+            .route(
+                "/device_authorization",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["clientId"], "cid");
+                    Json(json!({
+                        "deviceCode": "dev-code",
+                        "userCode": "ABCD-EFGH",
+                        "verificationUri": "https://device.sso.example/",
+                        "expiresIn": 600,
+                        "interval": 0
+                    }))
+                }),
+            )
+            .route(
+                "/token",
+                post(move |Json(body): Json<serde_json::Value>| {
+                    let polls = polls.clone();
+                    async move {
+                        assert_eq!(body["deviceCode"], "dev-code");
+                        if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                            (
+                                StatusCode::BAD_REQUEST,
+                                Json(json!({"error": "authorization_pending"})),
+                            )
+                                .into_response()
+                        } else {
+                            Json(json!({
+                                "accessToken": "access",
+                                "refreshToken": "refresh",
+                                "expiresIn": 3600
+                            }))
+                            .into_response()
+                        }
+                    }
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_oidc_base_url_override() {
+        let mut config = Config::default();
+        assert_eq!(
+            oidc_base_url(&config, "eu-west-1"),
+            "https://oidc.eu-west-1.amazonaws.com"
+        );
+        config.oidc_endpoint = Some("http://127.0.0.1:9000/".to_string());
+        assert_eq!(oidc_base_url(&config, "eu-west-1"), "http://127.0.0.1:9000");
+        assert_eq!(oidc_host("http://127.0.0.1:9000"), "127.0.0.1:9000");
+        assert_eq!(
+            oidc_host("https://oidc.us-east-1.amazonaws.com"),
+            "oidc.us-east-1.amazonaws.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_device_flow_against_stand_in() {
+        let config = Config {
+            oidc_endpoint: Some(spawn_oidc_stand_in().await),
+            ..Default::default()
+        };
+        let client = DeviceAuthClient::new(&config, "us-east-1", None).unwrap();
+
+        let mut prompted = None;
+        let credentials = run_device_flow(&client, BUILDER_ID_START_URL, 2, |session| {
+            prompted = Some(session.authorization.user_code.clone());
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(prompted.as_deref(), Some("ABCD-EFGH"));
+        assert_eq!(credentials.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(credentials.client_id.as_deref(), Some("cid"));
+        assert_eq!(credentials.client_secret.as_deref(), Some("csecret"));
+        assert_eq!(credentials.auth_method.as_deref(), Some("idc"));
+        assert_eq!(credentials.region.as_deref(), Some("us-east-1"));
+        assert_eq!(credentials.priority, 2);
+        assert!(credentials.expires_at.is_some());
+    }
+}
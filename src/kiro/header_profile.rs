@@ -0,0 +1,261 @@
+//! 请求头配置（客户端指纹）
+//!
+//! 各类上游请求的请求头由命名配置定义：凭据 `headerProfile` > config.json 的 `headerProfile`
+//! > 内置配置。上游客户端版本升级时只需修改配置，无需改代码。
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use uuid::Uuid;
+
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::model::config::{Config, HeaderProfile, HeaderTemplates};
+
+/// 请求类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFlow {
+    Api,
+    Mcp,
+    SocialRefresh,
+    IdcRefresh,
+    UsageLimits,
+}
+
+/// 内置 generateAssistantResponse 请求头
+const BUILTIN_API: &[(&str, &str)] = &[
+    ("content-type", "application/json"),
+    ("x-amzn-codewhisperer-optout", "true"),
+    ("x-amzn-kiro-agent-mode", "vibe"),
+    (
+        "x-amz-user-agent",
+        "aws-sdk-js/1.0.27 KiroIDE-{kiroVersion}-{machineId}",
+    ),
+    (
+        "user-agent",
+        "aws-sdk-js/1.0.27 ua/2.1 os/{systemVersion} lang/js md/nodejs#{nodeVersion} api/codewhispererstreaming#1.0.27 m/E KiroIDE-{kiroVersion}-{machineId}",
+    ),
+    ("host", "{host}"),
+    ("amz-sdk-invocation-id", "{invocationId}"),
+    ("amz-sdk-request", "attempt=1; max=3"),
+];
+
+/// 内置 MCP 请求头
+const BUILTIN_MCP: &[(&str, &str)] = &[
+    ("content-type", "application/json"),
+    (
+        "x-amz-user-agent",
+        "aws-sdk-js/1.0.27 KiroIDE-{kiroVersion}-{machineId}",
+    ),
+    (
+        "user-agent",
+        "aws-sdk-js/1.0.27 ua/2.1 os/{systemVersion} lang/js md/nodejs#{nodeVersion} api/codewhispererstreaming#1.0.27 m/E KiroIDE-{kiroVersion}-{machineId}",
+    ),
+    ("host", "{host}"),
+    ("amz-sdk-invocation-id", "{invocationId}"),
+    ("amz-sdk-request", "attempt=1; max=3"),
+];
+
+/// 内置 Social Token 刷新请求头
+const BUILTIN_SOCIAL_REFRESH: &[(&str, &str)] = &[
+    ("accept", "application/json, text/plain, */*"),
+    ("content-type", "application/json"),
+    ("user-agent", "KiroIDE-{kiroVersion}-{machineId}"),
+    ("accept-encoding", "gzip, compress, deflate, br"),
+    ("host", "{host}"),
+];
+
+/// 内置 IdC Token 刷新请求头
+const BUILTIN_IDC_REFRESH: &[(&str, &str)] = &[
+    ("content-type", "application/json"),
+    ("host", "{host}"),
+    ("connection", "keep-alive"),
+    (
+        "x-amz-user-agent",
+        "aws-sdk-js/3.738.0 ua/2.1 os/other lang/js md/browser#unknown_unknown api/sso-oidc#3.738.0 m/E KiroIDE",
+    ),
+    ("accept", "*/*"),
+    ("accept-language", "*"),
+    ("sec-fetch-mode", "cors"),
+    ("user-agent", "node"),
+    ("accept-encoding", "br, gzip, deflate"),
+];
+
+/// 内置 getUsageLimits 请求头
+const BUILTIN_USAGE_LIMITS: &[(&str, &str)] = &[
+    (
+        "x-amz-user-agent",
+        "aws-sdk-js/1.0.0 KiroIDE-{kiroVersion}-{machineId}",
+    ),
+    (
+        "user-agent",
+        "aws-sdk-js/1.0.0 ua/2.1 os/darwin#24.6.0 lang/js md/nodejs#22.21.1 api/codewhispererruntime#1.0.0 m/N,E KiroIDE-{kiroVersion}-{machineId}",
+    ),
+    ("host", "{host}"),
+    ("amz-sdk-invocation-id", "{invocationId}"),
+    ("amz-sdk-request", "attempt=1; max=1"),
+];
+
+impl HeaderFlow {
+    fn builtin(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Api => BUILTIN_API,
+            Self::Mcp => BUILTIN_MCP,
+            Self::SocialRefresh => BUILTIN_SOCIAL_REFRESH,
+            Self::IdcRefresh => BUILTIN_IDC_REFRESH,
+            Self::UsageLimits => BUILTIN_USAGE_LIMITS,
+        }
+    }
+
+    fn templates(self, profile: &HeaderProfile) -> Option<&HeaderTemplates> {
+        match self {
+            Self::Api => profile.api.as_ref(),
+            Self::Mcp => profile.mcp.as_ref(),
+            Self::SocialRefresh => profile.social_refresh.as_ref(),
+            Self::IdcRefresh => profile.idc_refresh.as_ref(),
+            Self::UsageLimits => profile.usage_limits.as_ref(),
+        }
+    }
+}
+
+/// 校验凭据引用的请求头配置是否存在
+pub fn check_profile(credentials: &KiroCredentials, config: &Config) -> anyhow::Result<()> {
+    match &credentials.header_profile {
+        Some(name) if !config.header_profiles.contains_key(name) => {
+            anyhow::bail!("请求头配置不存在: {}", name)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 凭据使用的请求头配置（未配置或找不到时为 None，即内置配置）
+///
+/// 引用不存在的配置已在加载凭据时告警，这里直接回退
+fn profile_for<'a>(credentials: &KiroCredentials, config: &'a Config) -> Option<&'a HeaderProfile> {
+    let name = credentials
+        .header_profile
+        .as_ref()
+        .or(config.header_profile.as_ref())?;
+    config.header_profiles.get(name)
+}
+
+/// 按凭据的请求头配置生成请求头
+///
+/// # Arguments
+/// * `machine_id` - 凭据的 machineId
+/// * `host` - 请求的目标主机（`{host}` 占位符）
+pub fn render_headers(
+    flow: HeaderFlow,
+    credentials: &KiroCredentials,
+    config: &Config,
+    machine_id: &str,
+    host: &str,
+) -> anyhow::Result<HeaderMap> {
+    let invocation_id = Uuid::new_v4().to_string();
+    let render = |template: &str| {
+        template
+            .replace("{kiroVersion}", &config.kiro_version)
+            .replace("{machineId}", machine_id)
+            .replace("{systemVersion}", &config.system_version)
+            .replace("{nodeVersion}", &config.node_version)
+            .replace("{host}", host)
+            .replace("{invocationId}", &invocation_id)
+    };
+
+    let templates: Vec<(&str, &str)> =
+        match profile_for(credentials, config).and_then(|profile| flow.templates(profile)) {
+            Some(templates) => templates
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect(),
+            None => flow.builtin().to_vec(),
+        };
+
+    let mut headers = HeaderMap::new();
+    for (name, template) in templates {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow::anyhow!("无效的请求头名称: {}", name))?;
+        let value = HeaderValue::from_str(&render(template))
+            .map_err(|_| anyhow::anyhow!("无效的请求头值: {}", name))?;
+        headers.insert(header_name, value);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_profile() {
+        let config = Config {
+            kiro_version: "0.8.0".to_string(),
+            ..Default::default()
+        };
+        let headers = render_headers(
+            HeaderFlow::Api,
+            &KiroCredentials::default(),
+            &config,
+            "m1",
+            "q.us-east-1.amazonaws.com",
+        )
+        .unwrap();
+
+        assert_eq!(headers["x-amzn-kiro-agent-mode"], "vibe");
+        assert_eq!(
+            headers["x-amz-user-agent"],
+            "aws-sdk-js/1.0.27 KiroIDE-0.8.0-m1"
+        );
+        assert_eq!(headers["host"], "q.us-east-1.amazonaws.com");
+        assert!(Uuid::parse_str(headers["amz-sdk-invocation-id"].to_str().unwrap()).is_ok());
+        // 按模板顺序发送
+        let names: Vec<&str> = headers.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names[0], "content-type");
+        assert_eq!(names[names.len() - 1], "amz-sdk-request");
+    }
+
+    #[test]
+    fn test_credential_pins_profile() {
+        let profile = HeaderProfile {
+            api: Some(vec![
+                (
+                    "x-amz-user-agent".to_string(),
+                    "aws-sdk-js/2.0.0 KiroIDE-{kiroVersion}-{machineId}".to_string(),
+                ),
+                ("x-amzn-kiro-agent-mode".to_string(), "spec".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let config = Config {
+            kiro_version: "0.9.0".to_string(),
+            header_profiles: [("next".to_string(), profile)].into(),
+            ..Default::default()
+        };
+        let credentials = KiroCredentials {
+            header_profile: Some("next".to_string()),
+            ..Default::default()
+        };
+
+        let headers = render_headers(HeaderFlow::Api, &credentials, &config, "m1", "h").unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(
+            headers["x-amz-user-agent"],
+            "aws-sdk-js/2.0.0 KiroIDE-0.9.0-m1"
+        );
+        assert_eq!(headers["x-amzn-kiro-agent-mode"], "spec");
+
+        // 配置未定义的请求类型沿用内置
+        let headers =
+            render_headers(HeaderFlow::IdcRefresh, &credentials, &config, "m1", "h").unwrap();
+        assert_eq!(headers["user-agent"], "node");
+
+        // 找不到配置时使用内置
+        let unknown = KiroCredentials {
+            header_profile: Some("missing".to_string()),
+            ..Default::default()
+        };
+        let headers = render_headers(HeaderFlow::Api, &unknown, &config, "m1", "h").unwrap();
+        assert_eq!(headers["x-amzn-kiro-agent-mode"], "vibe");
+
+        assert!(check_profile(&credentials, &config).is_ok());
+        assert!(check_profile(&KiroCredentials::default(), &config).is_ok());
+        assert!(check_profile(&unknown, &config).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! 对冲请求
+//!
+//! 流式请求在 `delayMs` 内未收到上游首个 `assistantResponseEvent` 时，改用另一个凭据
+//! 发送相同请求，先产出首个事件的一路胜出，另一路被取消（丢弃连接）。
+//! 对冲请求数按 API Key 限流，只有实际发出的对冲请求才会计入。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use bytes::Bytes;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::common::auth;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::model::config::HedgingConfig;
+
+/// 对冲限流窗口
+const BUDGET_WINDOW: Duration = Duration::from_secs(60);
+
+tokio::task_local! {
+    static API_KEY: String;
+}
+
+/// 对冲统计（用于 Admin API）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgingStats {
+    /// 实际发出的对冲请求数
+    pub fired: u64,
+    /// 对冲请求先产出首个事件的次数
+    pub hedge_won: u64,
+    /// 发出对冲后原请求仍先产出首个事件的次数
+    pub primary_won: u64,
+    /// 因 API Key 限流未发出的次数
+    pub rate_limited: u64,
+}
+
+/// 对冲预算与统计
+#[derive(Debug, Default)]
+pub struct Hedging {
+    max_per_minute: usize,
+    /// API Key -> 窗口内发出对冲的时间
+    fired_at: Mutex<HashMap<String, VecDeque<Instant>>>,
+    fired: AtomicU64,
+    hedge_won: AtomicU64,
+    primary_won: AtomicU64,
+    rate_limited: AtomicU64,
+}
+
+impl Hedging {
+    pub fn new(config: &HedgingConfig) -> Self {
+        Self {
+            max_per_minute: config.max_per_minute,
+            ..Default::default()
+        }
+    }
+
+    /// 为 API Key 占用一次对冲名额，超过每分钟上限时返回 false
+    pub fn try_fire(&self, api_key: &str, now: Instant) -> bool {
+        if self.max_per_minute > 0 {
+            let mut fired_at = self.fired_at.lock();
+            fired_at.retain(|_, times| {
+                while times
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) >= BUDGET_WINDOW)
+                {
+                    times.pop_front();
+                }
+                !times.is_empty()
+            });
+
+            let times = fired_at.entry(api_key.to_string()).or_default();
+            if times.len() >= self.max_per_minute {
+                self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+            times.push_back(now);
+        }
+        self.fired.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// 记录已发出对冲的请求由哪一路胜出
+    pub fn record_winner(&self, hedge_won: bool) {
+        let counter = if hedge_won {
+            &self.hedge_won
+        } else {
+            &self.primary_won
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> HedgingStats {
+        HedgingStats {
+            fired: self.fired.load(Ordering::Relaxed),
+            hedge_won: self.hedge_won.load(Ordering::Relaxed),
+            primary_won: self.primary_won.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 当前请求的 API Key（仅在 `api_key_middleware` 作用域内有值）
+pub fn current_api_key() -> String {
+    API_KEY.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// 记录请求的 API Key，用于按 Key 限制对冲次数（与各协议的认证方式一致）
+pub async fn api_key_middleware(request: Request<Body>, next: Next) -> Response {
+    let api_key = request_api_key(&request);
+    API_KEY.scope(api_key, next.run(request)).await
+}
+
+fn request_api_key(request: &Request<Body>) -> String {
+    let api_key = if request.uri().path().starts_with("/v1beta/") {
+        auth::extract_gemini_api_key(request)
+    } else {
+        auth::extract_api_key(request)
+    };
+    api_key.unwrap_or_default()
+}
+
+/// 读取上游响应直到首个 `assistantResponseEvent`（或响应体结束），返回可从头读取的响应
+pub async fn wait_first_event(response: reqwest::Response) -> anyhow::Result<reqwest::Response> {
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let extensions = response.extensions().clone();
+    let mut body = response.bytes_stream();
+    let mut decoder = EventStreamDecoder::new();
+    let mut buffered: Vec<Bytes> = Vec::new();
+
+    'read: while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        let fed = decoder.feed(&chunk);
+        buffered.push(chunk);
+        // 无法解析时不再等待，交由调用方按原样处理
+        if fed.is_err() {
+            break;
+        }
+        for frame in decoder.decode_iter() {
+            if frame.is_ok_and(|f| f.event_type() == Some("assistantResponseEvent")) {
+                break 'read;
+            }
+        }
+    }
+
+    let replay = futures::stream::iter(buffered.into_iter().map(Ok)).chain(body);
+    let mut primed = http::Response::new(reqwest::Body::wrap_stream(replay));
+    *primed.status_mut() = status;
+    *primed.version_mut() = version;
+    *primed.headers_mut() = headers;
+    *primed.extensions_mut() = extensions;
+    Ok(reqwest::Response::from(primed))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::kiro::parser::crc::crc32;
+
+    /// 编码一个 AWS Event Stream 事件帧
+    pub(crate) fn event_frame(event_type: &str, payload: &str) -> Vec<u8> {
+        let mut headers = Vec::new();
+        for (name, value) in [(":message-type", "event"), (":event-type", event_type)] {
+            headers.push(name.len() as u8);
+            headers.extend_from_slice(name.as_bytes());
+            headers.push(7);
+            headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            headers.extend_from_slice(value.as_bytes());
+        }
+
+        let total = 12 + headers.len() + payload.len() + 4;
+        let mut frame = Vec::with_capacity(total);
+        frame.extend_from_slice(&(total as u32).to_be_bytes());
+        frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&crc32(&frame).to_be_bytes());
+        frame.extend_from_slice(&headers);
+        frame.extend_from_slice(payload.as_bytes());
+        frame.extend_from_slice(&crc32(&frame).to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_budget_per_api_key() {
+        let hedging = Hedging::new(&HedgingConfig {
+            max_per_minute: 2,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(hedging.try_fire("a", now));
+        assert!(hedging.try_fire("a", now));
+        assert!(!hedging.try_fire("a", now));
+        // 其他 API Key 有独立名额
+        assert!(hedging.try_fire("b", now));
+        // 窗口过后恢复
+        assert!(hedging.try_fire("a", now + BUDGET_WINDOW));
+
+        hedging.record_winner(true);
+        let stats = hedging.stats();
+        assert_eq!(stats.fired, 4);
+        assert_eq!(stats.rate_limited, 1);
+        assert_eq!(stats.hedge_won, 1);
+        assert_eq!(stats.primary_won, 0);
+    }
+
+    #[test]
+    fn test_request_api_key_matches_route_auth() {
+        let request = |uri: &str, header: Option<(&str, &str)>| {
+            let mut builder = Request::builder().uri(uri);
+            if let Some((name, value)) = header {
+                builder = builder.header(name, value);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let gemini = "/v1beta/models/gemini-pro:streamGenerateContent";
+        assert_eq!(
+            request_api_key(&request(&format!("{}?key=q", gemini), None)),
+            "q"
+        );
+        assert_eq!(
+            request_api_key(&request(gemini, Some(("x-goog-api-key", "g")))),
+            "g"
+        );
+        assert_eq!(
+            request_api_key(&request("/v1/messages", Some(("x-api-key", "a")))),
+            "a"
+        );
+        // 其他协议不接受查询参数中的 Key
+        assert_eq!(request_api_key(&request("/v1/messages?key=q", None)), "");
+    }
+
+    #[tokio::test]
+    async fn test_wait_first_event_replays_body() {
+        let mut first = event_frame("meteringEvent", "{}");
+        let assistant = event_frame("assistantResponseEvent", r#"{"content":"hi"}"#);
+        first.extend_from_slice(&assistant[..5]);
+        let chunks: Vec<Result<Vec<u8>, std::io::Error>> =
+            vec![Ok(first), Ok(assistant[5..].to_vec())];
+        // 首个事件之后的响应体永不结束，等待必须在首个事件处返回
+        let body = futures::stream::iter(chunks).chain(futures::stream::pending());
+        let upstream = reqwest::Response::from(
+            http::Response::builder()
+                .status(200)
+                .body(reqwest::Body::wrap_stream(body))
+                .unwrap(),
+        );
+
+        let primed = tokio::time::timeout(Duration::from_secs(5), wait_first_event(upstream))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(primed.status(), 200);
+
+        let mut replay = primed.bytes_stream();
+        let mut decoder = EventStreamDecoder::new();
+        for _ in 0..2 {
+            decoder
+                .feed(&replay.next().await.unwrap().unwrap())
+                .unwrap();
+        }
+        let events: Vec<String> = decoder
+            .decode_iter()
+            .map(|f| f.unwrap().event_type().unwrap().to_string())
+            .collect();
+        assert_eq!(events, vec!["meteringEvent", "assistantResponseEvent"]);
+    }
+}
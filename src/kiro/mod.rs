@@ -1,7 +1,18 @@
 //! Kiro API 客户端模块
 
+pub mod balance_monitor;
+pub mod circuit_breaker;
+pub mod concurrency;
+pub mod credential_store;
+pub mod device_auth;
+pub mod header_profile;
+pub mod hedging;
 pub mod machine_id;
 pub mod model;
 pub mod parser;
 pub mod provider;
+pub mod proxy_pool;
+pub mod region;
+pub mod retry_policy;
+pub mod sso_import;
 pub mod token_manager;
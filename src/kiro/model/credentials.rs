@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+use crate::http_client::ProxyConfig;
+use crate::kiro::credential_store;
+
 /// Kiro OAuth 凭证
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -53,10 +56,39 @@ pub struct KiroCredentials {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub region: Option<String>,
 
+    /// 凭据级 API 区域（可选，优先于 config.json 的 apiRegions / region）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_region: Option<String>,
+
+    /// 请求头配置名称（可选，优先于 config.json 的 headerProfile）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_profile: Option<String>,
+
     /// 凭据级 Machine ID 配置（可选）
     /// 未配置时回退到 config.json 的 machineId；都未配置时由 refreshToken 派生
     #[serde(skip_serializing_if = "Option::is_none")]
     pub machine_id: Option<String>,
+
+    /// 凭据级代理地址（可选，优先于 proxyPool 与全局代理）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+
+    /// 凭据级代理认证用户名（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_username: Option<String>,
+
+    /// 凭据级代理认证密码（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_password: Option<String>,
+
+    /// 使用的代理池名称（可选，对应 config.json 的 proxyPools）
+    /// 未配置凭据级代理时，从该代理池粘性分配出口
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_pool: Option<String>,
+
+    /// 该凭据的最大并发请求数（可选，含进行中的流式响应；不配置表示不限制）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<u32>,
 }
 
 /// 判断是否为零（用于跳过序列化）
@@ -92,6 +124,7 @@ impl CredentialsConfig {
     /// - 如果文件不存在，返回空数组
     /// - 如果文件内容为空，返回空数组
     /// - 支持单对象或数组格式
+    /// - 加密文件使用全局凭据密钥自动解密
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         let path = path.as_ref();
 
@@ -107,6 +140,8 @@ impl CredentialsConfig {
             return Ok(CredentialsConfig::Multiple(vec![]));
         }
 
+        // 加密文件自动解密
+        let content = credential_store::open(&content)?;
         let config = serde_json::from_str(&content)?;
         Ok(config)
     }
@@ -188,6 +223,16 @@ impl KiroCredentials {
             self.auth_method = Some(canonical.to_string());
         }
     }
+
+    /// 凭据级代理配置（未配置 proxyUrl 时为 None）
+    pub fn proxy_config(&self) -> Option<ProxyConfig> {
+        let url = self.proxy_url.as_deref()?;
+        let proxy = ProxyConfig::new(url);
+        Some(match (&self.proxy_username, &self.proxy_password) {
+            (Some(username), Some(password)) => proxy.with_auth(username, password),
+            _ => proxy,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -212,6 +257,23 @@ mod tests {
         assert_eq!(creds.auth_method, Some("social".to_string()));
     }
 
+    #[test]
+    fn test_proxy_config() {
+        let json = r#"{
+            "refreshToken": "test_refresh",
+            "proxyUrl": "socks5://127.0.0.1:1080",
+            "proxyUsername": "user",
+            "proxyPassword": "pass"
+        }"#;
+
+        let creds = KiroCredentials::from_json(json).unwrap();
+        let proxy = creds.proxy_config().unwrap();
+        assert_eq!(proxy.url, "socks5://127.0.0.1:1080");
+        assert_eq!(proxy.username.as_deref(), Some("user"));
+        assert_eq!(proxy.password.as_deref(), Some("pass"));
+        assert!(KiroCredentials::default().proxy_config().is_none());
+    }
+
     #[test]
     fn test_from_json_with_unknown_keys() {
         let json = r#"{
@@ -237,6 +299,7 @@ mod tests {
             priority: 0,
             region: None,
             machine_id: None,
+            ..Default::default()
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -347,6 +410,7 @@ mod tests {
             priority: 0,
             region: Some("eu-west-1".to_string()),
             machine_id: None,
+            ..Default::default()
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -369,6 +433,7 @@ mod tests {
             priority: 0,
             region: None,
             machine_id: None,
+            ..Default::default()
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -473,6 +538,7 @@ mod tests {
             priority: 3,
             region: Some("us-west-2".to_string()),
             machine_id: Some("c".repeat(64)),
+            ..Default::default()
         };
 
         let json = original.to_pretty_json().unwrap();
@@ -0,0 +1,78 @@
+//! AWS SSO OIDC 设备授权流程数据模型
+//!
+//! 对应 OIDC 的 RegisterClient、StartDeviceAuthorization、CreateToken 接口
+
+use serde::{Deserialize, Serialize};
+
+/// 注册 OIDC 客户端请求体
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterClientRequest {
+    pub client_name: String,
+    pub client_type: String,
+    pub scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer_url: Option<String>,
+}
+
+/// 注册 OIDC 客户端响应体
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterClientResponse {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// 发起设备授权请求体
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartDeviceAuthorizationRequest {
+    pub client_id: String,
+    pub client_secret: String,
+    pub start_url: String,
+}
+
+/// 发起设备授权响应体
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartDeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    /// 设备码有效期（秒）
+    pub expires_in: i64,
+    /// 建议的轮询间隔（秒）
+    #[serde(default)]
+    pub interval: Option<u64>,
+}
+
+/// 设备码换取 Token 请求体
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceTokenRequest {
+    pub client_id: String,
+    pub client_secret: String,
+    pub grant_type: String,
+    pub device_code: String,
+}
+
+/// 设备码换取 Token 响应体
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceTokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+}
+
+/// OIDC 错误响应体（如 authorization_pending）
+#[derive(Debug, Deserialize)]
+pub struct OidcErrorResponse {
+    pub error: String,
+    #[serde(default)]
+    pub error_description: Option<String>,
+}
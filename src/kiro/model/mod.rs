@@ -5,11 +5,13 @@
 //! - `events`: 响应事件类型
 //! - `requests`: 请求类型
 //! - `credentials`: OAuth 凭证
+//! - `device_auth`: 设备授权流程
 //! - `token_refresh`: Token 刷新
 //! - `usage_limits`: 使用额度查询
 
 pub mod common;
 pub mod credentials;
+pub mod device_auth;
 pub mod events;
 pub mod requests;
 pub mod token_refresh;
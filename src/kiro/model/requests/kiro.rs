@@ -25,7 +25,7 @@ use super::conversation::ConversationState;
 ///     ));
 ///
 /// let request = KiroRequest::new(state);
-/// let json = request.to_json().unwrap();
+/// let body = request.to_body(Some("arn:aws:codewhisperer:us-east-1:123:profile/ABC")).unwrap();
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -36,6 +36,34 @@ pub struct KiroRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub profile_arn: Option<String>,
 }
+
+impl KiroRequest {
+    /// 创建请求（Profile ARN 由发送请求的凭据决定）
+    pub fn new(conversation_state: ConversationState) -> Self {
+        Self {
+            conversation_state,
+            profile_arn: None,
+        }
+    }
+
+    /// 序列化为请求体
+    ///
+    /// `profile_arn` 为实际发送请求的凭据的 Profile ARN，优先于请求自带的值
+    pub fn to_body(&self, profile_arn: Option<&str>) -> serde_json::Result<String> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Body<'a> {
+            conversation_state: &'a ConversationState,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            profile_arn: Option<&'a str>,
+        }
+
+        serde_json::to_string(&Body {
+            conversation_state: &self.conversation_state,
+            profile_arn: profile_arn.or(self.profile_arn.as_deref()),
+        })
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,4 +93,30 @@ mod tests {
             "Test message"
         );
     }
+
+    #[test]
+    fn test_kiro_request_to_body_uses_credential_profile_arn() {
+        let json = r#"{
+            "conversationState": {
+                "conversationId": "conv-789",
+                "currentMessage": {
+                    "userInputMessage": {
+                        "content": "hi",
+                        "modelId": "claude-3-5-sonnet",
+                        "userInputMessageContext": {}
+                    }
+                }
+            }
+        }"#;
+        let request: KiroRequest = serde_json::from_str(json).unwrap();
+
+        let body: serde_json::Value =
+            serde_json::from_str(&request.to_body(Some("arn:cred-3")).unwrap()).unwrap();
+        assert_eq!(body["profileArn"], "arn:cred-3");
+        assert_eq!(body["conversationState"]["conversationId"], "conv-789");
+
+        let body: serde_json::Value =
+            serde_json::from_str(&request.to_body(None).unwrap()).unwrap();
+        assert!(body.get("profileArn").is_none());
+    }
 }
@@ -171,6 +171,15 @@ impl UsageLimitsResponse {
         total
     }
 
+    /// 获取激活中的免费试用过期时间（Unix 时间戳）
+    pub fn free_trial_expiry(&self) -> Option<f64> {
+        self.primary_breakdown()?
+            .free_trial_info
+            .as_ref()
+            .filter(|trial| trial.is_active())?
+            .free_trial_expiry
+    }
+
     /// 获取总当前使用量（精确值）
     ///
     /// 累加基础使用量、激活的免费试用使用量和激活的奖励使用量
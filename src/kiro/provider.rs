@@ -4,26 +4,28 @@
 //! 支持流式和非流式请求
 //! 支持多凭据故障转移和重试
 
-use reqwest::Client;
-use reqwest::header::{AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST, HeaderMap, HeaderValue};
+use reqwest::header::{AUTHORIZATION, CONNECTION, HeaderMap, HeaderValue};
+use reqwest::{Client, RequestBuilder};
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
-use uuid::Uuid;
 
-use crate::http_client::{ProxyConfig, build_client};
+use crate::http_client::{ProxyConfig, UpstreamEndpoint, send_upstream};
+use crate::kiro::concurrency::hold_until_body_done;
+use crate::kiro::header_profile::{HeaderFlow, render_headers};
+use crate::kiro::hedging;
 use crate::kiro::machine_id;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::region;
+use crate::kiro::retry_policy::{RetryPolicy, parse_retry_after};
 use crate::kiro::token_manager::{CallContext, MultiTokenManager};
+use crate::model::config::RetryAction;
+use crate::request_log::{self, ServedBy};
 
 #[cfg(test)]
 use crate::kiro::model::credentials::KiroCredentials;
 
-/// 每个凭据的最大重试次数
-const MAX_RETRIES_PER_CREDENTIAL: usize = 3;
-
-/// 总重试次数硬上限（避免无限重试）
-const MAX_TOTAL_RETRIES: usize = 9;
-
 /// Kiro API Provider
 ///
 /// 核心组件，负责与 Kiro API 通信
@@ -31,335 +33,406 @@ const MAX_TOTAL_RETRIES: usize = 9;
 #[derive(Clone)]
 pub struct KiroProvider {
     token_manager: Arc<MultiTokenManager>,
-    client: Client,
+    retry_policy: Arc<RetryPolicy>,
 }
 
 impl KiroProvider {
     /// 创建新的 KiroProvider 实例
+    ///
+    /// 出口代理由每次调用所选凭据决定（凭据级代理 > 代理池 > 全局代理）
     pub fn new(token_manager: Arc<MultiTokenManager>) -> Self {
-        Self::with_proxy(token_manager, None)
-    }
-
-    /// 创建带代理配置的 KiroProvider 实例
-    pub fn with_proxy(token_manager: Arc<MultiTokenManager>, proxy: Option<ProxyConfig>) -> Self {
-        let client = build_client(proxy.as_ref(), 720, token_manager.config().tls_backend)
-            .expect("创建 HTTP 客户端失败");
-
+        let retry_policy = Arc::new(RetryPolicy::new(
+            token_manager.config().retry_policy.clone(),
+        ));
         Self {
             token_manager,
-            client,
+            retry_policy,
         }
     }
 
+    /// 获取指定端点与出口代理的 HTTP 客户端（每个代理一个连接池）
+    fn client_for(
+        &self,
+        endpoint: UpstreamEndpoint,
+        proxy: Option<&ProxyConfig>,
+    ) -> anyhow::Result<Client> {
+        self.token_manager.upstream_client(proxy, endpoint)
+    }
+
+    /// 端点未启用连接复用时发送 `Connection: close`，与 Kiro IDE 保持一致
+    fn keep_alive(&self, endpoint: UpstreamEndpoint) -> bool {
+        endpoint.keep_alive(&self.token_manager.config().upstream_http)
+    }
+
     /// 获取 token_manager 的引用
     pub fn token_manager(&self) -> &MultiTokenManager {
         &self.token_manager
     }
 
-    /// 获取 API 基础 URL
-    pub fn base_url(&self) -> String {
+    /// 获取指定区域的 API URL
+    pub fn base_url_for(&self, region: &str) -> String {
         format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            self.token_manager.config().region
+            "{}/generateAssistantResponse",
+            region::api_base_url(self.token_manager.config(), region)
         )
     }
 
-    /// 获取 MCP API URL
-    pub fn mcp_url(&self) -> String {
+    /// 获取指定区域的 MCP API URL
+    pub fn mcp_url_for(&self, region: &str) -> String {
         format!(
-            "https://q.{}.amazonaws.com/mcp",
-            self.token_manager.config().region
+            "{}/mcp",
+            region::api_base_url(self.token_manager.config(), region)
         )
     }
 
-    /// 获取 API 基础域名
-    pub fn base_domain(&self) -> String {
-        format!("q.{}.amazonaws.com", self.token_manager.config().region)
+    /// 获取指定区域的 API 域名
+    pub fn base_domain_for(&self, region: &str) -> String {
+        region::api_host(self.token_manager.config(), region)
     }
 
     /// 构建请求头
     ///
     /// # Arguments
     /// * `ctx` - API 调用上下文，包含凭据和 token
-    fn build_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
+    /// * `region` - 请求发往的 API 区域
+    fn build_headers(&self, ctx: &CallContext, region: &str) -> anyhow::Result<HeaderMap> {
         let config = self.token_manager.config();
 
         let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
             .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
 
-        let kiro_version = &config.kiro_version;
-        let os_name = &config.system_version;
-        let node_version = &config.node_version;
-
-        let x_amz_user_agent = format!("aws-sdk-js/1.0.27 KiroIDE-{}-{}", kiro_version, machine_id);
-
-        let user_agent = format!(
-            "aws-sdk-js/1.0.27 ua/2.1 os/{} lang/js md/nodejs#{} api/codewhispererstreaming#1.0.27 m/E KiroIDE-{}-{}",
-            os_name, node_version, kiro_version, machine_id
-        );
-
-        let mut headers = HeaderMap::new();
-
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(
-            "x-amzn-codewhisperer-optout",
-            HeaderValue::from_static("true"),
-        );
-        headers.insert("x-amzn-kiro-agent-mode", HeaderValue::from_static("vibe"));
-        headers.insert(
-            "x-amz-user-agent",
-            HeaderValue::from_str(&x_amz_user_agent).unwrap(),
-        );
-        headers.insert(
-            reqwest::header::USER_AGENT,
-            HeaderValue::from_str(&user_agent).unwrap(),
-        );
-        headers.insert(HOST, HeaderValue::from_str(&self.base_domain()).unwrap());
-        headers.insert(
-            "amz-sdk-invocation-id",
-            HeaderValue::from_str(&Uuid::new_v4().to_string()).unwrap(),
-        );
-        headers.insert(
-            "amz-sdk-request",
-            HeaderValue::from_static("attempt=1; max=3"),
-        );
+        let mut headers = render_headers(
+            HeaderFlow::Api,
+            &ctx.credentials,
+            config,
+            &machine_id,
+            &self.base_domain_for(region),
+        )?;
         headers.insert(
             AUTHORIZATION,
             HeaderValue::from_str(&format!("Bearer {}", ctx.token)).unwrap(),
         );
-        headers.insert(CONNECTION, HeaderValue::from_static("close"));
+        if !self.keep_alive(UpstreamEndpoint::Api) {
+            headers.insert(CONNECTION, HeaderValue::from_static("close"));
+        }
 
         Ok(headers)
     }
 
     /// 构建 MCP 请求头
-    fn build_mcp_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
+    fn build_mcp_headers(&self, ctx: &CallContext, region: &str) -> anyhow::Result<HeaderMap> {
         let config = self.token_manager.config();
 
         let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
             .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
 
-        let kiro_version = &config.kiro_version;
-        let os_name = &config.system_version;
-        let node_version = &config.node_version;
-
-        let x_amz_user_agent = format!("aws-sdk-js/1.0.27 KiroIDE-{}-{}", kiro_version, machine_id);
-
-        let user_agent = format!(
-            "aws-sdk-js/1.0.27 ua/2.1 os/{} lang/js md/nodejs#{} api/codewhispererstreaming#1.0.27 m/E KiroIDE-{}-{}",
-            os_name, node_version, kiro_version, machine_id
-        );
-
-        let mut headers = HeaderMap::new();
-
-        // 按照严格顺序添加请求头
-        headers.insert("content-type", HeaderValue::from_static("application/json"));
-        headers.insert(
-            "x-amz-user-agent",
-            HeaderValue::from_str(&x_amz_user_agent).unwrap(),
-        );
-        headers.insert("user-agent", HeaderValue::from_str(&user_agent).unwrap());
-        headers.insert("host", HeaderValue::from_str(&self.base_domain()).unwrap());
-        headers.insert(
-            "amz-sdk-invocation-id",
-            HeaderValue::from_str(&Uuid::new_v4().to_string()).unwrap(),
-        );
-        headers.insert(
-            "amz-sdk-request",
-            HeaderValue::from_static("attempt=1; max=3"),
-        );
+        let mut headers = render_headers(
+            HeaderFlow::Mcp,
+            &ctx.credentials,
+            config,
+            &machine_id,
+            &self.base_domain_for(region),
+        )?;
         headers.insert(
             "Authorization",
             HeaderValue::from_str(&format!("Bearer {}", ctx.token)).unwrap(),
         );
-        headers.insert("Connection", HeaderValue::from_static("close"));
+        if !self.keep_alive(UpstreamEndpoint::Mcp) {
+            headers.insert("Connection", HeaderValue::from_static("close"));
+        }
 
         Ok(headers)
     }
 
     /// 发送非流式 API 请求
     ///
-    /// 支持多凭据故障转移：
+    /// 支持多凭据故障转移（默认策略，可通过 config.json 的 `retryPolicy` 调整）：
     /// - 400 Bad Request: 直接返回错误，不计入凭据失败
     /// - 401/403: 视为凭据/权限问题，计入失败次数并允许故障转移
     /// - 402 MONTHLY_REQUEST_COUNT: 视为额度用尽，禁用凭据并切换
     /// - 429/5xx/网络等瞬态错误: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
     ///
     /// # Arguments
-    /// * `request_body` - JSON 格式的请求体字符串
+    /// * `request` - Kiro 请求（Profile ARN 由实际使用的凭据填充）
     ///
     /// # Returns
     /// 返回原始的 HTTP Response，不做解析
-    pub async fn call_api(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, false).await
+    pub async fn call_api(&self, request: &KiroRequest) -> anyhow::Result<reqwest::Response> {
+        let response = self.call_api_with_retry(request, false, None).await?;
+        request_log::report_served(&response);
+        Ok(response)
     }
 
     /// 发送流式 API 请求
     ///
-    /// 支持多凭据故障转移：
+    /// 支持多凭据故障转移（默认策略，可通过 config.json 的 `retryPolicy` 调整）：
     /// - 400 Bad Request: 直接返回错误，不计入凭据失败
     /// - 401/403: 视为凭据/权限问题，计入失败次数并允许故障转移
     /// - 402 MONTHLY_REQUEST_COUNT: 视为额度用尽，禁用凭据并切换
     /// - 429/5xx/网络等瞬态错误: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
     ///
     /// # Arguments
-    /// * `request_body` - JSON 格式的请求体字符串
+    /// * `request` - Kiro 请求（Profile ARN 由实际使用的凭据填充）
+    ///
+    /// 启用 `hedging` 时，首个事件过慢会改用另一个凭据发出对冲请求，见 `call_api_stream_hedged`
     ///
     /// # Returns
     /// 返回原始的 HTTP Response，调用方负责处理流式数据
-    pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, true).await
+    pub async fn call_api_stream(
+        &self,
+        request: &KiroRequest,
+    ) -> anyhow::Result<reqwest::Response> {
+        let response = if self.token_manager.config().hedging.enabled {
+            self.call_api_stream_hedged(request).await?
+        } else {
+            self.call_api_with_retry(request, true, None).await?
+        };
+        request_log::report_served(&response);
+        Ok(response)
     }
 
-    /// 发送 MCP API 请求
-    ///
-    /// 用于 WebSearch 等工具调用
+    /// 带对冲的流式 API 调用
     ///
-    /// # Arguments
-    /// * `request_body` - JSON 格式的 MCP 请求体字符串
-    ///
-    /// # Returns
-    /// 返回原始的 HTTP Response
-    pub async fn call_mcp(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_mcp_with_retry(request_body).await
-    }
-
-    /// 内部方法：带重试逻辑的 MCP API 调用
-    async fn call_mcp_with_retry(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        let total_credentials = self.token_manager.total_count();
-        let max_retries = (total_credentials * MAX_RETRIES_PER_CREDENTIAL).min(MAX_TOTAL_RETRIES);
-        let mut last_error: Option<anyhow::Error> = None;
+    /// 原请求在 `delayMs` 内未产出首个 `assistantResponseEvent` 时，使用另一个凭据发送相同请求，
+    /// 先产出首个事件的一路胜出，另一路被丢弃（连接关闭、并发槽位释放）。
+    /// 对冲请求只在实际发出时计入 API Key 的每分钟名额；没有其他可用凭据时不发出。
+    async fn call_api_stream_hedged(
+        &self,
+        request: &KiroRequest,
+    ) -> anyhow::Result<reqwest::Response> {
+        let delay = Duration::from_millis(self.token_manager.config().hedging.delay_ms);
+        let primary_id = AtomicU64::new(0);
+        let fired = AtomicBool::new(false);
+
+        let primary = async {
+            let response = self
+                .call_api_with_retry(request, true, Some(&primary_id))
+                .await?;
+            hedging::wait_first_event(response).await
+        };
+        tokio::pin!(primary);
 
-        for attempt in 0..max_retries {
-            // 获取调用上下文
-            let ctx = match self.token_manager.acquire_context().await {
-                Ok(c) => c,
-                Err(e) => {
-                    last_error = Some(e);
-                    continue;
-                }
-            };
+        if let Ok(result) = tokio::time::timeout(delay, &mut primary).await {
+            return result;
+        }
 
-            let url = self.mcp_url();
-            let headers = match self.build_mcp_headers(&ctx) {
-                Ok(h) => h,
-                Err(e) => {
-                    last_error = Some(e);
-                    continue;
+        let hedge = self.send_hedge(request, &primary_id, &fired);
+        tokio::pin!(hedge);
+        let mut hedge_done = false;
+
+        loop {
+            tokio::select! {
+                result = &mut primary => {
+                    // 原请求失败时仍等待已发出的对冲请求
+                    if result.is_err() && !hedge_done && let Ok(Some(response)) = (&mut hedge).await {
+                        self.token_manager.hedging().record_winner(true);
+                        return Ok(response);
+                    }
+                    if fired.load(Ordering::Acquire) {
+                        self.token_manager.hedging().record_winner(false);
+                    }
+                    return result;
                 }
-            };
-
-            // 发送请求
-            let response = match self
-                .client
-                .post(&url)
-                .headers(headers)
-                .body(request_body.to_string())
-                .send()
-                .await
-            {
-                Ok(resp) => resp,
-                Err(e) => {
-                    tracing::warn!(
-                        "MCP 请求发送失败（尝试 {}/{}）: {}",
-                        attempt + 1,
-                        max_retries,
-                        e
-                    );
-                    last_error = Some(e.into());
-                    if attempt + 1 < max_retries {
-                        sleep(Self::retry_delay(attempt)).await;
+                result = &mut hedge, if !hedge_done => {
+                    hedge_done = true;
+                    match result {
+                        Ok(Some(response)) => {
+                            tracing::info!("对冲请求先于原请求产出首个事件");
+                            self.token_manager.hedging().record_winner(true);
+                            return Ok(response);
+                        }
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!("对冲请求失败，继续等待原请求: {}", e),
                     }
-                    continue;
                 }
-            };
-
-            let status = response.status();
-
-            // 成功响应
-            if status.is_success() {
-                self.token_manager.report_success(ctx.id);
-                return Ok(response);
             }
+        }
+    }
 
-            // 失败响应
-            let body = response.text().await.unwrap_or_default();
+    /// 发送对冲请求（单次尝试，不重试）
+    ///
+    /// 未发出（没有其他可用凭据或超过 API Key 名额）时返回 `Ok(None)`
+    async fn send_hedge(
+        &self,
+        request: &KiroRequest,
+        primary_id: &AtomicU64,
+        fired: &AtomicBool,
+    ) -> anyhow::Result<Option<reqwest::Response>> {
+        let endpoint = UpstreamEndpoint::Api;
+        let Some(ctx) = self
+            .token_manager
+            .acquire_hedge_context(primary_id.load(Ordering::Acquire))
+            .await
+        else {
+            return Ok(None);
+        };
 
-            // 402 额度用尽
-            if status.as_u16() == 402 && Self::is_monthly_request_limit(&body) {
-                let has_available = self.token_manager.report_quota_exhausted(ctx.id);
-                if !has_available {
-                    anyhow::bail!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body);
-                }
-                last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
-                continue;
-            }
+        let regions = self.token_manager.api_regions(&ctx.credentials);
+        let region = self
+            .pick_region(endpoint, &regions, 0)
+            .ok_or_else(|| anyhow::anyhow!("上游端点 {} 处于熔断状态", endpoint.as_str()))?;
+        let client = self.client_for(endpoint, ctx.proxy.as_ref())?;
+        let builder = self.build_api_request(&client, &ctx, &region, request)?;
 
-            // 400 Bad Request
-            if status.as_u16() == 400 {
-                anyhow::bail!("MCP 请求失败: {} {}", status, body);
-            }
+        if !self.acquire_probes(endpoint, &region, ctx.id) {
+            return Ok(None);
+        }
+        if !self
+            .token_manager
+            .hedging()
+            .try_fire(&hedging::current_api_key(), std::time::Instant::now())
+        {
+            tracing::debug!("API Key 对冲名额已用尽，不发出对冲请求");
+            self.release_probes(endpoint, &region, ctx.id);
+            return Ok(None);
+        }
+        fired.store(true, Ordering::Release);
+        tracing::info!(
+            "首个事件超时，使用凭据 #{}（区域 {}）发出对冲请求",
+            ctx.id,
+            region
+        );
 
-            // 401/403 凭据问题
-            if matches!(status.as_u16(), 401 | 403) {
-                let has_available = self.token_manager.report_failure(ctx.id);
-                if !has_available {
-                    anyhow::bail!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body);
-                }
-                last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
-                continue;
+        let upstream_http = &self.token_manager.config().upstream_http;
+        let response = match send_upstream(builder, upstream_http).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.record_circuits(endpoint, &region, ctx.id, false, false);
+                return Err(e);
             }
+        };
 
-            // 瞬态错误
-            if matches!(status.as_u16(), 408 | 429) || status.is_server_error() {
-                tracing::warn!(
-                    "MCP 请求失败（上游瞬态错误，尝试 {}/{}）: {} {}",
-                    attempt + 1,
-                    max_retries,
-                    status,
-                    body
-                );
-                last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
-                if attempt + 1 < max_retries {
-                    sleep(Self::retry_delay(attempt)).await;
-                }
-                continue;
-            }
+        let status = response.status();
+        if status.is_success() {
+            self.record_circuits(endpoint, &region, ctx.id, true, true);
+            self.token_manager.report_success(ctx.id);
+            let response = Self::served_by(response, ctx.id, &region);
+            let response = match ctx.permit {
+                Some(permit) if permit.is_limited() => hold_until_body_done(response, permit),
+                _ => response,
+            };
+            return hedging::wait_first_event(response).await.map(Some);
+        }
 
-            // 其他 4xx
-            if status.is_client_error() {
-                anyhow::bail!("MCP 请求失败: {} {}", status, body);
+        let body = response.text().await.unwrap_or_default();
+        let action = self.retry_policy.classify(status, &body);
+        self.record_action(endpoint, &region, ctx.id, action);
+        match action {
+            RetryAction::Failover => {
+                self.token_manager.report_failure(ctx.id);
             }
-
-            // 兜底
-            last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
-            if attempt + 1 < max_retries {
-                sleep(Self::retry_delay(attempt)).await;
+            RetryAction::Disable => {
+                self.token_manager.report_disabled(ctx.id);
+            }
+            RetryAction::QuotaExhausted => {
+                self.report_quota_exhausted(ctx.id);
             }
+            RetryAction::FailFast | RetryAction::Retry => {}
         }
+        anyhow::bail!("对冲请求失败（{:?}）: {} {}", action, status, body)
+    }
 
-        Err(last_error.unwrap_or_else(|| {
-            anyhow::anyhow!("MCP 请求失败：已达到最大重试次数（{}次）", max_retries)
-        }))
+    /// 发送 MCP API 请求
+    ///
+    /// 用于 WebSearch 等工具调用
+    ///
+    /// # Arguments
+    /// * `request_body` - JSON 格式的 MCP 请求体字符串
+    ///
+    /// # Returns
+    /// 返回原始的 HTTP Response
+    pub async fn call_mcp(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
+        let response = self.call_mcp_with_retry(request_body).await?;
+        request_log::report_served(&response);
+        Ok(response)
+    }
+
+    /// 内部方法：带重试逻辑的 MCP API 调用
+    async fn call_mcp_with_retry(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
+        self.send_with_retry("MCP", UpstreamEndpoint::Mcp, None, |client, ctx, region| {
+            let headers = self.build_mcp_headers(ctx, region)?;
+            Ok(client
+                .post(self.mcp_url_for(region))
+                .headers(headers)
+                .body(request_body.to_string()))
+        })
+        .await
     }
 
     /// 内部方法：带重试逻辑的 API 调用
     ///
-    /// 重试策略：
-    /// - 每个凭据最多重试 MAX_RETRIES_PER_CREDENTIAL 次
-    /// - 总重试次数 = min(凭据数量 × 每凭据重试次数, MAX_TOTAL_RETRIES)
-    /// - 硬上限 9 次，避免无限重试
+    /// `in_flight` 记录当前尝试使用的凭据 ID（供对冲请求避开）
     async fn call_api_with_retry(
         &self,
-        request_body: &str,
+        request: &KiroRequest,
         is_stream: bool,
+        in_flight: Option<&AtomicU64>,
     ) -> anyhow::Result<reqwest::Response> {
-        let total_credentials = self.token_manager.total_count();
-        let max_retries = (total_credentials * MAX_RETRIES_PER_CREDENTIAL).min(MAX_TOTAL_RETRIES);
+        let label = if is_stream {
+            "流式 API"
+        } else {
+            "非流式 API"
+        };
+        self.send_with_retry(
+            label,
+            UpstreamEndpoint::Api,
+            in_flight,
+            |client, ctx, region| self.build_api_request(client, ctx, region, request),
+        )
+        .await
+    }
+
+    /// 构建 generateAssistantResponse 请求
+    fn build_api_request(
+        &self,
+        client: &Client,
+        ctx: &CallContext,
+        region: &str,
+        request: &KiroRequest,
+    ) -> anyhow::Result<RequestBuilder> {
+        let headers = self.build_headers(ctx, region)?;
+        // 使用实际发送请求的凭据的 Profile ARN
+        let request_body = request.to_body(ctx.credentials.profile_arn.as_deref())?;
+        tracing::debug!("Kiro request body: {}", request_body);
+        Ok(client
+            .post(self.base_url_for(region))
+            .headers(headers)
+            .body(request_body))
+    }
+
+    /// 按重试策略发送上游请求
+    ///
+    /// 重试策略（config.json 的 `retryPolicy`）：
+    /// - 总尝试次数 = min(凭据数量 × 每凭据次数, 总次数上限)
+    /// - 失败响应按响应体规则与状态码决定重试、故障转移、禁用凭据或直接返回
+    /// - 重试前按 `Retry-After` 或指数退避等待，超过整体截止时间时停止
+    /// - 网络错误/超时与 5xx 视为区域性故障，配置了多个 API 区域时立即切换到下一个区域
+    ///
+    /// # Arguments
+    /// * `label` - 日志与错误信息中的请求类型
+    /// * `in_flight` - 记录当前尝试使用的凭据 ID（可选）
+    /// * `build` - 根据所选凭据与 API 区域构建请求
+    async fn send_with_retry(
+        &self,
+        label: &str,
+        endpoint: UpstreamEndpoint,
+        in_flight: Option<&AtomicU64>,
+        build: impl Fn(&Client, &CallContext, &str) -> anyhow::Result<RequestBuilder>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let max_retries = self
+            .retry_policy
+            .max_attempts(self.token_manager.total_count());
+        let deadline = self.retry_policy.deadline(Instant::now());
         let mut last_error: Option<anyhow::Error> = None;
-        let api_type = if is_stream { "流式" } else { "非流式" };
+        let upstream_http = &self.token_manager.config().upstream_http;
+        // 区域性故障后的区域偏移（从凭据主区域起按顺序切换）
+        let mut region_shift = 0usize;
 
         for attempt in 0..max_retries {
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return Err(Self::deadline_exceeded(label, last_error));
+            }
+
             // 获取调用上下文（绑定 index、credentials、token）
             let ctx = match self.token_manager.acquire_context().await {
                 Ok(c) => c,
@@ -369,37 +442,71 @@ impl KiroProvider {
                 }
             };
 
-            let url = self.base_url();
-            let headers = match self.build_headers(&ctx) {
-                Ok(h) => h,
+            if let Some(in_flight) = in_flight {
+                in_flight.store(ctx.id, Ordering::Release);
+            }
+
+            // 选择第一个未熔断的 API 区域，全部熔断时不再发送请求
+            let regions = self.token_manager.api_regions(&ctx.credentials);
+            let Some(region) = self.pick_region(endpoint, &regions, region_shift) else {
+                let message = format!(
+                    "{} 请求失败：上游端点 {}（{}）处于熔断状态",
+                    label,
+                    endpoint.as_str(),
+                    regions.join(", ")
+                );
+                return Err(Self::give_up(message, last_error));
+            };
+            let has_other_region = regions.len() > 1;
+
+            let request = match self
+                .client_for(endpoint, ctx.proxy.as_ref())
+                .and_then(|client| build(&client, &ctx, &region))
+            {
+                Ok(r) => r,
                 Err(e) => {
                     last_error = Some(e);
                     continue;
                 }
             };
 
+            // 半开状态的凭据与端点只放行一个探测请求，名额已被占用时换用其他凭据
+            if !self.acquire_probes(endpoint, &region, ctx.id) {
+                last_error = Some(anyhow::anyhow!(
+                    "凭据 #{} 或上游端点 {}（{}）处于熔断状态",
+                    ctx.id,
+                    endpoint.as_str(),
+                    region
+                ));
+                continue;
+            }
+
             // 发送请求
-            let response = match self
-                .client
-                .post(&url)
-                .headers(headers)
-                .body(request_body.to_string())
-                .send()
-                .await
-            {
+            let response = match send_upstream(request, upstream_http).await {
                 Ok(resp) => resp,
                 Err(e) => {
                     tracing::warn!(
-                        "API 请求发送失败（尝试 {}/{}）: {}",
+                        "{} 请求发送失败（区域 {}，尝试 {}/{}）: {}",
+                        label,
+                        region,
                         attempt + 1,
                         max_retries,
                         e
                     );
                     // 网络错误通常是上游/链路瞬态问题，不应导致"禁用凭据"或"切换凭据"
-                    // （否则一段时间网络抖动会把所有凭据都误禁用，需要重启才能恢复）
-                    last_error = Some(e.into());
-                    if attempt + 1 < max_retries {
-                        sleep(Self::retry_delay(attempt)).await;
+                    // （否则一段时间网络抖动会把所有凭据都误禁用，需要重启才能恢复），
+                    // 只计入熔断统计，由熔断器暂时隔离持续出错的端点与凭据
+                    self.record_circuits(endpoint, &region, ctx.id, false, false);
+                    last_error = Some(e);
+                    if has_other_region {
+                        region_shift += 1;
+                        continue;
+                    }
+                    if !self
+                        .wait_before_retry(attempt, max_retries, None, deadline)
+                        .await
+                    {
+                        return Err(Self::deadline_exceeded(label, last_error));
                     }
                     continue;
                 }
@@ -409,164 +516,187 @@ impl KiroProvider {
 
             // 成功响应
             if status.is_success() {
+                self.record_circuits(endpoint, &region, ctx.id, true, true);
                 self.token_manager.report_success(ctx.id);
-                return Ok(response);
+                let response = Self::served_by(response, ctx.id, &region);
+                // 并发槽位保持到响应体读取完毕（流式响应即整个流结束）
+                return Ok(match ctx.permit {
+                    Some(permit) if permit.is_limited() => hold_until_body_done(response, permit),
+                    _ => response,
+                });
             }
 
-            // 失败响应：读取 body 用于日志/错误信息
+            // 失败响应：读取 body 用于规则匹配与日志/错误信息
+            let retry_after = parse_retry_after(response.headers());
             let body = response.text().await.unwrap_or_default();
+            let action = self.retry_policy.classify(status, &body);
+            self.record_action(endpoint, &region, ctx.id, action);
 
-            // 402 Payment Required 且额度用尽：禁用凭据并故障转移
-            if status.as_u16() == 402 && Self::is_monthly_request_limit(&body) {
-                tracing::warn!(
-                    "API 请求失败（额度已用尽，禁用凭据并切换，尝试 {}/{}）: {} {}",
-                    attempt + 1,
-                    max_retries,
-                    status,
-                    body
-                );
-
-                let has_available = self.token_manager.report_quota_exhausted(ctx.id);
-                if !has_available {
-                    anyhow::bail!(
-                        "{} API 请求失败（所有凭据已用尽）: {} {}",
-                        api_type,
-                        status,
-                        body
-                    );
-                }
-
-                last_error = Some(anyhow::anyhow!(
-                    "{} API 请求失败: {} {}",
-                    api_type,
-                    status,
-                    body
-                ));
-                continue;
-            }
-
-            // 400 Bad Request - 请求问题，重试/切换凭据无意义
-            if status.as_u16() == 400 {
-                anyhow::bail!("{} API 请求失败: {} {}", api_type, status, body);
-            }
-
-            // 401/403 - 更可能是凭据/权限问题：计入失败并允许故障转移
-            if matches!(status.as_u16(), 401 | 403) {
-                tracing::warn!(
-                    "API 请求失败（可能为凭据错误，尝试 {}/{}）: {} {}",
-                    attempt + 1,
-                    max_retries,
-                    status,
-                    body
-                );
-
-                let has_available = self.token_manager.report_failure(ctx.id);
-                if !has_available {
-                    anyhow::bail!(
-                        "{} API 请求失败（所有凭据已用尽）: {} {}",
-                        api_type,
-                        status,
-                        body
-                    );
-                }
-
-                last_error = Some(anyhow::anyhow!(
-                    "{} API 请求失败: {} {}",
-                    api_type,
-                    status,
-                    body
-                ));
-                continue;
-            }
-
-            // 429/408/5xx - 瞬态上游错误：重试但不禁用或切换凭据
-            // （避免 429 high traffic / 502 high load 等瞬态错误把所有凭据锁死）
-            if matches!(status.as_u16(), 408 | 429) || status.is_server_error() {
-                tracing::warn!(
-                    "API 请求失败（上游瞬态错误，尝试 {}/{}）: {} {}",
-                    attempt + 1,
-                    max_retries,
-                    status,
-                    body
-                );
-                last_error = Some(anyhow::anyhow!(
-                    "{} API 请求失败: {} {}",
-                    api_type,
-                    status,
-                    body
-                ));
-                if attempt + 1 < max_retries {
-                    sleep(Self::retry_delay(attempt)).await;
-                }
-                continue;
-            }
-
-            // 其他 4xx - 通常为请求/配置问题：直接返回，不计入凭据失败
-            if status.is_client_error() {
-                anyhow::bail!("{} API 请求失败: {} {}", api_type, status, body);
-            }
-
-            // 兜底：当作可重试的瞬态错误处理（不切换凭据）
             tracing::warn!(
-                "API 请求失败（未知错误，尝试 {}/{}）: {} {}",
+                "{} 请求失败（{:?}，区域 {}，尝试 {}/{}）: {} {}",
+                label,
+                action,
+                region,
                 attempt + 1,
                 max_retries,
                 status,
                 body
             );
-            last_error = Some(anyhow::anyhow!(
-                "{} API 请求失败: {} {}",
-                api_type,
-                status,
-                body
-            ));
-            if attempt + 1 < max_retries {
-                sleep(Self::retry_delay(attempt)).await;
+
+            let has_available = match action {
+                RetryAction::FailFast => {
+                    anyhow::bail!("{} 请求失败: {} {}", label, status, body);
+                }
+                RetryAction::Retry => {
+                    last_error = Some(anyhow::anyhow!("{} 请求失败: {} {}", label, status, body));
+                    // 5xx 视为区域性故障，切换区域后立即重试
+                    if status.is_server_error() && has_other_region {
+                        region_shift += 1;
+                        continue;
+                    }
+                    if !self
+                        .wait_before_retry(attempt, max_retries, retry_after, deadline)
+                        .await
+                    {
+                        return Err(Self::deadline_exceeded(label, last_error));
+                    }
+                    continue;
+                }
+                RetryAction::Failover => self.token_manager.report_failure(ctx.id),
+                RetryAction::Disable => self.token_manager.report_disabled(ctx.id),
+                RetryAction::QuotaExhausted => self.report_quota_exhausted(ctx.id),
+            };
+
+            if !has_available {
+                anyhow::bail!("{} 请求失败（所有凭据已用尽）: {} {}", label, status, body);
             }
+            last_error = Some(anyhow::anyhow!("{} 请求失败: {} {}", label, status, body));
         }
 
         // 所有重试都失败
         Err(last_error.unwrap_or_else(|| {
             anyhow::anyhow!(
-                "{} API 请求失败：已达到最大重试次数（{}次）",
-                api_type,
+                "{} 请求失败：已达到最大重试次数（{}次）",
+                label,
                 max_retries
             )
         }))
     }
 
-    fn retry_delay(attempt: usize) -> Duration {
-        // 指数退避 + 少量抖动，避免上游抖动时放大故障
-        const BASE_MS: u64 = 200;
-        const MAX_MS: u64 = 2_000;
-        let exp = BASE_MS.saturating_mul(2u64.saturating_pow(attempt.min(6) as u32));
-        let backoff = exp.min(MAX_MS);
-        let jitter_max = (backoff / 4).max(1);
-        let jitter = fastrand::u64(0..=jitter_max);
-        Duration::from_millis(backoff.saturating_add(jitter))
-    }
-
-    fn is_monthly_request_limit(body: &str) -> bool {
-        if body.contains("MONTHLY_REQUEST_COUNT") {
+    /// 重试前等待，等待后会超过整体截止时间时返回 false
+    async fn wait_before_retry(
+        &self,
+        attempt: usize,
+        max_retries: usize,
+        retry_after: Option<Duration>,
+        deadline: Option<Instant>,
+    ) -> bool {
+        if attempt + 1 >= max_retries {
             return true;
         }
 
-        let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        let delay = self.retry_policy.delay(attempt, retry_after);
+        if deadline.is_some_and(|d| Instant::now() + delay >= d) {
             return false;
-        };
+        }
+        sleep(delay).await;
+        true
+    }
 
-        if value
-            .get("reason")
-            .and_then(|v| v.as_str())
-            .is_some_and(|v| v == "MONTHLY_REQUEST_COUNT")
-        {
-            return true;
+    /// 在响应中附加实际使用的凭据与 API 区域（供请求日志记录）
+    fn served_by(mut response: reqwest::Response, id: u64, region: &str) -> reqwest::Response {
+        response.extensions_mut().insert(ServedBy {
+            credential_id: id,
+            region: region.to_string(),
+        });
+        response
+    }
+
+    /// 从 `shift` 起选择第一个未熔断的 API 区域
+    fn pick_region(
+        &self,
+        endpoint: UpstreamEndpoint,
+        regions: &[String],
+        shift: usize,
+    ) -> Option<String> {
+        (0..regions.len())
+            .map(|i| &regions[(shift + i) % regions.len()])
+            .find(|region| self.token_manager.endpoint_permits(endpoint, region))
+            .cloned()
+    }
+
+    /// 即将发送请求：占用凭据与端点的探测名额（半开时各只放行一个）
+    ///
+    /// 返回 true 后必须通过 `record_circuits` 记录结果或 `release_probes` 归还
+    fn acquire_probes(&self, endpoint: UpstreamEndpoint, region: &str, id: u64) -> bool {
+        if !self.token_manager.try_credential(id) {
+            return false;
+        }
+        if !self.token_manager.try_endpoint(endpoint, region) {
+            self.token_manager.release_credential(id);
+            return false;
+        }
+        true
+    }
+
+    /// 归还未发出请求占用的探测名额
+    fn release_probes(&self, endpoint: UpstreamEndpoint, region: &str, id: u64) {
+        self.token_manager.release_endpoint(endpoint, region);
+        self.token_manager.release_credential(id);
+    }
+
+    /// 按失败响应的处理动作记录熔断统计
+    fn record_action(
+        &self,
+        endpoint: UpstreamEndpoint,
+        region: &str,
+        id: u64,
+        action: RetryAction,
+    ) {
+        match action {
+            // 请求本身的问题，不影响端点与凭据健康度
+            RetryAction::FailFast => self.record_circuits(endpoint, region, id, true, true),
+            RetryAction::Retry => self.record_circuits(endpoint, region, id, false, false),
+            // 凭据问题：上游端点本身可用
+            _ => self.record_circuits(endpoint, region, id, true, false),
+        }
+    }
+
+    /// 记录端点（按区域）与凭据熔断统计
+    fn record_circuits(
+        &self,
+        endpoint: UpstreamEndpoint,
+        region: &str,
+        id: u64,
+        endpoint_ok: bool,
+        credential_ok: bool,
+    ) {
+        self.token_manager
+            .record_endpoint(endpoint, region, endpoint_ok);
+        self.token_manager.record_credential(id, credential_ok);
+    }
+
+    fn deadline_exceeded(label: &str, last_error: Option<anyhow::Error>) -> anyhow::Error {
+        Self::give_up(
+            format!("{} 请求失败：已超过请求截止时间", label),
+            last_error,
+        )
+    }
+
+    /// 放弃重试：在最近一次错误上附加原因
+    fn give_up(message: String, last_error: Option<anyhow::Error>) -> anyhow::Error {
+        match last_error {
+            Some(e) => e.context(message),
+            None => anyhow::anyhow!(message),
         }
+    }
 
-        value
-            .pointer("/error/reason")
-            .and_then(|v| v.as_str())
-            .is_some_and(|v| v == "MONTHLY_REQUEST_COUNT")
+    /// 报告额度用尽，并在后台查询该凭据的额度重置时间
+    fn report_quota_exhausted(&self, id: u64) -> bool {
+        let has_available = self.token_manager.report_quota_exhausted(id);
+        let token_manager = self.token_manager.clone();
+        tokio::spawn(async move { token_manager.check_quota_recovery().await });
+        has_available
     }
 }
 
@@ -575,6 +705,8 @@ mod tests {
     use super::*;
     use crate::kiro::token_manager::CallContext;
     use crate::model::config::Config;
+    use reqwest::header::CONTENT_TYPE;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     fn create_test_provider(config: Config, credentials: KiroCredentials) -> KiroProvider {
         let tm = MultiTokenManager::new(config, vec![credentials], None, None, false).unwrap();
@@ -586,8 +718,12 @@ mod tests {
         let config = Config::default();
         let credentials = KiroCredentials::default();
         let provider = create_test_provider(config, credentials);
-        assert!(provider.base_url().contains("amazonaws.com"));
-        assert!(provider.base_url().contains("generateAssistantResponse"));
+        assert!(provider.base_url_for("us-east-1").contains("amazonaws.com"));
+        assert!(
+            provider
+                .base_url_for("us-east-1")
+                .contains("generateAssistantResponse")
+        );
     }
 
     #[test]
@@ -596,7 +732,14 @@ mod tests {
         config.region = "us-east-1".to_string();
         let credentials = KiroCredentials::default();
         let provider = create_test_provider(config, credentials);
-        assert_eq!(provider.base_domain(), "q.us-east-1.amazonaws.com");
+        assert_eq!(
+            provider.base_domain_for("us-east-1"),
+            "q.us-east-1.amazonaws.com"
+        );
+        assert_eq!(
+            provider.mcp_url_for("eu-central-1"),
+            "https://q.eu-central-1.amazonaws.com/mcp"
+        );
     }
 
     #[test]
@@ -614,8 +757,10 @@ mod tests {
             id: 1,
             credentials,
             token: "test_token".to_string(),
+            proxy: None,
+            permit: None,
         };
-        let headers = provider.build_headers(&ctx).unwrap();
+        let headers = provider.build_headers(&ctx, "us-east-1").unwrap();
 
         assert_eq!(headers.get(CONTENT_TYPE).unwrap(), "application/json");
         assert_eq!(headers.get("x-amzn-codewhisperer-optout").unwrap(), "true");
@@ -628,24 +773,256 @@ mod tests {
                 .unwrap()
                 .starts_with("Bearer ")
         );
-        assert_eq!(headers.get(CONNECTION).unwrap(), "close");
+        // 默认复用连接，不发送 Connection: close
+        assert!(headers.get(CONNECTION).is_none());
     }
 
     #[test]
-    fn test_is_monthly_request_limit_detects_reason() {
-        let body = r#"{"message":"You have reached the limit.","reason":"MONTHLY_REQUEST_COUNT"}"#;
-        assert!(KiroProvider::is_monthly_request_limit(body));
+    fn test_build_headers_without_keep_alive() {
+        let mut config = Config::default();
+        config.upstream_http.keep_alive.api = false;
+        let credentials = KiroCredentials {
+            refresh_token: Some("a".repeat(150)),
+            ..Default::default()
+        };
+        let provider = create_test_provider(config, credentials.clone());
+        let ctx = CallContext {
+            id: 1,
+            credentials,
+            token: "test_token".to_string(),
+            proxy: None,
+            permit: None,
+        };
+
+        let headers = provider.build_headers(&ctx, "us-east-1").unwrap();
+        assert_eq!(headers.get(CONNECTION).unwrap(), "close");
     }
 
     #[test]
-    fn test_is_monthly_request_limit_nested_reason() {
-        let body = r#"{"error":{"reason":"MONTHLY_REQUEST_COUNT"}}"#;
-        assert!(KiroProvider::is_monthly_request_limit(body));
+    fn test_failed_credential_probe_leaves_endpoint_probe_free() {
+        use crate::model::config::CircuitBreakerConfig;
+
+        let config = Config {
+            api_regions: vec!["r1".to_string()],
+            circuit_breaker: CircuitBreakerConfig {
+                min_requests: 1,
+                open_secs: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let provider = create_test_provider(config, KiroCredentials::default());
+        let tm = provider.token_manager();
+        let endpoint = UpstreamEndpoint::Api;
+
+        tm.record_endpoint(endpoint, "r1", false);
+        tm.record_credential(1, false);
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+
+        // 凭据的探测名额已被其他请求占用
+        assert!(tm.try_credential(1));
+
+        // 选择区域不占用端点探测名额；凭据探测失败后端点名额仍可用
+        let region = provider
+            .pick_region(endpoint, &["r1".to_string()], 0)
+            .unwrap();
+        assert!(!provider.acquire_probes(endpoint, &region, 1));
+        assert!(tm.try_endpoint(endpoint, &region));
     }
 
-    #[test]
-    fn test_is_monthly_request_limit_false() {
-        let body = r#"{"message":"nope","reason":"DAILY_REQUEST_COUNT"}"#;
-        assert!(!KiroProvider::is_monthly_request_limit(body));
+    /// 启动本地替身端点：`/mcp` 返回指定状态码并计数
+    async fn stand_in(status: u16, hits: Arc<AtomicUsize>) -> String {
+        let app = axum::Router::new().route(
+            "/mcp",
+            axum::routing::post(move || {
+                let hits = hits.clone();
+                async move {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    axum::http::StatusCode::from_u16(status).unwrap()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_regional_failover() {
+        let failing = Arc::new(AtomicUsize::new(0));
+        let healthy = Arc::new(AtomicUsize::new(0));
+
+        let config = Config {
+            api_regions: vec!["r1".to_string(), "r2".to_string()],
+            api_endpoints: [
+                ("r1".to_string(), stand_in(503, failing.clone()).await),
+                ("r2".to_string(), stand_in(200, healthy.clone()).await),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        let credentials = KiroCredentials {
+            access_token: Some("test_token".to_string()),
+            refresh_token: Some("a".repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        let provider = create_test_provider(config, credentials);
+
+        // 主区域 503 后立即切换到下一个区域
+        let response = provider.call_mcp("{}").await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(failing.load(Ordering::SeqCst), 1);
+        assert_eq!(healthy.load(Ordering::SeqCst), 1);
+        // 请求日志记录实际处理请求的区域
+        let served = response.extensions().get::<ServedBy>().unwrap();
+        assert_eq!(served.region, "r2");
+
+        let circuits = provider.token_manager().snapshot().endpoint_circuits;
+        let r1 = circuits.iter().find(|c| c.region == "r1").unwrap();
+        assert_eq!(r1.status.failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_body_uses_serving_credential_profile_arn() {
+        use crate::kiro::model::requests::conversation::ConversationState;
+
+        // 记录每次请求的 (Authorization, profileArn)，第一次请求返回 403 触发故障转移
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        let app = axum::Router::new().route(
+            "/generateAssistantResponse",
+            axum::routing::post(
+                move |headers: axum::http::HeaderMap, body: axum::Json<serde_json::Value>| {
+                    let recorder = recorder.clone();
+                    async move {
+                        let mut seen = recorder.lock();
+                        seen.push((
+                            headers["authorization"].to_str().unwrap().to_string(),
+                            body["profileArn"].as_str().unwrap_or_default().to_string(),
+                        ));
+                        if seen.len() == 1 {
+                            axum::http::StatusCode::FORBIDDEN
+                        } else {
+                            axum::http::StatusCode::OK
+                        }
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = Config {
+            api_endpoints: [("us-east-1".to_string(), format!("http://{}", addr))].into(),
+            ..Default::default()
+        };
+        let credentials = |id: u64, token: char| KiroCredentials {
+            id: Some(id),
+            access_token: Some(format!("token_{}", id)),
+            refresh_token: Some(token.to_string().repeat(150)),
+            profile_arn: Some(format!("arn:aws:codewhisperer:us-east-1:1:profile/p{}", id)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        let tm = MultiTokenManager::new(
+            config,
+            vec![credentials(1, 'a'), credentials(2, 'b')],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        let provider = KiroProvider::new(Arc::new(tm));
+
+        let request = KiroRequest::new(ConversationState::new("c1"));
+        let response = provider.call_api(&request).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        // 故障转移前后，请求体中的 profileArn 都属于发送该请求的凭据
+        let seen = seen.lock();
+        assert_eq!(seen.len(), 2);
+        assert_ne!(seen[0].0, seen[1].0);
+        for (authorization, profile_arn) in seen.iter() {
+            let id = &authorization["Bearer token_".len()..];
+            assert!(profile_arn.ends_with(&format!("/p{}", id)));
+        }
+        let served = response.extensions().get::<ServedBy>().unwrap();
+        assert!(seen[1].1.ends_with(&format!("/p{}", served.credential_id)));
+    }
+
+    #[tokio::test]
+    async fn test_hedged_stream_uses_faster_credential() {
+        use crate::kiro::hedging::tests::event_frame;
+        use crate::kiro::model::requests::conversation::ConversationState;
+
+        // 第一个请求迟迟不返回，之后的请求立即返回首个事件
+        let hits = Arc::new(AtomicUsize::new(0));
+        let slow_token = Arc::new(parking_lot::Mutex::new(String::new()));
+        let counter = hits.clone();
+        let slow = slow_token.clone();
+        let app = axum::Router::new().route(
+            "/generateAssistantResponse",
+            axum::routing::post(move |headers: axum::http::HeaderMap| {
+                let counter = counter.clone();
+                let slow = slow.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                        *slow.lock() = headers["authorization"].to_str().unwrap().to_string();
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                    }
+                    event_frame("assistantResponseEvent", r#"{"content":"hi"}"#)
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = Config {
+            api_endpoints: [("us-east-1".to_string(), format!("http://{}", addr))].into(),
+            ..Default::default()
+        };
+        config.hedging.enabled = true;
+        config.hedging.delay_ms = 100;
+
+        let credentials = |id: u64, token: char| KiroCredentials {
+            id: Some(id),
+            access_token: Some(format!("token_{}", id)),
+            refresh_token: Some(token.to_string().repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        let tm = MultiTokenManager::new(
+            config,
+            vec![credentials(1, 'a'), credentials(2, 'b')],
+            None,
+            None,
+            true,
+        )
+        .unwrap();
+        let provider = KiroProvider::new(Arc::new(tm));
+
+        let request = KiroRequest::new(ConversationState::new("c1"));
+        let response =
+            tokio::time::timeout(Duration::from_secs(10), provider.call_api_stream(&request))
+                .await
+                .unwrap()
+                .unwrap();
+        let served = response.extensions().get::<ServedBy>().unwrap();
+        // 主请求的凭据是随机选的：实际处理请求的应是对冲请求的凭据
+        let slow_id: u64 = slow_token.lock()["Bearer token_".len()..].parse().unwrap();
+        assert_ne!(served.credential_id, slow_id);
+        let body = response.bytes().await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("assistantResponseEvent"));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let stats = provider.token_manager().snapshot().hedging;
+        assert_eq!(stats.fired, 1);
+        assert_eq!(stats.hedge_won, 1);
+        assert_eq!(stats.primary_won, 0);
     }
 }
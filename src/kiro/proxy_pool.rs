@@ -0,0 +1,300 @@
+//! 代理池
+//!
+//! 按 config.json 的 `proxyPools` 构建命名代理池，凭据通过 `proxyPool` 引用。
+//! 每个凭据粘性绑定池内一个出口，出口健康检查失败时才重新分配到负载最低的健康出口。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use crate::http_client::{ProxyConfig, build_client};
+use crate::model::config::{Config, ProxyPoolConfig, TlsBackend};
+
+/// 健康检查请求超时（秒）
+const HEALTH_CHECK_TIMEOUT_SECS: u64 = 10;
+
+/// 代理池中的单个出口
+struct PoolMember {
+    proxy: ProxyConfig,
+    healthy: AtomicBool,
+}
+
+/// 命名代理池
+pub struct ProxyPool {
+    name: String,
+    members: Vec<PoolMember>,
+    health_check_url: String,
+    health_check_interval: Duration,
+    tls_backend: TlsBackend,
+    /// 凭据 ID -> 出口下标
+    assignments: Mutex<HashMap<u64, usize>>,
+    /// 凭据改用其他出口的次数
+    reassignments: AtomicU64,
+}
+
+impl ProxyPool {
+    fn new(name: &str, config: &ProxyPoolConfig, global: &Config) -> Self {
+        let members = config
+            .proxies
+            .iter()
+            .map(|url| {
+                let proxy = ProxyConfig::new(url);
+                let proxy = match (&config.username, &config.password) {
+                    (Some(username), Some(password)) => proxy.with_auth(username, password),
+                    _ => proxy,
+                };
+                PoolMember {
+                    proxy,
+                    healthy: AtomicBool::new(true),
+                }
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            members,
+            health_check_url: config
+                .health_check_url
+                .clone()
+                .unwrap_or_else(|| format!("https://q.{}.amazonaws.com", global.region)),
+            health_check_interval: Duration::from_secs(config.health_check_interval_secs.max(1)),
+            tls_backend: global.tls_backend,
+            assignments: Mutex::new(HashMap::new()),
+            reassignments: AtomicU64::new(0),
+        }
+    }
+
+    /// 为凭据分配出口（粘性）
+    ///
+    /// 已分配的出口健康时沿用；否则分配到已分配凭据最少的健康出口。
+    /// 没有健康出口时保留原分配（无原分配则按 ID 取模），避免请求因健康检查误判而无出口可用
+    pub fn assign(&self, credential_id: u64) -> Option<ProxyConfig> {
+        if self.members.is_empty() {
+            return None;
+        }
+
+        let mut assignments = self.assignments.lock();
+        let current = assignments.get(&credential_id).copied();
+        let idx = match current {
+            Some(idx) if self.is_healthy(idx) => idx,
+            _ => {
+                let mut load = vec![0usize; self.members.len()];
+                for (id, &idx) in assignments.iter() {
+                    if *id != credential_id {
+                        load[idx] += 1;
+                    }
+                }
+                let healthiest = (0..self.members.len())
+                    .filter(|&idx| self.is_healthy(idx))
+                    .min_by_key(|&idx| load[idx]);
+                let idx = healthiest
+                    .or(current)
+                    .unwrap_or(credential_id as usize % self.members.len());
+                if current.is_some_and(|old| old != idx) {
+                    self.reassignments.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!(
+                        "代理池 {} 出口不可用，凭据 #{} 改用 {}",
+                        self.name,
+                        credential_id,
+                        self.members[idx].proxy.url
+                    );
+                }
+                assignments.insert(credential_id, idx);
+                idx
+            }
+        };
+
+        Some(self.members[idx].proxy.clone())
+    }
+
+    /// 已分配给凭据的出口
+    fn assigned_proxies(&self) -> impl Iterator<Item = ProxyConfig> {
+        let assignments = self.assignments.lock();
+        let mut used: Vec<usize> = assignments.values().copied().collect();
+        used.sort_unstable();
+        used.dedup();
+        used.into_iter().map(|idx| self.members[idx].proxy.clone())
+    }
+
+    fn is_healthy(&self, idx: usize) -> bool {
+        self.members[idx].healthy.load(Ordering::Relaxed)
+    }
+
+    /// 检查池内所有出口，经代理收到任意 HTTP 响应即视为健康
+    pub async fn check_health(&self) {
+        for member in &self.members {
+            let healthy = match build_client(
+                Some(&member.proxy),
+                HEALTH_CHECK_TIMEOUT_SECS,
+                self.tls_backend,
+            ) {
+                Ok(client) => client.get(&self.health_check_url).send().await.is_ok(),
+                Err(e) => {
+                    tracing::warn!(
+                        "代理池 {} 出口 {} 配置无效: {}",
+                        self.name,
+                        member.proxy.url,
+                        e
+                    );
+                    false
+                }
+            };
+
+            let was_healthy = member.healthy.swap(healthy, Ordering::Relaxed);
+            if was_healthy != healthy {
+                if healthy {
+                    tracing::info!("代理池 {} 出口 {} 已恢复", self.name, member.proxy.url);
+                } else {
+                    tracing::warn!(
+                        "代理池 {} 出口 {} 健康检查失败",
+                        self.name,
+                        member.proxy.url
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// 所有命名代理池
+#[derive(Default)]
+pub struct ProxyPools {
+    pools: HashMap<String, Arc<ProxyPool>>,
+}
+
+impl ProxyPools {
+    /// 从配置构建代理池
+    pub fn from_config(config: &Config) -> Self {
+        let pools = config
+            .proxy_pools
+            .iter()
+            .map(|(name, pool)| (name.clone(), Arc::new(ProxyPool::new(name, pool, config))))
+            .collect();
+        Self { pools }
+    }
+
+    /// 从指定代理池为凭据分配出口，代理池不存在时返回 None
+    pub fn assign(&self, pool: &str, credential_id: u64) -> Option<ProxyConfig> {
+        match self.pools.get(pool) {
+            Some(pool) => pool.assign(credential_id),
+            None => {
+                tracing::warn!("凭据 #{} 引用的代理池不存在: {}", credential_id, pool);
+                None
+            }
+        }
+    }
+
+    /// 释放凭据占用的出口（凭据删除时调用）
+    pub fn release(&self, credential_id: u64) {
+        for pool in self.pools.values() {
+            pool.assignments.lock().remove(&credential_id);
+        }
+    }
+
+    /// 所有代理池中已分配给凭据的出口
+    pub fn assigned_proxies(&self) -> Vec<ProxyConfig> {
+        self.pools
+            .values()
+            .flat_map(|pool| pool.assigned_proxies())
+            .collect()
+    }
+
+    /// 出口重新分配的累计次数（变化时说明有出口不再被使用）
+    pub fn reassignments(&self) -> u64 {
+        self.pools
+            .values()
+            .map(|pool| pool.reassignments.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// 为每个代理池启动健康检查后台任务
+    pub fn spawn_health_checks(&self) {
+        for pool in self.pools.values() {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(pool.health_check_interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    ticker.tick().await;
+                    pool.check_health().await;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(proxies: &[&str], health_check_url: Option<String>) -> ProxyPool {
+        let config = ProxyPoolConfig {
+            proxies: proxies.iter().map(|p| p.to_string()).collect(),
+            username: None,
+            password: None,
+            health_check_url,
+            health_check_interval_secs: 60,
+        };
+        ProxyPool::new("test", &config, &Config::default())
+    }
+
+    #[test]
+    fn test_assign_is_sticky_and_balanced() {
+        let pool = pool(&["http://p1:8080", "http://p2:8080"], None);
+
+        let first = pool.assign(1).unwrap();
+        let second = pool.assign(2).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(pool.assign(1).unwrap(), first);
+        assert_eq!(pool.assign(2).unwrap(), second);
+    }
+
+    #[test]
+    fn test_assign_moves_off_unhealthy_proxy() {
+        let pool = pool(&["http://p1:8080", "http://p2:8080"], None);
+
+        assert_eq!(pool.assign(1).unwrap().url, "http://p1:8080");
+        pool.members[0].healthy.store(false, Ordering::Relaxed);
+        assert_eq!(pool.assign(1).unwrap().url, "http://p2:8080");
+
+        // 全部不可用时保留原分配
+        pool.members[1].healthy.store(false, Ordering::Relaxed);
+        assert_eq!(pool.assign(1).unwrap().url, "http://p2:8080");
+    }
+
+    #[test]
+    fn test_unknown_pool_returns_none() {
+        let pools = ProxyPools::default();
+        assert!(pools.assign("missing", 1).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_health_marks_unreachable_proxy() {
+        // 本地 HTTP 服务充当代理：对代理的绝对 URI 请求同样会返回响应
+        let app = axum::Router::new().fallback(|| async { "ok" });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // 绑定后立即释放端口，得到一个无人监听的地址
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let pool = pool(
+            &[
+                &format!("http://{}", closed_addr),
+                &format!("http://{}", addr),
+            ],
+            Some("http://health.invalid/".to_string()),
+        );
+        pool.check_health().await;
+
+        assert!(!pool.is_healthy(0));
+        assert!(pool.is_healthy(1));
+        assert_eq!(pool.assign(1).unwrap().url, format!("http://{}", addr));
+    }
+}
@@ -0,0 +1,107 @@
+//! API 区域路由
+//!
+//! 凭据的 API 区域顺序：凭据 `apiRegion` > config.json 的 `apiRegions`（为空时为 `region`），
+//! 区域性 5xx/超时时按此顺序切换。各区域地址默认为 `https://q.{region}.amazonaws.com`，
+//! 可通过 `apiEndpoints` 覆盖。
+
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::model::config::Config;
+
+/// 凭据的 API 区域（按故障转移顺序，首个为主区域）
+pub fn api_regions(credentials: &KiroCredentials, config: &Config) -> Vec<String> {
+    let fallback = if config.api_regions.is_empty() {
+        std::slice::from_ref(&config.region)
+    } else {
+        config.api_regions.as_slice()
+    };
+
+    let mut regions: Vec<String> = Vec::with_capacity(fallback.len() + 1);
+    for region in credentials.api_region.iter().chain(fallback) {
+        if !regions.contains(region) {
+            regions.push(region.clone());
+        }
+    }
+    regions
+}
+
+/// 凭据的主 API 区域
+pub fn primary_api_region(credentials: &KiroCredentials, config: &Config) -> String {
+    credentials
+        .api_region
+        .clone()
+        .or_else(|| config.api_regions.first().cloned())
+        .unwrap_or_else(|| config.region.clone())
+}
+
+/// 区域的 API 基础地址（不含路径、无结尾斜杠）
+pub fn api_base_url(config: &Config, region: &str) -> String {
+    match config.api_endpoints.get(region) {
+        Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+        None => format!("https://q.{}.amazonaws.com", region),
+    }
+}
+
+/// 区域的 API 主机名（用于 Host 请求头）
+pub fn api_host(config: &Config, region: &str) -> String {
+    let base = api_base_url(config, region);
+    match reqwest::Url::parse(&base) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => format!("q.{}.amazonaws.com", region),
+        },
+        Err(_) => format!("q.{}.amazonaws.com", region),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_regions_order() {
+        let mut config = Config {
+            region: "us-east-1".to_string(),
+            ..Default::default()
+        };
+        let credentials = KiroCredentials {
+            api_region: Some("eu-central-1".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            api_regions(&KiroCredentials::default(), &config),
+            vec!["us-east-1"]
+        );
+        assert_eq!(
+            api_regions(&credentials, &config),
+            vec!["eu-central-1", "us-east-1"]
+        );
+
+        config.api_regions = vec!["us-west-2".to_string(), "eu-central-1".to_string()];
+        assert_eq!(
+            api_regions(&credentials, &config),
+            vec!["eu-central-1", "us-west-2"]
+        );
+        assert_eq!(
+            primary_api_region(&KiroCredentials::default(), &config),
+            "us-west-2"
+        );
+    }
+
+    #[test]
+    fn test_api_endpoint_override() {
+        let mut config = Config::default();
+        assert_eq!(
+            api_base_url(&config, "us-east-1"),
+            "https://q.us-east-1.amazonaws.com"
+        );
+        assert_eq!(api_host(&config, "us-east-1"), "q.us-east-1.amazonaws.com");
+
+        config
+            .api_endpoints
+            .insert("local".to_string(), "http://127.0.0.1:8080/".to_string());
+        assert_eq!(api_base_url(&config, "local"), "http://127.0.0.1:8080");
+        assert_eq!(api_host(&config, "local"), "127.0.0.1:8080");
+    }
+}
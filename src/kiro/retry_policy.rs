@@ -0,0 +1,265 @@
+//! 上游请求重试策略
+//!
+//! 由 config.json 的 `retryPolicy` 构建，统一 API 与 MCP 请求的错误分类、
+//! 退避间隔、`Retry-After` 处理和整体截止时间。
+
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+use crate::model::config::{RetryAction, RetryPolicyConfig};
+
+/// 指数退避的最大指数，避免移位溢出
+const MAX_BACKOFF_EXPONENT: usize = 6;
+
+/// 重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    config: RetryPolicyConfig,
+}
+
+impl RetryPolicy {
+    pub fn new(config: RetryPolicyConfig) -> Self {
+        Self { config }
+    }
+
+    /// 单个请求的最大尝试次数：min(凭据数 × 每凭据次数, 总次数上限)，至少 1 次
+    pub fn max_attempts(&self, total_credentials: usize) -> usize {
+        total_credentials
+            .saturating_mul(self.config.max_retries_per_credential)
+            .min(self.config.max_total_retries)
+            .max(1)
+    }
+
+    /// 请求的整体截止时间（未配置时为 None）
+    pub fn deadline(&self, start: Instant) -> Option<Instant> {
+        (self.config.deadline_secs > 0)
+            .then(|| start + Duration::from_secs(self.config.deadline_secs))
+    }
+
+    /// 判断失败响应的处理动作
+    ///
+    /// 优先匹配响应体规则，其次是配置的状态码（具体状态码优先于类别），最后是内置默认
+    pub fn classify(&self, status: StatusCode, body: &str) -> RetryAction {
+        let code = status.as_u16();
+
+        if let Some(rule) = self
+            .config
+            .body_rules
+            .iter()
+            .find(|rule| rule.status.is_none_or(|s| s == code) && body.contains(&rule.contains))
+        {
+            return rule.action;
+        }
+
+        let class = format!("{}xx", code / 100);
+        self.config
+            .status_actions
+            .get(&code.to_string())
+            .or_else(|| self.config.status_actions.get(&class))
+            .copied()
+            .unwrap_or_else(|| Self::default_action(status))
+    }
+
+    /// 内置状态码处理动作
+    ///
+    /// - 400: 请求问题，重试/切换凭据无意义
+    /// - 401/403: 更可能是凭据/权限问题，计入失败并允许故障转移
+    /// - 408/429/5xx: 瞬态上游错误，重试但不禁用或切换凭据
+    ///   （避免 429 high traffic / 502 high load 等瞬态错误把所有凭据锁死）
+    /// - 其他 4xx: 通常为请求/配置问题，直接返回
+    /// - 兜底: 当作可重试的瞬态错误
+    fn default_action(status: StatusCode) -> RetryAction {
+        match status.as_u16() {
+            400 => RetryAction::FailFast,
+            401 | 403 => RetryAction::Failover,
+            408 | 429 => RetryAction::Retry,
+            _ if status.is_server_error() => RetryAction::Retry,
+            _ if status.is_client_error() => RetryAction::FailFast,
+            _ => RetryAction::Retry,
+        }
+    }
+
+    /// 第 `attempt` 次失败后的等待时间
+    ///
+    /// 上游给出 `Retry-After` 且启用时使用它（不超过配置上限），否则指数退避 + 少量抖动
+    pub fn delay(&self, attempt: usize, retry_after: Option<Duration>) -> Duration {
+        if self.config.honor_retry_after
+            && let Some(retry_after) = retry_after
+        {
+            return retry_after.min(Duration::from_secs(self.config.max_retry_after_secs));
+        }
+
+        let exp = self
+            .config
+            .backoff_base_ms
+            .saturating_mul(1u64 << attempt.min(MAX_BACKOFF_EXPONENT));
+        let backoff = exp.min(self.config.backoff_max_ms);
+        let jitter_max = (backoff / 4).max(1);
+        let jitter = fastrand::u64(0..=jitter_max);
+        Duration::from_millis(backoff.saturating_add(jitter))
+    }
+}
+
+/// 解析 `Retry-After` 响应头（秒数或 HTTP 日期）
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::BodyRule;
+    use reqwest::header::HeaderValue;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(RetryPolicyConfig::default())
+    }
+
+    #[test]
+    fn test_default_classification() {
+        let policy = policy();
+        let classify = |code| policy.classify(StatusCode::from_u16(code).unwrap(), "");
+        assert_eq!(classify(400), RetryAction::FailFast);
+        assert_eq!(classify(401), RetryAction::Failover);
+        assert_eq!(classify(403), RetryAction::Failover);
+        assert_eq!(classify(402), RetryAction::FailFast);
+        assert_eq!(classify(404), RetryAction::FailFast);
+        assert_eq!(classify(408), RetryAction::Retry);
+        assert_eq!(classify(429), RetryAction::Retry);
+        assert_eq!(classify(502), RetryAction::Retry);
+    }
+
+    #[test]
+    fn test_monthly_request_limit_body_rule() {
+        let policy = policy();
+        let body = r#"{"message":"You have reached the limit.","reason":"MONTHLY_REQUEST_COUNT"}"#;
+        assert_eq!(
+            policy.classify(StatusCode::PAYMENT_REQUIRED, body),
+            RetryAction::QuotaExhausted
+        );
+
+        let nested = r#"{"error":{"reason":"MONTHLY_REQUEST_COUNT"}}"#;
+        assert_eq!(
+            policy.classify(StatusCode::PAYMENT_REQUIRED, nested),
+            RetryAction::QuotaExhausted
+        );
+
+        let other = r#"{"message":"nope","reason":"DAILY_REQUEST_COUNT"}"#;
+        assert_eq!(
+            policy.classify(StatusCode::PAYMENT_REQUIRED, other),
+            RetryAction::FailFast
+        );
+
+        // 规则限定了状态码
+        assert_eq!(
+            policy.classify(StatusCode::FORBIDDEN, body),
+            RetryAction::Failover
+        );
+    }
+
+    #[test]
+    fn test_configured_status_actions() {
+        let policy = RetryPolicy::new(RetryPolicyConfig {
+            status_actions: [
+                ("5xx".to_string(), RetryAction::Failover),
+                ("503".to_string(), RetryAction::FailFast),
+            ]
+            .into(),
+            body_rules: vec![BodyRule {
+                status: None,
+                contains: "SUSPENDED".to_string(),
+                action: RetryAction::Disable,
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            policy.classify(StatusCode::BAD_GATEWAY, ""),
+            RetryAction::Failover
+        );
+        assert_eq!(
+            policy.classify(StatusCode::SERVICE_UNAVAILABLE, ""),
+            RetryAction::FailFast
+        );
+        assert_eq!(
+            policy.classify(StatusCode::SERVICE_UNAVAILABLE, "account SUSPENDED"),
+            RetryAction::Disable
+        );
+        // 未覆盖的状态码沿用内置默认
+        assert_eq!(
+            policy.classify(StatusCode::TOO_MANY_REQUESTS, ""),
+            RetryAction::Retry
+        );
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let policy = policy();
+        assert_eq!(policy.max_attempts(1), 3);
+        assert_eq!(policy.max_attempts(10), 9);
+        assert_eq!(policy.max_attempts(0), 1);
+    }
+
+    #[test]
+    fn test_delay_backoff_and_retry_after() {
+        let policy = policy();
+        let first = policy.delay(0, None);
+        assert!(first >= Duration::from_millis(200) && first <= Duration::from_millis(250));
+        let capped = policy.delay(10, None);
+        assert!(capped >= Duration::from_millis(2_000) && capped <= Duration::from_millis(2_500));
+
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(5))),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(3600))),
+            Duration::from_secs(30)
+        );
+
+        let ignoring = RetryPolicy::new(RetryPolicyConfig {
+            honor_retry_after: false,
+            ..Default::default()
+        });
+        assert!(ignoring.delay(0, Some(Duration::from_secs(5))) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let start = Instant::now();
+        assert!(policy().deadline(start).is_none());
+
+        let policy = RetryPolicy::new(RetryPolicyConfig {
+            deadline_secs: 30,
+            ..Default::default()
+        });
+        assert_eq!(
+            policy.deadline(start),
+            Some(start + Duration::from_secs(30))
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! 从 Kiro IDE / AWS SSO 缓存文件导入凭据
+//!
+//! Kiro IDE 将 Token 保存在 `~/.aws/sso/cache/kiro-auth-token.json`，
+//! IdC 登录时的客户端注册信息保存在同目录下以 `clientIdHash` 命名的文件中。
+//! 同时兼容 AWS CLI 的 SSO 缓存格式（Token 与 clientId/clientSecret 在同一文件）。
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::token_manager::MultiTokenManager;
+
+/// 缓存中的 Token 文件
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedToken {
+    #[serde(default)]
+    access_token: Option<String>,
+    refresh_token: String,
+    #[serde(default)]
+    expires_at: Option<String>,
+    /// Kiro IDE: `social` / `IdC`
+    #[serde(default)]
+    auth_method: Option<String>,
+    #[serde(default)]
+    profile_arn: Option<String>,
+    #[serde(default)]
+    region: Option<String>,
+    /// Kiro IDE: 客户端注册文件名（不含扩展名）
+    #[serde(default)]
+    client_id_hash: Option<String>,
+    /// AWS CLI: 客户端注册信息直接写在 Token 文件中
+    #[serde(default)]
+    client_id: Option<String>,
+    #[serde(default)]
+    client_secret: Option<String>,
+}
+
+/// 缓存中的客户端注册文件
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientRegistration {
+    client_id: String,
+    client_secret: String,
+}
+
+/// 待解析的缓存文件
+#[derive(Debug, Clone)]
+pub struct CacheFile {
+    /// 来源标识（文件路径或上传的文件名）
+    pub source: String,
+    /// 文件内容
+    pub content: serde_json::Value,
+}
+
+impl CacheFile {
+    pub fn new(source: impl Into<String>, content: serde_json::Value) -> Self {
+        Self {
+            source: source.into(),
+            content,
+        }
+    }
+
+    /// 来源文件名（不含扩展名），用于匹配 `clientIdHash`
+    fn stem(&self) -> &str {
+        Path::new(&self.source)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(&self.source)
+    }
+}
+
+/// 解析得到的候选凭据
+#[derive(Debug, Clone)]
+pub struct ImportCandidate {
+    pub source: String,
+    pub credentials: KiroCredentials,
+}
+
+/// 未导入的来源及原因
+#[derive(Debug, Clone)]
+pub struct SkippedSource {
+    pub source: String,
+    pub reason: String,
+}
+
+/// 导入结果
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// 成功导入的 (来源, 凭据 ID)
+    pub imported: Vec<(String, u64)>,
+    /// 跳过的来源（重复、缺少注册信息、验证失败等）
+    pub skipped: Vec<SkippedSource>,
+}
+
+/// 默认缓存目录：`~/.aws/sso/cache`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".aws").join("sso").join("cache"))
+}
+
+/// refreshToken 的 SHA256 十六进制摘要（用于去重，避免直接比较明文）
+pub fn refresh_token_hash(refresh_token: &str) -> String {
+    hex::encode(Sha256::digest(refresh_token.as_bytes()))
+}
+
+/// 读取缓存文件
+///
+/// 目录会展开为其中所有 `.json` 文件（不递归），无法读取或解析的文件记为跳过
+pub fn read_cache_files(paths: &[PathBuf]) -> (Vec<CacheFile>, Vec<SkippedSource>) {
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+
+    let mut expanded = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            match std::fs::read_dir(path) {
+                Ok(dir) => {
+                    let mut entries: Vec<PathBuf> = dir
+                        .filter_map(|e| e.ok().map(|e| e.path()))
+                        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                        .collect();
+                    entries.sort();
+                    expanded.extend(entries);
+                }
+                Err(e) => skipped.push(SkippedSource {
+                    source: path.display().to_string(),
+                    reason: format!("读取目录失败: {}", e),
+                }),
+            }
+        } else {
+            expanded.push(path.clone());
+        }
+    }
+
+    for path in expanded {
+        let source = path.display().to_string();
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| format!("读取文件失败: {}", e))
+            .and_then(|s| serde_json::from_str(&s).map_err(|e| format!("JSON 解析失败: {}", e)));
+        match parsed {
+            Ok(content) => files.push(CacheFile::new(source, content)),
+            Err(reason) => skipped.push(SkippedSource { source, reason }),
+        }
+    }
+
+    (files, skipped)
+}
+
+/// 将 Token 文件与客户端注册文件合并为候选凭据
+///
+/// 非 Token、非注册信息的文件会被忽略；同批次内 refreshToken 相同的只保留第一个
+pub fn parse_cache_files(
+    files: &[CacheFile],
+    priority: u32,
+) -> (Vec<ImportCandidate>, Vec<SkippedSource>) {
+    let mut registrations: HashMap<&str, ClientRegistration> = HashMap::new();
+    let mut tokens = Vec::new();
+
+    for file in files {
+        if file.content.get("refreshToken").is_some() {
+            tokens.push(file);
+        } else if let Ok(registration) =
+            serde_json::from_value::<ClientRegistration>(file.content.clone())
+        {
+            registrations.insert(file.stem(), registration);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    let mut skipped = Vec::new();
+    let mut seen = HashSet::new();
+
+    for file in tokens {
+        let skip = |reason: &str| SkippedSource {
+            source: file.source.clone(),
+            reason: reason.to_string(),
+        };
+
+        let token: CachedToken = match serde_json::from_value(file.content.clone()) {
+            Ok(token) => token,
+            Err(e) => {
+                skipped.push(skip(&format!("Token 文件格式无效: {}", e)));
+                continue;
+            }
+        };
+        if !seen.insert(refresh_token_hash(&token.refresh_token)) {
+            skipped.push(skip("重复凭据"));
+            continue;
+        }
+
+        let client = match (&token.client_id, &token.client_secret) {
+            (Some(id), Some(secret)) => Some((id.clone(), secret.clone())),
+            _ => token
+                .client_id_hash
+                .as_deref()
+                .and_then(|hash| registrations.get(hash))
+                .map(|r| (r.client_id.clone(), r.client_secret.clone())),
+        };
+
+        let is_social = token
+            .auth_method
+            .as_deref()
+            .is_some_and(|m| m.eq_ignore_ascii_case("social"));
+        let (auth_method, client_id, client_secret) = match client {
+            _ if is_social => ("social", None, None),
+            Some((id, secret)) => ("idc", Some(id), Some(secret)),
+            None if token.auth_method.is_some() => {
+                skipped.push(skip("缺少客户端注册信息（clientId/clientSecret）"));
+                continue;
+            }
+            None => ("social", None, None),
+        };
+
+        candidates.push(ImportCandidate {
+            source: file.source.clone(),
+            credentials: KiroCredentials {
+                access_token: token.access_token,
+                refresh_token: Some(token.refresh_token),
+                profile_arn: token.profile_arn,
+                expires_at: token.expires_at,
+                auth_method: Some(auth_method.to_string()),
+                client_id,
+                client_secret,
+                priority,
+                region: token.region,
+                ..Default::default()
+            },
+        });
+    }
+
+    (candidates, skipped)
+}
+
+/// 逐个添加候选凭据，跳过与现有凭据 refreshToken 相同的条目
+pub async fn import_candidates(
+    token_manager: &MultiTokenManager,
+    candidates: Vec<ImportCandidate>,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    let existing = token_manager.refresh_token_hashes();
+
+    for candidate in candidates {
+        let duplicated = candidate
+            .credentials
+            .refresh_token
+            .as_deref()
+            .is_some_and(|t| existing.contains(&refresh_token_hash(t)));
+        if duplicated {
+            report.skipped.push(SkippedSource {
+                source: candidate.source,
+                reason: "凭据已存在".to_string(),
+            });
+            continue;
+        }
+
+        match token_manager.add_credential(candidate.credentials).await {
+            Ok(id) => report.imported.push((candidate.source, id)),
+            Err(e) => report.skipped.push(SkippedSource {
+                source: candidate.source,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    tracing::info!(
+        "凭据导入完成：成功 {} 个，跳过 {} 个",
+        report.imported.len(),
+        report.skipped.len()
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_kiro_idc_token_with_registration() {
+        let files = vec![
+            CacheFile::new(
+                "/cache/kiro-auth-token.json",
+                json!({
+                    "accessToken": "access",
+                    "refreshToken": "refresh-idc",
+                    "expiresAt": "2030-01-01T00:00:00Z",
+                    "authMethod": "IdC",
+                    "provider": "BuilderId",
+                    "region": "eu-west-1",
+                    "clientIdHash": "abc123"
+                }),
+            ),
+            CacheFile::new(
+                "/cache/abc123.json",
+                json!({"clientId": "cid", "clientSecret": "csecret", "expiresAt": "2030-01-01T00:00:00Z"}),
+            ),
+        ];
+
+        let (candidates, skipped) = parse_cache_files(&files, 3);
+        assert!(skipped.is_empty());
+        assert_eq!(candidates.len(), 1);
+        let cred = &candidates[0].credentials;
+        assert_eq!(cred.auth_method.as_deref(), Some("idc"));
+        assert_eq!(cred.client_id.as_deref(), Some("cid"));
+        assert_eq!(cred.client_secret.as_deref(), Some("csecret"));
+        assert_eq!(cred.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(cred.priority, 3);
+    }
+
+    #[test]
+    fn test_parse_social_and_aws_cli_formats() {
+        let files = vec![
+            CacheFile::new(
+                "kiro-auth-token.json",
+                json!({"refreshToken": "refresh-social", "authMethod": "social", "provider": "Github", "profileArn": "arn:aws:codewhisperer:us-east-1:1:profile/X"}),
+            ),
+            CacheFile::new(
+                "aws-cli.json",
+                json!({"startUrl": "https://x.awsapps.com/start", "region": "us-east-1", "refreshToken": "refresh-cli", "clientId": "c2", "clientSecret": "s2"}),
+            ),
+        ];
+
+        let (candidates, skipped) = parse_cache_files(&files, 0);
+        assert!(skipped.is_empty());
+        assert_eq!(
+            candidates[0].credentials.auth_method.as_deref(),
+            Some("social")
+        );
+        assert!(candidates[0].credentials.profile_arn.is_some());
+        assert!(candidates[0].credentials.client_id.is_none());
+        assert_eq!(
+            candidates[1].credentials.auth_method.as_deref(),
+            Some("idc")
+        );
+        assert_eq!(candidates[1].credentials.client_id.as_deref(), Some("c2"));
+    }
+
+    #[test]
+    fn test_parse_skips_missing_registration_and_duplicates() {
+        let token = json!({"refreshToken": "same", "authMethod": "IdC", "clientIdHash": "missing"});
+        let files = vec![
+            CacheFile::new("a.json", token.clone()),
+            CacheFile::new("b.json", token),
+            CacheFile::new("unrelated.json", json!({"foo": "bar"})),
+        ];
+
+        let (candidates, skipped) = parse_cache_files(&files, 0);
+        assert!(candidates.is_empty());
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].reason.contains("缺少客户端注册信息"));
+        assert_eq!(skipped[1].reason, "重复凭据");
+    }
+
+    #[test]
+    fn test_read_cache_files_expands_directory() {
+        let dir = std::env::temp_dir().join(format!("kiro-sso-import-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("kiro-auth-token.json"), r#"{"refreshToken":"r"}"#).unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let (files, skipped) = read_cache_files(std::slice::from_ref(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert!(files[0].source.ends_with("kiro-auth-token.json"));
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].source.ends_with("broken.json"));
+    }
+}
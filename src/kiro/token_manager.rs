@@ -7,17 +7,29 @@ use anyhow::bail;
 use chrono::{DateTime, Duration, Utc};
 use parking_lot::Mutex;
 use serde::Serialize;
-use tokio::sync::Mutex as TokioMutex;
+use tokio::sync::{Mutex as TokioMutex, Notify, Semaphore};
 
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-
-use crate::http_client::{ProxyConfig, build_client};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use crate::http_client::{ProxyConfig, UpstreamClients, UpstreamEndpoint, send_upstream};
+use crate::kiro::circuit_breaker::{CircuitBreaker, CircuitBreakerStatus, CircuitState};
+use crate::kiro::concurrency::{self, ConcurrencyPermit, QueueTicket};
+use crate::kiro::credential_store;
+use crate::kiro::device_auth::{oidc_base_url, oidc_host};
+use crate::kiro::header_profile::{self, HeaderFlow, render_headers};
+use crate::kiro::hedging::{Hedging, HedgingStats};
 use crate::kiro::machine_id;
 use crate::kiro::model::credentials::KiroCredentials;
 use crate::kiro::model::token_refresh::{
     IdcRefreshRequest, IdcRefreshResponse, RefreshRequest, RefreshResponse,
 };
 use crate::kiro::model::usage_limits::UsageLimitsResponse;
+use crate::kiro::proxy_pool::ProxyPools;
+use crate::kiro::region;
+use crate::kiro::sso_import::refresh_token_hash;
 use crate::model::config::Config;
 
 /// Token 管理器
@@ -54,8 +66,13 @@ impl TokenManager {
     /// 如果 Token 过期或即将过期，会自动刷新
     pub async fn ensure_valid_token(&mut self) -> anyhow::Result<String> {
         if is_token_expired(&self.credentials) || is_token_expiring_soon(&self.credentials) {
-            self.credentials =
-                refresh_token(&self.credentials, &self.config, self.proxy.as_ref()).await?;
+            self.credentials = refresh_token(
+                &self.credentials,
+                &self.config,
+                &UpstreamClients::default(),
+                self.proxy.as_ref(),
+            )
+            .await?;
 
             // 刷新后再次检查 token 时间有效性
             if is_token_expired(&self.credentials) {
@@ -74,7 +91,14 @@ impl TokenManager {
     /// 调用 getUsageLimits API 查询当前账户的使用额度
     pub async fn get_usage_limits(&mut self) -> anyhow::Result<UsageLimitsResponse> {
         let token = self.ensure_valid_token().await?;
-        get_usage_limits(&self.credentials, &self.config, &token, self.proxy.as_ref()).await
+        get_usage_limits(
+            &self.credentials,
+            &self.config,
+            &UpstreamClients::default(),
+            &token,
+            self.proxy.as_ref(),
+        )
+        .await
     }
 }
 
@@ -127,6 +151,7 @@ pub(crate) fn validate_refresh_token(credentials: &KiroCredentials) -> anyhow::R
 pub(crate) async fn refresh_token(
     credentials: &KiroCredentials,
     config: &Config,
+    clients: &UpstreamClients,
     proxy: Option<&ProxyConfig>,
 ) -> anyhow::Result<KiroCredentials> {
     validate_refresh_token(credentials)?;
@@ -145,9 +170,9 @@ pub(crate) async fn refresh_token(
         || auth_method.eq_ignore_ascii_case("builder-id")
         || auth_method.eq_ignore_ascii_case("iam")
     {
-        refresh_idc_token(credentials, config, proxy).await
+        refresh_idc_token(credentials, config, clients, proxy).await
     } else {
-        refresh_social_token(credentials, config, proxy).await
+        refresh_social_token(credentials, config, clients, proxy).await
     }
 }
 
@@ -155,6 +180,7 @@ pub(crate) async fn refresh_token(
 async fn refresh_social_token(
     credentials: &KiroCredentials,
     config: &Config,
+    clients: &UpstreamClients,
     proxy: Option<&ProxyConfig>,
 ) -> anyhow::Result<KiroCredentials> {
     tracing::info!("正在刷新 Social Token...");
@@ -167,27 +193,24 @@ async fn refresh_social_token(
     let refresh_domain = format!("prod.{}.auth.desktop.kiro.dev", region);
     let machine_id = machine_id::generate_from_credentials(credentials, config)
         .ok_or_else(|| anyhow::anyhow!("无法生成 machineId"))?;
-    let kiro_version = &config.kiro_version;
-
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let headers = render_headers(
+        HeaderFlow::SocialRefresh,
+        credentials,
+        config,
+        &machine_id,
+        &refresh_domain,
+    )?;
+
+    let client = clients.get(proxy, config, UpstreamEndpoint::TokenRefresh)?;
     let body = RefreshRequest {
         refresh_token: refresh_token.to_string(),
     };
 
-    let response = client
-        .post(&refresh_url)
-        .header("Accept", "application/json, text/plain, */*")
-        .header("Content-Type", "application/json")
-        .header(
-            "User-Agent",
-            format!("KiroIDE-{}-{}", kiro_version, machine_id),
-        )
-        .header("Accept-Encoding", "gzip, compress, deflate, br")
-        .header("host", &refresh_domain)
-        .header("Connection", "close")
-        .json(&body)
-        .send()
-        .await?;
+    let mut request = client.post(&refresh_url).headers(headers);
+    if !UpstreamEndpoint::TokenRefresh.keep_alive(&config.upstream_http) {
+        request = request.header("Connection", "close");
+    }
+    let response = send_upstream(request.json(&body), &config.upstream_http).await?;
 
     let status = response.status();
     if !status.is_success() {
@@ -223,13 +246,11 @@ async fn refresh_social_token(
     Ok(new_credentials)
 }
 
-/// IdC Token 刷新所需的 x-amz-user-agent header
-const IDC_AMZ_USER_AGENT: &str = "aws-sdk-js/3.738.0 ua/2.1 os/other lang/js md/browser#unknown_unknown api/sso-oidc#3.738.0 m/E KiroIDE";
-
 /// 刷新 IdC Token (AWS SSO OIDC)
 async fn refresh_idc_token(
     credentials: &KiroCredentials,
     config: &Config,
+    clients: &UpstreamClients,
     proxy: Option<&ProxyConfig>,
 ) -> anyhow::Result<KiroCredentials> {
     tracing::info!("正在刷新 IdC Token...");
@@ -246,9 +267,19 @@ async fn refresh_idc_token(
 
     // 优先使用凭据级 region，未配置时回退到 config.region
     let region = credentials.region.as_ref().unwrap_or(&config.region);
-    let refresh_url = format!("https://oidc.{}.amazonaws.com/token", region);
-
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let oidc_url = oidc_base_url(config, region);
+    let refresh_url = format!("{}/token", oidc_url);
+    // 内置配置不使用 machineId，无法生成时不影响刷新
+    let machine_id = machine_id::generate_from_credentials(credentials, config).unwrap_or_default();
+    let headers = render_headers(
+        HeaderFlow::IdcRefresh,
+        credentials,
+        config,
+        &machine_id,
+        &oidc_host(&oidc_url),
+    )?;
+
+    let client = clients.get(proxy, config, UpstreamEndpoint::TokenRefresh)?;
     let body = IdcRefreshRequest {
         client_id: client_id.to_string(),
         client_secret: client_secret.to_string(),
@@ -256,20 +287,8 @@ async fn refresh_idc_token(
         grant_type: "refresh_token".to_string(),
     };
 
-    let response = client
-        .post(&refresh_url)
-        .header("Content-Type", "application/json")
-        .header("Host", format!("oidc.{}.amazonaws.com", region))
-        .header("Connection", "keep-alive")
-        .header("x-amz-user-agent", IDC_AMZ_USER_AGENT)
-        .header("Accept", "*/*")
-        .header("Accept-Language", "*")
-        .header("sec-fetch-mode", "cors")
-        .header("User-Agent", "node")
-        .header("Accept-Encoding", "br, gzip, deflate")
-        .json(&body)
-        .send()
-        .await?;
+    let request = client.post(&refresh_url).headers(headers).json(&body);
+    let response = send_upstream(request, &config.upstream_http).await?;
 
     let status = response.status();
     if !status.is_success() {
@@ -301,28 +320,25 @@ async fn refresh_idc_token(
     Ok(new_credentials)
 }
 
-/// getUsageLimits API 所需的 x-amz-user-agent header 前缀
-const USAGE_LIMITS_AMZ_USER_AGENT_PREFIX: &str = "aws-sdk-js/1.0.0";
-
 /// 获取使用额度信息
 pub(crate) async fn get_usage_limits(
     credentials: &KiroCredentials,
     config: &Config,
+    clients: &UpstreamClients,
     token: &str,
     proxy: Option<&ProxyConfig>,
 ) -> anyhow::Result<UsageLimitsResponse> {
     tracing::debug!("正在获取使用额度信息...");
 
-    let region = &config.region;
-    let host = format!("q.{}.amazonaws.com", region);
+    let region = region::primary_api_region(credentials, config);
+    let host = region::api_host(config, &region);
     let machine_id = machine_id::generate_from_credentials(credentials, config)
         .ok_or_else(|| anyhow::anyhow!("无法生成 machineId"))?;
-    let kiro_version = &config.kiro_version;
 
     // 构建 URL
     let mut url = format!(
-        "https://{}/getUsageLimits?origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
-        host
+        "{}/getUsageLimits?origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
+        region::api_base_url(config, &region)
     );
 
     // profileArn 是可选的
@@ -330,30 +346,24 @@ pub(crate) async fn get_usage_limits(
         url.push_str(&format!("&profileArn={}", urlencoding::encode(profile_arn)));
     }
 
-    // 构建 User-Agent headers
-    let user_agent = format!(
-        "aws-sdk-js/1.0.0 ua/2.1 os/darwin#24.6.0 lang/js md/nodejs#22.21.1 \
-         api/codewhispererruntime#1.0.0 m/N,E KiroIDE-{}-{}",
-        kiro_version, machine_id
-    );
-    let amz_user_agent = format!(
-        "{} KiroIDE-{}-{}",
-        USAGE_LIMITS_AMZ_USER_AGENT_PREFIX, kiro_version, machine_id
-    );
+    let headers = render_headers(
+        HeaderFlow::UsageLimits,
+        credentials,
+        config,
+        &machine_id,
+        &host,
+    )?;
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = clients.get(proxy, config, UpstreamEndpoint::UsageLimits)?;
 
-    let response = client
+    let mut request = client
         .get(&url)
-        .header("x-amz-user-agent", &amz_user_agent)
-        .header("User-Agent", &user_agent)
-        .header("host", &host)
-        .header("amz-sdk-invocation-id", uuid::Uuid::new_v4().to_string())
-        .header("amz-sdk-request", "attempt=1; max=1")
-        .header("Authorization", format!("Bearer {}", token))
-        .header("Connection", "close")
-        .send()
-        .await?;
+        .headers(headers)
+        .header("Authorization", format!("Bearer {}", token));
+    if !UpstreamEndpoint::UsageLimits.keep_alive(&config.upstream_http) {
+        request = request.header("Connection", "close");
+    }
+    let response = send_upstream(request, &config.upstream_http).await?;
 
     let status = response.status();
     if !status.is_success() {
@@ -390,6 +400,12 @@ struct CredentialEntry {
     disabled_reason: Option<DisabledReason>,
     /// 最后使用时间（用于负载均衡冷却）
     last_used_at: Option<std::time::Instant>,
+    /// 最近一次查询到的余额
+    balance: Option<CachedBalance>,
+    /// 凭据级熔断器
+    circuit: CircuitBreaker,
+    /// 凭据级并发槽位（未配置 maxConcurrency 时不限制）
+    slots: Option<Arc<Semaphore>>,
 }
 
 /// 禁用原因
@@ -399,8 +415,70 @@ enum DisabledReason {
     Manual,
     /// 连续失败达到阈值后自动禁用
     TooManyFailures,
-    /// 额度已用尽（如 MONTHLY_REQUEST_COUNT）
-    QuotaExceeded,
+    /// 额度已用尽（如 MONTHLY_REQUEST_COUNT），`reset_at` 为额度重置时间（查询到之前为 None）
+    QuotaExceeded { reset_at: Option<DateTime<Utc>> },
+}
+
+impl DisabledReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DisabledReason::Manual => "manual",
+            DisabledReason::TooManyFailures => "tooManyFailures",
+            DisabledReason::QuotaExceeded { .. } => "quotaExceeded",
+        }
+    }
+}
+
+/// 缓存的凭据余额（由余额轮询或额度查询更新）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedBalance {
+    /// 订阅类型
+    pub subscription_title: Option<String>,
+    /// 当前使用量
+    pub current_usage: f64,
+    /// 使用限额
+    pub usage_limit: f64,
+    /// 剩余额度
+    pub remaining: f64,
+    /// 剩余额度百分比（0-100）
+    pub remaining_percentage: f64,
+    /// 下次重置时间（Unix 时间戳）
+    pub next_reset_at: Option<f64>,
+    /// 免费试用过期时间（Unix 时间戳，仅试用激活时）
+    pub trial_expires_at: Option<f64>,
+    /// 查询时间（RFC3339）
+    pub checked_at: String,
+}
+
+impl CachedBalance {
+    /// 从额度查询结果构建
+    pub fn from_usage(usage: &UsageLimitsResponse, now: DateTime<Utc>) -> Self {
+        let current_usage = usage.current_usage();
+        let usage_limit = usage.usage_limit();
+        let remaining = (usage_limit - current_usage).max(0.0);
+        let remaining_percentage = if usage_limit > 0.0 {
+            (remaining / usage_limit * 100.0).min(100.0)
+        } else {
+            0.0
+        };
+
+        Self {
+            subscription_title: usage.subscription_title().map(|s| s.to_string()),
+            current_usage,
+            usage_limit,
+            remaining,
+            remaining_percentage,
+            next_reset_at: usage.next_date_reset,
+            trial_expires_at: usage.free_trial_expiry(),
+            checked_at: now.to_rfc3339(),
+        }
+    }
+
+    /// 剩余额度百分比是否低于阈值
+    pub fn is_low(&self, threshold_percent: f64) -> bool {
+        self.usage_limit > 0.0 && self.remaining_percentage < threshold_percent
+    }
 }
 
 // ============================================================================
@@ -425,6 +503,16 @@ pub struct CredentialEntrySnapshot {
     pub has_profile_arn: bool,
     /// Token 过期时间
     pub expires_at: Option<String>,
+    /// 禁用原因（manual / tooManyFailures / quotaExceeded）
+    pub disabled_reason: Option<String>,
+    /// 额度用尽禁用的自动恢复时间（RFC3339）
+    pub disabled_until: Option<String>,
+    /// 最近一次查询到的余额
+    pub balance: Option<CachedBalance>,
+    /// 凭据级熔断器状态
+    pub circuit: CircuitBreakerStatus,
+    /// API 区域（按故障转移顺序）
+    pub api_regions: Vec<String>,
 }
 
 /// 凭据管理器状态快照
@@ -439,6 +527,22 @@ pub struct ManagerSnapshot {
     pub total: usize,
     /// 可用凭据数量
     pub available: usize,
+    /// 上游端点熔断器状态
+    pub endpoint_circuits: Vec<EndpointCircuitSnapshot>,
+    /// 对冲请求统计
+    pub hedging: HedgingStats,
+}
+
+/// 上游端点熔断器状态快照
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointCircuitSnapshot {
+    /// 端点名称（api / mcp / tokenRefresh / usageLimits）
+    pub endpoint: &'static str,
+    /// 区域
+    pub region: String,
+    #[serde(flatten)]
+    pub status: CircuitBreakerStatus,
 }
 
 /// 多凭据 Token 管理器
@@ -447,7 +551,24 @@ pub struct ManagerSnapshot {
 /// 故障统计基于 API 调用结果，而非 Token 刷新结果
 pub struct MultiTokenManager {
     config: Config,
+    /// 全局代理（凭据未配置代理或代理池时使用）
     proxy: Option<ProxyConfig>,
+    /// 命名代理池
+    proxy_pools: ProxyPools,
+    /// 上游 HTTP Client（每个出口代理一个连接池）
+    upstream_clients: UpstreamClients,
+    /// 上次清理上游客户端时代理池的重新分配次数
+    pruned_reassignments: AtomicU64,
+    /// 上游端点熔断器
+    endpoint_circuits: Mutex<HashMap<(UpstreamEndpoint, String), CircuitBreaker>>,
+    /// 全局并发槽位（未配置 maxConcurrency 时不限制）
+    global_slots: Option<Arc<Semaphore>>,
+    /// 并发槽位释放通知（唤醒排队中的请求）
+    slot_released: Arc<Notify>,
+    /// 排队中的请求数
+    queue_waiting: Arc<AtomicUsize>,
+    /// 对冲请求预算与统计
+    hedging: Hedging,
     /// 凭据条目列表
     entries: Mutex<Vec<CredentialEntry>>,
     /// 当前活动凭据 ID
@@ -456,12 +577,57 @@ pub struct MultiTokenManager {
     refresh_lock: TokioMutex<()>,
     /// 凭据文件路径（用于回写）
     credentials_path: Option<PathBuf>,
-    /// 是否为多凭据格式（数组格式才回写）
-    is_multiple_format: bool,
+    /// 是否为多凭据格式（单对象格式在凭据数量超过 1 个时升级为数组格式）
+    is_multiple_format: AtomicBool,
+    /// 凭据文件上次加载/写入时的内容指纹（文件不存在时为 None），用于检测外部修改
+    file_fingerprint: Mutex<Option<String>>,
+    /// 上次回写的凭据内容（加密前）指纹，内容未变化时跳过回写与备份轮转
+    written_fingerprint: Mutex<Option<String>>,
 }
 
-/// 每个凭据最大 API 调用失败次数
-const MAX_FAILURES_PER_CREDENTIAL: u32 = 3;
+/// 启动额度恢复后台任务
+///
+/// 按 `interval` 周期检查额度用尽的凭据，到达重置时间后自动重新启用
+pub fn spawn_quota_recovery(
+    manager: Arc<MultiTokenManager>,
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            manager.check_quota_recovery().await;
+        }
+    })
+}
+
+/// 读取文件内容，文件不存在时返回 None
+fn read_file_content(path: &std::path::Path) -> anyhow::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow::Error::new(e).context(format!("读取凭据文件失败: {:?}", path))),
+    }
+}
+
+/// 读取文件内容指纹，文件不存在时返回 None
+fn read_file_fingerprint(path: &std::path::Path) -> anyhow::Result<Option<String>> {
+    Ok(read_file_content(path)?
+        .as_deref()
+        .map(credential_store::fingerprint))
+}
+
+/// 额度未能按时恢复（或未返回重置时间）时的重查间隔
+const QUOTA_RECHECK_INTERVAL: Duration = Duration::hours(1);
+
+/// 按凭据的 maxConcurrency 创建并发槽位
+fn credential_slots(credentials: &KiroCredentials) -> Option<Arc<Semaphore>> {
+    credentials
+        .max_concurrency
+        .filter(|&n| n > 0)
+        .map(|n| Arc::new(Semaphore::new(n as usize)))
+}
 
 /// API 调用上下文
 ///
@@ -475,6 +641,10 @@ pub struct CallContext {
     pub credentials: KiroCredentials,
     /// 访问 Token
     pub token: String,
+    /// 该凭据的出口代理
+    pub proxy: Option<ProxyConfig>,
+    /// 占用的并发槽位，随上下文（及其持有者）释放
+    pub permit: Option<Arc<ConcurrencyPermit>>,
 }
 
 impl MultiTokenManager {
@@ -485,7 +655,7 @@ impl MultiTokenManager {
     /// * `credentials` - 凭据列表
     /// * `proxy` - 可选的代理配置
     /// * `credentials_path` - 凭据文件路径（用于回写）
-    /// * `is_multiple_format` - 是否为多凭据格式（数组格式才回写）
+    /// * `is_multiple_format` - 是否为多凭据格式（决定回写格式）
     pub fn new(
         config: Config,
         credentials: Vec<KiroCredentials>,
@@ -519,6 +689,10 @@ impl MultiTokenManager {
                         has_new_machine_ids = true;
                     }
                 }
+                if let Err(e) = header_profile::check_profile(&cred, config_ref) {
+                    tracing::warn!("凭据 #{} {}，使用默认请求头", id, e);
+                }
+                let slots = credential_slots(&cred);
                 CredentialEntry {
                     id,
                     credentials: cred,
@@ -526,6 +700,9 @@ impl MultiTokenManager {
                     disabled: false,
                     disabled_reason: None,
                     last_used_at: None,
+                    balance: None,
+                    circuit: CircuitBreaker::new(config_ref.circuit_breaker),
+                    slots,
                 }
             })
             .collect();
@@ -549,14 +726,34 @@ impl MultiTokenManager {
             .map(|e| e.id)
             .unwrap_or(0);
 
+        // 记录当前文件内容指纹，回写前据此检测文件是否被外部修改
+        let file_fingerprint = match &credentials_path {
+            Some(path) => read_file_fingerprint(path)?,
+            None => None,
+        };
+
+        let proxy_pools = ProxyPools::from_config(&config);
+        let global_slots = (config.concurrency.max_concurrency > 0)
+            .then(|| Arc::new(Semaphore::new(config.concurrency.max_concurrency)));
+        let hedging = Hedging::new(&config.hedging);
         let manager = Self {
             config,
             proxy,
+            proxy_pools,
+            upstream_clients: UpstreamClients::default(),
+            pruned_reassignments: AtomicU64::new(0),
+            endpoint_circuits: Mutex::new(HashMap::new()),
+            global_slots,
+            slot_released: Arc::new(Notify::new()),
+            queue_waiting: Arc::new(AtomicUsize::new(0)),
+            hedging,
             entries: Mutex::new(entries),
             current_id: Mutex::new(initial_id),
             refresh_lock: TokioMutex::new(()),
             credentials_path,
-            is_multiple_format,
+            is_multiple_format: AtomicBool::new(is_multiple_format),
+            file_fingerprint: Mutex::new(file_fingerprint),
+            written_fingerprint: Mutex::new(None),
         };
 
         // 如果有新分配的 ID 或新生成的 machineId，立即持久化到配置文件
@@ -576,6 +773,66 @@ impl MultiTokenManager {
         &self.config
     }
 
+    /// 获取代理配置的引用
+    pub fn proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    /// 获取代理池
+    pub fn proxy_pools(&self) -> &ProxyPools {
+        &self.proxy_pools
+    }
+
+    /// 获取对冲请求预算与统计
+    pub fn hedging(&self) -> &Hedging {
+        &self.hedging
+    }
+
+    /// 解析凭据的出口代理
+    ///
+    /// 优先级：凭据级 proxyUrl > 凭据引用的代理池 > 全局代理
+    fn proxy_for(&self, id: u64, credentials: &KiroCredentials) -> Option<ProxyConfig> {
+        if let Some(proxy) = credentials.proxy_config() {
+            return Some(proxy);
+        }
+        if let Some(pool) = credentials.proxy_pool.as_deref()
+            && let Some(proxy) = self.proxy_pools.assign(pool, id)
+        {
+            // 有凭据改用其他出口时，原出口可能已无人使用
+            let reassignments = self.proxy_pools.reassignments();
+            if reassignments != self.pruned_reassignments.load(Ordering::Relaxed) {
+                self.prune_upstream_clients();
+            }
+            return Some(proxy);
+        }
+        self.proxy.clone()
+    }
+
+    /// 获取指定出口代理与端点的上游 HTTP Client
+    pub fn upstream_client(
+        &self,
+        proxy: Option<&ProxyConfig>,
+        endpoint: UpstreamEndpoint,
+    ) -> anyhow::Result<reqwest::Client> {
+        self.upstream_clients.get(proxy, &self.config, endpoint)
+    }
+
+    /// 移除已无凭据使用的出口代理的上游客户端
+    fn prune_upstream_clients(&self) {
+        self.pruned_reassignments
+            .store(self.proxy_pools.reassignments(), Ordering::Relaxed);
+        let mut active: HashSet<ProxyConfig> =
+            self.proxy_pools.assigned_proxies().into_iter().collect();
+        active.extend(self.proxy.clone());
+        active.extend(
+            self.entries
+                .lock()
+                .iter()
+                .filter_map(|e| e.credentials.proxy_config()),
+        );
+        self.upstream_clients.retain_proxies(&active);
+    }
+
     /// 获取当前活动凭据的克隆
     pub fn credentials(&self) -> KiroCredentials {
         let entries = self.entries.lock();
@@ -607,7 +864,6 @@ impl MultiTokenManager {
     pub async fn acquire_context(&self) -> anyhow::Result<CallContext> {
         let total = self.total_count();
         let mut tried_count = 0;
-        const COOLDOWN_DURATION: std::time::Duration = std::time::Duration::from_secs(30);
 
         loop {
             if tried_count >= total {
@@ -618,76 +874,12 @@ impl MultiTokenManager {
                 );
             }
 
-            let (id, credentials) = {
-                let mut entries = self.entries.lock();
-                let now = std::time::Instant::now();
-
-                // 没有可用凭据：如果是"自动禁用导致全灭"，做一次类似重启的自愈
-                if entries.iter().all(|e| e.disabled)
-                    && entries.iter().any(|e| {
-                        e.disabled && e.disabled_reason == Some(DisabledReason::TooManyFailures)
-                    })
-                {
-                    tracing::warn!(
-                        "所有凭据均已被自动禁用，执行自愈：重置失败计数并重新启用（等价于重启）"
-                    );
-                    for e in entries.iter_mut() {
-                        if e.disabled_reason == Some(DisabledReason::TooManyFailures) {
-                            e.disabled = false;
-                            e.disabled_reason = None;
-                            e.failure_count = 0;
-                        }
-                    }
-                }
-
-                // 筛选可用凭据（未禁用 + 不在冷却期）
-                let available: Vec<usize> = entries
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, e)| {
-                        !e.disabled
-                            && e.last_used_at
-                                .map(|t| now.duration_since(t) >= COOLDOWN_DURATION)
-                                .unwrap_or(true)
-                    })
-                    .map(|(idx, _)| idx)
-                    .collect();
-
-                let selected_idx = if !available.is_empty() {
-                    // 随机选择一个可用凭据
-                    let rand_idx = fastrand::usize(..available.len());
-                    available[rand_idx]
-                } else {
-                    // 所有凭据都在冷却期或已禁用，使用最久未使用的凭据（LRU 策略）
-                    entries
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, e)| !e.disabled)
-                        .min_by_key(|(_, e)| e.last_used_at)
-                        .map(|(idx, _)| idx)
-                        .ok_or_else(|| {
-                            let available = entries.iter().filter(|e| !e.disabled).count();
-                            anyhow::anyhow!("所有凭据均已禁用（{}/{}）", available, total)
-                        })?
-                };
-
-                // 更新最后使用时间
-                entries[selected_idx].last_used_at = Some(now);
-
-                let selected_id = entries[selected_idx].id;
-                let selected_creds = entries[selected_idx].credentials.clone();
-
-                // 更新 current_id
-                drop(entries);
-                let mut current_id = self.current_id.lock();
-                *current_id = selected_id;
-
-                (selected_id, selected_creds)
-            };
+            let (id, credentials, permit) = self.acquire_slot(total).await?;
 
             // 尝试获取/刷新 Token
             match self.try_ensure_token(id, &credentials).await {
-                Ok(ctx) => {
+                Ok(mut ctx) => {
+                    ctx.permit = Some(Arc::new(permit));
                     return Ok(ctx);
                 }
                 Err(e) => {
@@ -701,6 +893,187 @@ impl MultiTokenManager {
         }
     }
 
+    /// 获取对冲请求的调用上下文
+    ///
+    /// 使用 `exclude` 以外的凭据，不排队等待；没有其他可用凭据或槽位已满时返回 None
+    pub async fn acquire_hedge_context(&self, exclude: u64) -> Option<CallContext> {
+        let selected = self.select_credential(self.total_count(), Some(exclude));
+        let Ok(Some((id, credentials, permit))) = selected else {
+            return None;
+        };
+
+        match self.try_ensure_token(id, &credentials).await {
+            Ok(mut ctx) => {
+                ctx.permit = Some(Arc::new(permit));
+                Some(ctx)
+            }
+            Err(e) => {
+                tracing::warn!("凭据 #{} Token 刷新失败，放弃对冲请求: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// 选择凭据并获取并发槽位，槽位全部占满时排队等待
+    async fn acquire_slot(
+        &self,
+        total: usize,
+    ) -> anyhow::Result<(u64, KiroCredentials, ConcurrencyPermit)> {
+        let queue = &self.config.concurrency;
+        let deadline =
+            tokio::time::Instant::now() + std::time::Duration::from_secs(queue.queue_timeout_secs);
+        let mut ticket: Option<QueueTicket> = None;
+
+        loop {
+            // 先注册唤醒再检查槽位，避免错过检查与等待之间释放的槽位
+            let released = self.slot_released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if let Some(selected) = self.select_credential(total, None)? {
+                return Ok(selected);
+            }
+
+            if ticket.is_none() {
+                let entered = QueueTicket::enter(&self.queue_waiting, queue.queue_size)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("并发已满且排队请求数已达上限（{}）", queue.queue_size)
+                    })?;
+                tracing::debug!("并发已满，请求进入排队（位置 {}）", entered.position());
+                concurrency::report_queue_position(entered.position());
+                ticket = Some(entered);
+            }
+
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                anyhow::bail!("排队等待并发槽位超时（{} 秒）", queue.queue_timeout_secs);
+            }
+        }
+    }
+
+    /// 选择一个可用凭据并占用并发槽位，槽位已满时返回 None
+    ///
+    /// `exclude` 指定的凭据不参与选择（对冲请求避开原请求的凭据）
+    fn select_credential(
+        &self,
+        total: usize,
+        exclude: Option<u64>,
+    ) -> anyhow::Result<Option<(u64, KiroCredentials, ConcurrencyPermit)>> {
+        const COOLDOWN_DURATION: std::time::Duration = std::time::Duration::from_secs(30);
+
+        let mut entries = self.entries.lock();
+        let now = std::time::Instant::now();
+
+        // 没有可用凭据：如果是"自动禁用导致全灭"，做一次类似重启的自愈
+        if entries.iter().all(|e| e.disabled)
+            && entries
+                .iter()
+                .any(|e| e.disabled && e.disabled_reason == Some(DisabledReason::TooManyFailures))
+        {
+            tracing::warn!(
+                "所有凭据均已被自动禁用，执行自愈：重置失败计数并重新启用（等价于重启）"
+            );
+            for e in entries.iter_mut() {
+                if e.disabled_reason == Some(DisabledReason::TooManyFailures) {
+                    e.disabled = false;
+                    e.disabled_reason = None;
+                    e.failure_count = 0;
+                }
+            }
+        }
+
+        // 全局并发已满（槽位只在持有 entries 锁时获取，检查后不会被抢占）
+        if self
+            .global_slots
+            .as_ref()
+            .is_some_and(|slots| slots.available_permits() == 0)
+        {
+            return Ok(None);
+        }
+        let has_slot = |e: &CredentialEntry| {
+            e.slots
+                .as_ref()
+                .is_none_or(|slots| slots.available_permits() > 0)
+        };
+        let selectable = |e: &CredentialEntry| !e.disabled && exclude != Some(e.id);
+
+        // 筛选可用凭据（未禁用 + 未熔断 + 有空闲槽位 + 不在冷却期）
+        let available: Vec<usize> = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                selectable(e)
+                    && e.circuit.permits(now)
+                    && has_slot(e)
+                    && e.last_used_at
+                        .map(|t| now.duration_since(t) >= COOLDOWN_DURATION)
+                        .unwrap_or(true)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+
+        // 额度接近耗尽的凭据仅在没有其他可用凭据时使用
+        let preferred: Vec<usize> = available
+            .iter()
+            .copied()
+            .filter(|&idx| !self.is_low_quota(&entries[idx]))
+            .collect();
+        let candidates = if preferred.is_empty() {
+            &available
+        } else {
+            &preferred
+        };
+
+        let selected_idx = if !candidates.is_empty() {
+            // 随机选择一个可用凭据
+            let rand_idx = fastrand::usize(..candidates.len());
+            candidates[rand_idx]
+        } else {
+            // 所有凭据都在冷却期、已熔断、槽位已满或已禁用，使用最久未使用的未熔断凭据（LRU 策略，额度充足的优先）
+            let available = entries.iter().filter(|e| selectable(e)).count();
+            if available == 0 {
+                anyhow::bail!("所有凭据均已禁用（{}/{}）", available, total);
+            }
+            if !entries
+                .iter()
+                .any(|e| selectable(e) && e.circuit.permits(now))
+            {
+                anyhow::bail!("所有可用凭据均处于熔断状态（{}/{}）", available, total);
+            }
+            match entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| selectable(e) && e.circuit.permits(now) && has_slot(e))
+                .min_by_key(|(_, e)| (self.is_low_quota(e), e.last_used_at))
+            {
+                Some((idx, _)) => idx,
+                // 未熔断的凭据槽位均已占满，排队等待
+                None => return Ok(None),
+            }
+        };
+
+        let acquire = |slots: &Option<Arc<Semaphore>>| {
+            slots
+                .as_ref()
+                .and_then(|slots| slots.clone().try_acquire_owned().ok())
+        };
+        let global = acquire(&self.global_slots);
+        let credential = acquire(&entries[selected_idx].slots);
+        let permit = ConcurrencyPermit::new(global, credential, self.slot_released.clone());
+
+        // 更新最后使用时间（半开探测名额在实际发送请求时占用，见 `try_credential`）
+        entries[selected_idx].last_used_at = Some(now);
+
+        let selected_id = entries[selected_idx].id;
+        let selected_creds = entries[selected_idx].credentials.clone();
+
+        // 更新 current_id
+        drop(entries);
+        let mut current_id = self.current_id.lock();
+        *current_id = selected_id;
+
+        Ok(Some((selected_id, selected_creds, permit)))
+    }
+
     /// 切换到下一个优先级最高的可用凭据（内部方法）
     fn switch_to_next_by_priority(&self) {
         let entries = self.entries.lock();
@@ -778,8 +1151,29 @@ impl MultiTokenManager {
 
             if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
                 // 确实需要刷新
-                let new_creds =
-                    refresh_token(&current_creds, &self.config, self.proxy.as_ref()).await?;
+                let proxy = self.proxy_for(id, &current_creds);
+                // Token 刷新使用凭据 region（与 API 区域无关）
+                let refresh_region = current_creds
+                    .region
+                    .clone()
+                    .unwrap_or_else(|| self.config.region.clone());
+                if !self.try_endpoint(UpstreamEndpoint::TokenRefresh, &refresh_region) {
+                    anyhow::bail!("Token 刷新端点（{}）处于熔断状态", refresh_region);
+                }
+                let result = refresh_token(
+                    &current_creds,
+                    &self.config,
+                    &self.upstream_clients,
+                    proxy.as_ref(),
+                )
+                .await;
+                self.record_endpoint(
+                    UpstreamEndpoint::TokenRefresh,
+                    &refresh_region,
+                    result.is_ok(),
+                );
+                self.record_credential(id, result.is_ok());
+                let new_creds = result?;
 
                 if is_token_expired(&new_creds) {
                     anyhow::bail!("刷新后的 Token 仍然无效或已过期");
@@ -813,31 +1207,31 @@ impl MultiTokenManager {
             .clone()
             .ok_or_else(|| anyhow::anyhow!("没有可用的 accessToken"))?;
 
+        let proxy = self.proxy_for(id, &creds);
+
         Ok(CallContext {
             id,
             credentials: creds,
             token,
+            proxy,
+            permit: None,
         })
     }
 
     /// 将凭据列表回写到源文件
     ///
-    /// 仅在以下条件满足时回写：
-    /// - 源文件是多凭据格式（数组）
-    /// - credentials_path 已设置
+    /// - 按源文件格式回写：单对象格式且只有 1 个凭据时写回单对象，否则写为数组
+    /// - 临时文件 + fsync + rename 原子替换，并保留 `credentialsBackupCount` 个轮转备份
+    /// - 文件自上次加载/写入后被外部修改时拒绝覆盖
+    /// - 内容与上次回写相同时跳过写入，不轮转备份
     ///
     /// # Returns
     /// - `Ok(true)` - 成功写入文件
-    /// - `Ok(false)` - 跳过写入（非多凭据格式或无路径配置）
-    /// - `Err(_)` - 写入失败
+    /// - `Ok(false)` - 跳过写入（无路径配置或内容未变化）
+    /// - `Err(_)` - 写入失败或文件已被外部修改
     fn persist_credentials(&self) -> anyhow::Result<bool> {
         use anyhow::Context;
 
-        // 仅多凭据格式才回写
-        if !self.is_multiple_format {
-            return Ok(false);
-        }
-
         let path = match &self.credentials_path {
             Some(p) => p,
             None => return Ok(false),
@@ -856,19 +1250,76 @@ impl MultiTokenManager {
                 .collect()
         };
 
-        // 序列化为 pretty JSON
-        let json = serde_json::to_string_pretty(&credentials).context("序列化凭据失败")?;
+        // 序列化为 pretty JSON（单对象格式仅在只有 1 个凭据时保持）
+        let json = if !self.is_multiple_format.load(Ordering::SeqCst) && credentials.len() == 1 {
+            serde_json::to_string_pretty(&credentials[0])
+        } else {
+            if !self.is_multiple_format.swap(true, Ordering::SeqCst) {
+                tracing::info!("凭据数量不为 1，凭据文件将转换为数组格式");
+            }
+            serde_json::to_string_pretty(&credentials)
+        }
+        .context("序列化凭据失败")?;
+        let content_fingerprint = credential_store::fingerprint(&json);
+        // 配置了密钥时加密写入
+        let json = credential_store::seal(&json)?;
+
+        let write = || -> anyhow::Result<bool> {
+            // 持有指纹锁完成 检查 → 写入 → 更新指纹，避免并发回写交错
+            let mut last_fingerprint = self.file_fingerprint.lock();
+            let on_disk = read_file_content(path)?;
+            if on_disk.as_deref().map(credential_store::fingerprint) != *last_fingerprint {
+                anyhow::bail!(
+                    "凭据文件自上次加载后已被外部修改，拒绝覆盖（请重启服务重新加载）: {:?}",
+                    path
+                );
+            }
 
-        // 写入文件（在 Tokio runtime 内使用 block_in_place 避免阻塞 worker）
-        if tokio::runtime::Handle::try_current().is_ok() {
-            tokio::task::block_in_place(|| std::fs::write(path, &json))
+            // 首次从明文切换为加密时不再备份明文，避免残留明文副本
+            let plaintext_to_encrypted = credential_store::is_encrypted(&json)
+                && on_disk
+                    .as_deref()
+                    .is_some_and(|c| !credential_store::is_encrypted(c));
+            let mut written_fingerprint = self.written_fingerprint.lock();
+            if !plaintext_to_encrypted
+                && written_fingerprint.as_deref() == Some(content_fingerprint.as_str())
+            {
+                return Ok(false);
+            }
+            let backups = if plaintext_to_encrypted {
+                0
+            } else {
+                self.config.credentials_backup_count
+            };
+
+            credential_store::write_atomic(path, &json, backups)
                 .with_context(|| format!("回写凭据文件失败: {:?}", path))?;
+            if plaintext_to_encrypted {
+                // 已有的明文备份同样加密
+                credential_store::seal_backups(path)
+                    .with_context(|| format!("加密凭据文件备份失败: {:?}", path))?;
+            }
+            *last_fingerprint = Some(credential_store::fingerprint(&json));
+            *written_fingerprint = Some(content_fingerprint);
+            Ok(true)
+        };
+
+        // 在 Tokio runtime 内使用 block_in_place 避免阻塞 worker
+        let written = if tokio::runtime::Handle::try_current().is_ok() {
+            tokio::task::block_in_place(write)?
         } else {
-            std::fs::write(path, &json).with_context(|| format!("回写凭据文件失败: {:?}", path))?;
+            write()?
+        };
+
+        if written {
+            tracing::debug!("已回写凭据到文件: {:?}", path);
         }
+        Ok(written)
+    }
 
-        tracing::debug!("已回写凭据到文件: {:?}", path);
-        Ok(true)
+    /// 回写当前凭据状态（关闭前调用）
+    pub fn persist(&self) -> anyhow::Result<bool> {
+        self.persist_credentials()
     }
 
     /// 报告指定凭据 API 调用成功
@@ -908,10 +1359,10 @@ impl MultiTokenManager {
             "凭据 #{} API 调用失败（{}/{}）",
             id,
             failure_count,
-            MAX_FAILURES_PER_CREDENTIAL
+            self.max_failures()
         );
 
-        if failure_count >= MAX_FAILURES_PER_CREDENTIAL {
+        if failure_count >= self.max_failures() {
             entry.disabled = true;
             entry.disabled_reason = Some(DisabledReason::TooManyFailures);
             tracing::error!("凭据 #{} 已连续失败 {} 次，已被禁用", id, failure_count);
@@ -958,9 +1409,9 @@ impl MultiTokenManager {
         }
 
         entry.disabled = true;
-        entry.disabled_reason = Some(DisabledReason::QuotaExceeded);
+        entry.disabled_reason = Some(DisabledReason::QuotaExceeded { reset_at: None });
         // 设为阈值，便于在管理面板中直观看到该凭据已不可用
-        entry.failure_count = MAX_FAILURES_PER_CREDENTIAL;
+        entry.failure_count = self.max_failures();
 
         tracing::error!("凭据 #{} 额度已用尽（MONTHLY_REQUEST_COUNT），已被禁用", id);
 
@@ -983,6 +1434,227 @@ impl MultiTokenManager {
         false
     }
 
+    /// 报告指定凭据不可用（重试策略动作为 `disable`）
+    ///
+    /// 立即禁用该凭据（不等待连续失败阈值）并切换到优先级最高的可用凭据，
+    /// 返回是否还有可用凭据
+    pub fn report_disabled(&self, id: u64) -> bool {
+        let mut entries = self.entries.lock();
+        let mut current_id = self.current_id.lock();
+
+        let entry = match entries.iter_mut().find(|e| e.id == id) {
+            Some(e) => e,
+            None => return entries.iter().any(|e| !e.disabled),
+        };
+
+        if !entry.disabled {
+            entry.disabled = true;
+            entry.disabled_reason = Some(DisabledReason::TooManyFailures);
+            entry.failure_count = self.max_failures();
+            tracing::error!("凭据 #{} 命中禁用规则，已被禁用", id);
+        }
+
+        match entries
+            .iter()
+            .filter(|e| !e.disabled)
+            .min_by_key(|e| e.credentials.priority)
+        {
+            Some(next) => {
+                *current_id = next.id;
+                true
+            }
+            None => {
+                tracing::error!("所有凭据均已禁用！");
+                false
+            }
+        }
+    }
+
+    /// 凭据的 API 区域（按故障转移顺序，首个为主区域）
+    pub fn api_regions(&self, credentials: &KiroCredentials) -> Vec<String> {
+        region::api_regions(credentials, &self.config)
+    }
+
+    /// 指定区域的上游端点当前是否允许请求（不占用探测名额，用于选择区域）
+    pub fn endpoint_permits(&self, endpoint: UpstreamEndpoint, region: &str) -> bool {
+        self.endpoint_circuits
+            .lock()
+            .get(&(endpoint, region.to_string()))
+            .is_none_or(|c| c.permits(std::time::Instant::now()))
+    }
+
+    /// 指定区域的上游端点是否允许请求（熔断打开时拒绝，半开时放行探测请求）
+    pub fn try_endpoint(&self, endpoint: UpstreamEndpoint, region: &str) -> bool {
+        let config = self.config.circuit_breaker;
+        self.endpoint_circuits
+            .lock()
+            .entry((endpoint, region.to_string()))
+            .or_insert_with(|| CircuitBreaker::new(config))
+            .try_acquire(std::time::Instant::now())
+    }
+
+    /// 归还 `try_endpoint` 占用但未使用的探测名额
+    pub fn release_endpoint(&self, endpoint: UpstreamEndpoint, region: &str) {
+        if let Some(circuit) = self
+            .endpoint_circuits
+            .lock()
+            .get_mut(&(endpoint, region.to_string()))
+        {
+            circuit.release(std::time::Instant::now());
+        }
+    }
+
+    /// 记录指定区域的上游端点调用结果
+    pub fn record_endpoint(&self, endpoint: UpstreamEndpoint, region: &str, success: bool) {
+        let config = self.config.circuit_breaker;
+        let mut circuits = self.endpoint_circuits.lock();
+        let circuit = circuits
+            .entry((endpoint, region.to_string()))
+            .or_insert_with(|| CircuitBreaker::new(config));
+        let was_open = circuit.state() == CircuitState::Open;
+        circuit.record(success, std::time::Instant::now());
+        if !was_open && circuit.state() == CircuitState::Open {
+            tracing::error!(
+                "上游端点 {}（{}）错误率过高，已熔断",
+                endpoint.as_str(),
+                region
+            );
+        }
+    }
+
+    /// 即将使用凭据发送请求：半开状态的凭据占用唯一的探测名额
+    ///
+    /// 返回 false 表示凭据已熔断或探测名额已被占用；返回 true 后必须通过
+    /// `record_credential` 记录结果
+    pub fn try_credential(&self, id: u64) -> bool {
+        let mut entries = self.entries.lock();
+        entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .is_some_and(|e| e.circuit.try_acquire(std::time::Instant::now()))
+    }
+
+    /// 归还 `try_credential` 占用但未使用的探测名额
+    pub fn release_credential(&self, id: u64) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.circuit.release(std::time::Instant::now());
+        }
+    }
+
+    /// 记录凭据调用结果（用于凭据级熔断）
+    pub fn record_credential(&self, id: u64, success: bool) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            let was_open = entry.circuit.state() == CircuitState::Open;
+            entry.circuit.record(success, std::time::Instant::now());
+            if !was_open && entry.circuit.state() == CircuitState::Open {
+                tracing::error!("凭据 #{} 错误率过高，已熔断", id);
+            }
+        }
+    }
+
+    /// 上游端点熔断器状态
+    fn endpoint_circuits(&self) -> Vec<EndpointCircuitSnapshot> {
+        let now = std::time::Instant::now();
+        let mut circuits: Vec<_> = self
+            .endpoint_circuits
+            .lock()
+            .iter()
+            .map(|((endpoint, region), circuit)| EndpointCircuitSnapshot {
+                endpoint: endpoint.as_str(),
+                region: region.clone(),
+                status: circuit.status(now),
+            })
+            .collect();
+        circuits.sort_by(|a, b| (a.endpoint, &a.region).cmp(&(b.endpoint, &b.region)));
+        circuits
+    }
+
+    /// 凭据连续失败多少次后禁用
+    fn max_failures(&self) -> u32 {
+        self.config.retry_policy.max_failures_per_credential.max(1)
+    }
+
+    /// 凭据余额是否低于配置的低额度阈值
+    fn is_low_quota(&self, entry: &CredentialEntry) -> bool {
+        entry
+            .balance
+            .as_ref()
+            .is_some_and(|b| b.is_low(self.config.balance_monitor.low_quota_percent))
+    }
+
+    /// 记录凭据的最新余额
+    fn cache_balance(&self, id: u64, usage: &UsageLimitsResponse, now: DateTime<Utc>) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.balance = Some(CachedBalance::from_usage(usage, now));
+        }
+    }
+
+    /// 检查额度用尽的凭据（额度恢复后台任务的一轮）
+    ///
+    /// - 尚未记录重置时间的：查询 getUsageLimits 记录 `nextDateReset`
+    /// - 已到重置时间的：查询 getUsageLimits 确认额度已恢复后重新启用，否则顺延
+    pub async fn check_quota_recovery(&self) {
+        let now = Utc::now();
+        let due: Vec<u64> = self
+            .entries
+            .lock()
+            .iter()
+            .filter(|e| match e.disabled_reason {
+                Some(DisabledReason::QuotaExceeded { reset_at }) => {
+                    e.disabled && reset_at.is_none_or(|t| t <= now)
+                }
+                _ => false,
+            })
+            .map(|e| e.id)
+            .collect();
+
+        for id in due {
+            match self.get_usage_limits_for(id).await {
+                Ok(usage) => self.apply_quota_usage(id, &usage, Utc::now()),
+                Err(e) => tracing::warn!("凭据 #{} 额度查询失败，稍后重试: {}", id, e),
+            }
+        }
+    }
+
+    /// 根据额度查询结果更新额度用尽凭据的状态
+    fn apply_quota_usage(&self, id: u64, usage: &UsageLimitsResponse, now: DateTime<Utc>) {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries.iter_mut().find(|e| e.id == id) else {
+            return;
+        };
+        // 期间可能已被手动重置或禁用
+        let Some(DisabledReason::QuotaExceeded { reset_at }) = entry.disabled_reason else {
+            return;
+        };
+
+        let has_quota = usage.current_usage() < usage.usage_limit();
+        if reset_at.is_some() && has_quota {
+            entry.disabled = false;
+            entry.disabled_reason = None;
+            entry.failure_count = 0;
+            tracing::info!("凭据 #{} 额度已重置，已自动重新启用", id);
+            return;
+        }
+
+        // 未返回重置时间或重置时间已过但额度未恢复时，稍后再查
+        let next_reset = usage
+            .next_date_reset
+            .and_then(|ts| DateTime::from_timestamp(ts as i64, 0))
+            .filter(|t| *t > now)
+            .unwrap_or(now + QUOTA_RECHECK_INTERVAL);
+        entry.disabled_reason = Some(DisabledReason::QuotaExceeded {
+            reset_at: Some(next_reset),
+        });
+        tracing::info!(
+            "凭据 #{} 额度用尽，将于 {} 后检查并自动恢复",
+            id,
+            next_reset.to_rfc3339()
+        );
+    }
+
     /// 切换到优先级最高的可用凭据
     ///
     /// 返回是否成功切换
@@ -1015,8 +1687,9 @@ impl MultiTokenManager {
         get_usage_limits(
             &ctx.credentials,
             &self.config,
+            &self.upstream_clients,
             &ctx.token,
-            self.proxy.as_ref(),
+            ctx.proxy.as_ref(),
         )
         .await
     }
@@ -1030,6 +1703,7 @@ impl MultiTokenManager {
         let entries = self.entries.lock();
         let current_id = *self.current_id.lock();
         let available = entries.iter().filter(|e| !e.disabled).count();
+        let now = std::time::Instant::now();
 
         ManagerSnapshot {
             entries: entries
@@ -1048,14 +1722,36 @@ impl MultiTokenManager {
                     }),
                     has_profile_arn: e.credentials.profile_arn.is_some(),
                     expires_at: e.credentials.expires_at.clone(),
+                    disabled_reason: e.disabled_reason.map(|r| r.as_str().to_string()),
+                    disabled_until: match e.disabled_reason {
+                        Some(DisabledReason::QuotaExceeded { reset_at }) => {
+                            reset_at.map(|t| t.to_rfc3339())
+                        }
+                        _ => None,
+                    },
+                    balance: e.balance.clone(),
+                    circuit: e.circuit.status(now),
+                    api_regions: region::api_regions(&e.credentials, &self.config),
                 })
                 .collect(),
             current_id,
             total: entries.len(),
             available,
+            endpoint_circuits: self.endpoint_circuits(),
+            hedging: self.hedging.stats(),
         }
     }
 
+    /// 现有凭据 refreshToken 的 SHA256 摘要集合（用于导入去重）
+    pub fn refresh_token_hashes(&self) -> HashSet<String> {
+        self.entries
+            .lock()
+            .iter()
+            .filter_map(|e| e.credentials.refresh_token.as_deref())
+            .map(refresh_token_hash)
+            .collect()
+    }
+
     /// 设置凭据禁用状态（Admin API）
     pub fn set_disabled(&self, id: u64, disabled: bool) -> anyhow::Result<()> {
         {
@@ -1109,13 +1805,16 @@ impl MultiTokenManager {
             entry.failure_count = 0;
             entry.disabled = false;
             entry.disabled_reason = None;
+            entry.circuit.reset();
         }
         // 持久化更改
         self.persist_credentials()?;
         Ok(())
     }
 
-    /// 获取指定凭据的使用额度（Admin API）
+    /// 获取指定凭据的使用额度（Admin API / 余额轮询）
+    ///
+    /// 查询成功时会更新该凭据的缓存余额
     pub async fn get_usage_limits_for(&self, id: u64) -> anyhow::Result<UsageLimitsResponse> {
         let credentials = {
             let entries = self.entries.lock();
@@ -1141,8 +1840,14 @@ impl MultiTokenManager {
             };
 
             if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
-                let new_creds =
-                    refresh_token(&current_creds, &self.config, self.proxy.as_ref()).await?;
+                let proxy = self.proxy_for(id, &current_creds);
+                let new_creds = refresh_token(
+                    &current_creds,
+                    &self.config,
+                    &self.upstream_clients,
+                    proxy.as_ref(),
+                )
+                .await?;
                 {
                     let mut entries = self.entries.lock();
                     if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
@@ -1176,15 +1881,30 @@ impl MultiTokenManager {
                 .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?
         };
 
-        get_usage_limits(&credentials, &self.config, &token, self.proxy.as_ref()).await
+        let proxy = self.proxy_for(id, &credentials);
+        let usage = get_usage_limits(
+            &credentials,
+            &self.config,
+            &self.upstream_clients,
+            &token,
+            proxy.as_ref(),
+        )
+        .await?;
+        self.cache_balance(id, &usage, Utc::now());
+        Ok(usage)
+    }
+
+    /// 所有凭据 ID（余额轮询用）
+    pub fn credential_ids(&self) -> Vec<u64> {
+        self.entries.lock().iter().map(|e| e.id).collect()
     }
 
     /// 添加新凭据（Admin API）
     ///
     /// # 流程
-    /// 1. 验证凭据基本字段（refresh_token 不为空）
-    /// 2. 尝试刷新 Token 验证凭据有效性
-    /// 3. 分配新 ID（当前最大 ID + 1）
+    /// 1. 验证凭据基本字段（refresh_token 不为空、引用的请求头配置存在）
+    /// 2. 分配新 ID（当前最大 ID + 1）
+    /// 3. 尝试刷新 Token 验证凭据有效性（使用凭据的出口代理）
     /// 4. 添加到 entries 列表
     /// 5. 持久化到配置文件
     ///
@@ -1194,17 +1914,24 @@ impl MultiTokenManager {
     pub async fn add_credential(&self, new_cred: KiroCredentials) -> anyhow::Result<u64> {
         // 1. 基本验证
         validate_refresh_token(&new_cred)?;
+        header_profile::check_profile(&new_cred, &self.config)?;
 
-        // 2. 尝试刷新 Token 验证凭据有效性
-        let mut validated_cred =
-            refresh_token(&new_cred, &self.config, self.proxy.as_ref()).await?;
-
-        // 3. 分配新 ID
+        // 2. 分配新 ID（代理池按凭据 ID 分配出口，需先确定 ID）
         let new_id = {
             let entries = self.entries.lock();
             entries.iter().map(|e| e.id).max().unwrap_or(0) + 1
         };
 
+        // 3. 尝试刷新 Token 验证凭据有效性
+        let proxy = self.proxy_for(new_id, &new_cred);
+        let mut validated_cred = refresh_token(
+            &new_cred,
+            &self.config,
+            &self.upstream_clients,
+            proxy.as_ref(),
+        )
+        .await?;
+
         // 4. 设置 ID 并保留用户输入的元数据
         validated_cred.id = Some(new_id);
         validated_cred.priority = new_cred.priority;
@@ -1219,8 +1946,12 @@ impl MultiTokenManager {
         validated_cred.client_secret = new_cred.client_secret;
         validated_cred.region = new_cred.region;
         validated_cred.machine_id = new_cred.machine_id;
+        if validated_cred.profile_arn.is_none() {
+            validated_cred.profile_arn = new_cred.profile_arn;
+        }
 
         {
+            let slots = credential_slots(&validated_cred);
             let mut entries = self.entries.lock();
             entries.push(CredentialEntry {
                 id: new_id,
@@ -1229,6 +1960,9 @@ impl MultiTokenManager {
                 disabled: false,
                 disabled_reason: None,
                 last_used_at: None,
+                balance: None,
+                circuit: CircuitBreaker::new(self.config.circuit_breaker),
+                slots,
             });
         }
 
@@ -1295,6 +2029,10 @@ impl MultiTokenManager {
             }
         }
 
+        // 释放代理池出口，并移除已无人使用的出口代理的客户端
+        self.proxy_pools.release(id);
+        self.prune_upstream_clients();
+
         // 持久化更改
         self.persist_credentials()?;
 
@@ -1420,6 +2158,114 @@ mod tests {
         );
     }
 
+    fn temp_credentials_path() -> PathBuf {
+        std::env::temp_dir().join(format!("kiro-credentials-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    fn cleanup_credentials_file(path: &std::path::Path) {
+        let _ = std::fs::remove_file(path);
+        for n in 1..=3 {
+            let _ = std::fs::remove_file(credential_store::backup_path(path, n));
+        }
+    }
+
+    #[test]
+    fn test_persist_keeps_single_object_format_with_backup() {
+        let path = temp_credentials_path();
+        std::fs::write(&path, r#"{"refreshToken":"r1"}"#).unwrap();
+
+        let cred = KiroCredentials {
+            refresh_token: Some("r1".to_string()),
+            ..Default::default()
+        };
+        // 补全 ID 时立即回写
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![cred],
+            None,
+            Some(path.clone()),
+            false,
+        )
+        .unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(written.is_object());
+        assert_eq!(written["id"], 1);
+        assert_eq!(
+            std::fs::read_to_string(credential_store::backup_path(&path, 1)).unwrap(),
+            r#"{"refreshToken":"r1"}"#
+        );
+
+        manager.set_priority(1, 2).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["priority"], 2);
+
+        cleanup_credentials_file(&path);
+    }
+
+    #[test]
+    fn test_persist_skips_unchanged_content() {
+        let path = temp_credentials_path();
+        std::fs::write(&path, r#"{"refreshToken":"r1"}"#).unwrap();
+
+        let cred = KiroCredentials {
+            refresh_token: Some("r1".to_string()),
+            ..Default::default()
+        };
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![cred],
+            None,
+            Some(path.clone()),
+            false,
+        )
+        .unwrap();
+        assert!(credential_store::backup_path(&path, 1).exists());
+
+        // 内容未变化：不重写文件，也不轮转备份
+        assert!(!manager.persist().unwrap());
+        assert!(!credential_store::backup_path(&path, 2).exists());
+
+        manager.set_priority(1, 2).unwrap();
+        assert!(credential_store::backup_path(&path, 2).exists());
+        assert!(!manager.persist().unwrap());
+        assert!(!credential_store::backup_path(&path, 3).exists());
+
+        cleanup_credentials_file(&path);
+    }
+
+    #[test]
+    fn test_persist_refuses_externally_modified_file() {
+        let path = temp_credentials_path();
+        std::fs::write(&path, r#"[{"id":1,"refreshToken":"r1","machineId":"m"}]"#).unwrap();
+
+        let cred = KiroCredentials {
+            id: Some(1),
+            refresh_token: Some("r1".to_string()),
+            machine_id: Some("m".to_string()),
+            ..Default::default()
+        };
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![cred],
+            None,
+            Some(path.clone()),
+            true,
+        )
+        .unwrap();
+
+        let external = r#"[{"id":1,"refreshToken":"edited-by-hand"}]"#;
+        std::fs::write(&path, external).unwrap();
+
+        let err = manager.set_priority(1, 5).unwrap_err().to_string();
+        assert!(err.contains("外部修改"), "实际: {}", err);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), external);
+
+        cleanup_credentials_file(&path);
+    }
+
     #[test]
     fn test_multi_token_manager_report_failure() {
         let config = Config::default();
@@ -1505,10 +2351,10 @@ mod tests {
             MultiTokenManager::new(config, vec![cred1, cred2], None, None, false).unwrap();
 
         // 凭据会自动分配 ID（从 1 开始）
-        for _ in 0..MAX_FAILURES_PER_CREDENTIAL {
+        for _ in 0..manager.max_failures() {
             manager.report_failure(1);
         }
-        for _ in 0..MAX_FAILURES_PER_CREDENTIAL {
+        for _ in 0..manager.max_failures() {
             manager.report_failure(2);
         }
 
@@ -1561,6 +2407,319 @@ mod tests {
         assert_eq!(manager.available_count(), 0);
     }
 
+    fn usage_response(current: f64, limit: f64, reset_at: DateTime<Utc>) -> UsageLimitsResponse {
+        serde_json::from_value(serde_json::json!({
+            "nextDateReset": reset_at.timestamp() as f64,
+            "usageBreakdownList": [{
+                "currentUsageWithPrecision": current,
+                "usageLimitWithPrecision": limit
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_quota_exhausted_records_reset_and_re_enables() {
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        let now = Utc::now();
+        let reset_at = now + Duration::days(3);
+
+        manager.report_quota_exhausted(1);
+        // 首次查询：记录重置时间，不恢复
+        manager.apply_quota_usage(1, &usage_response(50.0, 50.0, reset_at), now);
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.available, 0);
+        assert_eq!(
+            snapshot.entries[0].disabled_reason.as_deref(),
+            Some("quotaExceeded")
+        );
+        assert_eq!(
+            snapshot.entries[0].disabled_until,
+            Some(
+                DateTime::from_timestamp(reset_at.timestamp(), 0)
+                    .unwrap()
+                    .to_rfc3339()
+            )
+        );
+
+        // 到期后额度仍未恢复：顺延而不是启用
+        let later = reset_at + Duration::minutes(1);
+        manager.apply_quota_usage(1, &usage_response(50.0, 50.0, reset_at), later);
+        assert_eq!(manager.available_count(), 0);
+        assert!(manager.snapshot().entries[0].disabled_until.is_some());
+
+        // 额度已恢复：自动重新启用
+        manager.apply_quota_usage(1, &usage_response(0.0, 50.0, reset_at), later);
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.available, 1);
+        assert!(snapshot.entries[0].disabled_reason.is_none());
+        assert!(snapshot.entries[0].disabled_until.is_none());
+    }
+
+    #[test]
+    fn test_quota_recovery_ignores_manually_disabled() {
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        manager.set_disabled(1, true).unwrap();
+
+        let now = Utc::now();
+        manager.apply_quota_usage(1, &usage_response(0.0, 50.0, now), now);
+        assert_eq!(manager.available_count(), 0);
+        assert_eq!(
+            manager.snapshot().entries[0].disabled_reason.as_deref(),
+            Some("manual")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_acquire_context_deprioritizes_low_quota() {
+        let creds = ["t1", "t2"]
+            .map(|token| KiroCredentials {
+                access_token: Some(token.to_string()),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .to_vec();
+        let manager = MultiTokenManager::new(Config::default(), creds, None, None, false).unwrap();
+
+        let now = Utc::now();
+        manager.cache_balance(1, &usage_response(95.0, 100.0, now), now);
+        manager.cache_balance(2, &usage_response(10.0, 100.0, now), now);
+        let balance = manager.snapshot().entries[0].balance.clone().unwrap();
+        assert_eq!(balance.remaining, 5.0);
+
+        // 额度充足的凭据优先；其进入冷却后才使用接近耗尽的凭据
+        assert_eq!(manager.acquire_context().await.unwrap().token, "t2");
+        assert_eq!(manager.acquire_context().await.unwrap().token, "t1");
+    }
+
+    #[tokio::test]
+    async fn test_acquire_context_skips_open_circuits() {
+        let creds = ["t1", "t2"]
+            .map(|token| KiroCredentials {
+                access_token: Some(token.to_string()),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .to_vec();
+        let mut config = Config::default();
+        config.circuit_breaker.min_requests = 2;
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        manager.record_credential(1, false);
+        manager.record_credential(1, false);
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.entries[0].circuit.state, CircuitState::Open);
+        // 熔断不等于禁用
+        assert_eq!(snapshot.available, 2);
+
+        // 即使 #2 在冷却期内也不会选择已熔断的 #1
+        for _ in 0..3 {
+            assert_eq!(manager.acquire_context().await.unwrap().token, "t2");
+        }
+
+        manager.record_credential(2, false);
+        manager.record_credential(2, false);
+        assert!(manager.acquire_context().await.is_err());
+
+        manager.reset_and_enable(2).unwrap();
+        assert_eq!(manager.acquire_context().await.unwrap().token, "t2");
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_taken_only_when_sending() {
+        let creds = vec![KiroCredentials {
+            access_token: Some("t1".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        }];
+        let mut config = Config::default();
+        config.circuit_breaker.min_requests = 2;
+        config.circuit_breaker.open_secs = 0;
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        manager.record_credential(1, false);
+        manager.record_credential(1, false);
+
+        // 仅获取上下文（未发送请求）不占用半开探测名额
+        manager.acquire_context().await.unwrap();
+        manager.acquire_context().await.unwrap();
+        let circuit = &manager.snapshot().entries[0].circuit;
+        assert_eq!(circuit.state, CircuitState::Open);
+
+        assert!(manager.try_credential(1));
+        assert_eq!(
+            manager.snapshot().entries[0].circuit.state,
+            CircuitState::HalfOpen
+        );
+        manager.record_credential(1, true);
+        assert_eq!(
+            manager.snapshot().entries[0].circuit.state,
+            CircuitState::Closed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_acquire_context_queues_when_credential_saturated() {
+        let cred = KiroCredentials {
+            access_token: Some("t1".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            max_concurrency: Some(1),
+            ..Default::default()
+        };
+        let manager = Arc::new(
+            MultiTokenManager::new(Config::default(), vec![cred], None, None, false).unwrap(),
+        );
+
+        let first = manager.acquire_context().await.unwrap();
+        assert!(first.permit.as_ref().unwrap().is_limited());
+
+        let waiter = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.acquire_context().await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(manager.queue_waiting.load(Ordering::Relaxed), 1);
+
+        // 释放槽位后排队的请求获得凭据
+        drop(first);
+        let second = waiter.await.unwrap().unwrap();
+        assert_eq!(second.token, "t1");
+        assert_eq!(manager.queue_waiting.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_context_rejects_when_queue_full() {
+        let cred = KiroCredentials {
+            access_token: Some("t1".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        config.concurrency.max_concurrency = 1;
+        config.concurrency.queue_size = 0;
+        let manager = MultiTokenManager::new(config, vec![cred], None, None, false).unwrap();
+
+        let _held = manager.acquire_context().await.unwrap();
+        let err = match manager.acquire_context().await {
+            Ok(_) => panic!("队列已满时应拒绝请求"),
+            Err(e) => e,
+        };
+        assert!(err.to_string().contains("排队"));
+    }
+
+    #[test]
+    fn test_endpoint_circuit_opens_and_is_reported() {
+        let mut config = Config::default();
+        config.circuit_breaker.min_requests = 2;
+        let manager = MultiTokenManager::new(config, vec![], None, None, false).unwrap();
+
+        assert!(manager.try_endpoint(UpstreamEndpoint::Api, "us-east-1"));
+        manager.record_endpoint(UpstreamEndpoint::Api, "us-east-1", false);
+        manager.record_endpoint(UpstreamEndpoint::Api, "us-east-1", false);
+        assert!(!manager.try_endpoint(UpstreamEndpoint::Api, "us-east-1"));
+        assert!(manager.try_endpoint(UpstreamEndpoint::Api, "eu-central-1"));
+        assert!(manager.try_endpoint(UpstreamEndpoint::Mcp, "us-east-1"));
+
+        let circuits = manager.snapshot().endpoint_circuits;
+        assert_eq!(circuits[0].endpoint, "api");
+        assert_eq!(circuits[0].region, "eu-central-1");
+        assert_eq!(circuits[0].status.state, CircuitState::Closed);
+        assert_eq!(circuits[1].region, "us-east-1");
+        assert_eq!(circuits[1].status.state, CircuitState::Open);
+        assert_eq!(circuits[2].endpoint, "mcp");
+    }
+
+    #[test]
+    fn test_proxy_for_prefers_credential_then_pool_then_global() {
+        let config = Config {
+            proxy_pools: [(
+                "egress".to_string(),
+                serde_json::from_value(serde_json::json!({"proxies": ["http://pool:8080"]}))
+                    .unwrap(),
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let creds = vec![
+            KiroCredentials {
+                proxy_url: Some("socks5://own:1080".to_string()),
+                proxy_pool: Some("egress".to_string()),
+                ..Default::default()
+            },
+            KiroCredentials {
+                proxy_pool: Some("egress".to_string()),
+                ..Default::default()
+            },
+            KiroCredentials {
+                proxy_pool: Some("missing".to_string()),
+                ..Default::default()
+            },
+        ];
+        let global = ProxyConfig::new("http://global:3128");
+        let manager =
+            MultiTokenManager::new(config, creds.clone(), Some(global), None, false).unwrap();
+
+        let url = |idx: usize| manager.proxy_for(idx as u64 + 1, &creds[idx]).unwrap().url;
+        assert_eq!(url(0), "socks5://own:1080");
+        assert_eq!(url(1), "http://pool:8080");
+        assert_eq!(url(2), "http://global:3128");
+    }
+
+    #[test]
+    fn test_delete_credential_evicts_unused_proxy_clients() {
+        let config = Config {
+            proxy_pools: [(
+                "egress".to_string(),
+                serde_json::from_value(serde_json::json!({"proxies": ["http://pool:8080"]}))
+                    .unwrap(),
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let creds = vec![
+            KiroCredentials {
+                proxy_url: Some("socks5://own:1080".to_string()),
+                ..Default::default()
+            },
+            KiroCredentials {
+                proxy_pool: Some("egress".to_string()),
+                ..Default::default()
+            },
+        ];
+        let manager = MultiTokenManager::new(config, creds.clone(), None, None, false).unwrap();
+        for (idx, cred) in creds.iter().enumerate() {
+            let proxy = manager.proxy_for(idx as u64 + 1, cred);
+            manager
+                .upstream_client(proxy.as_ref(), UpstreamEndpoint::Api)
+                .unwrap();
+        }
+        assert_eq!(manager.upstream_clients.len(), 2);
+
+        manager.set_disabled(1, true).unwrap();
+        manager.delete_credential(1).unwrap();
+        assert_eq!(manager.upstream_clients.len(), 1);
+
+        // 代理池出口在最后一个凭据删除后释放
+        manager.set_disabled(2, true).unwrap();
+        manager.delete_credential(2).unwrap();
+        assert_eq!(manager.upstream_clients.len(), 0);
+    }
+
     // ============ 凭据级 Region 优先级测试 ============
 
     /// 辅助函数：获取 OIDC 刷新使用的 region（用于测试）
@@ -1655,7 +2814,7 @@ mod tests {
     #[test]
     fn test_api_call_still_uses_config_region() {
         // 验证 API 调用（如 getUsageLimits）仍使用 config.region
-        // 这确保只有 OIDC 刷新使用凭据 region，API 调用行为不变
+        // 这确保只有 OIDC 刷新使用凭据 region，API 区域由 apiRegion 单独配置
         let mut config = Config::default();
         config.region = "us-west-2".to_string();
 
@@ -1663,12 +2822,19 @@ mod tests {
         credentials.region = Some("eu-west-1".to_string());
 
         // API 调用应使用 config.region，而非 credentials.region
-        let api_region = &config.region;
-        let api_host = format!("q.{}.amazonaws.com", api_region);
+        let api_region = region::primary_api_region(&credentials, &config);
+        let api_host = region::api_host(&config, &api_region);
 
         assert_eq!(api_host, "q.us-west-2.amazonaws.com");
         // 确认凭据 region 不影响 API 调用
-        assert_ne!(api_region, credentials.region.as_ref().unwrap());
+        assert_ne!(&api_region, credentials.region.as_ref().unwrap());
+
+        // 凭据 apiRegion 决定 API 区域
+        credentials.api_region = Some("eu-central-1".to_string());
+        assert_eq!(
+            region::primary_api_region(&credentials, &config),
+            "eu-central-1"
+        );
     }
 
     #[test]
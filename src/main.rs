@@ -1,21 +1,27 @@
 mod admin;
 mod admin_ui;
 mod anthropic;
+mod batch;
 mod common;
+mod gemini;
 mod http_client;
 mod kiro;
+mod mcp;
 mod model;
+mod ollama;
 mod openai;
 pub mod token;
 mod request_log;
+mod shutdown;
 
 use std::sync::Arc;
 
 use clap::Parser;
+use kiro::credential_store::{self, CredentialsKey};
 use kiro::model::credentials::{CredentialsConfig, KiroCredentials};
 use kiro::provider::KiroProvider;
 use kiro::token_manager::MultiTokenManager;
-use model::arg::Args;
+use model::arg::{Args, Command};
 use model::config::Config;
 
 #[tokio::main]
@@ -23,13 +29,16 @@ async fn main() {
     // 解析命令行参数
     let args = Args::parse();
 
-    // 初始化日志
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
+    // 初始化日志（MCP stdio 模式下 stdout 用于协议消息，日志输出到 stderr）
+    let subscriber = tracing_subscriber::fmt().with_env_filter(
+        tracing_subscriber::EnvFilter::from_default_env()
+            .add_directive(tracing::Level::INFO.into()),
+    );
+    if args.mcp_stdio {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
 
     // 加载配置
     let config_path = args
@@ -40,10 +49,37 @@ async fn main() {
         std::process::exit(1);
     });
 
+    // generate-key 子命令：生成新密钥后退出（此时密钥文件可能尚不存在）
+    if let Some(Command::GenerateKey { output }) = &args.command {
+        if let Err(e) = run_generate_key(output.as_deref()) {
+            tracing::error!("命令执行失败: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 解析凭据文件加密密钥（须在加载凭据前初始化）
+    let credentials_key = CredentialsKey::resolve(&config).unwrap_or_else(|e| {
+        tracing::error!("加载凭据密钥失败: {}", e);
+        std::process::exit(1);
+    });
+    credential_store::init_credentials_key(credentials_key.clone());
+
     // 加载凭证（支持单对象或数组格式）
     let credentials_path = args
         .credentials
         .unwrap_or_else(|| KiroCredentials::default_credentials_path().to_string());
+
+    // 密钥管理子命令：只操作凭据文件，无需加载凭据
+    if let Some(command @ (Command::EncryptCredentials | Command::RotateKey { .. })) = &args.command
+    {
+        if let Err(e) = run_key_command(command, &credentials_path, credentials_key.as_ref()) {
+            tracing::error!("命令执行失败: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let credentials_config = CredentialsConfig::load(&credentials_path).unwrap_or_else(|e| {
         tracing::error!("加载凭证失败: {}", e);
         std::process::exit(1);
@@ -60,12 +96,6 @@ async fn main() {
     let first_credentials = credentials_list.first().cloned().unwrap_or_default();
     tracing::debug!("主凭证: {:?}", first_credentials);
 
-    // 获取 API Key
-    let api_key = config.api_key.clone().unwrap_or_else(|| {
-        tracing::error!("配置文件中未设置 apiKey");
-        std::process::exit(1);
-    });
-
     // 构建代理配置
     let proxy_config = config.proxy_url.as_ref().map(|url| {
         let mut proxy = http_client::ProxyConfig::new(url);
@@ -92,7 +122,71 @@ async fn main() {
         std::process::exit(1);
     });
     let token_manager = Arc::new(token_manager);
-    let kiro_provider = KiroProvider::with_proxy(token_manager.clone(), proxy_config.clone());
+
+    // 子命令：执行完成后退出，不启动服务
+    if let Some(command) = args.command {
+        let result = match command {
+            Command::Login {
+                start_url,
+                region,
+                priority,
+            } => run_login(&token_manager, start_url, region, priority).await,
+            Command::Import { paths, priority } => {
+                run_import(&token_manager, paths, priority).await
+            }
+            Command::GenerateKey { .. } | Command::EncryptCredentials | Command::RotateKey { .. } => {
+                unreachable!("密钥管理子命令已在加载凭据前处理")
+            }
+        };
+        if let Err(e) = result {
+            tracing::error!("命令执行失败: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 获取 API Key
+    let api_key = config.api_key.clone().unwrap_or_else(|| {
+        tracing::error!("配置文件中未设置 apiKey");
+        std::process::exit(1);
+    });
+    let kiro_provider = KiroProvider::new(token_manager.clone());
+
+    // 代理池出口健康检查
+    token_manager.proxy_pools().spawn_health_checks();
+
+    // 额度用尽的凭据到达重置时间后自动恢复
+    kiro::token_manager::spawn_quota_recovery(
+        token_manager.clone(),
+        std::time::Duration::from_secs(config.quota_recovery_interval_secs.max(1)),
+    );
+
+    // 定期轮询凭据余额并发出低额度告警
+    if config.balance_monitor.interval_secs > 0 {
+        match kiro::balance_monitor::BalanceMonitor::new(token_manager.clone()) {
+            Ok(monitor) => {
+                kiro::balance_monitor::spawn_balance_monitor(
+                    Arc::new(monitor),
+                    std::time::Duration::from_secs(config.balance_monitor.interval_secs),
+                );
+            }
+            Err(e) => tracing::warn!("余额轮询初始化失败: {}", e),
+        }
+    }
+
+    // 初始化网页搜索后端
+    if let Err(e) =
+        anthropic::init_web_search(&config.web_search, proxy_config.as_ref(), config.tls_backend)
+    {
+        tracing::error!("初始化网页搜索后端失败: {}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) =
+        anthropic::init_web_fetch(&config.web_fetch, proxy_config.as_ref(), config.tls_backend)
+    {
+        tracing::error!("初始化网页抓取配置失败: {}", e);
+        std::process::exit(1);
+    }
 
     // 初始化 count_tokens 配置
     token::init_config(token::CountTokensConfig {
@@ -103,27 +197,77 @@ async fn main() {
         tls_backend: config.tls_backend,
     });
 
+    // MCP stdio 模式：直接在 stdin/stdout 上提供服务
+    if args.mcp_stdio {
+        let server = mcp::McpServer::new(kiro_provider);
+        if let Err(e) = mcp::serve_stdio(server).await {
+            tracing::error!("MCP stdio 服务异常退出: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // 创建请求日志记录器
     let request_logger = Arc::new(request_log::RequestLogger::new());
 
-    // 构建 Anthropic API 路由（从第一个凭据获取 profile_arn）
+    // 创建 Message Batches 管理器，并恢复上次未完成的批处理
+    let batch_manager = batch::BatchManager::new(
+        &config.batch_dir,
+        config.batch_concurrency,
+        anthropic::create_batch_executor(kiro_provider.clone()),
+    )
+    .unwrap_or_else(|e| {
+        tracing::error!("初始化批处理存储失败: {}", e);
+        std::process::exit(1);
+    });
+    batch_manager.resume();
+
+    // 构建 Anthropic API 路由
     let anthropic_app = anthropic::create_router_with_provider(
         &api_key,
         Some(kiro_provider.clone()),
-        first_credentials.profile_arn.clone(),
         Some(request_logger.clone()),
+        Some(batch_manager),
     );
 
     // 构建 OpenAI 兼容 API 路由
     let openai_app = openai::create_router_with_provider(
         &api_key,
-        Some(kiro_provider),
-        first_credentials.profile_arn.clone(),
+        Some(kiro_provider.clone()),
         Some(request_logger.clone()),
     );
 
-    // 合并 Anthropic 和 OpenAI 路由
-    let combined_app = anthropic_app.merge(openai_app);
+    // 构建 Gemini 兼容 API 路由
+    let gemini_app = gemini::create_router_with_provider(
+        &api_key,
+        Some(kiro_provider.clone()),
+        Some(request_logger.clone()),
+    );
+
+    // 构建 Ollama 兼容 API 路由
+    let ollama_app = ollama::create_router_with_provider(
+        &api_key,
+        config.ollama_allow_anonymous,
+        Some(kiro_provider.clone()),
+        Some(request_logger.clone()),
+    );
+
+    // 构建 MCP streamable HTTP 路由
+    let mcp_app = mcp::create_router(
+        &api_key,
+        mcp::McpServer::new(kiro_provider),
+    );
+
+    // 合并 Anthropic、OpenAI、Gemini、Ollama 和 MCP 路由
+    let combined_app = anthropic_app
+        .merge(openai_app)
+        .merge(gemini_app)
+        .merge(ollama_app)
+        .merge(mcp_app)
+        .layer(axum::middleware::from_fn(
+            kiro::concurrency::queue_position_middleware,
+        ))
+        .layer(axum::middleware::from_fn(kiro::hedging::api_key_middleware));
 
     // 构建 Admin API 路由（如果配置了非空的 admin_api_key）
     // 安全检查：空字符串被视为未配置，防止空 key 绕过认证
@@ -163,7 +307,20 @@ async fn main() {
     tracing::info!("  GET  /v1/models");
     tracing::info!("  POST /v1/messages");
     tracing::info!("  POST /v1/messages/count_tokens");
+    tracing::info!("  POST /v1/messages/batches");
+    tracing::info!("  GET  /v1/messages/batches");
+    tracing::info!("  GET  /v1/messages/batches/:batch_id");
+    tracing::info!("  POST /v1/messages/batches/:batch_id/cancel");
+    tracing::info!("  GET  /v1/messages/batches/:batch_id/results");
     tracing::info!("  POST /v1/chat/completions (OpenAI 兼容)");
+    tracing::info!("  GET  /v1beta/models (Gemini 兼容)");
+    tracing::info!("  POST /v1beta/models/:model:generateContent (Gemini 兼容)");
+    tracing::info!("  POST /v1beta/models/:model:streamGenerateContent (Gemini 兼容)");
+    tracing::info!("  POST /api/chat (Ollama 兼容)");
+    tracing::info!("  POST /api/generate (Ollama 兼容)");
+    tracing::info!("  GET  /api/tags (Ollama 兼容)");
+    tracing::info!("  POST /api/show (Ollama 兼容)");
+    tracing::info!("  POST /mcp (MCP streamable HTTP)");
     if admin_key_valid {
         tracing::info!("Admin API:");
         tracing::info!("  GET  /api/admin/credentials");
@@ -175,6 +332,150 @@ async fn main() {
         tracing::info!("  GET  /admin");
     }
 
+    // 优雅关闭：SIGTERM/SIGINT 后停止接收新请求，等待进行中的流式响应结束
+    let shutdown = shutdown::Shutdown::new();
+    shutdown.spawn_signal_handler();
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        shutdown.clone(),
+        shutdown::drain_middleware,
+    ));
+
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let server = axum::serve(listener, app).with_graceful_shutdown(shutdown.draining());
+    if let Err(e) = shutdown
+        .run(
+            server,
+            std::time::Duration::from_secs(config.shutdown_timeout_secs),
+        )
+        .await
+    {
+        tracing::error!("服务异常退出: {}", e);
+    }
+
+    // 退出前输出请求记录并回写凭据状态
+    request_logger.flush();
+    match token_manager.persist() {
+        Ok(true) => tracing::info!("已回写凭据状态"),
+        Ok(false) => {}
+        Err(e) => tracing::warn!("回写凭据状态失败: {}", e),
+    }
+    tracing::info!("服务已关闭");
+}
+
+/// 执行设备授权流程并将得到的凭据添加到 Token 管理器
+async fn run_login(
+    token_manager: &MultiTokenManager,
+    start_url: Option<String>,
+    region: Option<String>,
+    priority: u32,
+) -> anyhow::Result<()> {
+    let config = token_manager.config();
+    let region = region.unwrap_or_else(|| config.region.clone());
+    let start_url =
+        start_url.unwrap_or_else(|| kiro::device_auth::BUILDER_ID_START_URL.to_string());
+    let client = kiro::device_auth::DeviceAuthClient::new(config, region, token_manager.proxy())?;
+
+    let credentials =
+        kiro::device_auth::run_device_flow(&client, &start_url, priority, |session| {
+            let auth = &session.authorization;
+            println!("请在浏览器中打开以下地址完成授权：");
+            println!(
+                "  {}",
+                auth.verification_uri_complete
+                    .as_deref()
+                    .unwrap_or(&auth.verification_uri)
+            );
+            println!("验证码: {}", auth.user_code);
+        })
+        .await?;
+
+    let credential_id = token_manager.add_credential(credentials).await?;
+    println!("授权成功，已添加凭据 #{}", credential_id);
+    Ok(())
+}
+
+/// 从 Kiro IDE / AWS SSO 缓存文件导入凭据
+async fn run_import(
+    token_manager: &MultiTokenManager,
+    paths: Vec<std::path::PathBuf>,
+    priority: u32,
+) -> anyhow::Result<()> {
+    use kiro::sso_import;
+
+    let paths = if paths.is_empty() {
+        vec![
+            sso_import::default_cache_dir()
+                .ok_or_else(|| anyhow::anyhow!("无法确定用户主目录，请指定缓存路径"))?,
+        ]
+    } else {
+        paths
+    };
+
+    let (files, mut skipped) = sso_import::read_cache_files(&paths);
+    let (candidates, parse_skipped) = sso_import::parse_cache_files(&files, priority);
+    skipped.extend(parse_skipped);
+    let report = sso_import::import_candidates(token_manager, candidates).await;
+    skipped.extend(report.skipped);
+
+    for (source, id) in &report.imported {
+        println!("已导入凭据 #{}: {}", id, source);
+    }
+    for item in &skipped {
+        println!("已跳过 {}: {}", item.source, item.reason);
+    }
+    println!(
+        "导入完成：成功 {} 个，跳过 {} 个",
+        report.imported.len(),
+        skipped.len()
+    );
+    Ok(())
+}
+
+/// 生成凭据文件加密密钥：指定文件时写入文件，否则输出到标准输出
+fn run_generate_key(output: Option<&std::path::Path>) -> anyhow::Result<()> {
+    match output {
+        Some(path) => {
+            credential_store::write_new_key_file(path)?;
+            println!("已生成密钥文件: {}", path.display());
+            println!("请在 config.json 中设置 credentialsKeyFile 指向该文件");
+        }
+        None => println!("{}", CredentialsKey::generate().to_base64()),
+    }
+    Ok(())
+}
+
+/// 凭据文件密钥管理：加密明文文件、轮换密钥
+fn run_key_command(
+    command: &Command,
+    credentials_path: &str,
+    current_key: Option<&CredentialsKey>,
+) -> anyhow::Result<()> {
+    match command {
+        Command::EncryptCredentials => {
+            let key = current_key.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "未配置密钥，请设置 {} 或 credentialsKeyFile（可用 generate-key 生成）",
+                    credential_store::CREDENTIALS_KEY_ENV
+                )
+            })?;
+            credential_store::encrypt_file(credentials_path, key)?;
+            println!("凭据文件已加密: {}", credentials_path);
+        }
+        Command::RotateKey { new_key_file } => {
+            let new_key = if new_key_file.exists() {
+                CredentialsKey::from_file(new_key_file)?
+            } else {
+                credential_store::write_new_key_file(new_key_file)?
+            };
+            credential_store::rotate_file(credentials_path, current_key, &new_key)?;
+            println!("凭据文件已使用新密钥加密: {}", credentials_path);
+            println!(
+                "请将 credentialsKeyFile 改为 {}（或更新 {}）后重启服务",
+                new_key_file.display(),
+                credential_store::CREDENTIALS_KEY_ENV
+            );
+        }
+        _ => {}
+    }
+    Ok(())
 }
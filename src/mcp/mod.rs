@@ -0,0 +1,18 @@
+//! MCP 服务端模块
+//!
+//! 将 kiro-rs 作为 Model Context Protocol 工具提供方，支持 stdio 与 streamable HTTP 两种传输。
+//!
+//! # 工具
+//! - `ask_model` - 通过凭据池执行一次性补全
+//! - `web_search` - 调用 Kiro MCP 网页搜索
+//! - `credential_status` - 查看凭据池状态
+
+mod protocol;
+mod router;
+mod server;
+mod stdio;
+mod tools;
+
+pub use router::create_router;
+pub use server::McpServer;
+pub use stdio::serve_stdio;
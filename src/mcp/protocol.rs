@@ -0,0 +1,154 @@
+//! MCP JSON-RPC 2.0 消息类型
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 服务端首选的协议版本
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// 支持的协议版本（客户端请求的版本在列表中时原样返回）
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+// JSON-RPC 标准错误码
+pub const PARSE_ERROR: i32 = -32700;
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+
+/// JSON-RPC 请求或通知（无 id 时为通知）
+#[derive(Debug, Deserialize)]
+pub struct JsonRpcRequest {
+    #[serde(default)]
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Option<Value>,
+}
+
+impl JsonRpcRequest {
+    /// 是否为通知（不需要响应）
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+/// JSON-RPC 响应
+#[derive(Debug, Serialize)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+impl JsonRpcResponse {
+    /// 创建成功响应
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// 创建错误响应
+    pub fn error(id: Value, code: i32, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(JsonRpcError {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+/// JSON-RPC 错误
+#[derive(Debug, Serialize)]
+pub struct JsonRpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+/// tools/call 请求参数
+#[derive(Debug, Deserialize)]
+pub struct CallToolParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Option<Value>,
+}
+
+/// tools/call 结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    pub content: Vec<TextContent>,
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// 创建成功结果
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![TextContent::new(text)],
+            is_error: false,
+        }
+    }
+
+    /// 创建工具执行失败结果（按 MCP 约定放在 result 中，而非 JSON-RPC 错误）
+    pub fn error(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![TextContent::new(text)],
+            is_error: true,
+        }
+    }
+}
+
+/// 文本内容
+#[derive(Debug, Serialize)]
+pub struct TextContent {
+    #[serde(rename = "type")]
+    pub content_type: &'static str,
+    pub text: String,
+}
+
+impl TextContent {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            content_type: "text",
+            text: text.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_has_no_id() {
+        let req: JsonRpcRequest =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+                .unwrap();
+        assert!(req.is_notification());
+
+        let req: JsonRpcRequest =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).unwrap();
+        assert!(!req.is_notification());
+    }
+
+    #[test]
+    fn test_serialize_error_response() {
+        let resp = JsonRpcResponse::error(Value::from(1), METHOD_NOT_FOUND, "nope");
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!(json["error"]["code"], -32601);
+        assert!(json.get("result").is_none());
+    }
+}
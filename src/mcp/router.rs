@@ -0,0 +1,93 @@
+//! MCP streamable HTTP 传输
+//!
+//! 单一端点 `/mcp`：POST 接收 JSON-RPC 消息并直接返回 JSON 响应，
+//! 服务端不主动推送消息，因此 GET（SSE 流）返回 405
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{DefaultBodyLimit, State},
+    http::{Request, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::post,
+};
+use serde_json::json;
+
+use crate::common::auth;
+
+use super::server::McpServer;
+
+/// 请求体最大大小限制 (10MB)
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// 路由状态
+#[derive(Clone)]
+struct McpState {
+    api_key: String,
+    server: McpServer,
+}
+
+/// API Key 认证中间件
+async fn auth_middleware(
+    State(state): State<McpState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    match auth::extract_api_key(&request) {
+        Some(key) if auth::constant_time_eq(&key, &state.api_key) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32001, "message": "Invalid API key"}
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /mcp
+async fn post_mcp(State(state): State<McpState>, body: String) -> Response {
+    match state.server.handle_message(&body).await {
+        Some(response) => Json(response).into_response(),
+        // 只包含通知或响应时返回 202
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// GET / DELETE /mcp
+///
+/// 服务端无状态，不提供 SSE 推送流，也没有需要终止的会话
+async fn method_not_allowed() -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, "POST")]).into_response()
+}
+
+/// 创建 MCP streamable HTTP 路由
+///
+/// # 端点
+/// - `POST /mcp` - JSON-RPC 请求（支持批量）
+///
+/// # 认证
+/// 与其他 API 相同，支持 `x-api-key` header 和 `Authorization: Bearer <token>` header
+pub fn create_router(api_key: impl Into<String>, server: McpServer) -> Router {
+    let state = McpState {
+        api_key: api_key.into(),
+        server,
+    };
+
+    Router::new()
+        .route(
+            "/mcp",
+            post(post_mcp)
+                .get(method_not_allowed)
+                .delete(method_not_allowed),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+        .with_state(state)
+}
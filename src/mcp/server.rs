@@ -0,0 +1,265 @@
+//! MCP 服务端核心
+//!
+//! 与传输层无关：stdio 与 streamable HTTP 共用同一套 JSON-RPC 分发逻辑
+
+use std::sync::Arc;
+
+use serde_json::{Value, json};
+
+use crate::kiro::provider::KiroProvider;
+
+use super::protocol::{
+    CallToolParams, INVALID_PARAMS, INVALID_REQUEST, JsonRpcRequest, JsonRpcResponse,
+    LATEST_PROTOCOL_VERSION, METHOD_NOT_FOUND, PARSE_ERROR, SUPPORTED_PROTOCOL_VERSIONS,
+};
+use super::tools;
+
+/// 服务端名称（initialize 响应中的 serverInfo.name）
+const SERVER_NAME: &str = "kiro-rs";
+
+/// MCP 服务端
+#[derive(Clone)]
+pub struct McpServer {
+    provider: Arc<KiroProvider>,
+}
+
+impl McpServer {
+    pub fn new(provider: KiroProvider) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
+    pub fn provider(&self) -> &KiroProvider {
+        &self.provider
+    }
+
+    /// 处理一条原始 JSON-RPC 消息（单条或批量数组）
+    ///
+    /// 消息中只有通知时返回 None
+    pub async fn handle_message(&self, raw: &str) -> Option<Value> {
+        let value: Value = match serde_json::from_str(raw) {
+            Ok(v) => v,
+            Err(e) => {
+                return Some(to_value(JsonRpcResponse::error(
+                    Value::Null,
+                    PARSE_ERROR,
+                    format!("Parse error: {}", e),
+                )));
+            }
+        };
+
+        match value {
+            Value::Array(items) => {
+                if items.is_empty() {
+                    return Some(to_value(JsonRpcResponse::error(
+                        Value::Null,
+                        INVALID_REQUEST,
+                        "Empty batch",
+                    )));
+                }
+                let mut responses = Vec::new();
+                for item in items {
+                    if let Some(resp) = self.handle_value(item).await {
+                        responses.push(to_value(resp));
+                    }
+                }
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            other => self.handle_value(other).await.map(to_value),
+        }
+    }
+
+    async fn handle_value(&self, value: Value) -> Option<JsonRpcResponse> {
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        match serde_json::from_value::<JsonRpcRequest>(value) {
+            Ok(request) => self.handle_request(request).await,
+            Err(e) => Some(JsonRpcResponse::error(
+                id,
+                INVALID_REQUEST,
+                format!("Invalid request: {}", e),
+            )),
+        }
+    }
+
+    /// 处理单个请求，通知返回 None
+    async fn handle_request(&self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        if request.is_notification() {
+            tracing::debug!(method = %request.method, "收到 MCP 通知");
+            return None;
+        }
+
+        let id = request.id.clone().unwrap_or(Value::Null);
+        if request.jsonrpc != "2.0" {
+            return Some(JsonRpcResponse::error(
+                id,
+                INVALID_REQUEST,
+                "Invalid request: jsonrpc must be \"2.0\"",
+            ));
+        }
+        tracing::debug!(method = %request.method, "收到 MCP 请求");
+
+        let response = match request.method.as_str() {
+            "initialize" => JsonRpcResponse::success(id, initialize_result(request.params)),
+            "ping" => JsonRpcResponse::success(id, json!({})),
+            "tools/list" => {
+                JsonRpcResponse::success(id, json!({ "tools": tools::tool_definitions() }))
+            }
+            "tools/call" => self.call_tool(id, request.params).await,
+            other => {
+                JsonRpcResponse::error(id, METHOD_NOT_FOUND, format!("Method not found: {}", other))
+            }
+        };
+        Some(response)
+    }
+
+    async fn call_tool(&self, id: Value, params: Option<Value>) -> JsonRpcResponse {
+        let params: CallToolParams = match serde_json::from_value(params.unwrap_or(Value::Null)) {
+            Ok(p) => p,
+            Err(e) => {
+                return JsonRpcResponse::error(
+                    id,
+                    INVALID_PARAMS,
+                    format!("Invalid params: {}", e),
+                );
+            }
+        };
+
+        if !tools::is_known_tool(&params.name) {
+            return JsonRpcResponse::error(
+                id,
+                INVALID_PARAMS,
+                format!("Unknown tool: {}", params.name),
+            );
+        }
+
+        tracing::info!(tool = %params.name, "执行 MCP 工具调用");
+        let arguments = params.arguments.unwrap_or_else(|| json!({}));
+        let result = tools::call_tool(self, &params.name, arguments).await;
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap_or_default())
+    }
+}
+
+/// 构建 initialize 响应，协议版本按客户端请求协商
+fn initialize_result(params: Option<Value>) -> Value {
+    let requested = params
+        .as_ref()
+        .and_then(|p| p.get("protocolVersion"))
+        .and_then(|v| v.as_str());
+    let protocol_version = requested
+        .filter(|v| SUPPORTED_PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(LATEST_PROTOCOL_VERSION);
+
+    json!({
+        "protocolVersion": protocol_version,
+        "capabilities": {
+            "tools": { "listChanged": false }
+        },
+        "serverInfo": {
+            "name": SERVER_NAME,
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    })
+}
+
+fn to_value(response: JsonRpcResponse) -> Value {
+    serde_json::to_value(response).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::kiro::token_manager::MultiTokenManager;
+    use crate::model::config::Config;
+
+    fn create_test_server() -> McpServer {
+        let tm = MultiTokenManager::new(
+            Config::default(),
+            vec![KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        McpServer::new(KiroProvider::new(Arc::new(tm)))
+    }
+
+    #[tokio::test]
+    async fn test_handle_message_dispatch() {
+        let server = create_test_server();
+
+        // 通知不产生响应
+        let resp = server
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .await;
+        assert!(resp.is_none());
+
+        // 批量消息中只返回请求的响应
+        let resp = server
+            .handle_message(
+                r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},
+                    {"jsonrpc":"2.0","method":"notifications/initialized"},
+                    {"jsonrpc":"2.0","id":2,"method":"unknown"}]"#,
+            )
+            .await
+            .unwrap();
+        let items = resp.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["id"], 1);
+        assert_eq!(items[1]["error"]["code"], METHOD_NOT_FOUND);
+
+        let resp = server.handle_message("{not json").await.unwrap();
+        assert_eq!(resp["error"]["code"], PARSE_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_call_credential_status_and_unknown_tool() {
+        let server = create_test_server();
+
+        let resp = server
+            .handle_message(
+                r#"{"jsonrpc":"2.0","id":"a","method":"tools/call","params":{"name":"credential_status"}}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["result"]["isError"], false);
+        let text = resp["result"]["content"][0]["text"].as_str().unwrap();
+        let snapshot: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(snapshot["total"], 1);
+
+        let resp = server
+            .handle_message(
+                r#"{"jsonrpc":"2.0","id":"b","method":"tools/call","params":{"name":"rm_rf"}}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_initialize_negotiates_version() {
+        let result = initialize_result(Some(json!({"protocolVersion": "2024-11-05"})));
+        assert_eq!(result["protocolVersion"], "2024-11-05");
+
+        let result = initialize_result(Some(json!({"protocolVersion": "1999-01-01"})));
+        assert_eq!(result["protocolVersion"], LATEST_PROTOCOL_VERSION);
+        assert_eq!(result["serverInfo"]["name"], SERVER_NAME);
+    }
+
+    #[test]
+    fn test_tool_definitions_have_schemas() {
+        let tools = tools::tool_definitions();
+        let names: Vec<&str> = tools
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| {
+                assert_eq!(t["inputSchema"]["type"], "object");
+                t["name"].as_str().unwrap()
+            })
+            .collect();
+        assert_eq!(names, ["ask_model", "web_search", "credential_status"]);
+        assert!(names.iter().all(|n| tools::is_known_tool(n)));
+    }
+}
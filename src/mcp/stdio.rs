@@ -0,0 +1,31 @@
+//! MCP stdio 传输
+//!
+//! 按 MCP 规范，每条 JSON-RPC 消息占一行（换行分隔），stdout 只输出协议消息，日志写入 stderr
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use super::server::McpServer;
+
+/// 在 stdin / stdout 上运行 MCP 服务，直到 stdin 关闭
+pub async fn serve_stdio(server: McpServer) -> anyhow::Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    tracing::info!("MCP stdio 服务已启动");
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(response) = server.handle_message(&line).await {
+            let mut output = serde_json::to_vec(&response)?;
+            output.push(b'\n');
+            stdout.write_all(&output).await?;
+            stdout.flush().await?;
+        }
+    }
+
+    tracing::info!("stdin 已关闭，MCP stdio 服务退出");
+    Ok(())
+}
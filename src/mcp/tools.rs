@@ -0,0 +1,154 @@
+//! MCP 工具定义与实现
+//!
+//! - `ask_model`: 通过凭据池执行一次性补全
+//! - `web_search`: 调用 Kiro MCP 网页搜索
+//! - `credential_status`: 查看凭据池状态
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::anthropic::{execute_message_request, websearch};
+
+use super::protocol::CallToolResult;
+use super::server::McpServer;
+
+/// ask_model 未指定模型时使用的默认模型
+const DEFAULT_MODEL: &str = "claude-sonnet-4.5";
+
+/// ask_model 未指定 max_tokens 时的默认值
+const DEFAULT_MAX_TOKENS: i32 = 4096;
+
+/// 工具列表（tools/list 响应中的 tools 字段）
+pub fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "ask_model",
+            "description": "Send a one-shot prompt to a Claude model and return its text answer.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "prompt": {"type": "string", "description": "User prompt"},
+                    "system": {"type": "string", "description": "Optional system prompt"},
+                    "model": {
+                        "type": "string",
+                        "description": "Model name containing sonnet, opus or haiku (default: claude-sonnet-4.5)"
+                    },
+                    "max_tokens": {"type": "integer", "minimum": 1, "description": "Maximum output tokens"}
+                },
+                "required": ["prompt"]
+            }
+        },
+        {
+            "name": "web_search",
+            "description": "Search the web and return titles, URLs and snippets of matching pages.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Search query"}
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "credential_status",
+            "description": "Show the state of the proxy's credential pool (priority, disabled flag, failure count).",
+            "inputSchema": {
+                "type": "object",
+                "properties": {}
+            }
+        }
+    ])
+}
+
+/// 工具是否存在
+pub fn is_known_tool(name: &str) -> bool {
+    matches!(name, "ask_model" | "web_search" | "credential_status")
+}
+
+/// 执行工具调用
+pub async fn call_tool(server: &McpServer, name: &str, arguments: Value) -> CallToolResult {
+    let result = match name {
+        "ask_model" => ask_model(server, arguments).await,
+        "web_search" => web_search(server, arguments).await,
+        "credential_status" => credential_status(server),
+        other => Err(format!("未知工具: {}", other)),
+    };
+
+    result.unwrap_or_else(|e| {
+        tracing::warn!(tool = %name, "MCP 工具执行失败: {}", e);
+        CallToolResult::error(e)
+    })
+}
+
+fn parse_arguments<T: for<'de> Deserialize<'de>>(arguments: Value) -> Result<T, String> {
+    serde_json::from_value(arguments).map_err(|e| format!("参数无效: {}", e))
+}
+
+#[derive(Debug, Deserialize)]
+struct AskModelArguments {
+    prompt: String,
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    max_tokens: Option<i32>,
+}
+
+async fn ask_model(server: &McpServer, arguments: Value) -> Result<CallToolResult, String> {
+    let args: AskModelArguments = parse_arguments(arguments)?;
+
+    let mut params = json!({
+        "model": args.model.as_deref().unwrap_or(DEFAULT_MODEL),
+        "max_tokens": args.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": [{"role": "user", "content": args.prompt}]
+    });
+    if let Some(system) = args.system.filter(|s| !s.is_empty()) {
+        params["system"] = Value::String(system);
+    }
+
+    let message = execute_message_request(server.provider(), params)
+        .await
+        .map_err(|e| e.message)?;
+
+    let text = message["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|block| block["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    Ok(CallToolResult::text(text))
+}
+
+#[derive(Debug, Deserialize)]
+struct WebSearchArguments {
+    query: String,
+}
+
+async fn web_search(server: &McpServer, arguments: Value) -> Result<CallToolResult, String> {
+    let args: WebSearchArguments = parse_arguments(arguments)?;
+    if args.query.trim().is_empty() {
+        return Err("query 不能为空".to_string());
+    }
+
+    let results = websearch::search(server.provider(), &args.query)
+        .await
+        .map_err(|e| format!("搜索失败: {}", e))?;
+
+    let results: Vec<Value> = results
+        .map(|r| r.results)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| json!({"title": r.title, "url": r.url, "snippet": r.snippet}))
+        .collect();
+    let text = serde_json::to_string_pretty(&json!({"query": args.query, "results": results}))
+        .map_err(|e| e.to_string())?;
+    Ok(CallToolResult::text(text))
+}
+
+fn credential_status(server: &McpServer) -> Result<CallToolResult, String> {
+    let snapshot = server.provider().token_manager().snapshot();
+    let text = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    Ok(CallToolResult::text(text))
+}
@@ -1,4 +1,6 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
 
 /// Anthropic <-> Kiro API 客户端
 #[derive(Parser, Debug)]
@@ -11,4 +13,58 @@ pub struct Args {
     /// 凭证文件路径
     #[arg(long)]
     pub credentials: Option<String>,
+
+    /// 以 MCP stdio 模式运行（通过 stdin/stdout 提供 MCP 工具，不启动 HTTP 服务）
+    #[arg(long)]
+    pub mcp_stdio: bool,
+
+    /// 子命令（未指定时启动服务）
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// 子命令
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 通过 Builder ID / IAM Identity Center 设备授权添加凭据
+    Login {
+        /// IAM Identity Center 起始 URL（未指定时使用 Builder ID）
+        #[arg(long)]
+        start_url: Option<String>,
+
+        /// OIDC Region（未指定时使用配置文件中的 region）
+        #[arg(long)]
+        region: Option<String>,
+
+        /// 新凭据优先级
+        #[arg(long, default_value_t = 0)]
+        priority: u32,
+    },
+
+    /// 生成凭据文件加密密钥
+    GenerateKey {
+        /// 写入的密钥文件（未指定时输出到标准输出）
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// 使用当前密钥加密明文凭据文件
+    EncryptCredentials,
+
+    /// 使用新密钥重新加密凭据文件
+    RotateKey {
+        /// 新密钥文件（不存在时自动生成）
+        #[arg(long)]
+        new_key_file: PathBuf,
+    },
+
+    /// 从 Kiro IDE / AWS SSO 缓存文件导入凭据
+    Import {
+        /// 缓存文件或目录（未指定时扫描 ~/.aws/sso/cache）
+        paths: Vec<PathBuf>,
+
+        /// 导入凭据的优先级
+        #[arg(long, default_value_t = 0)]
+        priority: u32,
+    },
 }
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum TlsBackend {
     Rustls,
@@ -15,6 +16,599 @@ impl Default for TlsBackend {
     }
 }
 
+/// 网页搜索后端类型
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebSearchBackendKind {
+    /// Kiro MCP 网页搜索（消耗凭据额度）
+    #[default]
+    Kiro,
+    /// 通用 JSON HTTP 搜索服务（SearXNG 或自建服务）
+    Http,
+    /// 本地 JSON 文件中的固定结果（用于测试）
+    Fixture,
+}
+
+/// 网页搜索配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSearchConfig {
+    /// 搜索后端
+    #[serde(default)]
+    pub backend: WebSearchBackendKind,
+
+    /// 单次搜索超时（秒），未设置时使用后端默认值
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// 结果缓存有效期（秒），0 表示不缓存
+    #[serde(default = "default_web_search_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+
+    /// 结果缓存最大条目数
+    #[serde(default = "default_web_search_cache_capacity")]
+    pub cache_capacity: usize,
+
+    /// `http` 后端配置
+    #[serde(default)]
+    pub http: Option<HttpSearchConfig>,
+
+    /// `fixture` 后端的结果文件路径
+    #[serde(default)]
+    pub fixture_path: Option<String>,
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            backend: WebSearchBackendKind::default(),
+            timeout_secs: None,
+            cache_ttl_secs: default_web_search_cache_ttl_secs(),
+            cache_capacity: default_web_search_cache_capacity(),
+            http: None,
+            fixture_path: None,
+        }
+    }
+}
+
+/// 通用 JSON HTTP 搜索后端配置
+///
+/// 默认字段名与 SearXNG 的 `format=json` 响应一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpSearchConfig {
+    /// 搜索地址，如 `http://127.0.0.1:8888/search?format=json`
+    pub url: String,
+
+    /// 查询参数名
+    #[serde(default = "default_http_search_query_param")]
+    pub query_param: String,
+
+    /// Bearer 认证密钥（可选）
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// 响应中结果数组的字段名
+    #[serde(default = "default_http_search_results_field")]
+    pub results_field: String,
+
+    /// 结果标题字段名
+    #[serde(default = "default_http_search_title_field")]
+    pub title_field: String,
+
+    /// 结果 URL 字段名
+    #[serde(default = "default_http_search_url_field")]
+    pub url_field: String,
+
+    /// 结果摘要字段名
+    #[serde(default = "default_http_search_snippet_field")]
+    pub snippet_field: String,
+
+    /// 最多保留的结果数
+    #[serde(default = "default_http_search_max_results")]
+    pub max_results: usize,
+
+    /// 是否经由全局 HTTP 代理访问
+    #[serde(default)]
+    pub use_proxy: bool,
+}
+
+/// 网页抓取（web_fetch 服务端工具）配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebFetchConfig {
+    /// 允许抓取的域名（含子域名），为空表示不限制
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+
+    /// 禁止抓取的域名（含子域名），优先于 allowedDomains
+    #[serde(default)]
+    pub blocked_domains: Vec<String>,
+
+    /// 响应体最大字节数，超出部分截断
+    #[serde(default = "default_web_fetch_max_bytes")]
+    pub max_bytes: usize,
+
+    /// 允许的 Content-Type（不含参数）
+    #[serde(default = "default_web_fetch_content_types")]
+    pub allowed_content_types: Vec<String>,
+
+    /// 单次抓取超时（秒）
+    #[serde(default = "default_web_fetch_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// 是否经由全局 HTTP 代理抓取
+    #[serde(default = "default_true")]
+    pub use_proxy: bool,
+
+    /// 是否允许抓取 localhost 与内网地址
+    #[serde(default)]
+    pub allow_private_hosts: bool,
+}
+
+impl Default for WebFetchConfig {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            blocked_domains: Vec::new(),
+            max_bytes: default_web_fetch_max_bytes(),
+            allowed_content_types: default_web_fetch_content_types(),
+            timeout_secs: default_web_fetch_timeout_secs(),
+            use_proxy: true,
+            allow_private_hosts: false,
+        }
+    }
+}
+
+/// 上游连接复用与超时配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamHttpConfig {
+    /// 每个主机保留的最大空闲连接数
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+
+    /// 空闲连接保留时间（秒）
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+
+    /// 是否允许通过 ALPN 协商 HTTP/2（仅对启用连接复用的端点生效）
+    #[serde(default)]
+    pub http2: bool,
+
+    /// 建立连接超时（秒）
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+
+    /// 发出请求到收到响应头的超时（秒）
+    #[serde(default = "default_first_byte_timeout_secs")]
+    pub first_byte_timeout_secs: u64,
+
+    /// 两次读取之间的最长间隔（秒），用于检测卡住的流式响应
+    #[serde(default = "default_stream_idle_timeout_secs")]
+    pub stream_idle_timeout_secs: u64,
+
+    /// 按端点开关连接复用，关闭的端点每次新建连接并发送 `Connection: close`
+    #[serde(default)]
+    pub keep_alive: KeepAliveEndpoints,
+}
+
+impl Default for UpstreamHttpConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            http2: false,
+            connect_timeout_secs: default_connect_timeout_secs(),
+            first_byte_timeout_secs: default_first_byte_timeout_secs(),
+            stream_idle_timeout_secs: default_stream_idle_timeout_secs(),
+            keep_alive: KeepAliveEndpoints::default(),
+        }
+    }
+}
+
+/// 各上游端点是否复用连接
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct KeepAliveEndpoints {
+    /// generateAssistantResponse
+    #[serde(default = "default_true")]
+    pub api: bool,
+
+    /// MCP（网页搜索）
+    #[serde(default = "default_true")]
+    pub mcp: bool,
+
+    /// Token 刷新
+    #[serde(default = "default_true")]
+    pub token_refresh: bool,
+
+    /// 使用额度查询
+    #[serde(default = "default_true")]
+    pub usage_limits: bool,
+}
+
+impl Default for KeepAliveEndpoints {
+    fn default() -> Self {
+        Self {
+            api: true,
+            mcp: true,
+            token_refresh: true,
+            usage_limits: true,
+        }
+    }
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    32
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_first_byte_timeout_secs() -> u64 {
+    120
+}
+
+fn default_stream_idle_timeout_secs() -> u64 {
+    300
+}
+
+/// 上游错误的处理动作
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RetryAction {
+    /// 退避后重试，不计入凭据失败（瞬态错误）
+    Retry,
+    /// 计入凭据失败次数并切换到其他凭据
+    Failover,
+    /// 立即禁用当前凭据并切换
+    Disable,
+    /// 视为额度用尽：禁用当前凭据并切换，额度重置后自动恢复
+    QuotaExhausted,
+    /// 直接返回错误
+    FailFast,
+}
+
+/// 按响应体内容匹配的错误规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BodyRule {
+    /// 仅匹配该状态码（可选，不填匹配所有失败状态码）
+    #[serde(default)]
+    pub status: Option<u16>,
+
+    /// 响应体包含该字符串时命中
+    pub contains: String,
+
+    /// 命中后的处理动作
+    pub action: RetryAction,
+}
+
+/// 上游请求重试与故障转移策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicyConfig {
+    /// 每个凭据的最大尝试次数（总次数 = min(凭据数 × 该值, maxTotalRetries)）
+    #[serde(default = "default_max_retries_per_credential")]
+    pub max_retries_per_credential: usize,
+
+    /// 单个请求的总尝试次数上限
+    #[serde(default = "default_max_total_retries")]
+    pub max_total_retries: usize,
+
+    /// 凭据连续失败多少次后禁用
+    #[serde(default = "default_max_failures_per_credential")]
+    pub max_failures_per_credential: u32,
+
+    /// 指数退避初始间隔（毫秒）
+    #[serde(default = "default_backoff_base_ms")]
+    pub backoff_base_ms: u64,
+
+    /// 指数退避最大间隔（毫秒）
+    #[serde(default = "default_backoff_max_ms")]
+    pub backoff_max_ms: u64,
+
+    /// 是否遵循上游 `Retry-After` 响应头
+    #[serde(default = "default_true")]
+    pub honor_retry_after: bool,
+
+    /// `Retry-After` 等待时间上限（秒）
+    #[serde(default = "default_max_retry_after_secs")]
+    pub max_retry_after_secs: u64,
+
+    /// 单个请求（含所有重试）的整体截止时间（秒），0 表示不限制
+    #[serde(default)]
+    pub deadline_secs: u64,
+
+    /// 按状态码覆盖处理动作，键为具体状态码（如 "429"）或状态码类别（如 "5xx"）
+    #[serde(default)]
+    pub status_actions: HashMap<String, RetryAction>,
+
+    /// 按响应体匹配的规则，优先于状态码规则，按顺序取第一条命中的规则
+    #[serde(default = "default_body_rules")]
+    pub body_rules: Vec<BodyRule>,
+}
+
+impl Default for RetryPolicyConfig {
+    fn default() -> Self {
+        Self {
+            max_retries_per_credential: default_max_retries_per_credential(),
+            max_total_retries: default_max_total_retries(),
+            max_failures_per_credential: default_max_failures_per_credential(),
+            backoff_base_ms: default_backoff_base_ms(),
+            backoff_max_ms: default_backoff_max_ms(),
+            honor_retry_after: true,
+            max_retry_after_secs: default_max_retry_after_secs(),
+            deadline_secs: 0,
+            status_actions: HashMap::new(),
+            body_rules: default_body_rules(),
+        }
+    }
+}
+
+fn default_max_retries_per_credential() -> usize {
+    3
+}
+
+fn default_max_total_retries() -> usize {
+    9
+}
+
+fn default_max_failures_per_credential() -> u32 {
+    3
+}
+
+fn default_backoff_base_ms() -> u64 {
+    200
+}
+
+fn default_backoff_max_ms() -> u64 {
+    2_000
+}
+
+fn default_max_retry_after_secs() -> u64 {
+    30
+}
+
+fn default_body_rules() -> Vec<BodyRule> {
+    vec![BodyRule {
+        status: Some(402),
+        contains: "MONTHLY_REQUEST_COUNT".to_string(),
+        action: RetryAction::QuotaExhausted,
+    }]
+}
+
+/// 熔断器配置（按凭据与上游端点分别统计）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerConfig {
+    /// 是否启用熔断
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// 错误率统计的滑动窗口（秒）
+    #[serde(default = "default_circuit_window_secs")]
+    pub window_secs: u64,
+
+    /// 窗口内请求数达到该值后才计算错误率
+    #[serde(default = "default_circuit_min_requests")]
+    pub min_requests: u32,
+
+    /// 错误率（百分比）达到该值时打开熔断
+    #[serde(default = "default_circuit_failure_rate_percent")]
+    pub failure_rate_percent: f64,
+
+    /// 熔断打开持续时间（秒），之后进入半开状态放行探测请求
+    #[serde(default = "default_circuit_open_secs")]
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: default_circuit_window_secs(),
+            min_requests: default_circuit_min_requests(),
+            failure_rate_percent: default_circuit_failure_rate_percent(),
+            open_secs: default_circuit_open_secs(),
+        }
+    }
+}
+
+fn default_circuit_window_secs() -> u64 {
+    60
+}
+
+fn default_circuit_min_requests() -> u32 {
+    10
+}
+
+fn default_circuit_failure_rate_percent() -> f64 {
+    50.0
+}
+
+fn default_circuit_open_secs() -> u64 {
+    30
+}
+
+/// 并发限制与排队配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyConfig {
+    /// 全局最大并发上游请求数（含进行中的流式响应），0 表示不限制
+    #[serde(default)]
+    pub max_concurrency: usize,
+
+    /// 槽位占满时最多排队的请求数，超出直接拒绝
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+
+    /// 排队等待超时（秒）
+    #[serde(default = "default_queue_timeout_secs")]
+    pub queue_timeout_secs: u64,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 0,
+            queue_size: default_queue_size(),
+            queue_timeout_secs: default_queue_timeout_secs(),
+        }
+    }
+}
+
+fn default_queue_size() -> usize {
+    100
+}
+
+fn default_queue_timeout_secs() -> u64 {
+    60
+}
+
+/// 对冲请求配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgingConfig {
+    /// 是否启用（仅作用于流式 API 请求）
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 首个 assistantResponseEvent 未到达时，发出对冲请求前的等待时间（毫秒）
+    #[serde(default = "default_hedging_delay_ms")]
+    pub delay_ms: u64,
+
+    /// 每个 API Key 每分钟最多发出的对冲请求数，0 表示不限制
+    #[serde(default = "default_hedging_max_per_minute")]
+    pub max_per_minute: usize,
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay_ms: default_hedging_delay_ms(),
+            max_per_minute: default_hedging_max_per_minute(),
+        }
+    }
+}
+
+fn default_hedging_delay_ms() -> u64 {
+    2000
+}
+
+fn default_hedging_max_per_minute() -> usize {
+    10
+}
+
+/// 请求头模板：按顺序发送的 [名称, 值] 列表
+///
+/// 值支持占位符 `{kiroVersion}`、`{machineId}`、`{systemVersion}`、`{nodeVersion}`、
+/// `{host}`、`{invocationId}`
+pub type HeaderTemplates = Vec<(String, String)>;
+
+/// 请求头配置（客户端指纹）
+///
+/// 定义各类上游请求的完整请求头（`Authorization` 与 `Connection: close` 由程序追加），
+/// 未配置的请求类型沿用内置配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderProfile {
+    /// generateAssistantResponse 请求
+    #[serde(default)]
+    pub api: Option<HeaderTemplates>,
+
+    /// MCP 请求
+    #[serde(default)]
+    pub mcp: Option<HeaderTemplates>,
+
+    /// Social Token 刷新
+    #[serde(default)]
+    pub social_refresh: Option<HeaderTemplates>,
+
+    /// IdC Token 刷新
+    #[serde(default)]
+    pub idc_refresh: Option<HeaderTemplates>,
+
+    /// getUsageLimits 请求
+    #[serde(default)]
+    pub usage_limits: Option<HeaderTemplates>,
+}
+
+/// 代理池配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyPoolConfig {
+    /// 代理地址列表，支持 http/https/socks5
+    pub proxies: Vec<String>,
+
+    /// 代理认证用户名（可选，池内共用）
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// 代理认证密码（可选，池内共用）
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// 健康检查地址（可选，默认为当前 region 的 Kiro API 地址，收到任意 HTTP 响应即视为健康）
+    #[serde(default)]
+    pub health_check_url: Option<String>,
+
+    /// 健康检查间隔（秒）
+    #[serde(default = "default_proxy_health_check_interval_secs")]
+    pub health_check_interval_secs: u64,
+}
+
+fn default_proxy_health_check_interval_secs() -> u64 {
+    60
+}
+
+/// 余额轮询与低额度告警配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceMonitorConfig {
+    /// 轮询所有凭据余额的间隔（秒），0 表示不轮询
+    #[serde(default)]
+    pub interval_secs: u64,
+
+    /// 剩余额度百分比低于该值时告警，并在选择凭据时降低其优先级
+    #[serde(default = "default_low_quota_percent")]
+    pub low_quota_percent: f64,
+
+    /// 免费试用剩余天数低于该值时告警
+    #[serde(default = "default_trial_expiry_warning_days")]
+    pub trial_expiry_warning_days: u64,
+
+    /// 告警 webhook 地址（可选，以 POST JSON 推送）
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+impl Default for BalanceMonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            low_quota_percent: default_low_quota_percent(),
+            trial_expiry_warning_days: default_trial_expiry_warning_days(),
+            webhook_url: None,
+        }
+    }
+}
+
+fn default_low_quota_percent() -> f64 {
+    10.0
+}
+
+fn default_trial_expiry_warning_days() -> u64 {
+    3
+}
+
 /// KNA 应用配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -28,6 +622,16 @@ pub struct Config {
     #[serde(default = "default_region")]
     pub region: String,
 
+    /// API 区域故障转移顺序（可选，为空时只使用 region）
+    /// 凭据配置了 apiRegion 时优先使用，其后按此顺序在区域性 5xx/超时时切换
+    #[serde(default)]
+    pub api_regions: Vec<String>,
+
+    /// 按区域覆盖 API 地址（可选，如 {"us-east-1": "http://127.0.0.1:8080"}）
+    /// 默认为 https://q.{region}.amazonaws.com，可指向本地替身服务做测试
+    #[serde(default)]
+    pub api_endpoints: HashMap<String, String>,
+
     #[serde(default = "default_kiro_version")]
     pub kiro_version: String,
 
@@ -43,6 +647,20 @@ pub struct Config {
     #[serde(default = "default_node_version")]
     pub node_version: String,
 
+    /// 默认请求头配置名称（可选，凭据可通过 `headerProfile` 单独指定）
+    /// 未配置或找不到时使用内置配置
+    #[serde(default)]
+    pub header_profile: Option<String>,
+
+    /// 命名请求头配置（名称 -> 配置）
+    #[serde(default)]
+    pub header_profiles: HashMap<String, HeaderProfile>,
+
+    /// 请求头配置文件路径（可选，内容为 名称 -> 配置 的 JSON 对象）
+    /// 与 `headerProfiles` 合并，同名时以 config.json 中的为准
+    #[serde(default)]
+    pub header_profiles_file: Option<String>,
+
     #[serde(default = "default_tls_backend")]
     pub tls_backend: TlsBackend,
 
@@ -71,9 +689,80 @@ pub struct Config {
     #[serde(default)]
     pub proxy_password: Option<String>,
 
+    /// 上游连接复用与超时配置
+    #[serde(default)]
+    pub upstream_http: UpstreamHttpConfig,
+
+    /// 上游请求重试与故障转移策略
+    #[serde(default)]
+    pub retry_policy: RetryPolicyConfig,
+
+    /// 熔断器配置
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    /// 并发限制与排队配置（凭据级上限见凭据的 `maxConcurrency`）
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+
+    /// 对冲请求配置（首个事件过慢时改用另一个凭据并行请求）
+    #[serde(default)]
+    pub hedging: HedgingConfig,
+
+    /// 命名代理池（凭据通过 `proxyPool` 引用，按凭据粘性分配出口）
+    #[serde(default)]
+    pub proxy_pools: HashMap<String, ProxyPoolConfig>,
+
     /// Admin API 密钥（可选，启用 Admin API 功能）
     #[serde(default)]
     pub admin_api_key: Option<String>,
+
+    /// 凭据文件加密密钥文件路径（可选，环境变量 KIRO_CREDENTIALS_KEY 优先）
+    /// 配置密钥后凭据文件以 AES-256-GCM 加密存储
+    #[serde(default)]
+    pub credentials_key_file: Option<String>,
+
+    /// 凭据文件回写时保留的轮转备份数量（`<path>.bak.1` 为最新，0 表示不备份）
+    #[serde(default = "default_credentials_backup_count")]
+    pub credentials_backup_count: usize,
+
+    /// 额度用尽凭据的恢复检查间隔（秒），到达 nextDateReset 后自动重新启用
+    #[serde(default = "default_quota_recovery_interval_secs")]
+    pub quota_recovery_interval_secs: u64,
+
+    /// 收到 SIGTERM/SIGINT 后等待进行中流式响应结束的最长时间（秒）
+    /// 超时后向仍未结束的流发送错误事件并退出
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// 余额轮询与低额度告警配置
+    #[serde(default)]
+    pub balance_monitor: BalanceMonitorConfig,
+
+    /// AWS SSO OIDC 服务地址覆盖（可选，默认 https://oidc.{region}.amazonaws.com）
+    /// 用于 IdC Token 刷新与设备授权，可指向本地替身服务做测试
+    #[serde(default)]
+    pub oidc_endpoint: Option<String>,
+
+    /// Message Batches 存储目录（批处理任务落盘位置，重启后可恢复）
+    #[serde(default = "default_batch_dir")]
+    pub batch_dir: String,
+
+    /// Message Batches 全局并发上限（同时执行的批处理请求数）
+    #[serde(default = "default_batch_concurrency")]
+    pub batch_concurrency: usize,
+
+    /// 是否允许 Ollama 兼容接口免认证访问（Ollama 客户端通常无法配置 API Key）
+    #[serde(default)]
+    pub ollama_allow_anonymous: bool,
+
+    /// 网页搜索后端配置
+    #[serde(default)]
+    pub web_search: WebSearchConfig,
+
+    /// 网页抓取配置
+    #[serde(default)]
+    pub web_fetch: WebFetchConfig,
 }
 
 fn default_host() -> String {
@@ -105,21 +794,105 @@ fn default_count_tokens_auth_type() -> String {
     "x-api-key".to_string()
 }
 
+fn default_credentials_backup_count() -> usize {
+    3
+}
+
+fn default_quota_recovery_interval_secs() -> u64 {
+    60
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
 fn default_tls_backend() -> TlsBackend {
     TlsBackend::Rustls
 }
 
+fn default_batch_dir() -> String {
+    "batches".to_string()
+}
+
+fn default_batch_concurrency() -> usize {
+    4
+}
+
+fn default_web_search_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_web_search_cache_capacity() -> usize {
+    256
+}
+
+fn default_http_search_query_param() -> String {
+    "q".to_string()
+}
+
+fn default_http_search_results_field() -> String {
+    "results".to_string()
+}
+
+fn default_http_search_title_field() -> String {
+    "title".to_string()
+}
+
+fn default_http_search_url_field() -> String {
+    "url".to_string()
+}
+
+fn default_http_search_snippet_field() -> String {
+    "content".to_string()
+}
+
+fn default_http_search_max_results() -> usize {
+    10
+}
+
+fn default_web_fetch_max_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_web_fetch_content_types() -> Vec<String> {
+    [
+        "text/html",
+        "application/xhtml+xml",
+        "text/plain",
+        "text/markdown",
+        "application/json",
+        "application/xml",
+        "text/xml",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+fn default_web_fetch_timeout_secs() -> u64 {
+    20
+}
+
+fn default_true() -> bool {
+    true
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             host: default_host(),
             port: default_port(),
             region: default_region(),
+            api_regions: Vec::new(),
+            api_endpoints: HashMap::new(),
             kiro_version: default_kiro_version(),
             machine_id: None,
             api_key: None,
             system_version: default_system_version(),
             node_version: default_node_version(),
+            header_profile: None,
+            header_profiles: HashMap::new(),
+            header_profiles_file: None,
             tls_backend: default_tls_backend(),
             count_tokens_api_url: None,
             count_tokens_api_key: None,
@@ -127,7 +900,24 @@ impl Default for Config {
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            upstream_http: UpstreamHttpConfig::default(),
+            retry_policy: RetryPolicyConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            concurrency: ConcurrencyConfig::default(),
+            hedging: HedgingConfig::default(),
+            proxy_pools: HashMap::new(),
             admin_api_key: None,
+            credentials_key_file: None,
+            credentials_backup_count: default_credentials_backup_count(),
+            quota_recovery_interval_secs: default_quota_recovery_interval_secs(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            balance_monitor: BalanceMonitorConfig::default(),
+            oidc_endpoint: None,
+            batch_dir: default_batch_dir(),
+            batch_concurrency: default_batch_concurrency(),
+            ollama_allow_anonymous: false,
+            web_search: WebSearchConfig::default(),
+            web_fetch: WebFetchConfig::default(),
         }
     }
 }
@@ -147,7 +937,22 @@ impl Config {
         }
 
         let content = fs::read_to_string(path)?;
-        let config: Config = serde_json::from_str(&content)?;
+        let mut config: Config = serde_json::from_str(&content)?;
+
+        if let Some(file) = &config.header_profiles_file {
+            let content = fs::read_to_string(file)
+                .map_err(|e| anyhow::anyhow!("读取请求头配置文件 {} 失败: {}", file, e))?;
+            let profiles: HashMap<String, HeaderProfile> = serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("解析请求头配置文件 {} 失败: {}", file, e))?;
+            for (name, profile) in profiles {
+                config.header_profiles.entry(name).or_insert(profile);
+            }
+        }
+        if let Some(name) = &config.header_profile
+            && !config.header_profiles.contains_key(name)
+        {
+            anyhow::bail!("headerProfile 引用的请求头配置不存在: {}", name);
+        }
         Ok(config)
     }
 }
@@ -0,0 +1,534 @@
+//! Ollama → Kiro 协议转换器
+//!
+//! 负责将 Ollama `/api/chat`、`/api/generate` 请求格式转换为 Kiro API 请求格式
+
+use std::collections::{HashSet, VecDeque};
+
+use uuid::Uuid;
+
+use crate::kiro::model::requests::conversation::{
+    AssistantMessage, ConversationState, CurrentMessage, HistoryAssistantMessage,
+    HistoryUserMessage, KiroImage, Message, UserInputMessage, UserInputMessageContext, UserMessage,
+};
+use crate::kiro::model::requests::tool::{
+    InputSchema, Tool, ToolResult, ToolSpecification, ToolUseEntry,
+};
+
+use super::types::{ChatMessage, ChatRequest, GenerateRequest};
+
+/// 对外暴露的模型列表：(Ollama 模型名, Kiro 模型 ID)
+pub const MODELS: &[(&str, &str)] = &[
+    ("claude-sonnet-4.5:latest", "claude-sonnet-4.5"),
+    ("claude-opus-4.5:latest", "claude-opus-4.5"),
+    ("claude-haiku-4.5:latest", "claude-haiku-4.5"),
+];
+
+/// 模型映射：将模型名映射到 Kiro 模型 ID
+///
+/// 按 sonnet / opus / haiku 关键字匹配，其他模型名不支持
+pub fn map_model(model: &str) -> Option<String> {
+    let model_lower = model.to_lowercase();
+
+    if model_lower.contains("sonnet") {
+        Some("claude-sonnet-4.5".to_string())
+    } else if model_lower.contains("opus") {
+        Some("claude-opus-4.5".to_string())
+    } else if model_lower.contains("haiku") {
+        Some("claude-haiku-4.5".to_string())
+    } else {
+        None
+    }
+}
+
+/// 转换结果
+#[derive(Debug)]
+pub struct ConversionResult {
+    /// 转换后的 Kiro 请求
+    pub conversation_state: ConversationState,
+}
+
+/// 转换错误
+#[derive(Debug)]
+pub enum ConversionError {
+    UnsupportedModel(String),
+    EmptyMessages,
+    InvalidImage,
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::UnsupportedModel(model) => write!(f, "model '{}' not found", model),
+            ConversionError::EmptyMessages => write!(f, "消息列表为空"),
+            ConversionError::InvalidImage => write!(f, "无法识别的图片格式"),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// 将 /api/chat 请求转换为 Kiro 请求
+pub fn convert_chat_request(req: &ChatRequest) -> Result<ConversionResult, ConversionError> {
+    convert_messages(&req.model, &req.messages, req.tools.as_deref())
+}
+
+/// 将 /api/generate 请求转换为 Kiro 请求
+///
+/// `system` 与 `prompt` 组装为单轮对话
+pub fn convert_generate_request(
+    req: &GenerateRequest,
+) -> Result<ConversionResult, ConversionError> {
+    let mut messages = Vec::new();
+    if let Some(system) = req.system.as_ref().filter(|s| !s.is_empty()) {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: system.clone(),
+            images: None,
+            tool_calls: None,
+            tool_name: None,
+        });
+    }
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: req.prompt.clone(),
+        images: req.images.clone(),
+        tool_calls: None,
+        tool_name: None,
+    });
+    convert_messages(&req.model, &messages, None)
+}
+
+/// 单轮 user 输入（user 与 tool 消息合并）
+#[derive(Default)]
+struct UserTurn {
+    texts: Vec<String>,
+    images: Vec<KiroImage>,
+    tool_results: Vec<ToolResult>,
+}
+
+impl UserTurn {
+    fn is_empty(&self) -> bool {
+        self.texts.is_empty() && self.images.is_empty() && self.tool_results.is_empty()
+    }
+
+    fn text(&self) -> String {
+        self.texts.join("\n")
+    }
+}
+
+/// 工具调用 ID 分配器
+///
+/// Ollama 的 tool_calls 不带 ID，tool 消息按 `tool_name` 或调用顺序与之配对
+#[derive(Default)]
+struct ToolIdTracker {
+    pending: VecDeque<(String, String)>,
+    counter: usize,
+}
+
+impl ToolIdTracker {
+    fn next_id(&mut self) -> String {
+        self.counter += 1;
+        format!("call_{}", self.counter)
+    }
+
+    fn assign_call(&mut self, name: &str) -> String {
+        let id = self.next_id();
+        self.pending.push_back((name.to_string(), id.clone()));
+        id
+    }
+
+    fn resolve_result(&mut self, name: Option<&str>) -> String {
+        let index = match name {
+            Some(name) => self.pending.iter().position(|(n, _)| n == name),
+            None => (!self.pending.is_empty()).then_some(0),
+        };
+        match index.and_then(|i| self.pending.remove(i)) {
+            Some((_, id)) => id,
+            None => self.next_id(),
+        }
+    }
+}
+
+fn convert_messages(
+    model: &str,
+    messages: &[ChatMessage],
+    tools: Option<&[super::types::Tool]>,
+) -> Result<ConversionResult, ConversionError> {
+    // 1. 映射模型
+    let model_id =
+        map_model(model).ok_or_else(|| ConversionError::UnsupportedModel(model.to_string()))?;
+
+    // 2. 提取系统消息
+    let system_content = messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let messages: Vec<&ChatMessage> = messages.iter().filter(|m| m.role != "system").collect();
+    if messages.is_empty() {
+        return Err(ConversionError::EmptyMessages);
+    }
+
+    // 3. 最后一条 assistant 之后的 user/tool 消息作为 currentMessage
+    let split = messages
+        .iter()
+        .rposition(|m| m.role == "assistant")
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let (history_messages, current_messages) = messages.split_at(split);
+
+    let mut tracker = ToolIdTracker::default();
+    let mut history = Vec::new();
+    if !system_content.is_empty() {
+        history.push(Message::User(HistoryUserMessage::new(
+            &system_content,
+            &model_id,
+        )));
+        history.push(Message::Assistant(HistoryAssistantMessage::new(
+            "I will follow these instructions.",
+        )));
+    }
+
+    let mut turn = UserTurn::default();
+    for msg in history_messages {
+        if msg.role == "assistant" {
+            // Kiro 要求 user/assistant 交替，缺少前置 user 时补一条
+            let user = if turn.is_empty() {
+                HistoryUserMessage::new("Continue", &model_id)
+            } else {
+                build_history_user(std::mem::take(&mut turn), &model_id)
+            };
+            history.push(Message::User(user));
+            history.push(Message::Assistant(convert_assistant_message(
+                msg,
+                &mut tracker,
+            )));
+        } else {
+            add_to_turn(&mut turn, msg, &mut tracker)?;
+        }
+    }
+
+    let mut current = UserTurn::default();
+    for msg in current_messages {
+        add_to_turn(&mut current, msg, &mut tracker)?;
+    }
+    if current.texts.is_empty() && current.tool_results.is_empty() {
+        current.texts.push("Continue".to_string());
+    }
+
+    // 4. 转换工具定义，并为历史中缺失的工具生成占位符
+    let mut kiro_tools = convert_tools(tools);
+    let existing: HashSet<String> = kiro_tools
+        .iter()
+        .map(|t| t.tool_specification.name.to_lowercase())
+        .collect();
+    for name in collect_history_tool_names(&history) {
+        if !existing.contains(&name.to_lowercase()) {
+            kiro_tools.push(create_placeholder_tool(&name));
+        }
+    }
+
+    // 5. 过滤孤立的工具结果
+    let valid_ids = collect_history_tool_use_ids(&history);
+    let tool_results: Vec<ToolResult> = current
+        .tool_results
+        .drain(..)
+        .filter(|r| {
+            let valid = valid_ids.contains(&r.tool_use_id);
+            if !valid {
+                tracing::warn!(
+                    "跳过孤立的 tool 消息：找不到对应的 tool_call，tool_use_id={}",
+                    r.tool_use_id
+                );
+            }
+            valid
+        })
+        .collect();
+
+    // 6. 构建当前消息
+    let mut context = UserInputMessageContext::new();
+    if !kiro_tools.is_empty() {
+        context = context.with_tools(kiro_tools);
+    }
+    if !tool_results.is_empty() {
+        context = context.with_tool_results(tool_results);
+    }
+    let mut user_input = UserInputMessage::new(current.text(), &model_id)
+        .with_context(context)
+        .with_origin("AI_EDITOR");
+    if !current.images.is_empty() {
+        user_input = user_input.with_images(current.images);
+    }
+
+    let conversation_state = ConversationState::new(Uuid::new_v4().to_string())
+        .with_agent_continuation_id(Uuid::new_v4().to_string())
+        .with_agent_task_type("vibe")
+        .with_chat_trigger_type("MANUAL")
+        .with_current_message(CurrentMessage::new(user_input))
+        .with_history(history);
+
+    Ok(ConversionResult { conversation_state })
+}
+
+/// 将 user / tool 消息加入当前轮次
+fn add_to_turn(
+    turn: &mut UserTurn,
+    msg: &ChatMessage,
+    tracker: &mut ToolIdTracker,
+) -> Result<(), ConversionError> {
+    if msg.role == "tool" {
+        let id = tracker.resolve_result(msg.tool_name.as_deref());
+        turn.tool_results
+            .push(ToolResult::success(id, &msg.content));
+        return Ok(());
+    }
+
+    if !msg.content.is_empty() {
+        turn.texts.push(msg.content.clone());
+    }
+    for data in msg.images.iter().flatten() {
+        let format = detect_image_format(data).ok_or(ConversionError::InvalidImage)?;
+        turn.images
+            .push(KiroImage::from_base64(format, data.clone()));
+    }
+    Ok(())
+}
+
+/// 根据 base64 数据头部识别图片格式
+///
+/// Ollama 的 images 字段只包含 base64 数据，不带 MIME 类型
+fn detect_image_format(data: &str) -> Option<&'static str> {
+    const SIGNATURES: &[(&str, &str)] = &[
+        ("iVBORw0KGgo", "png"),
+        ("/9j/", "jpeg"),
+        ("R0lGOD", "gif"),
+        ("UklGR", "webp"),
+    ];
+    SIGNATURES
+        .iter()
+        .find(|(prefix, _)| data.starts_with(prefix))
+        .map(|(_, format)| *format)
+}
+
+fn build_history_user(turn: UserTurn, model_id: &str) -> HistoryUserMessage {
+    let mut user_msg = UserMessage::new(turn.text(), model_id);
+    if !turn.images.is_empty() {
+        user_msg = user_msg.with_images(turn.images);
+    }
+    if !turn.tool_results.is_empty() {
+        user_msg = user_msg
+            .with_context(UserInputMessageContext::new().with_tool_results(turn.tool_results));
+    }
+    HistoryUserMessage {
+        user_input_message: user_msg,
+    }
+}
+
+/// 转换 assistant 消息
+fn convert_assistant_message(
+    msg: &ChatMessage,
+    tracker: &mut ToolIdTracker,
+) -> HistoryAssistantMessage {
+    let tool_uses: Vec<ToolUseEntry> = msg
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| {
+            let id = tracker.assign_call(&call.function.name);
+            let input = match &call.function.arguments {
+                // 部分客户端会把参数序列化为字符串
+                serde_json::Value::String(s) => {
+                    serde_json::from_str(s).unwrap_or(serde_json::json!({}))
+                }
+                serde_json::Value::Null => serde_json::json!({}),
+                other => other.clone(),
+            };
+            ToolUseEntry::new(id, &call.function.name).with_input(input)
+        })
+        .collect();
+
+    let mut assistant = AssistantMessage::new(&msg.content);
+    if !tool_uses.is_empty() {
+        assistant = assistant.with_tool_uses(tool_uses);
+    }
+    HistoryAssistantMessage {
+        assistant_response_message: assistant,
+    }
+}
+
+/// 转换工具定义
+fn convert_tools(tools: Option<&[super::types::Tool]>) -> Vec<Tool> {
+    tools
+        .unwrap_or_default()
+        .iter()
+        .filter(|t| t.tool_type == "function")
+        .map(|t| {
+            let description = t.function.description.clone().unwrap_or_default();
+            // 限制描述长度为 10000 字符
+            let description = match description.char_indices().nth(10000) {
+                Some((idx, _)) => description[..idx].to_string(),
+                None => description,
+            };
+            let schema = t.function.parameters.clone().unwrap_or_else(|| {
+                serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                })
+            });
+
+            Tool {
+                tool_specification: ToolSpecification {
+                    name: t.function.name.clone(),
+                    description,
+                    input_schema: InputSchema::from_json(schema),
+                },
+            }
+        })
+        .collect()
+}
+
+/// 收集历史消息中使用的所有工具名称
+fn collect_history_tool_names(history: &[Message]) -> Vec<String> {
+    let mut tool_names = Vec::new();
+    for msg in history {
+        if let Message::Assistant(assistant_msg) = msg
+            && let Some(ref tool_uses) = assistant_msg.assistant_response_message.tool_uses
+        {
+            for tool_use in tool_uses {
+                if !tool_names.contains(&tool_use.name) {
+                    tool_names.push(tool_use.name.clone());
+                }
+            }
+        }
+    }
+    tool_names
+}
+
+fn collect_history_tool_use_ids(history: &[Message]) -> HashSet<String> {
+    let mut ids = HashSet::new();
+    for msg in history {
+        if let Message::Assistant(assistant_msg) = msg
+            && let Some(ref tool_uses) = assistant_msg.assistant_response_message.tool_uses
+        {
+            ids.extend(tool_uses.iter().map(|t| t.tool_use_id.clone()));
+        }
+    }
+    ids
+}
+
+/// 为历史中使用但不在 tools 列表中的工具创建占位符定义
+fn create_placeholder_tool(name: &str) -> Tool {
+    Tool {
+        tool_specification: ToolSpecification {
+            name: name.to_string(),
+            description: "Tool used in conversation history".to_string(),
+            input_schema: InputSchema::from_json(serde_json::json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "properties": {},
+                "required": [],
+                "additionalProperties": true
+            })),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(json: serde_json::Value) -> ChatRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_map_model() {
+        assert_eq!(
+            map_model("claude-sonnet-4.5:latest").unwrap(),
+            "claude-sonnet-4.5"
+        );
+        assert_eq!(map_model("claude-opus-4.5").unwrap(), "claude-opus-4.5");
+        assert!(map_model("llama3:8b").is_none());
+    }
+
+    #[test]
+    fn test_convert_chat_with_tool_round_trip() {
+        let req = chat(serde_json::json!({
+            "model": "claude-sonnet-4.5",
+            "messages": [
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": "weather in Paris?"},
+                {"role": "assistant", "content": "", "tool_calls": [
+                    {"function": {"name": "get_weather", "arguments": {"city": "Paris"}}}
+                ]},
+                {"role": "tool", "content": "20C", "tool_name": "get_weather"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather",
+                "description": "Get weather",
+                "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }}]
+        }));
+        let state = convert_chat_request(&req).unwrap().conversation_state;
+
+        // system 配对 + user/assistant
+        assert_eq!(state.history.len(), 4);
+        let Message::Assistant(assistant) = &state.history[3] else {
+            panic!("expected assistant message");
+        };
+        let tool_use_id = &assistant
+            .assistant_response_message
+            .tool_uses
+            .as_ref()
+            .unwrap()[0]
+            .tool_use_id;
+
+        let ctx = &state
+            .current_message
+            .user_input_message
+            .user_input_message_context;
+        assert_eq!(ctx.tools.len(), 1);
+        assert_eq!(ctx.tool_results.len(), 1);
+        assert_eq!(&ctx.tool_results[0].tool_use_id, tool_use_id);
+    }
+
+    #[test]
+    fn test_convert_generate_request() {
+        let req: GenerateRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-haiku-4.5",
+            "prompt": "hello",
+            "images": ["iVBORw0KGgoAAAA"]
+        }))
+        .unwrap();
+        let state = convert_generate_request(&req).unwrap().conversation_state;
+        assert_eq!(state.current_message.user_input_message.content, "hello");
+        assert_eq!(
+            state.current_message.user_input_message.images[0].format,
+            "png"
+        );
+        assert!(state.history.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_image_and_unknown_model() {
+        let req = chat(serde_json::json!({
+            "model": "claude-haiku-4.5",
+            "messages": [{"role": "user", "content": "hi", "images": ["AAAA"]}]
+        }));
+        assert!(matches!(
+            convert_chat_request(&req),
+            Err(ConversionError::InvalidImage)
+        ));
+
+        let req = chat(serde_json::json!({
+            "model": "llama3",
+            "messages": [{"role": "user", "content": "hi"}]
+        }));
+        assert!(matches!(
+            convert_chat_request(&req),
+            Err(ConversionError::UnsupportedModel(_))
+        ));
+    }
+}
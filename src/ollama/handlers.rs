@@ -0,0 +1,440 @@
+//! Ollama API Handler 函数
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    Json as JsonExtractor,
+    body::Body,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use serde::Serialize;
+
+use crate::kiro::model::events::Event;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::provider::KiroProvider;
+use crate::request_log::{self, RequestLogger, RequestSummary};
+use crate::token;
+
+use super::converter::{
+    ConversionError, ConversionResult, MODELS, convert_chat_request, convert_generate_request,
+    map_model,
+};
+use super::stream::{Delta, StreamContext};
+use super::types::{
+    ChatMessage, ChatRequest, ErrorResponse, GenerateRequest, ModelDetails, ModelEntry,
+    ShowRequest, ShowResponse, TagsResponse, VersionResponse,
+};
+
+/// 对外报告的 Ollama 版本号
+const OLLAMA_VERSION: &str = "0.12.0";
+
+/// 模型列表中使用的固定修改时间
+const MODEL_MODIFIED_AT: &str = "2025-01-01T00:00:00Z";
+
+/// 应用状态
+#[derive(Clone)]
+pub struct AppState {
+    pub api_key: String,
+    pub allow_anonymous: bool,
+    pub kiro_provider: Option<Arc<KiroProvider>>,
+    pub request_logger: Option<Arc<RequestLogger>>,
+}
+
+impl AppState {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            allow_anonymous: false,
+            kiro_provider: None,
+            request_logger: None,
+        }
+    }
+
+    pub fn with_allow_anonymous(mut self, allow_anonymous: bool) -> Self {
+        self.allow_anonymous = allow_anonymous;
+        self
+    }
+
+    pub fn with_kiro_provider(mut self, provider: KiroProvider) -> Self {
+        self.kiro_provider = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn with_request_logger(mut self, logger: Arc<RequestLogger>) -> Self {
+        self.request_logger = Some(logger);
+        self
+    }
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(ErrorResponse::new(message))).into_response()
+}
+
+/// 请求的端点类型，决定响应的结构
+#[derive(Clone, Copy)]
+enum Endpoint {
+    Chat,
+    Generate,
+}
+
+/// chat / generate 请求的公共信息
+struct RequestInfo<'a> {
+    endpoint: Endpoint,
+    model: &'a str,
+    stream: bool,
+    message_count: usize,
+    max_tokens: Option<i32>,
+    input_tokens: i32,
+}
+
+fn model_details(kiro_model: &str) -> ModelDetails {
+    ModelDetails {
+        parent_model: kiro_model.to_string(),
+        format: "api".to_string(),
+        family: "claude".to_string(),
+        families: vec!["claude".to_string()],
+        parameter_size: String::new(),
+        quantization_level: String::new(),
+    }
+}
+
+/// GET /api/tags
+pub async fn list_tags() -> impl IntoResponse {
+    tracing::info!("Received GET /api/tags request");
+    Json(TagsResponse {
+        models: MODELS
+            .iter()
+            .map(|(name, kiro_model)| ModelEntry {
+                name: name.to_string(),
+                model: name.to_string(),
+                modified_at: MODEL_MODIFIED_AT.to_string(),
+                size: 0,
+                digest: String::new(),
+                details: model_details(kiro_model),
+            })
+            .collect(),
+    })
+}
+
+/// POST /api/show
+pub async fn show_model(JsonExtractor(payload): JsonExtractor<ShowRequest>) -> Response {
+    let Some(model) = payload.model.or(payload.name) else {
+        return error_response(StatusCode::BAD_REQUEST, "model is required");
+    };
+    tracing::info!(model = %model, "Received POST /api/show request");
+
+    let Some(kiro_model) = map_model(&model) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            ConversionError::UnsupportedModel(model).to_string(),
+        );
+    };
+
+    Json(ShowResponse {
+        modelfile: format!("FROM {}\n", kiro_model),
+        parameters: String::new(),
+        template: "{{ .Prompt }}".to_string(),
+        details: model_details(&kiro_model),
+        model_info: serde_json::json!({
+            "general.architecture": "claude",
+            "general.basename": kiro_model,
+            "claude.context_length": 200_000,
+        }),
+        capabilities: vec![
+            "completion".to_string(),
+            "tools".to_string(),
+            "vision".to_string(),
+        ],
+        modified_at: MODEL_MODIFIED_AT.to_string(),
+    })
+    .into_response()
+}
+
+/// GET /api/version
+pub async fn get_version() -> impl IntoResponse {
+    Json(VersionResponse {
+        version: OLLAMA_VERSION.to_string(),
+    })
+}
+
+/// POST /api/chat
+pub async fn post_chat(
+    State(state): State<AppState>,
+    JsonExtractor(payload): JsonExtractor<ChatRequest>,
+) -> Response {
+    tracing::info!(
+        model = %payload.model,
+        stream = %payload.is_stream(),
+        message_count = %payload.messages.len(),
+        "Received POST /api/chat request"
+    );
+
+    let info = RequestInfo {
+        endpoint: Endpoint::Chat,
+        model: &payload.model,
+        stream: payload.is_stream(),
+        message_count: payload.messages.len(),
+        max_tokens: payload.options.as_ref().and_then(|o| o.num_predict),
+        input_tokens: estimate_input_tokens(&payload.messages, payload.tools.as_deref()),
+    };
+    handle_request(&state, info, || convert_chat_request(&payload)).await
+}
+
+/// POST /api/generate
+pub async fn post_generate(
+    State(state): State<AppState>,
+    JsonExtractor(payload): JsonExtractor<GenerateRequest>,
+) -> Response {
+    tracing::info!(
+        model = %payload.model,
+        stream = %payload.is_stream(),
+        "Received POST /api/generate request"
+    );
+
+    let mut text = payload.prompt.clone();
+    text.push_str(payload.system.as_deref().unwrap_or_default());
+    let input_tokens = (token::count_tokens(&text) as i32).max(1);
+
+    let info = RequestInfo {
+        endpoint: Endpoint::Generate,
+        model: &payload.model,
+        stream: payload.is_stream(),
+        message_count: 1,
+        max_tokens: payload.options.as_ref().and_then(|o| o.num_predict),
+        input_tokens,
+    };
+    handle_request(&state, info, || convert_generate_request(&payload)).await
+}
+
+/// chat / generate 的公共处理流程
+async fn handle_request(
+    state: &AppState,
+    info: RequestInfo<'_>,
+    convert: impl FnOnce() -> Result<ConversionResult, ConversionError>,
+) -> Response {
+    // 检查 KiroProvider 是否可用
+    let provider = match &state.kiro_provider {
+        Some(p) => p.clone(),
+        None => {
+            tracing::error!("KiroProvider 未配置");
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Kiro API provider not configured",
+            );
+        }
+    };
+
+    // 转换请求
+    let conversion_result = match convert() {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::warn!("请求转换失败: {}", e);
+            let status = match e {
+                ConversionError::UnsupportedModel(_) => StatusCode::NOT_FOUND,
+                ConversionError::EmptyMessages | ConversionError::InvalidImage => {
+                    StatusCode::BAD_REQUEST
+                }
+            };
+            return error_response(status, e.to_string());
+        }
+    };
+
+    // 请求日志记录实际处理请求的凭据与 API 区域
+    let summary = RequestSummary {
+        model: info.model.to_string(),
+        max_tokens: info.max_tokens.unwrap_or(-1),
+        stream: info.stream,
+        message_count: info.message_count,
+    };
+    request_log::track(state.request_logger.as_deref(), summary, async move {
+        // 构建 Kiro 请求
+        let kiro_request = KiroRequest::new(conversion_result.conversation_state);
+
+        let ctx = StreamContext::new(info.model, info.input_tokens);
+
+        if info.stream {
+            handle_stream_request(provider, &kiro_request, ctx, info.endpoint).await
+        } else {
+            handle_non_stream_request(provider, &kiro_request, ctx, info.endpoint).await
+        }
+    })
+    .await
+}
+
+/// 估算输入 tokens
+fn estimate_input_tokens(messages: &[ChatMessage], tools: Option<&[super::types::Tool]>) -> i32 {
+    let mut text = String::new();
+    for msg in messages {
+        text.push_str(&msg.content);
+        for call in msg.tool_calls.iter().flatten() {
+            text.push_str(&call.function.name);
+            text.push_str(&call.function.arguments.to_string());
+        }
+    }
+    for tool in tools.unwrap_or_default() {
+        text.push_str(&tool.function.name);
+        text.push_str(tool.function.description.as_deref().unwrap_or_default());
+        if let Some(params) = &tool.function.parameters {
+            text.push_str(&params.to_string());
+        }
+    }
+
+    (token::count_tokens(&text) as i32).max(1)
+}
+
+/// 序列化为单行 NDJSON
+fn ndjson_line(value: &impl Serialize) -> Bytes {
+    let mut line = serde_json::to_vec(value).unwrap_or_default();
+    line.push(b'\n');
+    Bytes::from(line)
+}
+
+fn chunk_line(ctx: &StreamContext, endpoint: Endpoint, deltas: Vec<Delta>) -> Bytes {
+    match endpoint {
+        Endpoint::Chat => ndjson_line(&ctx.chat_chunk(deltas)),
+        Endpoint::Generate => ndjson_line(&ctx.generate_chunk(deltas)),
+    }
+}
+
+fn final_line(ctx: &StreamContext, endpoint: Endpoint) -> Bytes {
+    match endpoint {
+        Endpoint::Chat => ndjson_line(&ctx.chat_final()),
+        Endpoint::Generate => ndjson_line(&ctx.generate_final()),
+    }
+}
+
+/// 处理流式请求（NDJSON）
+async fn handle_stream_request(
+    provider: Arc<KiroProvider>,
+    request: &KiroRequest,
+    ctx: StreamContext,
+    endpoint: Endpoint,
+) -> Response {
+    let response = match provider.call_api_stream(request).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Kiro API 调用失败: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, format!("上游 API 调用失败: {}", e));
+        }
+    };
+
+    let body = create_ndjson_stream(response, ctx, endpoint).map(Ok::<_, Infallible>);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+/// 将 Kiro 响应流转换为 NDJSON 行流
+///
+/// Ollama 客户端逐行解析 JSON，因此不发送保活 ping
+fn create_ndjson_stream(
+    response: reqwest::Response,
+    ctx: StreamContext,
+    endpoint: Endpoint,
+) -> impl Stream<Item = Bytes> {
+    let body_stream = response.bytes_stream();
+
+    stream::unfold(
+        (body_stream, ctx, EventStreamDecoder::new(), false),
+        move |(mut body_stream, mut ctx, mut decoder, finished)| async move {
+            if finished {
+                return None;
+            }
+
+            match body_stream.next().await {
+                Some(Ok(chunk)) => {
+                    if let Err(e) = decoder.feed(&chunk) {
+                        tracing::warn!("缓冲区溢出: {}", e);
+                    }
+
+                    let mut lines = Vec::new();
+                    for result in decoder.decode_iter() {
+                        match result {
+                            Ok(frame) => {
+                                if let Ok(event) = Event::from_frame(frame) {
+                                    let deltas = ctx.process_kiro_event(&event);
+                                    if !deltas.is_empty() {
+                                        lines.push(chunk_line(&ctx, endpoint, deltas));
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                tracing::warn!("解码事件失败: {}", e);
+                            }
+                        }
+                    }
+
+                    Some((stream::iter(lines), (body_stream, ctx, decoder, false)))
+                }
+                Some(Err(e)) => {
+                    tracing::error!("读取响应流失败: {}", e);
+                    let lines = vec![final_line(&ctx, endpoint)];
+                    Some((stream::iter(lines), (body_stream, ctx, decoder, true)))
+                }
+                None => {
+                    let lines = vec![final_line(&ctx, endpoint)];
+                    Some((stream::iter(lines), (body_stream, ctx, decoder, true)))
+                }
+            }
+        },
+    )
+    .flatten()
+}
+
+/// 处理非流式请求
+async fn handle_non_stream_request(
+    provider: Arc<KiroProvider>,
+    request: &KiroRequest,
+    mut ctx: StreamContext,
+    endpoint: Endpoint,
+) -> Response {
+    let response = match provider.call_api(request).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("Kiro API 调用失败: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, format!("上游 API 调用失败: {}", e));
+        }
+    };
+
+    let body_bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("读取响应体失败: {}", e);
+            return error_response(StatusCode::BAD_GATEWAY, format!("读取响应失败: {}", e));
+        }
+    };
+
+    let mut decoder = EventStreamDecoder::new();
+    if let Err(e) = decoder.feed(&body_bytes) {
+        tracing::warn!("缓冲区溢出: {}", e);
+    }
+
+    let mut deltas = Vec::new();
+    for result in decoder.decode_iter() {
+        match result {
+            Ok(frame) => {
+                if let Ok(event) = Event::from_frame(frame) {
+                    deltas.extend(ctx.process_kiro_event(&event));
+                }
+            }
+            Err(e) => {
+                tracing::warn!("解码事件失败: {}", e);
+            }
+        }
+    }
+
+    match endpoint {
+        Endpoint::Chat => Json(ctx.complete_chat(deltas)).into_response(),
+        Endpoint::Generate => Json(ctx.complete_generate(deltas)).into_response(),
+    }
+}
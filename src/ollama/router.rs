@@ -0,0 +1,98 @@
+//! Ollama API 路由配置
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{DefaultBodyLimit, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+};
+
+use crate::common::auth;
+use crate::kiro::provider::KiroProvider;
+use crate::request_log::RequestLogger;
+
+use super::handlers::{AppState, get_version, list_tags, post_chat, post_generate, show_model};
+use super::types::ErrorResponse;
+
+/// 请求体最大大小限制 (50MB)
+const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
+
+/// API Key 认证中间件
+///
+/// Ollama 客户端通常不支持配置 API Key，开启 `ollamaAllowAnonymous` 后跳过认证
+async fn auth_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if state.allow_anonymous {
+        return next.run(request).await;
+    }
+
+    match auth::extract_api_key(&request) {
+        Some(key) if auth::constant_time_eq(&key, &state.api_key) => next.run(request).await,
+        _ => {
+            let error = ErrorResponse::authentication_error();
+            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+        }
+    }
+}
+
+/// CORS 中间件层
+fn cors_layer() -> tower_http::cors::CorsLayer {
+    use tower_http::cors::{Any, CorsLayer};
+
+    CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+}
+
+/// 创建带有 KiroProvider 的 Ollama API 路由
+///
+/// # 端点
+/// - `POST /api/chat` - 对话（默认 NDJSON 流式）
+/// - `POST /api/generate` - 单轮补全（默认 NDJSON 流式）
+/// - `GET /api/tags` - 获取可用模型列表
+/// - `POST /api/show` - 获取模型信息
+/// - `GET /api/version` - 获取版本号
+///
+/// # 认证
+/// 支持 `x-api-key` header 和 `Authorization: Bearer <token>` header，
+/// `allow_anonymous` 为 true 时不校验
+pub fn create_router_with_provider(
+    api_key: impl Into<String>,
+    allow_anonymous: bool,
+    kiro_provider: Option<KiroProvider>,
+    request_logger: Option<Arc<RequestLogger>>,
+) -> Router {
+    let mut state = AppState::new(api_key).with_allow_anonymous(allow_anonymous);
+    if let Some(provider) = kiro_provider {
+        state = state.with_kiro_provider(provider);
+    }
+    if let Some(logger) = request_logger {
+        state = state.with_request_logger(logger);
+    }
+
+    let api_routes = Router::new()
+        .route("/chat", post(post_chat))
+        .route("/generate", post(post_generate))
+        .route("/tags", get(list_tags))
+        .route("/show", post(show_model))
+        .route("/version", get(get_version))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest("/api", api_routes)
+        .layer(cors_layer())
+        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+        .with_state(state)
+}
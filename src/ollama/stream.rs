@@ -0,0 +1,296 @@
+//! Ollama 响应处理模块
+//!
+//! 实现 Kiro 事件 → Ollama ChatResponse / GenerateResponse 转换，流式与非流式共用
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::kiro::model::events::{Event, ToolUseEvent};
+use crate::token;
+
+use super::types::{
+    ChatResponse, GenerateResponse, Metrics, ResponseMessage, ToolCall, ToolCallFunction,
+};
+
+/// 上下文窗口大小（200k tokens）
+const CONTEXT_WINDOW_SIZE: i32 = 200_000;
+
+/// 输出增量
+#[derive(Debug, Clone)]
+pub enum Delta {
+    /// 文本增量
+    Text(String),
+    /// 完整的工具调用
+    ToolCall(ToolCall),
+}
+
+/// 响应处理上下文
+pub struct StreamContext {
+    /// 请求的模型名称
+    pub model: String,
+    /// 输入 tokens（估算值）
+    pub input_tokens: i32,
+    /// 从 contextUsageEvent 计算的实际输入 tokens
+    pub context_input_tokens: Option<i32>,
+    /// 输出 tokens 累计
+    pub output_tokens: i32,
+    /// 请求开始时间
+    started_at: Instant,
+    /// 首个输出到达时间
+    first_output_at: Option<Instant>,
+    /// 工具输入缓冲 (tool_use_id -> JSON 片段)
+    tool_buffers: HashMap<String, String>,
+    /// 结束原因
+    done_reason: Option<String>,
+}
+
+impl StreamContext {
+    /// 创建新的处理上下文
+    pub fn new(model: impl Into<String>, input_tokens: i32) -> Self {
+        Self {
+            model: model.into(),
+            input_tokens,
+            context_input_tokens: None,
+            output_tokens: 0,
+            started_at: Instant::now(),
+            first_output_at: None,
+            tool_buffers: HashMap::new(),
+            done_reason: None,
+        }
+    }
+
+    /// 处理 Kiro 事件，返回需要输出的增量
+    pub fn process_kiro_event(&mut self, event: &Event) -> Vec<Delta> {
+        let deltas = match event {
+            Event::AssistantResponse(resp) => {
+                if resp.content.is_empty() {
+                    return Vec::new();
+                }
+                self.output_tokens += token::count_tokens(&resp.content) as i32;
+                vec![Delta::Text(resp.content.clone())]
+            }
+            Event::ToolUse(tool_use) => self.process_tool_use(tool_use),
+            Event::ContextUsage(context_usage) => {
+                let actual_input_tokens = (context_usage.context_usage_percentage
+                    * (CONTEXT_WINDOW_SIZE as f64)
+                    / 100.0) as i32;
+                self.context_input_tokens = Some(actual_input_tokens);
+                Vec::new()
+            }
+            Event::Error {
+                error_code,
+                error_message,
+            } => {
+                tracing::error!("收到错误事件: {} - {}", error_code, error_message);
+                Vec::new()
+            }
+            Event::Exception {
+                exception_type,
+                message,
+            } => {
+                if exception_type == "ContentLengthExceededException" {
+                    self.done_reason = Some("length".to_string());
+                }
+                tracing::warn!("收到异常事件: {} - {}", exception_type, message);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+
+        if !deltas.is_empty() && self.first_output_at.is_none() {
+            self.first_output_at = Some(Instant::now());
+        }
+        deltas
+    }
+
+    /// 处理工具使用事件
+    ///
+    /// Ollama 的 tool_calls 参数为完整 JSON 对象，累积到 stop 后一次性输出
+    fn process_tool_use(&mut self, tool_use: &ToolUseEvent) -> Vec<Delta> {
+        let buffer = self
+            .tool_buffers
+            .entry(tool_use.tool_use_id.clone())
+            .or_default();
+        buffer.push_str(&tool_use.input);
+        if !tool_use.input.is_empty() {
+            self.output_tokens += (tool_use.input.len() as i32 + 3) / 4;
+        }
+
+        if !tool_use.stop {
+            return Vec::new();
+        }
+
+        let raw = self
+            .tool_buffers
+            .remove(&tool_use.tool_use_id)
+            .unwrap_or_default();
+        let arguments = if raw.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(&raw).unwrap_or_else(|e| {
+                tracing::warn!(
+                    "工具输入 JSON 解析失败: {}, tool_use_id: {}",
+                    e,
+                    tool_use.tool_use_id
+                );
+                serde_json::json!({})
+            })
+        };
+
+        vec![Delta::ToolCall(ToolCall {
+            function: ToolCallFunction {
+                name: tool_use.name.clone(),
+                arguments,
+            },
+        })]
+    }
+
+    /// 构建 /api/chat 的中间响应
+    pub fn chat_chunk(&self, deltas: Vec<Delta>) -> ChatResponse {
+        let (content, tool_calls) = split_deltas(deltas);
+        self.chat_response(content, tool_calls, false)
+    }
+
+    /// 构建 /api/chat 的最终响应（流式时为最后一行）
+    pub fn chat_final(&self) -> ChatResponse {
+        self.chat_response(String::new(), Vec::new(), true)
+    }
+
+    /// 构建 /api/chat 的非流式完整响应
+    pub fn complete_chat(&self, deltas: Vec<Delta>) -> ChatResponse {
+        let (content, tool_calls) = split_deltas(deltas);
+        self.chat_response(content, tool_calls, true)
+    }
+
+    /// 构建 /api/generate 的中间响应（工具调用不适用，直接忽略）
+    pub fn generate_chunk(&self, deltas: Vec<Delta>) -> GenerateResponse {
+        let (response, _) = split_deltas(deltas);
+        self.generate_response(response, false)
+    }
+
+    /// 构建 /api/generate 的最终响应（流式时为最后一行）
+    pub fn generate_final(&self) -> GenerateResponse {
+        self.generate_response(String::new(), true)
+    }
+
+    /// 构建 /api/generate 的非流式完整响应
+    pub fn complete_generate(&self, deltas: Vec<Delta>) -> GenerateResponse {
+        let (response, _) = split_deltas(deltas);
+        self.generate_response(response, true)
+    }
+
+    fn chat_response(
+        &self,
+        content: String,
+        tool_calls: Vec<ToolCall>,
+        done: bool,
+    ) -> ChatResponse {
+        ChatResponse {
+            model: self.model.clone(),
+            created_at: created_at(),
+            message: ResponseMessage {
+                role: "assistant".to_string(),
+                content,
+                tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            },
+            done,
+            done_reason: done.then(|| self.done_reason()),
+            metrics: done.then(|| self.metrics()),
+        }
+    }
+
+    fn generate_response(&self, response: String, done: bool) -> GenerateResponse {
+        GenerateResponse {
+            model: self.model.clone(),
+            created_at: created_at(),
+            response,
+            done,
+            done_reason: done.then(|| self.done_reason()),
+            metrics: done.then(|| self.metrics()),
+        }
+    }
+
+    fn done_reason(&self) -> String {
+        self.done_reason
+            .clone()
+            .unwrap_or_else(|| "stop".to_string())
+    }
+
+    /// 统计信息：prompt 阶段计为首个输出前的耗时，其余计为生成耗时
+    fn metrics(&self) -> Metrics {
+        let now = Instant::now();
+        let first_output_at = self.first_output_at.unwrap_or(now);
+        let nanos = |d: std::time::Duration| d.as_nanos() as u64;
+        Metrics {
+            total_duration: nanos(now - self.started_at),
+            load_duration: 0,
+            prompt_eval_count: self.context_input_tokens.unwrap_or(self.input_tokens),
+            prompt_eval_duration: nanos(first_output_at - self.started_at),
+            eval_count: self.output_tokens,
+            eval_duration: nanos(now - first_output_at),
+        }
+    }
+}
+
+/// 将增量拆分为合并后的文本与工具调用列表
+fn split_deltas(deltas: Vec<Delta>) -> (String, Vec<ToolCall>) {
+    let mut content = String::new();
+    let mut tool_calls = Vec::new();
+    for delta in deltas {
+        match delta {
+            Delta::Text(text) => content.push_str(&text),
+            Delta::ToolCall(call) => tool_calls.push(call),
+        }
+    }
+    (content, tool_calls)
+}
+
+fn created_at() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Nanos, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_event(input: &str, stop: bool) -> Event {
+        Event::ToolUse(ToolUseEvent {
+            name: "get_weather".to_string(),
+            tool_use_id: "tool-1".to_string(),
+            input: input.to_string(),
+            stop,
+        })
+    }
+
+    #[test]
+    fn test_tool_call_emitted_when_complete() {
+        let mut ctx = StreamContext::new("claude-sonnet-4.5:latest", 10);
+        assert!(
+            ctx.process_kiro_event(&tool_event("{\"city\":", false))
+                .is_empty()
+        );
+        let deltas = ctx.process_kiro_event(&tool_event("\"Paris\"}", true));
+        let response = ctx.chat_chunk(deltas);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json["message"]["tool_calls"][0]["function"]["arguments"]["city"],
+            "Paris"
+        );
+        assert_eq!(json["done"], false);
+        assert!(json.get("eval_count").is_none());
+    }
+
+    #[test]
+    fn test_complete_generate_merges_text() {
+        let ctx = StreamContext::new("claude-haiku-4.5:latest", 10);
+        let response = ctx.complete_generate(vec![
+            Delta::Text("Hello, ".to_string()),
+            Delta::Text("world".to_string()),
+        ]);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["response"], "Hello, world");
+        assert_eq!(json["done"], true);
+        assert_eq!(json["done_reason"], "stop");
+        assert_eq!(json["prompt_eval_count"], 10);
+    }
+}
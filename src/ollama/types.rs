@@ -0,0 +1,287 @@
+//! Ollama API 类型定义
+
+use serde::{Deserialize, Serialize};
+
+// === 错误响应 ===
+
+/// Ollama API 错误响应
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+impl ErrorResponse {
+    /// 创建新的错误响应
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            error: message.into(),
+        }
+    }
+
+    /// 创建认证错误响应
+    pub fn authentication_error() -> Self {
+        Self::new("unauthorized")
+    }
+}
+
+// === /api/chat 请求类型 ===
+
+/// /api/chat 请求体
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    pub model: String,
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub tools: Option<Vec<Tool>>,
+    /// Ollama 默认开启流式响应
+    #[serde(default)]
+    pub stream: Option<bool>,
+    #[serde(default)]
+    pub options: Option<Options>,
+}
+
+impl ChatRequest {
+    /// 是否启用流式响应（未设置时默认 true）
+    pub fn is_stream(&self) -> bool {
+        self.stream.unwrap_or(true)
+    }
+}
+
+/// 聊天消息
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatMessage {
+    /// "system" / "user" / "assistant" / "tool"
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    /// base64 编码图片（不带 data URL 前缀）
+    #[serde(default)]
+    pub images: Option<Vec<String>>,
+    /// 工具调用（assistant 消息）
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// 工具名称（tool 消息）
+    #[serde(default)]
+    pub tool_name: Option<String>,
+}
+
+/// 工具定义
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tool {
+    #[serde(rename = "type", default = "default_tool_type")]
+    pub tool_type: String,
+    pub function: ToolFunction,
+}
+
+fn default_tool_type() -> String {
+    "function".to_string()
+}
+
+/// 工具函数定义
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolFunction {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parameters: Option<serde_json::Value>,
+}
+
+/// 工具调用
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ToolCall {
+    pub function: ToolCallFunction,
+}
+
+/// 工具调用函数
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ToolCallFunction {
+    pub name: String,
+    /// Ollama 中参数为 JSON 对象（而非字符串）
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// 生成选项
+///
+/// Kiro 上游不支持采样参数，仅解析 `num_predict` 用于日志记录
+#[derive(Debug, Default, Deserialize)]
+pub struct Options {
+    #[serde(default)]
+    pub num_predict: Option<i32>,
+}
+
+// === /api/generate 请求类型 ===
+
+/// /api/generate 请求体
+#[derive(Debug, Deserialize)]
+pub struct GenerateRequest {
+    pub model: String,
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub images: Option<Vec<String>>,
+    #[serde(default)]
+    pub stream: Option<bool>,
+    #[serde(default)]
+    pub options: Option<Options>,
+}
+
+impl GenerateRequest {
+    /// 是否启用流式响应（未设置时默认 true）
+    pub fn is_stream(&self) -> bool {
+        self.stream.unwrap_or(true)
+    }
+}
+
+// === 响应类型 ===
+
+/// 统计信息（仅在最后一条响应中出现）
+///
+/// 时长单位为纳秒
+#[derive(Debug, Clone, Serialize)]
+pub struct Metrics {
+    pub total_duration: u64,
+    pub load_duration: u64,
+    pub prompt_eval_count: i32,
+    pub prompt_eval_duration: u64,
+    pub eval_count: i32,
+    pub eval_duration: u64,
+}
+
+/// 响应消息
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+/// /api/chat 响应（流式时为单行 NDJSON）
+#[derive(Debug, Serialize)]
+pub struct ChatResponse {
+    pub model: String,
+    pub created_at: String,
+    pub message: ResponseMessage,
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Metrics>,
+}
+
+/// /api/generate 响应（流式时为单行 NDJSON）
+#[derive(Debug, Serialize)]
+pub struct GenerateResponse {
+    pub model: String,
+    pub created_at: String,
+    pub response: String,
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Metrics>,
+}
+
+// === 模型信息 ===
+
+/// 模型详情
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDetails {
+    pub parent_model: String,
+    pub format: String,
+    pub family: String,
+    pub families: Vec<String>,
+    pub parameter_size: String,
+    pub quantization_level: String,
+}
+
+/// /api/tags 中的模型条目
+#[derive(Debug, Serialize)]
+pub struct ModelEntry {
+    pub name: String,
+    pub model: String,
+    pub modified_at: String,
+    pub size: u64,
+    pub digest: String,
+    pub details: ModelDetails,
+}
+
+/// /api/tags 响应
+#[derive(Debug, Serialize)]
+pub struct TagsResponse {
+    pub models: Vec<ModelEntry>,
+}
+
+/// /api/show 请求体（新版使用 model，旧版使用 name）
+#[derive(Debug, Deserialize)]
+pub struct ShowRequest {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// /api/show 响应
+#[derive(Debug, Serialize)]
+pub struct ShowResponse {
+    pub modelfile: String,
+    pub parameters: String,
+    pub template: String,
+    pub details: ModelDetails,
+    pub model_info: serde_json::Value,
+    pub capabilities: Vec<String>,
+    pub modified_at: String,
+}
+
+/// /api/version 响应
+#[derive(Debug, Serialize)]
+pub struct VersionResponse {
+    pub version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_defaults_to_stream() {
+        let req: ChatRequest = serde_json::from_str(r#"{"model":"m","messages":[]}"#).unwrap();
+        assert!(req.is_stream());
+
+        let req: ChatRequest =
+            serde_json::from_str(r#"{"model":"m","messages":[],"stream":false}"#).unwrap();
+        assert!(!req.is_stream());
+    }
+
+    #[test]
+    fn test_chat_response_flattens_metrics() {
+        let response = ChatResponse {
+            model: "m".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            message: ResponseMessage {
+                role: "assistant".to_string(),
+                content: String::new(),
+                tool_calls: None,
+            },
+            done: true,
+            done_reason: Some("stop".to_string()),
+            metrics: Some(Metrics {
+                total_duration: 1,
+                load_duration: 0,
+                prompt_eval_count: 2,
+                prompt_eval_duration: 0,
+                eval_count: 3,
+                eval_duration: 1,
+            }),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["eval_count"], 3);
+        assert_eq!(json["done_reason"], "stop");
+        assert!(json["message"].get("tool_calls").is_none());
+    }
+}
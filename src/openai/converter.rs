@@ -162,6 +162,8 @@ fn process_messages(
 
     // 收集用户消息缓冲区
     let mut user_buffer: Vec<(String, Vec<KiroImage>)> = Vec::new();
+    // 尚未归入历史的工具结果（其后若还有 assistant 消息，则属于历史中的 user 轮次）
+    let mut pending_tool_results: Vec<ToolResult> = Vec::new();
 
     for (i, msg) in messages.iter().enumerate() {
         let is_last = i == messages.len() - 1;
@@ -188,9 +190,10 @@ fn process_messages(
                 }
             }
             "assistant" => {
-                // 处理累积的用户消息
-                if !user_buffer.is_empty() {
-                    let merged = merge_user_buffer(&user_buffer, model_id);
+                // 处理累积的用户消息和工具结果
+                if !user_buffer.is_empty() || !pending_tool_results.is_empty() {
+                    let results = std::mem::take(&mut pending_tool_results);
+                    let merged = merge_user_buffer(&user_buffer, results, model_id);
                     history.push(Message::User(merged));
                     user_buffer.clear();
                 }
@@ -203,16 +206,19 @@ fn process_messages(
                 // 工具结果消息
                 if let Some(tool_call_id) = &msg.tool_call_id {
                     let content = extract_text_content(&msg.content);
-                    tool_results.push(ToolResult::success(tool_call_id, content));
+                    pending_tool_results.push(ToolResult::success(tool_call_id, content));
                 }
             }
             _ => {}
         }
     }
 
+    // 最后一条 assistant 之后的工具结果属于当前消息
+    tool_results.extend(pending_tool_results);
+
     // 处理结尾的孤立用户消息（非最后一条）
     if !user_buffer.is_empty() {
-        let merged = merge_user_buffer(&user_buffer, model_id);
+        let merged = merge_user_buffer(&user_buffer, Vec::new(), model_id);
         history.push(Message::User(merged));
 
         // 自动配对一个 "OK" 的 assistant 响应
@@ -313,8 +319,12 @@ fn parse_image_url(url: &str) -> Result<Option<KiroImage>, ConversionError> {
     }
 }
 
-/// 合并用户消息缓冲区
-fn merge_user_buffer(buffer: &[(String, Vec<KiroImage>)], model_id: &str) -> HistoryUserMessage {
+/// 合并用户消息缓冲区（连同之前轮次的工具结果）
+fn merge_user_buffer(
+    buffer: &[(String, Vec<KiroImage>)],
+    tool_results: Vec<ToolResult>,
+    model_id: &str,
+) -> HistoryUserMessage {
     let mut content_parts = Vec::new();
     let mut all_images = Vec::new();
 
@@ -332,6 +342,11 @@ fn merge_user_buffer(buffer: &[(String, Vec<KiroImage>)], model_id: &str) -> His
         user_msg = user_msg.with_images(all_images);
     }
 
+    if !tool_results.is_empty() {
+        user_msg =
+            user_msg.with_context(UserInputMessageContext::new().with_tool_results(tool_results));
+    }
+
     HistoryUserMessage {
         user_input_message: user_msg,
     }
@@ -457,6 +472,19 @@ fn validate_tool_pairing(history: &[Message], tool_results: &[ToolResult]) -> Ve
         }
     }
 
+    // 排除历史 user 消息中已配对的 tool_use
+    for msg in history {
+        if let Message::User(user_msg) = msg {
+            for result in &user_msg
+                .user_input_message
+                .user_input_message_context
+                .tool_results
+            {
+                valid_tool_use_ids.remove(&result.tool_use_id);
+            }
+        }
+    }
+
     // 过滤并验证 tool_results
     let mut filtered_results = Vec::new();
 
@@ -508,6 +536,38 @@ mod tests {
         assert!(result.is_some());
     }
 
+    #[test]
+    fn test_process_messages_multi_round_tool_results() {
+        let messages: Vec<ChatMessage> = serde_json::from_value(serde_json::json!([
+            {"role": "user", "content": "What's new in Rust?"},
+            {"role": "assistant", "content": null, "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "web_search", "arguments": "{\"query\":\"rust\"}"}}
+            ]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "result 1"},
+            {"role": "assistant", "content": null, "tool_calls": [
+                {"id": "call_2", "type": "function", "function": {"name": "web_search", "arguments": "{\"query\":\"rust 2024\"}"}}
+            ]},
+            {"role": "tool", "tool_call_id": "call_2", "content": "result 2"}
+        ]))
+        .unwrap();
+
+        let (_, history, _, _, tool_results) =
+            process_messages(&messages, "claude-sonnet-4.5").unwrap();
+
+        // 历史保持 user/assistant 交替，第一轮工具结果归入历史 user 消息
+        let roles: Vec<bool> = history.iter().map(|m| m.is_user()).collect();
+        assert_eq!(roles, [true, false, true, false]);
+        let Message::User(second_user) = &history[2] else {
+            panic!("应为 user 消息");
+        };
+        let ctx = &second_user.user_input_message.user_input_message_context;
+        assert_eq!(ctx.tool_results[0].tool_use_id, "call_1");
+
+        // 最后一轮工具结果作为当前消息，且通过配对校验
+        assert_eq!(tool_results.len(), 1);
+        assert_eq!(validate_tool_pairing(&history, &tool_results).len(), 1);
+    }
+
     #[test]
     fn test_parse_image_url_invalid() {
         let url = "invalid://url";
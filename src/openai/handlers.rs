@@ -20,13 +20,14 @@ use crate::kiro::model::events::Event;
 use crate::kiro::model::requests::kiro::KiroRequest;
 use crate::kiro::parser::decoder::EventStreamDecoder;
 use crate::kiro::provider::KiroProvider;
-use crate::request_log::RequestLogger;
+use crate::request_log::{self, RequestLogger, RequestSummary};
 
 use super::converter::{ConversionError, convert_request};
 use super::stream::{StreamContext, chunk_to_sse, done_sse};
+use super::websearch;
 use super::types::{
-    ChatCompletionRequest, ChatCompletionResponse, Choice, ErrorResponse, ResponseMessage,
-    ToolCall, FunctionCall, Usage,
+    Annotation, ChatCompletionRequest, ChatCompletionResponse, Choice, ErrorResponse,
+    ResponseMessage, ToolCall, FunctionCall, Usage,
 };
 
 /// 应用状态
@@ -34,7 +35,6 @@ use super::types::{
 pub struct AppState {
     pub api_key: String,
     pub kiro_provider: Option<Arc<KiroProvider>>,
-    pub profile_arn: Option<String>,
     pub request_logger: Option<Arc<RequestLogger>>,
 }
 
@@ -43,7 +43,6 @@ impl AppState {
         Self {
             api_key: api_key.into(),
             kiro_provider: None,
-            profile_arn: None,
             request_logger: None,
         }
     }
@@ -53,11 +52,6 @@ impl AppState {
         self
     }
 
-    pub fn with_profile_arn(mut self, arn: impl Into<String>) -> Self {
-        self.profile_arn = Some(arn.into());
-        self
-    }
-
     pub fn with_request_logger(mut self, logger: Arc<RequestLogger>) -> Self {
         self.request_logger = Some(logger);
         self
@@ -95,104 +89,72 @@ pub async fn chat_completions(
         }
     };
 
-    // 获取凭据上下文以记录使用的凭据 ID
-    let credential_id = match provider.token_manager().acquire_context().await {
-        Ok(ctx) => ctx.id,
-        Err(e) => {
-            tracing::error!("获取凭据上下文失败: {}", e);
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(ErrorResponse::new(
-                    "server_error",
-                    "No available credentials",
-                )),
-            )
-                .into_response();
-        }
+    // 请求日志记录实际处理请求的凭据与 API 区域
+    let summary = RequestSummary {
+        model: payload.model.clone(),
+        max_tokens: payload.effective_max_tokens(),
+        stream: payload.is_stream(),
+        message_count: payload.messages.len(),
     };
-
-    // 记录请求日志
-    if let Some(logger) = &state.request_logger {
-        logger.log_request(crate::request_log::RequestLogEntry {
-            id: uuid::Uuid::new_v4().to_string(),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            model: payload.model.clone(),
-            max_tokens: payload.effective_max_tokens(),
-            stream: payload.is_stream(),
-            message_count: payload.messages.len(),
-            credential_id,
-            success: true,
-        });
-    }
-
-    // 转换请求
-    let conversion_result = match convert_request(&payload) {
-        Ok(result) => result,
-        Err(e) => {
-            let message = match &e {
-                ConversionError::UnsupportedModel(model) => {
-                    format!("模型不支持: {}", model)
-                }
-                ConversionError::EmptyMessages => "消息列表为空".to_string(),
-                ConversionError::InvalidImageUrl(url) => {
-                    format!("无效的图片 URL: {}", url)
-                }
-            };
-            tracing::warn!("请求转换失败: {}", e);
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("invalid_request_error", message)),
-            )
-                .into_response();
+    request_log::track(state.request_logger.as_deref(), summary, async move {
+        // 转换请求
+        let conversion_result = match convert_request(&payload) {
+            Ok(result) => result,
+            Err(e) => {
+                let message = match &e {
+                    ConversionError::UnsupportedModel(model) => {
+                        format!("模型不支持: {}", model)
+                    }
+                    ConversionError::EmptyMessages => "消息列表为空".to_string(),
+                    ConversionError::InvalidImageUrl(url) => {
+                        format!("无效的图片 URL: {}", url)
+                    }
+                };
+                tracing::warn!("请求转换失败: {}", e);
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new("invalid_request_error", message)),
+                )
+                    .into_response();
+            }
+        };
+
+        // 需要网页搜索：由代理执行多轮搜索循环
+        if websearch::is_web_search_request(&payload) {
+            tracing::info!("检测到 WebSearch 请求，路由到网页搜索循环");
+            let input_tokens = estimate_input_tokens(&payload);
+            return websearch::handle_web_search_request(provider, payload, input_tokens)
+            .await;
         }
-    };
 
-    // 构建 Kiro 请求
-    let kiro_request = KiroRequest {
-        conversation_state: conversion_result.conversation_state,
-        profile_arn: state.profile_arn.clone(),
-    };
+        // 构建 Kiro 请求
+        let kiro_request = KiroRequest::new(conversion_result.conversation_state);
 
-    let request_body = match serde_json::to_string(&kiro_request) {
-        Ok(body) => body,
-        Err(e) => {
-            tracing::error!("序列化请求失败: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    "server_error",
-                    format!("序列化请求失败: {}", e),
-                )),
+        // 估算输入 tokens（简化版本）
+        let input_tokens = estimate_input_tokens(&payload);
+
+        if payload.is_stream() {
+            // 流式响应
+            handle_stream_request(
+                provider,
+                &kiro_request,
+                &conversion_result.original_model,
+                input_tokens,
+                payload.include_usage_in_stream(),
             )
-                .into_response();
+            .await
+        } else {
+            // 非流式响应
+            handle_non_stream_request(
+                provider,
+                &kiro_request,
+                &conversion_result.original_model,
+                input_tokens,
+            )
+            .await
         }
-    };
-
-    tracing::debug!("Kiro request body: {}", request_body);
-
-    // 估算输入 tokens（简化版本）
-    let input_tokens = estimate_input_tokens(&payload);
-
-    if payload.is_stream() {
-        // 流式响应
-        handle_stream_request(
-            provider,
-            &request_body,
-            &conversion_result.original_model,
-            input_tokens,
-            payload.include_usage_in_stream(),
-        )
-        .await
-    } else {
-        // 非流式响应
-        handle_non_stream_request(
-            provider,
-            &request_body,
-            &conversion_result.original_model,
-            input_tokens,
-        )
-        .await
-    }
+    })
+    .await
 }
 
 /// 估算输入 tokens
@@ -234,13 +196,13 @@ fn estimate_input_tokens(payload: &ChatCompletionRequest) -> i32 {
 /// 处理流式请求
 async fn handle_stream_request(
     provider: Arc<KiroProvider>,
-    request_body: &str,
+    request: &KiroRequest,
     model: &str,
     input_tokens: i32,
     include_usage: bool,
 ) -> Response {
     // 调用 Kiro API
-    let response = match provider.call_api_stream(request_body).await {
+    let response = match provider.call_api_stream(request).await {
         Ok(resp) => resp,
         Err(e) => {
             tracing::error!("Kiro API 调用失败: {}", e);
@@ -275,10 +237,10 @@ async fn handle_stream_request(
 }
 
 /// Ping 事件间隔（25秒）
-const PING_INTERVAL_SECS: u64 = 25;
+pub(super) const PING_INTERVAL_SECS: u64 = 25;
 
 /// 创建 ping 事件的 SSE 字符串
-fn create_ping_sse() -> Bytes {
+pub(super) fn create_ping_sse() -> Bytes {
     Bytes::from(": ping\n\n")
 }
 
@@ -373,12 +335,12 @@ fn create_sse_stream(
 /// 处理非流式请求
 async fn handle_non_stream_request(
     provider: Arc<KiroProvider>,
-    request_body: &str,
+    request: &KiroRequest,
     model: &str,
     input_tokens: i32,
 ) -> Response {
     // 调用 Kiro API
-    let response = match provider.call_api(request_body).await {
+    let response = match provider.call_api(request).await {
         Ok(resp) => resp,
         Err(e) => {
             tracing::error!("Kiro API 调用失败: {}", e);
@@ -409,9 +371,28 @@ async fn handle_non_stream_request(
         }
     };
 
+    let parsed = parse_kiro_response(&body_bytes, input_tokens);
+
+    // 构建响应
+    let response_body = build_completion_response(model, parsed, None);
+
+    (StatusCode::OK, Json(response_body)).into_response()
+}
+
+/// 非流式响应解析结果
+pub(super) struct ParsedResponse {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub finish_reason: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+}
+
+/// 解析 Kiro 非流式响应体
+pub(super) fn parse_kiro_response(body_bytes: &[u8], input_tokens: i32) -> ParsedResponse {
     // 解析事件流
     let mut decoder = EventStreamDecoder::new();
-    if let Err(e) = decoder.feed(&body_bytes) {
+    if let Err(e) = decoder.feed(body_bytes) {
         tracing::warn!("缓冲区溢出: {}", e);
     }
 
@@ -481,10 +462,22 @@ async fn handle_non_stream_request(
     }
 
     // 使用从 contextUsageEvent 计算的 input_tokens
-    let final_input_tokens = context_input_tokens.unwrap_or(input_tokens);
+    ParsedResponse {
+        text: text_content,
+        tool_calls,
+        finish_reason,
+        input_tokens: context_input_tokens.unwrap_or(input_tokens),
+        output_tokens,
+    }
+}
 
-    // 构建响应
-    let response_body = ChatCompletionResponse {
+/// 构建非流式 Chat Completion 响应
+pub(super) fn build_completion_response(
+    model: &str,
+    parsed: ParsedResponse,
+    annotations: Option<Vec<Annotation>>,
+) -> ChatCompletionResponse {
+    ChatCompletionResponse {
         id: format!("chatcmpl-{}", Uuid::new_v4().to_string().replace('-', "")),
         object: "chat.completion".to_string(),
         created: chrono::Utc::now().timestamp(),
@@ -493,28 +486,27 @@ async fn handle_non_stream_request(
             index: 0,
             message: ResponseMessage {
                 role: "assistant".to_string(),
-                content: if text_content.is_empty() {
+                content: if parsed.text.is_empty() {
                     None
                 } else {
-                    Some(text_content)
+                    Some(parsed.text)
                 },
-                tool_calls: if tool_calls.is_empty() {
+                tool_calls: if parsed.tool_calls.is_empty() {
                     None
                 } else {
-                    Some(tool_calls)
+                    Some(parsed.tool_calls)
                 },
+                annotations,
             },
-            finish_reason: Some(finish_reason),
+            finish_reason: Some(parsed.finish_reason),
         }],
         usage: Some(Usage {
-            prompt_tokens: final_input_tokens,
-            completion_tokens: output_tokens,
-            total_tokens: final_input_tokens + output_tokens,
+            prompt_tokens: parsed.input_tokens,
+            completion_tokens: parsed.output_tokens,
+            total_tokens: parsed.input_tokens + parsed.output_tokens,
         }),
         system_fingerprint: None,
-    };
-
-    (StatusCode::OK, Json(response_body)).into_response()
+    }
 }
 
 /// 过滤 thinking 标签
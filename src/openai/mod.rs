@@ -8,5 +8,6 @@ mod handlers;
 mod router;
 mod stream;
 mod types;
+mod websearch;
 
 pub use router::create_router_with_provider;
@@ -62,16 +62,12 @@ fn cors_layer() -> tower_http::cors::CorsLayer {
 pub fn create_router_with_provider(
     api_key: impl Into<String>,
     kiro_provider: Option<KiroProvider>,
-    profile_arn: Option<String>,
     request_logger: Option<Arc<RequestLogger>>,
 ) -> Router {
     let mut state = AppState::new(api_key);
     if let Some(provider) = kiro_provider {
         state = state.with_kiro_provider(provider);
     }
-    if let Some(arn) = profile_arn {
-        state = state.with_profile_arn(arn);
-    }
     if let Some(logger) = request_logger {
         state = state.with_request_logger(logger);
     }
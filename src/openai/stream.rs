@@ -76,6 +76,7 @@ impl StreamContext {
                     role: Some("assistant".to_string()),
                     content: None,
                     tool_calls: None,
+                    annotations: None,
                 },
                 finish_reason: None,
             }],
@@ -149,6 +150,7 @@ impl StreamContext {
                     role: None,
                     content: Some(filtered_content),
                     tool_calls: None,
+                    annotations: None,
                 },
                 finish_reason: None,
             }],
@@ -224,6 +226,7 @@ impl StreamContext {
                     role: None,
                     content: None,
                     tool_calls: Some(vec![tool_call]),
+                    annotations: None,
                 },
                 finish_reason: None,
             }],
@@ -73,6 +73,9 @@ pub struct ChatCompletionRequest {
     /// 流式响应选项
     #[serde(default)]
     pub stream_options: Option<StreamOptions>,
+    /// 网页搜索选项（存在即启用由代理执行的网页搜索）
+    #[serde(default)]
+    pub web_search_options: Option<serde_json::Value>,
 }
 
 impl ChatCompletionRequest {
@@ -213,6 +216,26 @@ pub struct ResponseMessage {
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// 网页搜索引用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
+}
+
+/// 消息注解（目前仅 url_citation）
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    #[serde(rename = "type")]
+    pub annotation_type: String,
+    pub url_citation: UrlCitation,
+}
+
+/// URL 引用，索引为 content 中的字符位置
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlCitation {
+    pub start_index: usize,
+    pub end_index: usize,
+    pub url: String,
+    pub title: String,
 }
 
 /// Token 使用统计
@@ -255,6 +278,9 @@ pub struct Delta {
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<DeltaToolCall>>,
+    /// 网页搜索引用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
 }
 
 /// 增量工具调用
@@ -0,0 +1,431 @@
+//! OpenAI 端网页搜索
+//!
+//! 请求带 `web_search_options` 或声明了名为 `web_search` 的函数工具时启用：
+//! 代理通过 Kiro MCP 执行模型发起的 web_search 调用，把结果作为 tool 消息回填并继续请求，
+//! 直到模型给出最终回答或达到搜索次数上限。
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use crate::anthropic::websearch as kiro_search;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::provider::KiroProvider;
+
+use super::converter::convert_request;
+use super::handlers::{
+    PING_INTERVAL_SECS, ParsedResponse, build_completion_response, create_ping_sse,
+    parse_kiro_response,
+};
+use super::stream::{StreamContext, chunk_to_sse, done_sse};
+use super::types::{
+    Annotation, ChatCompletionChunk, ChatCompletionRequest, ChatMessage, ChunkChoice, Delta,
+    DeltaFunction, DeltaToolCall, ErrorResponse, FunctionDefinition, MessageContent, Tool,
+    ToolCall, UrlCitation,
+};
+
+/// 由代理执行的函数工具名
+pub const WEB_SEARCH_TOOL_NAME: &str = "web_search";
+
+/// 是否需要由代理执行网页搜索
+pub fn is_web_search_request(req: &ChatCompletionRequest) -> bool {
+    req.web_search_options.is_some() || has_web_search_function(req)
+}
+
+fn has_web_search_function(req: &ChatCompletionRequest) -> bool {
+    req.tools
+        .iter()
+        .flatten()
+        .any(|t| t.tool_type == "function" && t.function.name == WEB_SEARCH_TOOL_NAME)
+}
+
+/// 通过 web_search_options 启用时，注入 web_search 函数定义供模型调用
+fn ensure_web_search_tool(req: &mut ChatCompletionRequest) {
+    if has_web_search_function(req) {
+        return;
+    }
+    let parameters = serde_json::from_value(kiro_search::web_search_input_schema()).ok();
+    req.tools.get_or_insert_with(Vec::new).push(Tool {
+        tool_type: "function".to_string(),
+        function: FunctionDefinition {
+            name: WEB_SEARCH_TOOL_NAME.to_string(),
+            description: Some(kiro_search::WEB_SEARCH_TOOL_DESCRIPTION.to_string()),
+            parameters,
+        },
+    });
+}
+
+/// 搜索循环的最终结果
+pub struct SearchOutcome {
+    /// 最终响应（文本为各轮文本拼接，token 为各轮累计）
+    pub response: ParsedResponse,
+    /// 回答中出现的搜索结果 URL
+    pub annotations: Vec<Annotation>,
+    /// 实际执行的搜索次数
+    pub web_search_requests: i32,
+}
+
+/// 执行网页搜索循环
+pub async fn run_web_search_loop(
+    provider: &KiroProvider,
+    mut req: ChatCompletionRequest,
+    input_tokens: i32,
+) -> anyhow::Result<SearchOutcome> {
+    ensure_web_search_tool(&mut req);
+
+    let max_uses = kiro_search::DEFAULT_MAX_USES;
+    let mut response = ParsedResponse {
+        text: String::new(),
+        tool_calls: Vec::new(),
+        finish_reason: "stop".to_string(),
+        input_tokens: 0,
+        output_tokens: 0,
+    };
+    let mut sources: Vec<(String, String)> = Vec::new();
+    let mut web_search_requests = 0;
+
+    // 超出上限后模型仍可能继续调用搜索，额外留出两轮让其基于已有结果作答
+    let max_turns = max_uses as usize + 2;
+    for _ in 0..max_turns {
+        let parsed = call_model(provider, &req, input_tokens).await?;
+        response.input_tokens += parsed.input_tokens;
+        response.output_tokens += parsed.output_tokens;
+        response.text.push_str(&parsed.text);
+        response.finish_reason = parsed.finish_reason;
+
+        let (search_calls, client_calls): (Vec<ToolCall>, Vec<ToolCall>) = parsed
+            .tool_calls
+            .into_iter()
+            .partition(|c| c.function.name == WEB_SEARCH_TOOL_NAME);
+
+        // 没有搜索调用，或同时调用了客户端工具：把结果交还给调用方
+        if search_calls.is_empty() || !client_calls.is_empty() {
+            if !search_calls.is_empty() {
+                tracing::warn!("同一轮中同时调用了客户端工具，忽略本轮的 web_search 调用");
+                response.finish_reason = "tool_calls".to_string();
+            }
+            response.tool_calls = client_calls;
+            let annotations = url_citations(&response.text, &sources);
+            return Ok(SearchOutcome {
+                response,
+                annotations,
+                web_search_requests,
+            });
+        }
+
+        req.messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: (!parsed.text.is_empty()).then_some(MessageContent::Text(parsed.text)),
+            tool_calls: Some(search_calls.clone()),
+            tool_call_id: None,
+            name: None,
+        });
+
+        for call in search_calls {
+            let query = serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+                .ok()
+                .and_then(|v| v["query"].as_str().map(str::to_string))
+                .unwrap_or_default();
+
+            let content = if web_search_requests >= max_uses {
+                "Error: the web search limit for this request has been reached. \
+                 Answer using the information already gathered."
+                    .to_string()
+            } else {
+                web_search_requests += 1;
+                tracing::info!(query = %query, "处理 WebSearch 请求");
+                match kiro_search::search(provider, &query).await {
+                    Ok(results) => {
+                        for r in results.iter().flat_map(|r| &r.results) {
+                            if !sources.iter().any(|(url, _)| url == &r.url) {
+                                sources.push((r.url.clone(), r.title.clone()));
+                            }
+                        }
+                        kiro_search::generate_search_summary(&query, &results)
+                    }
+                    Err(e) => {
+                        tracing::warn!("MCP API 调用失败: {}", e);
+                        format!("Error: web search failed: {}", e)
+                    }
+                }
+            };
+
+            req.messages.push(ChatMessage {
+                role: "tool".to_string(),
+                content: Some(MessageContent::Text(content)),
+                tool_calls: None,
+                tool_call_id: Some(call.id),
+                name: None,
+            });
+        }
+    }
+
+    tracing::warn!("网页搜索循环达到最大轮次 {}，提前结束", max_turns);
+    response.finish_reason = "stop".to_string();
+    let annotations = url_citations(&response.text, &sources);
+    Ok(SearchOutcome {
+        response,
+        annotations,
+        web_search_requests,
+    })
+}
+
+/// 以非流式方式调用一次模型
+async fn call_model(
+    provider: &KiroProvider,
+    req: &ChatCompletionRequest,
+    input_tokens: i32,
+) -> anyhow::Result<ParsedResponse> {
+    let conversion_result = convert_request(req)?;
+    let kiro_request = KiroRequest::new(conversion_result.conversation_state);
+
+    let response = provider.call_api(&kiro_request).await?;
+    let body_bytes = response.bytes().await?;
+    Ok(parse_kiro_response(&body_bytes, input_tokens))
+}
+
+/// 为回答中出现的搜索结果 URL 生成 url_citation（索引按字符计）
+fn url_citations(text: &str, sources: &[(String, String)]) -> Vec<Annotation> {
+    sources
+        .iter()
+        .filter_map(|(url, title)| {
+            let byte_index = text.find(url.as_str())?;
+            let start_index = text[..byte_index].chars().count();
+            Some(Annotation {
+                annotation_type: "url_citation".to_string(),
+                url_citation: UrlCitation {
+                    start_index,
+                    end_index: start_index + url.chars().count(),
+                    url: url.clone(),
+                    title: title.clone(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// 处理网页搜索请求（流式与非流式）
+pub async fn handle_web_search_request(
+    provider: Arc<KiroProvider>,
+    payload: ChatCompletionRequest,
+    input_tokens: i32,
+) -> Response {
+    let model = payload.model.clone();
+
+    if payload.is_stream() {
+        let ctx = StreamContext::new(&model, input_tokens, payload.include_usage_in_stream());
+        let handle = tokio::spawn(async move {
+            run_web_search_loop(&provider, payload, input_tokens).await
+        });
+
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header(header::CONNECTION, "keep-alive")
+            .body(Body::from_stream(create_sse_stream(handle, ctx)))
+            .unwrap();
+    }
+
+    match run_web_search_loop(&provider, payload, input_tokens).await {
+        Ok(outcome) => {
+            tracing::info!(searches = outcome.web_search_requests, "网页搜索循环完成");
+            let annotations = (!outcome.annotations.is_empty()).then_some(outcome.annotations);
+            let body = build_completion_response(&model, outcome.response, annotations);
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("网页搜索循环失败: {}", e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse::new(
+                    "server_error",
+                    format!("上游 API 调用失败: {}", e),
+                )),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 创建 SSE 事件流：等待循环结束期间发送 ping，结束后一次性输出结果
+fn create_sse_stream(
+    handle: JoinHandle<anyhow::Result<SearchOutcome>>,
+    mut ctx: StreamContext,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let initial_chunk = ctx.generate_initial_chunk();
+    let initial_stream = stream::iter(vec![Ok(Bytes::from(chunk_to_sse(&initial_chunk)))]);
+
+    let processing_stream = stream::unfold(
+        (
+            Some(handle),
+            ctx,
+            interval(Duration::from_secs(PING_INTERVAL_SECS)),
+        ),
+        |(handle, mut ctx, mut ping_interval)| async move {
+            let mut handle = handle?;
+            tokio::select! {
+                result = &mut handle => {
+                    let sse_data = match result {
+                        Ok(Ok(outcome)) => outcome_to_sse(&mut ctx, outcome),
+                        Ok(Err(e)) => error_sse(format!("上游 API 调用失败: {}", e)),
+                        Err(e) => error_sse(format!("网页搜索任务失败: {}", e)),
+                    };
+                    Some((stream::iter(sse_data), (None, ctx, ping_interval)))
+                }
+                _ = ping_interval.tick() => {
+                    tracing::trace!("发送 ping 保活事件");
+                    let sse_data: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
+                    Some((stream::iter(sse_data), (Some(handle), ctx, ping_interval)))
+                }
+            }
+        },
+    )
+    .flatten();
+
+    initial_stream.chain(processing_stream)
+}
+
+/// 将循环结果转换为内容 chunk、最终 chunk 与 [DONE]
+fn outcome_to_sse(
+    ctx: &mut StreamContext,
+    outcome: SearchOutcome,
+) -> Vec<Result<Bytes, Infallible>> {
+    let response = outcome.response;
+    let mut chunks = Vec::new();
+
+    if !response.text.is_empty() || !outcome.annotations.is_empty() {
+        chunks.push(delta_chunk(
+            ctx,
+            Delta {
+                content: Some(response.text),
+                annotations: (!outcome.annotations.is_empty()).then_some(outcome.annotations),
+                ..Default::default()
+            },
+        ));
+    }
+
+    if !response.tool_calls.is_empty() {
+        let tool_calls = response
+            .tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, call)| DeltaToolCall {
+                index: i as i32,
+                id: Some(call.id),
+                call_type: Some(call.call_type),
+                function: Some(DeltaFunction {
+                    name: Some(call.function.name),
+                    arguments: Some(call.function.arguments),
+                }),
+            })
+            .collect();
+        chunks.push(delta_chunk(
+            ctx,
+            Delta {
+                tool_calls: Some(tool_calls),
+                ..Default::default()
+            },
+        ));
+    }
+
+    ctx.input_tokens = response.input_tokens;
+    ctx.output_tokens = response.output_tokens;
+    ctx.finish_reason = Some(response.finish_reason);
+    chunks.extend(ctx.generate_final_chunk());
+
+    let mut sse_data: Vec<Result<Bytes, Infallible>> = chunks
+        .iter()
+        .map(|c| Ok(Bytes::from(chunk_to_sse(c))))
+        .collect();
+    sse_data.push(Ok(Bytes::from(done_sse())));
+    sse_data
+}
+
+fn delta_chunk(ctx: &StreamContext, delta: Delta) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        id: ctx.response_id.clone(),
+        object: "chat.completion.chunk".to_string(),
+        created: ctx.created,
+        model: ctx.model.clone(),
+        choices: vec![ChunkChoice {
+            index: 0,
+            delta,
+            finish_reason: None,
+        }],
+        usage: None,
+        system_fingerprint: None,
+    }
+}
+
+fn error_sse(message: String) -> Vec<Result<Bytes, Infallible>> {
+    tracing::error!("{}", message);
+    let error =
+        serde_json::to_string(&ErrorResponse::new("server_error", message)).unwrap_or_default();
+    vec![
+        Ok(Bytes::from(format!("data: {}\n\n", error))),
+        Ok(Bytes::from(done_sse())),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(body: serde_json::Value) -> ChatCompletionRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_is_web_search_request() {
+        let req = request(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "messages": [{"role": "user", "content": "hi"}]
+        }));
+        assert!(!is_web_search_request(&req));
+
+        let req = request(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "tools": [{"type": "function", "function": {"name": "web_search"}}]
+        }));
+        assert!(is_web_search_request(&req));
+
+        let mut req = request(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "web_search_options": {}
+        }));
+        assert!(is_web_search_request(&req));
+        ensure_web_search_tool(&mut req);
+        ensure_web_search_tool(&mut req);
+        let tools = req.tools.as_ref().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert!(tools[0].function.parameters.is_some());
+    }
+
+    #[test]
+    fn test_url_citations() {
+        let sources = vec![
+            ("https://www.rust-lang.org".to_string(), "Rust".to_string()),
+            ("https://example.com".to_string(), "Example".to_string()),
+        ];
+        let text = "详见 https://www.rust-lang.org 官网";
+        let annotations = url_citations(text, &sources);
+        assert_eq!(annotations.len(), 1);
+        let citation = &annotations[0].url_citation;
+        assert_eq!(citation.start_index, 3);
+        assert_eq!(citation.end_index, 3 + "https://www.rust-lang.org".len());
+        assert_eq!(citation.title, "Rust");
+    }
+}
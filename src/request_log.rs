@@ -2,14 +2,36 @@
 //!
 //! 提供内存中的请求日志记录功能，用于 Admin UI 实时显示
 
+use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::future::Future;
 use std::sync::Arc;
+use axum::response::Response;
 use parking_lot::Mutex;
 use serde::Serialize;
 
 /// 最大日志条目数
 const MAX_LOG_ENTRIES: usize = 50;
 
+tokio::task_local! {
+    static SERVED: RefCell<Option<ServedBy>>;
+}
+
+/// 实际发送上游请求的凭据与 API 区域（附加在上游响应的 extensions 中）
+#[derive(Debug, Clone)]
+pub struct ServedBy {
+    pub credential_id: u64,
+    pub region: String,
+}
+
+/// 请求摘要（处理完成后与实际使用的凭据一起记录）
+pub struct RequestSummary {
+    pub model: String,
+    pub max_tokens: i32,
+    pub stream: bool,
+    pub message_count: usize,
+}
+
 /// 单个请求日志条目
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,8 +48,12 @@ pub struct RequestLogEntry {
     pub stream: bool,
     /// 消息数量
     pub message_count: usize,
-    /// 使用的凭据 ID
-    pub credential_id: u64,
+    /// 实际处理请求的凭据 ID（未发出上游请求时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<u64>,
+    /// 实际处理请求的 API 区域
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
     /// 请求是否成功
     pub success: bool,
 }
@@ -62,6 +88,65 @@ impl RequestLogger {
         let logs = self.logs.lock();
         logs.iter().rev().cloned().collect()
     }
+
+    /// 将内存中的日志输出到进程日志并清空（关闭前调用，避免记录随进程丢失）
+    pub fn flush(&self) {
+        let logs: Vec<RequestLogEntry> = self.logs.lock().drain(..).collect();
+        for entry in &logs {
+            tracing::info!(
+                id = %entry.id,
+                timestamp = %entry.timestamp,
+                model = %entry.model,
+                stream = entry.stream,
+                credential_id = ?entry.credential_id,
+                region = ?entry.region,
+                success = entry.success,
+                "请求记录"
+            );
+        }
+        tracing::info!("已输出 {} 条请求记录", logs.len());
+    }
+}
+
+/// 记录上游响应的实际凭据（仅在 `track` 作用域内生效，多次调用时以最后一次为准）
+pub fn report_served(response: &reqwest::Response) {
+    if let Some(served) = response.extensions().get::<ServedBy>() {
+        let _ = SERVED.try_with(|cell| *cell.borrow_mut() = Some(served.clone()));
+    }
+}
+
+/// 处理请求并记录请求日志
+///
+/// 凭据 ID 与 API 区域取自处理过程中实际发送上游请求的凭据（见 `report_served`），
+/// 响应状态非 2xx 时记录为失败
+pub async fn track(
+    logger: Option<&RequestLogger>,
+    summary: RequestSummary,
+    handle: impl Future<Output = Response>,
+) -> Response {
+    let id = uuid::Uuid::new_v4().to_string();
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let (response, served) = SERVED
+        .scope(RefCell::new(None), async {
+            let response = handle.await;
+            (response, SERVED.with(|cell| cell.borrow_mut().take()))
+        })
+        .await;
+
+    if let Some(logger) = logger {
+        logger.log_request(RequestLogEntry {
+            id,
+            timestamp,
+            model: summary.model,
+            max_tokens: summary.max_tokens,
+            stream: summary.stream,
+            message_count: summary.message_count,
+            credential_id: served.as_ref().map(|s| s.credential_id),
+            region: served.map(|s| s.region),
+            success: response.status().is_success(),
+        });
+    }
+    response
 }
 
 impl Default for RequestLogger {
@@ -69,3 +154,65 @@ impl Default for RequestLogger {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    fn summary() -> RequestSummary {
+        RequestSummary {
+            model: "claude-sonnet-4".to_string(),
+            max_tokens: 1024,
+            stream: true,
+            message_count: 1,
+        }
+    }
+
+    fn upstream(served: Option<ServedBy>) -> reqwest::Response {
+        let mut response = http::Response::new(reqwest::Body::from(""));
+        if let Some(served) = served {
+            response.extensions_mut().insert(served);
+        }
+        reqwest::Response::from(response)
+    }
+
+    #[tokio::test]
+    async fn test_track_records_serving_credential() {
+        let logger = RequestLogger::new();
+        let response = track(Some(&logger), summary(), async {
+            // 多次上游调用（如服务端工具循环）以最后一次为准
+            report_served(&upstream(Some(ServedBy {
+                credential_id: 1,
+                region: "us-east-1".to_string(),
+            })));
+            report_served(&upstream(Some(ServedBy {
+                credential_id: 2,
+                region: "eu-central-1".to_string(),
+            })));
+            StatusCode::OK.into_response()
+        })
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let logs = logger.get_logs();
+        assert_eq!(logs[0].credential_id, Some(2));
+        assert_eq!(logs[0].region.as_deref(), Some("eu-central-1"));
+        assert!(logs[0].success);
+    }
+
+    #[tokio::test]
+    async fn test_track_without_upstream_request() {
+        let logger = RequestLogger::new();
+        track(Some(&logger), summary(), async {
+            report_served(&upstream(None));
+            StatusCode::BAD_REQUEST.into_response()
+        })
+        .await;
+
+        let logs = logger.get_logs();
+        assert_eq!(logs[0].credential_id, None);
+        assert!(!logs[0].success);
+    }
+}
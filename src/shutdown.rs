@@ -0,0 +1,448 @@
+//! 优雅关闭
+//!
+//! 收到 SIGTERM/SIGINT 后停止接收新请求，等待进行中的流式响应自然结束；
+//! 超过 `shutdownTimeoutSecs` 仍未结束的流会收到一个对应协议格式的错误事件后关闭
+//! （Gemini 非 SSE 的 streamGenerateContent 追加错误元素并闭合 JSON 数组）。
+
+use std::future::{Future, IntoFuture};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Request, State},
+    http::{StatusCode, Uri, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde_json::json;
+use tokio::sync::watch;
+
+/// 截止后等待错误事件发出、连接关闭的时间
+const FLUSH_GRACE: Duration = Duration::from_secs(5);
+
+/// 截止时发送给客户端的错误信息
+const DEADLINE_MESSAGE: &str = "服务正在关闭，响应已中断";
+
+/// 截止时追加的收尾内容
+#[derive(Debug, Clone)]
+enum Closing {
+    /// 流式错误事件（SSE / NDJSON）
+    Event(Bytes),
+    /// Gemini JSON 数组：追加错误元素并闭合数组
+    JsonArray,
+}
+
+impl Closing {
+    /// 生成收尾内容，`has_element` 表示数组中是否已有元素
+    fn payload(self, has_element: bool) -> Bytes {
+        match self {
+            Closing::Event(event) => event,
+            Closing::JsonArray => {
+                let separator = if has_element { "," } else { "" };
+                Bytes::from(format!("{}{}]", separator, gemini_error()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Running,
+    /// 已收到关闭信号，等待进行中的流结束
+    Draining,
+    /// 等待超时，未结束的流发送错误事件后关闭
+    Expired,
+}
+
+/// 关闭协调器
+pub struct Shutdown {
+    phase: watch::Sender<Phase>,
+    /// 进行中的流式响应数
+    in_flight: AtomicUsize,
+}
+
+impl Shutdown {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            phase: watch::Sender::new(Phase::Running),
+            in_flight: AtomicUsize::new(0),
+        })
+    }
+
+    /// 是否已开始关闭
+    pub fn is_draining(&self) -> bool {
+        *self.phase.borrow() != Phase::Running
+    }
+
+    /// 进行中的流式响应数
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// 开始关闭：拒绝新请求，等待进行中的流结束
+    pub fn begin(&self) {
+        self.phase.send_if_modified(|phase| {
+            let changed = *phase == Phase::Running;
+            if changed {
+                *phase = Phase::Draining;
+            }
+            changed
+        });
+    }
+
+    fn expire(&self) {
+        self.phase.send_replace(Phase::Expired);
+    }
+
+    /// 等待进入指定阶段
+    fn wait_phase(&self, phase: Phase) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.phase.subscribe();
+        async move {
+            let _ = rx.wait_for(|current| *current >= phase).await;
+        }
+    }
+
+    /// 开始关闭时完成（用于 `axum::serve(..).with_graceful_shutdown`）
+    pub fn draining(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.wait_phase(Phase::Draining)
+    }
+
+    /// 后台监听 SIGTERM/SIGINT，收到后开始关闭
+    pub fn spawn_signal_handler(self: &Arc<Self>) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let signal = wait_signal().await;
+            tracing::info!("收到 {}，开始优雅关闭", signal);
+            shutdown.begin();
+        });
+    }
+
+    /// 运行服务直到关闭完成
+    ///
+    /// 开始关闭后最多等待 `timeout`，之后向未结束的流发送错误事件，
+    /// 再等待 `FLUSH_GRACE` 让错误事件发出。
+    pub async fn run<F>(&self, serve: F, timeout: Duration) -> std::io::Result<()>
+    where
+        F: IntoFuture<Output = std::io::Result<()>>,
+    {
+        let serve = serve.into_future();
+        tokio::pin!(serve);
+
+        tokio::select! {
+            result = &mut serve => return result,
+            _ = self.draining() => {}
+        }
+
+        tracing::info!(
+            "已停止接收新请求，等待 {} 个进行中的流式响应结束（最长 {} 秒）",
+            self.in_flight(),
+            timeout.as_secs()
+        );
+        if let Ok(result) = tokio::time::timeout(timeout, &mut serve).await {
+            return result;
+        }
+
+        tracing::warn!(
+            "等待超时，向 {} 个未结束的流式响应发送错误事件",
+            self.in_flight()
+        );
+        self.expire();
+        match tokio::time::timeout(FLUSH_GRACE, serve).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!("仍有请求未结束，强制退出");
+                Ok(())
+            }
+        }
+    }
+
+    /// 跟踪流式响应体：截止时追加收尾内容（如有）并结束
+    fn track<S>(
+        self: &Arc<Self>,
+        body: S,
+        closing: Option<Closing>,
+    ) -> impl Stream<Item = Result<Bytes, axum::Error>> + Send + 'static
+    where
+        S: Stream<Item = Result<Bytes, axum::Error>> + Send + Unpin + 'static,
+    {
+        let guard = InFlightGuard::new(self.clone());
+        let expired = Box::pin(self.wait_phase(Phase::Expired));
+
+        futures::stream::unfold(
+            Some((body, expired, closing, false, guard)),
+            |state| async move {
+                let (mut body, mut expired, closing, mut has_element, guard) = state?;
+                tokio::select! {
+                    chunk = body.next() => {
+                        let chunk = chunk?;
+                        // JSON 数组模式下首个元素之前只有 `[` 与空白
+                        if let Ok(bytes) = &chunk {
+                            has_element |= bytes.contains(&b'{');
+                        }
+                        Some((chunk, Some((body, expired, closing, has_element, guard))))
+                    }
+                    _ = &mut expired => {
+                        drop(guard);
+                        closing.map(|closing| (Ok(closing.payload(has_element)), None))
+                    }
+                }
+            },
+        )
+    }
+}
+
+/// 进行中流式响应计数（响应体结束或被丢弃时释放）
+struct InFlightGuard(Arc<Shutdown>);
+
+impl InFlightGuard {
+    fn new(shutdown: Arc<Shutdown>) -> Self {
+        shutdown.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(shutdown)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 等待 SIGTERM 或 SIGINT，返回信号名
+async fn wait_signal() -> &'static str {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("监听 SIGINT 失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("监听 SIGTERM 失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+/// Gemini 错误对象
+fn gemini_error() -> serde_json::Value {
+    json!({
+        "error": { "code": 503, "message": DEADLINE_MESSAGE, "status": "UNAVAILABLE" }
+    })
+}
+
+/// 是否为 Gemini 非 SSE 的流式请求（响应为逐步写入的 JSON 数组）
+fn is_gemini_json_stream(uri: &Uri) -> bool {
+    uri.path().starts_with("/v1beta/")
+        && uri.path().ends_with(":streamGenerateContent")
+        && !uri
+            .query()
+            .is_some_and(|query| query.split('&').any(|pair| pair == "alt=sse"))
+}
+
+/// 按请求生成截止时的收尾内容（与各协议的流式错误格式一致）
+fn closing_for(uri: &Uri) -> Option<Closing> {
+    if is_gemini_json_stream(uri) {
+        return Some(Closing::JsonArray);
+    }
+    let path = uri.path();
+    let event = if path.starts_with("/v1/chat/completions") {
+        let error = json!({
+            "error": {
+                "message": DEADLINE_MESSAGE,
+                "type": "server_error",
+                "param": null,
+                "code": null,
+            }
+        });
+        format!("data: {}\n\ndata: [DONE]\n\n", error)
+    } else if path.starts_with("/v1/") {
+        let error = json!({
+            "type": "error",
+            "error": { "type": "overloaded_error", "message": DEADLINE_MESSAGE },
+        });
+        format!("event: error\ndata: {}\n\n", error)
+    } else if path.starts_with("/v1beta/") {
+        format!("data: {}\n\n", gemini_error())
+    } else if path.starts_with("/api/") {
+        format!("{}\n", json!({ "error": DEADLINE_MESSAGE }))
+    } else {
+        return None;
+    };
+    Some(Closing::Event(Bytes::from(event)))
+}
+
+/// 是否为流式响应（SSE 或 NDJSON）
+fn is_streaming(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.starts_with("text/event-stream") || v.starts_with("application/x-ndjson")
+        })
+}
+
+/// 关闭期间拒绝新请求，并跟踪进行中的流式响应
+pub async fn drain_middleware(
+    State(shutdown): State<Arc<Shutdown>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if shutdown.is_draining() {
+        let error = json!({
+            "error": { "type": "service_unavailable", "message": "服务正在关闭" }
+        });
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::CONNECTION, "close")],
+            Json(error),
+        )
+            .into_response();
+    }
+
+    let closing = closing_for(request.uri());
+    let response = next.run(request).await;
+    // Gemini JSON 数组模式的 Content-Type 是 application/json，按路由识别
+    let json_stream = matches!(closing, Some(Closing::JsonArray)) && response.status().is_success();
+    if !json_stream && !is_streaming(&response) {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(shutdown.track(body.into_data_stream(), closing));
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_expired_stream_gets_error_event() {
+        let shutdown = Shutdown::new();
+        let body = futures::stream::iter(vec![Ok(Bytes::from("data: a\n\n"))])
+            .chain(futures::stream::pending());
+        let mut stream = Box::pin(shutdown.track(body, closing("/v1/messages")));
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "data: a\n\n");
+        assert_eq!(shutdown.in_flight(), 1);
+
+        // 开始关闭不会中断进行中的流
+        shutdown.begin();
+        assert!(shutdown.is_draining());
+        let pending = tokio::time::timeout(Duration::from_millis(20), stream.next()).await;
+        assert!(pending.is_err());
+
+        shutdown.expire();
+        let event = stream.next().await.unwrap().unwrap();
+        assert!(event.starts_with(b"event: error\n"));
+        assert!(stream.next().await.is_none());
+        assert_eq!(shutdown.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_finished_stream_releases_in_flight() {
+        let shutdown = Shutdown::new();
+        let body = futures::stream::iter(vec![Ok(Bytes::from("{}\n"))]);
+        let stream = shutdown.track(body, closing("/api/chat"));
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(shutdown.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_expires_after_timeout() {
+        let shutdown = Shutdown::new();
+        // 模拟一直有未结束的流：截止后才完成
+        let serve = shutdown.wait_phase(Phase::Expired);
+        let serve = async move {
+            serve.await;
+            Ok(())
+        };
+
+        shutdown.begin();
+        shutdown
+            .run(serve, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(*shutdown.phase.borrow(), Phase::Expired);
+    }
+
+    fn closing(uri: &str) -> Option<Closing> {
+        closing_for(&uri.parse().unwrap())
+    }
+
+    fn deadline_event(uri: &str) -> Bytes {
+        closing(uri).unwrap().payload(false)
+    }
+
+    #[test]
+    fn test_deadline_event_format() {
+        let openai = deadline_event("/v1/chat/completions");
+        assert!(openai.starts_with(b"data: {\"error\""));
+        assert!(openai.ends_with(b"data: [DONE]\n\n"));
+
+        let gemini = deadline_event("/v1beta/models/m:streamGenerateContent?alt=sse");
+        assert!(gemini.starts_with(b"data: {\"error\""));
+        assert!(
+            std::str::from_utf8(&gemini)
+                .unwrap()
+                .contains("UNAVAILABLE")
+        );
+
+        let ollama = deadline_event("/api/generate");
+        assert!(ollama.ends_with(b"}\n"));
+
+        assert!(closing("/mcp").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gemini_json_stream_closes_array() {
+        let shutdown = Shutdown::new();
+        let body = futures::stream::iter(vec![
+            Ok(Bytes::from("[")),
+            Ok(Bytes::from("{\"candidates\":[]}")),
+        ])
+        .chain(futures::stream::pending());
+        let closing = closing("/v1beta/models/gemini-pro:streamGenerateContent?key=k");
+        assert!(matches!(closing, Some(Closing::JsonArray)));
+        let mut stream = Box::pin(shutdown.track(body, closing));
+        let mut body = Vec::new();
+        for _ in 0..2 {
+            body.extend_from_slice(&stream.next().await.unwrap().unwrap());
+        }
+
+        shutdown.expire();
+        while let Some(chunk) = stream.next().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        let array: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(array[1]["error"]["status"], "UNAVAILABLE");
+
+        // 尚无元素时直接输出错误元素
+        let payload = Closing::JsonArray.payload(false);
+        let array: serde_json::Value =
+            serde_json::from_slice(&[b"[".as_slice(), &payload].concat()).unwrap();
+        assert_eq!(array[0]["error"]["code"], 503);
+    }
+}
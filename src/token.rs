@@ -204,9 +204,7 @@ fn count_all_tokens_local(
             total += count_tokens(s);
         } else if let serde_json::Value::Array(arr) = &msg.content {
             for item in arr {
-                if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
-                    total += count_tokens(text);
-                }
+                total += count_block_tokens(item);
             }
         }
     }
@@ -224,6 +222,25 @@ fn count_all_tokens_local(
     total.max(1)
 }
 
+/// 估算单个内容块的 tokens：文本、工具调用参数与工具结果（服务端工具循环回填的搜索结果等）
+fn count_block_tokens(block: &serde_json::Value) -> u64 {
+    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+        return count_tokens(text);
+    }
+    match block.get("type").and_then(|v| v.as_str()) {
+        Some("tool_use") => block
+            .get("input")
+            .map(|input| count_tokens(&serde_json::to_string(input).unwrap_or_default()))
+            .unwrap_or(0),
+        Some("tool_result") => match block.get("content") {
+            Some(serde_json::Value::String(s)) => count_tokens(s),
+            Some(serde_json::Value::Array(items)) => items.iter().map(count_block_tokens).sum(),
+            _ => 0,
+        },
+        _ => 0,
+    }
+}
+
 /// 估算输出 tokens
 pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
     let mut total = 0;